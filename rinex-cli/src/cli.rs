@@ -1,8 +1,9 @@
 use crate::fops::filename;
 use crate::parser::parse_epoch;
+use crate::sp3;
 use clap::{Arg, ArgAction, ArgMatches, ColorChoice, Command};
 use log::{error, info, warn};
-use rinex::{prelude::*, quality::QcOpts, Merge};
+use rinex::{navigation::PreciseOrbits, prelude::*, quality::QcOpts, Merge};
 use std::str::FromStr;
 
 pub struct Cli {
@@ -146,6 +147,21 @@ Also drops observations that did not come with an LLI flag"))
                         .long("iono")
                         .action(ArgAction::SetTrue)
                         .help("Plot the ionospheric delay detector"))
+                    .arg(Arg::new("roti")
+                        .long("roti")
+                        .action(ArgAction::SetTrue)
+                        .help("Plot the Rate of TEC Index (ROTI), over 5' windows.
+Requires dual frequency phase observations."))
+                    .arg(Arg::new("s4")
+                        .long("s4")
+                        .action(ArgAction::SetTrue)
+                        .help("Plot the amplitude scintillation index (S4), over 60s windows.
+Requires SSI observations, only meaningful on high rate data."))
+                    .arg(Arg::new("sigma-phi")
+                        .long("sigma-phi")
+                        .action(ArgAction::SetTrue)
+                        .help("Plot the phase scintillation index (Sigma Phi), over 60s windows.
+Only meaningful on high rate data."))
                     .arg(Arg::new("anomalies")
                         .short('a')
                         .long("anomalies")
@@ -190,6 +206,28 @@ Ideally this information is contained in the file Header, but user can manually
                         .action(ArgAction::SetTrue)
                         .help("Display clock biases (offset, drift, drift changes) per epoch and vehicle.
 -fp must be a NAV file"))
+                .next_help_heading("Relative positioning")
+                    .arg(Arg::new("rtk")
+                        .long("rtk")
+                        .value_name("FILE")
+                        .help("Base station Observation RINEX. Resolves the position of the `--fp` rover
+relative to this base station, whose header must describe its position.
+Requires Navigation data (`--nav`). Solutions are plotted and stored in \"rtk.csv\"."))
+                    .arg(Arg::new("kinematic")
+                        .long("kinematic")
+                        .action(ArgAction::SetTrue)
+                        .help("Moving rover, in `--rtk` mode. The rover is considered static by default."))
+                .next_help_heading("Precise products")
+                    .arg(Arg::new("prec-clk")
+                        .long("prec-clk")
+                        .value_name("FILE")
+                        .help("Precise Clock RINEX. When combined to Navigation data (`--nav`),
+broadcast clocks are compared to precise clocks in the `--qc` report (SISRE)."))
+                    .arg(Arg::new("sp3")
+                        .long("sp3")
+                        .value_name("FILE")
+                        .help("Precise orbits (SP3). When combined to Navigation data (`--nav`),
+broadcast orbits are compared to precise orbits in the `--qc` report (SISRE)."))
                 .next_help_heading("ANTEX / APC ")
                     .arg(Arg::new("--atx")
                         .long("atx")
@@ -333,6 +371,15 @@ Refer to README"))
     pub fn iono_detector(&self) -> bool {
         self.matches.get_flag("iono")
     }
+    pub fn roti(&self) -> bool {
+        self.matches.get_flag("roti")
+    }
+    pub fn s4(&self) -> bool {
+        self.matches.get_flag("s4")
+    }
+    pub fn sigma_phi(&self) -> bool {
+        self.matches.get_flag("sigma-phi")
+    }
     pub fn identification(&self) -> bool {
         self.matches.get_flag("sv")
             | self.matches.get_flag("epochs")
@@ -396,6 +443,25 @@ Refer to README"))
     pub fn cs_graph(&self) -> bool {
         self.matches.get_flag("cs")
     }
+    /// Returns optionnal base station, for relative positioning
+    pub fn rtk_base(&self) -> Option<Rinex> {
+        let path = self.matches.get_one::<String>("rtk")?;
+        if let Ok(rnx) = Rinex::from_file(path) {
+            if rnx.is_observation_rinex() {
+                info!("--rtk base station provided");
+                return Some(rnx);
+            } else {
+                warn!("--rtk should be an observation rinex file");
+            }
+        } else {
+            error!("failed to parse base station file \"{}\"", filename(path));
+        }
+        None
+    }
+    /// Returns true if rover is moving, in relative positioning
+    pub fn kinematic(&self) -> bool {
+        self.matches.get_flag("kinematic")
+    }
     /// Returns optionnal RINEX file to "merge"
     pub fn to_merge(&self) -> Option<Rinex> {
         let fp = self.matches.get_one::<String>("merge")?;
@@ -470,6 +536,35 @@ Refer to README"))
         }
         None
     }
+    /// Returns optionnal precise Clock context
+    pub fn clk_context(&self) -> Option<Rinex> {
+        let path = self.matches.get_one::<String>("prec-clk")?;
+        if let Ok(rnx) = Rinex::from_file(path) {
+            if rnx.is_clocks_rinex() {
+                info!("--prec-clk context provided");
+                return Some(rnx);
+            } else {
+                warn!("--prec-clk should be a clock rinex file");
+            }
+        } else {
+            error!("failed to parse clock file \"{}\"", filename(path));
+        }
+        None
+    }
+    /// Returns optionnal precise orbits (SP3) context
+    pub fn sp3_context(&self) -> Option<PreciseOrbits> {
+        let path = self.matches.get_one::<String>("sp3")?;
+        match sp3::parse_orbits(path) {
+            Ok(orbits) => {
+                info!("--sp3 context provided");
+                Some(orbits)
+            },
+            Err(e) => {
+                error!("failed to parse sp3 file \"{}\": {}", filename(path), e);
+                None
+            },
+        }
+    }
     fn manual_ecef(&self) -> Option<&String> {
         self.matches.get_one::<String>("antenna-ecef")
     }
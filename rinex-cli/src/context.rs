@@ -1,6 +1,7 @@
 use crate::fops::filename;
 use crate::Cli;
 use log::info;
+use rinex::navigation::PreciseOrbits;
 use rinex::prelude::*;
 
 #[derive(Debug, Clone)]
@@ -10,6 +11,8 @@ pub struct Context {
     pub to_merge: Option<Rinex>,
     pub nav_rinex: Option<Rinex>,
     pub atx_rinex: Option<Rinex>,
+    pub clk_rinex: Option<Rinex>,
+    pub sp3: Option<PreciseOrbits>,
     pub ground_position: Option<GroundPosition>,
 }
 
@@ -25,6 +28,8 @@ impl Context {
         let primary_rinex = Rinex::from_file(fp).expect("failed to parse primary rinex");
         let nav_rinex = cli.nav_context();
         let atx_rinex = cli.atx_context();
+        let clk_rinex = cli.clk_context();
+        let sp3 = cli.sp3_context();
 
         let ground_position = match &primary_rinex.header.ground_position {
             Some(position) => {
@@ -64,6 +69,8 @@ impl Context {
             primary_rinex,
             nav_rinex,
             atx_rinex,
+            clk_rinex,
+            sp3,
         }
     }
 }
@@ -10,14 +10,17 @@ pub mod fops; // file operation helpers
 mod identification; // high level identification/macros
 pub mod parser;
 mod plot; // plotting operations
+mod sp3; // precise orbits
 
 mod preprocessing;
 use preprocessing::preprocess;
 
 //use horrorshow::Template;
 use rinex::{
+    cs::CsDetector,
     merge::Merge,
-    observation::{Combine, Dcb, IonoDelay, Mp},
+    observation::{Combine, Dcb, IonoDelay, Mp, Roti, Scintillation},
+    positioning::{RtkOpts, SppOpts},
     prelude::*,
     quality::*,
     split::Split,
@@ -132,6 +135,45 @@ pub fn main() -> Result<(), rinex::Error> {
         plot::plot_iono_detector(&mut plot_ctx, &data);
         info!("--iono detector");
     }
+    /*
+     * Rate of TEC Index (graph)
+     */
+    if cli.roti() {
+        let data = ctx.primary_rinex.roti(Duration::from_seconds(300.0));
+        plot::plot_iono_indices(
+            &mut plot_ctx,
+            "Rate of TEC Index (5')",
+            "ROTI [TECu/min]",
+            &data,
+        );
+        info!("--roti");
+    }
+    /*
+     * Amplitude scintillation index (graph)
+     */
+    if cli.s4() {
+        let data = ctx.primary_rinex.s4(Duration::from_seconds(60.0));
+        plot::plot_iono_indices(
+            &mut plot_ctx,
+            "Amplitude Scintillation Index (60s)",
+            "S4",
+            &data,
+        );
+        info!("--s4");
+    }
+    /*
+     * Phase scintillation index (graph)
+     */
+    if cli.sigma_phi() {
+        let data = ctx.primary_rinex.sigma_phi(Duration::from_seconds(60.0));
+        plot::plot_iono_indices(
+            &mut plot_ctx,
+            "Phase Scintillation Index (60s)",
+            "Sigma Phi [rad]",
+            &data,
+        );
+        info!("--sigma-phi");
+    }
     /*
      * [WL] recombination
      */
@@ -166,7 +208,7 @@ pub fn main() -> Result<(), rinex::Error> {
         plot::plot_gnss_recombination(
             &mut plot_ctx,
             "Melbourne-Wübbena signal combination",
-            "Meters of Li-Lj delay",
+            "Meters of wide lane delay",
             &data,
         );
         info!("--mw recombination");
@@ -226,8 +268,66 @@ pub fn main() -> Result<(), rinex::Error> {
      */
     if cli.cs_graph() {
         info!("cs detector");
-        //let mut detector = CsDetector::default();
-        //let cs = detector.cs_detection(&ctx.primary_rinex);
+        // detections are flagged (LLI lock loss) in the record,
+        // then emphasized on the observation plots (see plot_record)
+        let detector = CsDetector::default();
+        let cs = detector.cs_detection_mut(&mut ctx.primary_rinex);
+        let total: usize = cs
+            .values()
+            .map(|svs| svs.values().map(|epochs| epochs.len()).sum::<usize>())
+            .sum();
+        info!("{} cycle slips detected", total);
+    }
+
+    /*
+     * Relative positioning
+     */
+    if let Some(base) = cli.rtk_base() {
+        if let Some(nav) = &ctx.nav_rinex {
+            let opts = RtkOpts::default().with_kinematic(cli.kinematic());
+            let solutions = ctx.primary_rinex.rtk(&base, nav, opts);
+            let fixed = solutions.values().filter(|s| s.is_fixed()).count();
+            info!(
+                "rtk: {} solutions, {} fixed ({:.1} %)",
+                solutions.len(),
+                fixed,
+                100.0 * fixed as f64 / solutions.len().max(1) as f64
+            );
+            let csv_path = ctx.prefix.to_owned() + "/rtk.csv";
+            let mut csv_fd = std::fs::File::create(&csv_path)
+                .expect(&format!("failed to create \"{}\"", &csv_path));
+            writeln!(
+                csv_fd,
+                "epoch,x,y,z,east,north,up,std_east,std_north,std_up,status,ratio,ambiguities"
+            )
+            .expect("failed to write rtk solutions");
+            for (t, s) in &solutions {
+                writeln!(
+                    csv_fd,
+                    "{},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4},{:?},{:.2},{}",
+                    t,
+                    s.position.0,
+                    s.position.1,
+                    s.position.2,
+                    s.baseline.0,
+                    s.baseline.1,
+                    s.baseline.2,
+                    s.std_enu.0,
+                    s.std_enu.1,
+                    s.std_enu.2,
+                    s.status,
+                    s.ratio,
+                    s.ambiguities
+                )
+                .expect("failed to write rtk solutions");
+            }
+            info!("rtk solutions stored in \"{}\"", &csv_path);
+            if !qc_only {
+                plot::plot_rtk(&mut plot_ctx, &solutions);
+            }
+        } else {
+            error!("--rtk requires navigation data (--nav)");
+        }
     }
 
     /*
@@ -289,7 +389,10 @@ pub fn main() -> Result<(), rinex::Error> {
             nav_paths,
             ctx.nav_rinex,
             qc_opts,
-        ); // &ctx.nav_rinex
+        ) // &ctx.nav_rinex
+        .with_sisre(ctx.sp3.as_ref(), ctx.clk_rinex.as_ref())
+        .with_apc_estimate(SppOpts::default())
+        .with_velocity_estimate(SppOpts::default());
 
         let qc_path = ctx.prefix.to_owned() + "/report.html";
         let mut qc_fd =
@@ -79,11 +79,25 @@ pub fn plot_iono_detector(
     ctx: &mut PlotContext,
     data: &HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>>,
 ) {
-    // add a plot
-    ctx.add_cartesian2d_plot(
+    plot_iono_indices(
+        ctx,
         "Ionospheric Delay Detector",
         "Variations of Meters of delay",
+        data,
     );
+}
+
+/*
+ * Plots ionospheric indices, per signal and vehicle
+ */
+pub fn plot_iono_indices(
+    ctx: &mut PlotContext,
+    plot_title: &str,
+    y_title: &str,
+    data: &HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>>,
+) {
+    // add a plot
+    ctx.add_cartesian2d_plot(plot_title, y_title);
     // generate 1 marker per OP
     let markers = generate_markers(data.len());
     // plot all ops
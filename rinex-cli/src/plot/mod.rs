@@ -26,7 +26,12 @@ mod skyplot;
 pub use skyplot::skyplot;
 
 mod combination;
-pub use combination::{plot_gnss_dcb, plot_gnss_recombination, plot_iono_detector};
+pub use combination::{
+    plot_gnss_dcb, plot_gnss_recombination, plot_iono_detector, plot_iono_indices,
+};
+
+mod rtk;
+pub use rtk::plot_rtk;
 
 /*
  * Generates N marker symbols to be used
@@ -388,9 +393,7 @@ pub fn plot_record(ctx: &Context, plot_ctx: &mut PlotContext) {
         record::plot_observation(&ctx, plot_ctx);
     } else if let Some(r) = ctx.primary_rinex.record.as_meteo() {
         record::plot_meteo(plot_ctx, r);
-    } else if let Some(r) = ctx.primary_rinex.record.as_ionex() {
-        if let Some(borders) = ctx.primary_rinex.ionex_map_borders() {
-            record::plot_tec_map(plot_ctx, borders, r);
-        }
+    } else if ctx.primary_rinex.is_ionex() {
+        record::plot_tec_map(plot_ctx, &ctx.primary_rinex, ctx.nav_rinex.as_ref());
     }
 }
@@ -1,85 +1,108 @@
 use crate::plot::PlotContext;
-//use itertools::Itertools;
 use plotly::{
-    color::NamedColor,
-    common::{Marker, MarkerSymbol}, //color::Rgba},
+    color::Rgb,
+    common::{Marker, MarkerSymbol, Visible},
     layout::MapboxStyle,
-    //scatter_mapbox::Fill,
     ScatterMapbox,
 };
 use rinex::ionex::*;
+use rinex::prelude::*;
 
-pub fn plot_tec_map(
-    plot_ctx: &mut PlotContext,
-    _borders: ((f64, f64), (f64, f64)),
-    record: &Record,
-) {
-    let _cmap = colorous::TURBO;
-    plot_ctx.add_world_map(MapboxStyle::OpenStreetMap, (32.5, -40.0), 1);
+/*
+ * Plots TEC maps, one trace per map (only the first one is visible).
+ * When NAV context is provided, the broadcast GPS model is rendered on the same grid
+ * and epochs, and plotted along with its difference to these maps.
+ */
+pub fn plot_tec_map(plot_ctx: &mut PlotContext, ionex: &Rinex, nav: Option<&Rinex>) {
+    let record = match ionex.record.as_ionex() {
+        Some(record) => record,
+        None => return,
+    };
+    plot_maps(plot_ctx, "TEC", record);
 
-    let mut grid_lat: Vec<f64> = Vec::new();
-    let mut grid_lon: Vec<f64> = Vec::new();
-    let mut tec_max = -f64::INFINITY;
-    for (e_index, (_e, (tec, _, _))) in record.iter().enumerate() {
-        for point in tec {
-            if e_index == 0 {
-                // grab grid definition
-                grid_lat.push(point.latitude.into());
-                grid_lon.push(point.longitude.into());
+    let (nav, grid) = match (nav, &ionex.header.ionex) {
+        (Some(nav), Some(params)) => (nav, &params.grid),
+        _ => return,
+    };
+    let epochs: Vec<Epoch> = record.keys().copied().collect();
+    let (first, last) = match (epochs.first(), epochs.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return,
+    };
+    let dt = match ionex.header.sampling_interval {
+        Some(dt) => dt,
+        None if epochs.len() > 1 => epochs[1] - epochs[0],
+        None => Duration::from_hours(1.0),
+    };
+    let series = TimeSeries::inclusive(first, last, dt);
+    match nav.ionex_from_broadcast(Constellation::GPS, grid, series) {
+        Ok(model) => {
+            if let Some(record) = model.record.as_ionex() {
+                plot_maps(plot_ctx, "Broadcast TEC", record);
             }
-            if point.value > tec_max {
-                tec_max = point.value;
+            if let Some(diff) = ionex.ionex_diff(&model) {
+                if let Some(record) = diff.record.as_ionex() {
+                    plot_maps(plot_ctx, "TEC - Broadcast TEC", record);
+                }
             }
-        }
+        },
+        Err(e) => warn!("broadcast TEC maps: {}", e),
     }
+}
 
-    let grid = ScatterMapbox::new(grid_lat, grid_lon)
-        .marker(
-            Marker::new()
-                .size(5)
-                .symbol(MarkerSymbol::Circle)
-                .color(NamedColor::Black)
-                .opacity(0.5),
-        )
-        .name("TEC Grid");
-    plot_ctx.add_trace(grid);
-
-    /*
-     * Build heat map,
-     * we have no means to plot several heat map (day course)
-     * at the moment, we just plot the 1st epoch
-     */
-    /*
-    for (e_index, (e, (tec, _, _))) in record.iter().enumerate() {
-        if e_index == 0 {
-            // form the smallest area from that grid
-            for rows in
-            for i in 0..tec.len() / 4 {
-                println!("MAPS {}", i);
-                //for i in 0..maps.len() / 2 {
-                /*
-                let mut row1 = maps[0]
-                let mut row2 = maps[1]
-                if let Some(points12) = row1.next() {
-                    if let Some(points34) = row2.next() {
-                        // average TEC in that area
-                        let tec = (points12[0].value + points12[1].value
-                            +points34[0].value + points34[1].value ) / 4.0;
-                        let color = cmap.eval_continuous(tec / tec_max);
-                        let area = ScatterMapbox::new(
-                            vec![points12[0].latitude, points12[1].latitude, points34[0].latitude, points34[1].latitude],
-                            vec![points12[0].longitude, points12[1].longitude, points34[0].longitude, points34[1].longitude],
-                        )
-                        .opacity(0.5)
-                        .fill(Fill::ToSelf)
-                        .fill_color(Rgba::new(color.r, color.g, color.b, 0.9));
-                        ctx.add_trace(area);
-                        println!("YES");
-                    }
-                }*/
-            }
-        } else {
-            break ; // we need animations for that
+/*
+ * Plots given maps on a new world map,
+ * colored on a common scale
+ */
+fn plot_maps(plot_ctx: &mut PlotContext, title: &str, record: &Record) {
+    let cmap = colorous::TURBO;
+    let (mut tec_min, mut tec_max) = (f64::INFINITY, -f64::INFINITY);
+    for (tec, _, _) in record.values() {
+        for point in tec {
+            tec_min = tec_min.min(point.value);
+            tec_max = tec_max.max(point.value);
         }
-    }*/
+    }
+    if tec_max < tec_min {
+        return;
+    }
+    let span = (tec_max - tec_min).max(f64::EPSILON);
+
+    plot_ctx.add_world_map(MapboxStyle::OpenStreetMap, (32.5, -40.0), 1);
+    for (index, (e, (tec, _, _))) in record.iter().enumerate() {
+        let lat: Vec<f64> = tec.iter().map(|p| p.latitude).collect();
+        let lon: Vec<f64> = tec.iter().map(|p| p.longitude).collect();
+        let colors: Vec<Rgb> = tec
+            .iter()
+            .map(|p| {
+                let color = cmap.eval_continuous((p.value - tec_min) / span);
+                Rgb::new(color.r, color.g, color.b)
+            })
+            .collect();
+        let txt: Vec<String> = tec.iter().map(|p| format!("{:.1} TECu", p.value)).collect();
+        let trace = ScatterMapbox::new(lat, lon)
+            .marker(
+                Marker::new()
+                    .size(5)
+                    .symbol(MarkerSymbol::Circle)
+                    .color_array(colors)
+                    .opacity(0.5),
+            )
+            .hover_text_array(txt)
+            .name(&format!("{} {}", title, e))
+            .visible({
+                if index < 1 {
+                    Visible::True
+                } else {
+                    Visible::LegendOnly
+                }
+            });
+        plot_ctx.add_trace(trace);
+    }
+    trace!(
+        "{} maps: [{:.1}, {:.1}] TECu color scale",
+        title,
+        tec_min,
+        tec_max
+    );
 }
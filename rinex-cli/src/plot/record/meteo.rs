@@ -33,6 +33,9 @@ pub fn plot_meteo(plot_ctx: &mut PlotContext, record: &Record) {
             "WS" => "m/s",
             "RI" => "%",
             "HI" => "",
+            "DP" => "°C",
+            "TM" => "K",
+            "PW" => "mm",
             _ => unreachable!(),
         };
         plot_ctx.add_cartesian2d_plot(
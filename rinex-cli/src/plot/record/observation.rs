@@ -130,6 +130,33 @@ pub fn plot_observation(ctx: &Context, plot_ctx: &mut PlotContext) {
                 });
                 plot_ctx.add_trace(trace);
 
+                // emphasize cycle slips (loss of lock)
+                let cs_x: Vec<Epoch> = data
+                    .iter()
+                    .filter_map(|(cs, e, _y)| if *cs { Some(*e) } else { None })
+                    .collect();
+                if !cs_x.is_empty() {
+                    let cs_y: Vec<f64> = data
+                        .iter()
+                        .filter_map(|(cs, _e, y)| if *cs { Some(*y) } else { None })
+                        .collect();
+                    let trace = build_chart_epoch_axis(
+                        &format!("CS({}({}))", sv, observable),
+                        Mode::Markers,
+                        cs_x,
+                        cs_y,
+                    )
+                    .marker(Marker::new().symbol(MarkerSymbol::X).size(10))
+                    .visible({
+                        if index < 1 {
+                            Visible::True
+                        } else {
+                            Visible::LegendOnly
+                        }
+                    });
+                    plot_ctx.add_trace(trace);
+                }
+
                 if index == 0 && physics == "Signal Strength" {
                     // 1st Carrier encountered: plot Sv only once
                     // we also only augment the SSI plot when NAV context is provided
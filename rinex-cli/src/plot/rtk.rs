@@ -0,0 +1,34 @@
+use super::{build_chart_epoch_axis, Mode, PlotContext};
+use rinex::positioning::RtkSolution;
+use rinex::prelude::*;
+use std::collections::BTreeMap;
+
+/*
+ * Plot RTK solutions: baseline components and ambiguity validation ratio
+ */
+pub fn plot_rtk(ctx: &mut PlotContext, solutions: &BTreeMap<Epoch, RtkSolution>) {
+    let epochs: Vec<Epoch> = solutions.keys().copied().collect();
+    ctx.add_cartesian2d_plot("Baseline (base to rover)", "Offset [m]");
+    for (name, component) in [("East", 0), ("North", 1), ("Up", 2)] {
+        let data_y: Vec<f64> = solutions
+            .values()
+            .map(|solution| match component {
+                0 => solution.baseline.0,
+                1 => solution.baseline.1,
+                _ => solution.baseline.2,
+            })
+            .collect();
+        let trace = build_chart_epoch_axis(name, Mode::LinesMarkers, epochs.clone(), data_y);
+        ctx.add_trace(trace);
+    }
+    ctx.add_cartesian2d_plot("Ambiguity resolution", "Ratio");
+    for (name, fixed) in [("Fixed", true), ("Float", false)] {
+        let (data_x, data_y): (Vec<Epoch>, Vec<f64>) = solutions
+            .iter()
+            .filter(|(_, solution)| solution.is_fixed() == fixed)
+            .map(|(t, solution)| (*t, solution.ratio.min(1000.0)))
+            .unzip();
+        let trace = build_chart_epoch_axis(name, Mode::Markers, data_x, data_y);
+        ctx.add_trace(trace);
+    }
+}
@@ -0,0 +1,113 @@
+//! Minimal SP3 (c/d) reader: only precise orbits are retained,
+//! to be compared to broadcast ephemerides (SISRE).
+use rinex::navigation::PreciseOrbits;
+use rinex::prelude::*;
+use rinex::reader::BufferedReader;
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to read file")]
+    IoError(#[from] std::io::Error),
+    #[error("not an SP3 file")]
+    NotSp3,
+    #[error("failed to parse epoch \"{0}\"")]
+    EpochError(String),
+    #[error("failed to parse position \"{0}\"")]
+    PositionError(String),
+}
+
+/*
+ * SP3 time systems
+ */
+fn timescale(system: &str) -> TimeScale {
+    match system {
+        "GAL" => TimeScale::GST,
+        "BDT" => TimeScale::BDT,
+        "UTC" => TimeScale::UTC,
+        "TAI" => TimeScale::TAI,
+        _ => TimeScale::GPST,
+    }
+}
+
+/*
+ * Parses "*  YYYY MM DD hh mm ss.ssssssss" epoch descriptor
+ */
+fn parse_epoch(line: &str, ts: TimeScale) -> Result<Epoch, Error> {
+    let items: Vec<&str> = line[1..].split_ascii_whitespace().collect();
+    if items.len() < 6 {
+        return Err(Error::EpochError(line.to_string()));
+    }
+    let err = || Error::EpochError(line.to_string());
+    let y = i32::from_str(items[0]).map_err(|_| err())?;
+    let m = u8::from_str(items[1]).map_err(|_| err())?;
+    let d = u8::from_str(items[2]).map_err(|_| err())?;
+    let hh = u8::from_str(items[3]).map_err(|_| err())?;
+    let mm = u8::from_str(items[4]).map_err(|_| err())?;
+    let ss = f64::from_str(items[5]).map_err(|_| err())?;
+    let nanos = ((ss - ss.floor()) * 1.0E9).round() as u32;
+    Ok(Epoch::from_gregorian(
+        y,
+        m,
+        d,
+        hh,
+        mm,
+        ss.floor() as u8,
+        nanos,
+        ts,
+    ))
+}
+
+/*
+ * Parses "PG01 xxxxxx.xxxxxx yyyyyy.yyyyyy zzzzzz.zzzzzz" position record,
+ * expressed in km
+ */
+fn parse_position(line: &str) -> Result<Option<(Sv, (f64, f64, f64))>, Error> {
+    if line.len() < 46 {
+        return Err(Error::PositionError(line.to_string()));
+    }
+    let sv = Sv::from_str(line[1..4].replace(' ', "0").trim())
+        .map_err(|_| Error::PositionError(line.to_string()))?;
+    let mut xyz = [0.0_f64; 3];
+    for (i, value) in xyz.iter_mut().enumerate() {
+        *value = f64::from_str(line[4 + i * 14..18 + i * 14].trim())
+            .map_err(|_| Error::PositionError(line.to_string()))?;
+    }
+    if xyz == [0.0_f64; 3] {
+        return Ok(None); // bad or absent position
+    }
+    Ok(Some((sv, (xyz[0] * 1.0E3, xyz[1] * 1.0E3, xyz[2] * 1.0E3))))
+}
+
+/// Parses the precise orbits of given SP3 file, positions are expressed in meters ECEF.
+/// Clocks and velocities are not retained.
+pub fn parse_orbits(path: &str) -> Result<PreciseOrbits, Error> {
+    let reader = BufferedReader::new(path)?;
+    let mut orbits = PreciseOrbits::new();
+    let mut ts: Option<TimeScale> = None;
+    let mut epoch: Option<Epoch> = None;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if index == 0 && !line.starts_with('#') {
+            return Err(Error::NotSp3);
+        }
+        if line.starts_with("%c") && ts.is_none() {
+            // first descriptor holds the time system
+            ts = line.get(9..12).map(timescale);
+        } else if line.starts_with('*') {
+            epoch = Some(parse_epoch(&line, ts.unwrap_or(TimeScale::GPST))?);
+        } else if line.starts_with('P') {
+            if let Some(t) = epoch {
+                if let Some((sv, xyz)) = parse_position(&line)? {
+                    orbits.entry(t).or_insert_with(HashMap::new).insert(sv, xyz);
+                }
+            }
+        } else if line.starts_with("EOF") {
+            break;
+        }
+    }
+    Ok(orbits)
+}
@@ -47,13 +47,55 @@ pub fn do_report(cli: &Cli, fp: &str, constell: Constellation, rnx: &Rinex, nav:
      * RCVR antenna positionning
      * based of Pseudo Range observations
      */
-    report.push_str(" mean antenna; # of pos :     0\n");
-    report.push_str("  antenna WGS 84 (xyz)  :  0.0 0.0 0.0 (m)\n");
-    report.push_str("  antenna WGS 84 (geo)  :  0     0\n");
-    report.push_str("  antenna WGS 84 (geo)  :  0.0 ddeg  0.0 ddeg\n");
-    report.push_str("          WGS 84 height :  0.0 m\n");
-    report.push_str("|qc - header| position  :  0.0 m\n");
-    report.push_str("qc position offsets     :  0.0 m vertical  0.0 m horizontal\n");
+    let solutions = match nav {
+        Some(nav) => rnx.spp(nav, positioning::SppOpts::default()),
+        None => Default::default(),
+    };
+    report.push_str(&format!(
+        " mean antenna; # of pos : {:5}\n",
+        solutions.len()
+    ));
+    if !solutions.is_empty() {
+        let n = solutions.len() as f64;
+        let (mut x, mut y, mut z) = (0.0_f64, 0.0_f64, 0.0_f64);
+        for solution in solutions.values() {
+            x += solution.position.0 / n;
+            y += solution.position.1 / n;
+            z += solution.position.2 / n;
+        }
+        let mean = GroundPosition::from_ecef_wgs84((x, y, z));
+        let (lat, lon, alt) = mean.to_geodetic();
+        report.push_str(&format!(
+            "  antenna WGS 84 (xyz)  :  {:.4} {:.4} {:.4} (m)\n",
+            x, y, z
+        ));
+        report.push_str(&format!(
+            "  antenna WGS 84 (geo)  :  {} {}\n",
+            dms(lat, 'N', 'S'),
+            dms(lon, 'E', 'W')
+        ));
+        report.push_str(&format!(
+            "  antenna WGS 84 (geo)  :  {:.6} ddeg  {:.6} ddeg\n",
+            lat, lon
+        ));
+        report.push_str(&format!("          WGS 84 height :  {:.4} m\n", alt));
+        if let Some(header) = rnx.header.ground_position {
+            let (hx, hy, hz) = header.to_ecef_wgs84();
+            let (dx, dy, dz) = (x - hx, y - hy, z - hz);
+            report.push_str(&format!(
+                "|qc - header| position  :  {:.4} m\n",
+                (dx * dx + dy * dy + dz * dz).sqrt()
+            ));
+            // vertical offset: projection on the local vertical
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+            let up = lat.cos() * lon.cos() * dx + lat.cos() * lon.sin() * dy + lat.sin() * dz;
+            let horizontal = (dx * dx + dy * dy + dz * dz - up * up).max(0.0).sqrt();
+            report.push_str(&format!(
+                "qc position offsets     :  {:.4} m vertical  {:.4} m horizontal\n",
+                up, horizontal
+            ));
+        }
+    }
     
     report.push_str("Obs w/ SV duplication  : 0 (within non-repeated epochs)\n");
     report.push_str("Moving average MP12    : 0.0 m\n");
@@ -72,3 +114,15 @@ pub fn do_report(cli: &Cli, fp: &str, constell: Constellation, rnx: &Rinex, nav:
     report.push_str(" * or unknown elevation\n");
     report.push_str("      first epoch    last epoch     sn1   sn2\n");
 }
+
+/*
+ * Formats decimal degrees as teqc does: degrees, minutes and seconds
+ */
+fn dms(ddeg: f64, positive: char, negative: char) -> String {
+    let hemisphere = if ddeg < 0.0 { negative } else { positive };
+    let ddeg = ddeg.abs();
+    let deg = ddeg.floor();
+    let min = ((ddeg - deg) * 60.0).floor();
+    let sec = (ddeg - deg - min / 60.0) * 3600.0;
+    format!("{} {:3} {:02} {:05.2}", hemisphere, deg, min, sec)
+}
@@ -75,7 +75,9 @@ pub trait Smooth {
     fn moving_average(&self, window: Duration) -> Self;
     /// Moving average mutable implementation
     fn moving_average_mut(&mut self, window: Duration);
-    /// Applies a Hatch smoothing filter to Pseudo Range observations
+    /// Applies a Hatch smoothing filter to Pseudo Range observations.
+    /// The filter is reset whenever the associated phase is flagged
+    /// with a loss of lock (see [crate::observation::LliFlags])
     fn hatch_smoothing(&self) -> Self;
     /// Hatch filter mutable implementation
     fn hatch_smoothing_mut(&mut self);
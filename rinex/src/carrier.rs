@@ -238,16 +238,16 @@ impl Carrier {
     fn from_gps_observable(obs: &Observable) -> Result<Self, Error> {
         lazy_static! {
             static ref L1_CODES: Vec<&'static str> = vec![
-                "C1", "L1", "D1", "S1", "C1C", "L1C", "D1C", "S1C", "C1S", "L1S", "D1S", "S1S",
-                "C1L", "L1L", "D1L", "S1L", "C1X", "L1X", "D1X", "S1X", "C1P", "L1P", "D1P", "S1P",
-                "C1W", "L1W", "D1W", "S1W", "C1Y", "L1Y", "D1Y", "S1Y", "C1M", "L1M", "D1M", "S1M",
-                "L1N", "D1N", "S1N",
+                "C1", "P1", "L1", "D1", "S1", "C1C", "L1C", "D1C", "S1C", "C1S", "L1S", "D1S",
+                "S1S", "C1L", "L1L", "D1L", "S1L", "C1X", "L1X", "D1X", "S1X", "C1P", "L1P", "D1P",
+                "S1P", "C1W", "L1W", "D1W", "S1W", "C1Y", "L1Y", "D1Y", "S1Y", "C1M", "L1M", "D1M",
+                "S1M", "L1N", "D1N", "S1N",
             ];
             static ref L2_CODES: Vec<&'static str> = vec![
-                "C2", "L2", "D2", "S2", "C2C", "L2C", "D2C", "S2C", "C2D", "L2D", "D2D", "S2D",
-                "C2S", "L2S", "D2S", "S2S", "C2L", "L2L", "D2L", "S2L", "C2X", "L2X", "D2X", "S2X",
-                "C2P", "L2P", "D2P", "S2P", "C2W", "L2W", "D2W", "S2W", "C2Y", "L2Y", "D2Y", "S2Y",
-                "C2M", "L2M", "D2M", "S2M", "L2N", "D2N", "S2N",
+                "C2", "P2", "L2", "D2", "S2", "C2C", "L2C", "D2C", "S2C", "C2D", "L2D", "D2D",
+                "S2D", "C2S", "L2S", "D2S", "S2S", "C2L", "L2L", "D2L", "S2L", "C2X", "L2X", "D2X",
+                "S2X", "C2P", "L2P", "D2P", "S2P", "C2W", "L2W", "D2W", "S2W", "C2Y", "L2Y", "D2Y",
+                "S2Y", "C2M", "L2M", "D2M", "S2M", "L2N", "D2N", "S2N",
             ];
             static ref L5_CODES: Vec<&'static str> = vec![
                 "C5", "L5", "D5", "S5", "C5I", "L5I", "D5I", "S5I", "C5Q", "L5Q", "D5Q", "S5Q",
@@ -276,13 +276,15 @@ impl Carrier {
     fn from_glo_observable(obs: &Observable) -> Result<Self, Error> {
         lazy_static! {
             static ref G1_CODES: Vec<&'static str> = vec![
-                "C1", "L1", "D1", "S1", "C1C", "L1C", "D1C", "S1C", "C1P", "L1P", "D1P", "S1P",
+                "C1", "P1", "L1", "D1", "S1", "C1C", "L1C", "D1C", "S1C", "C1P", "L1P", "D1P",
+                "S1P",
             ];
             static ref G1A_CODES: Vec<&'static str> = vec![
                 "C4A", "L4A", "D4A", "S4A", "C4B", "L4B", "D4B", "S4B", "C4X", "L4X", "D4X", "S4X",
             ];
             static ref G2_CODES: Vec<&'static str> = vec![
-                "C2", "L2", "D2", "S2", "C2C", "L2C", "D2C", "S2C", "C2P", "L2P", "D2P", "S2P",
+                "C2", "P2", "L2", "D2", "S2", "C2C", "L2C", "D2C", "S2C", "C2P", "L2P", "D2P",
+                "S2P",
             ];
             static ref G2A_CODES: Vec<&'static str> = vec![
                 "C6A", "L6A", "D6A", "S6A", "C6B", "L6B", "D6B", "S6B", "C6X", "L6X", "D6X", "S6X",
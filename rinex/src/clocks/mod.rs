@@ -3,6 +3,9 @@ use hifitime::TimeScale;
 pub mod record;
 pub use record::{Data, DataType, Error, Record, System};
 
+pub mod stability;
+pub use stability::Deviation;
+
 /// Clocks `RINEX` specific header fields
 #[derive(Clone, Debug, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
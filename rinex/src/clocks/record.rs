@@ -4,6 +4,7 @@ use crate::{
 };
 use hifitime::Duration;
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
 use std::str::FromStr;
 use strum_macros::EnumString;
 use thiserror::Error;
@@ -263,6 +264,124 @@ pub(crate) fn fmt_epoch(
     Ok(lines)
 }
 
+/*
+ * Clock data type of given system:
+ * Sv clocks are found in AS data, stations clocks in AR data
+ */
+fn system_data_type(system: &System) -> DataType {
+    match system {
+        System::Sv(_) => DataType::AS,
+        System::Station(_) => DataType::AR,
+    }
+}
+
+/*
+ * Dominant sampling interval of given system clock, in this record
+ */
+pub(crate) fn dominant_sampling(rec: &Record, system: &System) -> Option<Duration> {
+    let dtype = system_data_type(system);
+    let mut histogram: BTreeMap<Duration, usize> = BTreeMap::new();
+    let mut previous: Option<Epoch> = None;
+    for (e, dtypes) in rec {
+        let sampled = dtypes
+            .get(&dtype)
+            .map(|systems| systems.contains_key(system))
+            .unwrap_or(false);
+        if sampled {
+            if let Some(previous) = previous {
+                *histogram.entry(*e - previous).or_default() += 1;
+            }
+            previous = Some(*e);
+        }
+    }
+    histogram
+        .into_iter()
+        .max_by_key(|(_, pop)| *pop)
+        .map(|(dt, _)| dt)
+}
+
+/*
+ * Interpolates clock data of given system at desired epoch.
+ * Sv clocks are searched in AS data, stations clocks in AR data.
+ * Bias is interpolated with a cubic Hermite polynomial when rates are known,
+ * linearly otherwise. Rate and acceleration are interpolated linearly,
+ * sigmas are propagated from the surrounding samples that provide one.
+ * We refuse to interpolate across a data gap, larger than the `sampling`
+ * interval of this clock, or a discontinuity (DR data).
+ */
+pub(crate) fn interpolate(
+    rec: &Record,
+    system: &System,
+    t: Epoch,
+    sampling: Duration,
+) -> Option<Data> {
+    let dtype = system_data_type(system);
+    fn sample<'a>(
+        (e, dtypes): (&Epoch, &'a HashMap<DataType, HashMap<System, Data>>),
+        dtype: &DataType,
+        system: &System,
+    ) -> Option<(Epoch, &'a Data)> {
+        dtypes
+            .get(dtype)
+            .and_then(|systems| systems.get(system))
+            .map(|data| (*e, data))
+    }
+    let (t0, d0) = rec
+        .range(..=t)
+        .rev()
+        .find_map(|item| sample(item, &dtype, system))?;
+    if t0 == t {
+        return Some(d0.clone());
+    }
+    let (t1, d1) = rec
+        .range(t..)
+        .find_map(|item| sample(item, &dtype, system))?;
+    let dt = t1 - t0;
+    if dt > sampling * 1.5 {
+        return None; // data gap
+    }
+    let discontinuity = rec
+        .range((Bound::Excluded(t0), Bound::Included(t1)))
+        .any(|(_, dtypes)| {
+            dtypes
+                .get(&DataType::DR)
+                .map(|systems| systems.contains_key(system))
+                .unwrap_or(false)
+        });
+    if discontinuity {
+        return None;
+    }
+
+    let h = dt.to_seconds();
+    let x = (t - t0).to_seconds() / h;
+    let linear = |v0: f64, v1: f64| (1.0 - x) * v0 + x * v1;
+    let interp = |v0: Option<f64>, v1: Option<f64>| Some(linear(v0?, v1?));
+    let sigma = |s0: Option<f64>, s1: Option<f64>| match (s0, s1) {
+        (Some(s0), Some(s1)) => {
+            Some(((1.0 - x).powi(2) * s0.powi(2) + x.powi(2) * s1.powi(2)).sqrt())
+        },
+        (s0, s1) => s0.or(s1),
+    };
+    let bias = match (d0.rate, d1.rate) {
+        (Some(r0), Some(r1)) => {
+            let (x2, x3) = (x.powi(2), x.powi(3));
+            (2.0 * x3 - 3.0 * x2 + 1.0) * d0.bias
+                + (x3 - 2.0 * x2 + x) * h * r0
+                + (-2.0 * x3 + 3.0 * x2) * d1.bias
+                + (x3 - x2) * h * r1
+        },
+        _ => linear(d0.bias, d1.bias),
+    };
+    Some(Data {
+        bias,
+        bias_sigma: sigma(d0.bias_sigma, d1.bias_sigma),
+        rate: interp(d0.rate, d1.rate),
+        rate_sigma: sigma(d0.rate_sigma, d1.rate_sigma),
+        accel: interp(d0.accel, d1.accel),
+        accel_sigma: sigma(d0.accel_sigma, d1.accel_sigma),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -286,6 +405,74 @@ mod test {
         let c = "A  G16  1994 07 14 20 59  0.000000  2   -0.123456789012E+00 -0.123456789012E+01";
         assert_eq!(is_new_epoch(c), false);
     }
+    #[test]
+    fn interpolation_discontinuity() {
+        let station = System::Station("USNO".to_string());
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2022, 1, 1);
+        let dt = Duration::from_seconds(30.0);
+        let mut rec = Record::new();
+        for i in 0..4 {
+            let mut systems = HashMap::new();
+            systems.insert(
+                station.clone(),
+                Data {
+                    bias: i as f64,
+                    rate: Some(1.0 / 30.0),
+                    ..Default::default()
+                },
+            );
+            let mut dtypes = HashMap::new();
+            dtypes.insert(DataType::AR, systems);
+            rec.insert(t0 + dt * i as f64, dtypes);
+        }
+        assert_eq!(dominant_sampling(&rec, &station), Some(dt));
+        // cubic Hermite on linear data remains linear
+        let data = interpolate(&rec, &station, t0 + Duration::from_seconds(10.0), dt).unwrap();
+        assert!((data.bias - 1.0 / 3.0).abs() < 1E-12);
+        assert_eq!(data.rate, Some(1.0 / 30.0));
+        // discontinuity in ]t0 + 30s, t0 + 60s]
+        let mut systems = HashMap::new();
+        systems.insert(station.clone(), Data::default());
+        rec.get_mut(&(t0 + dt * 2.0))
+            .unwrap()
+            .insert(DataType::DR, systems);
+        assert!(interpolate(&rec, &station, t0 + Duration::from_seconds(45.0), dt).is_none());
+        assert!(interpolate(&rec, &station, t0 + Duration::from_seconds(75.0), dt).is_some());
+    }
+    #[test]
+    fn interpolation_gap() {
+        let station = System::Station("USNO".to_string());
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2022, 1, 1);
+        let dt = Duration::from_seconds(30.0);
+        let mut rec = Record::new();
+        // 30s sampling, followed by isolated samples every 1000s
+        let epochs = (0..100)
+            .map(|i| t0 + dt * i as f64)
+            .chain((4..7).map(|i| t0 + Duration::from_seconds(1000.0 * i as f64)));
+        for (i, t) in epochs.enumerate() {
+            let mut systems = HashMap::new();
+            systems.insert(
+                station.clone(),
+                Data {
+                    bias: i as f64,
+                    bias_sigma: if i % 2 == 0 { Some(1.0) } else { None },
+                    ..Default::default()
+                },
+            );
+            let mut dtypes = HashMap::new();
+            dtypes.insert(DataType::AR, systems);
+            rec.insert(t, dtypes);
+        }
+        let sampling = dominant_sampling(&rec, &station).unwrap();
+        assert_eq!(sampling, dt);
+        // the surrounding samples are evenly spaced, but the gap remains
+        let t = t0 + Duration::from_seconds(4500.0);
+        assert!(interpolate(&rec, &station, t, sampling).is_none());
+        // single sigma is propagated
+        let data =
+            interpolate(&rec, &station, t0 + Duration::from_seconds(15.0), sampling).unwrap();
+        assert_eq!(data.bias_sigma, Some(1.0));
+    }
 }
 
 impl Merge for Record {
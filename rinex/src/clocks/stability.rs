@@ -0,0 +1,217 @@
+//! Clock (frequency) stability analysis
+use crate::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use strum_macros::EnumString;
+
+/// Frequency stability estimators, evaluated on clock (phase) data
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, EnumString)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Deviation {
+    /// Overlapping Allan deviation
+    #[strum(serialize = "adev", serialize = "ADEV")]
+    Adev,
+    /// Modified Allan deviation
+    #[strum(serialize = "mdev", serialize = "MDEV")]
+    Mdev,
+    /// Time deviation [s]
+    #[strum(serialize = "tdev", serialize = "TDEV")]
+    Tdev,
+    /// Overlapping Hadamard deviation
+    #[strum(serialize = "hdev", serialize = "HDEV")]
+    Hdev,
+}
+
+impl std::fmt::Display for Deviation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Adev => f.write_str("ADEV"),
+            Self::Mdev => f.write_str("MDEV"),
+            Self::Tdev => f.write_str("TDEV"),
+            Self::Hdev => f.write_str("HDEV"),
+        }
+    }
+}
+
+impl Deviation {
+    /// All known estimators
+    pub const ALL: [Self; 4] = [Self::Adev, Self::Mdev, Self::Tdev, Self::Hdev];
+}
+
+/*
+ * Projects phase data onto a regular grid of the dominant sampling interval.
+ * Missing samples (data gaps) are None.
+ */
+fn regular_grid(series: &BTreeMap<Epoch, f64>) -> Option<(Duration, Vec<Option<f64>>)> {
+    let t0 = *series.keys().next()?;
+    let mut histogram: HashMap<Duration, usize> = HashMap::new();
+    for (prev, next) in series.keys().zip(series.keys().skip(1)) {
+        *histogram.entry(*next - *prev).or_insert(0) += 1;
+    }
+    let (tau0, _) = histogram
+        .into_iter()
+        .max_by(|(dt_a, pop_a), (dt_b, pop_b)| pop_a.cmp(pop_b).then(dt_b.cmp(dt_a)))?;
+    let tau0_s = tau0.to_seconds();
+    let mut grid: Vec<Option<f64>> = Vec::with_capacity(series.len());
+    for (t, x) in series {
+        let pos = (*t - t0).to_seconds() / tau0_s;
+        let index = pos.round();
+        if (pos - index).abs() > 1.0E-6 {
+            continue; // does not fit the nominal sampling
+        }
+        let index = index as usize;
+        if index >= grid.len() {
+            grid.resize(index + 1, None);
+        }
+        grid[index] = Some(*x);
+    }
+    Some((tau0, grid))
+}
+
+/*
+ * Second difference of phase data, with averaging factor m
+ */
+fn second_diff(x: &[Option<f64>], i: usize, m: usize) -> Option<f64> {
+    Some(x[i + 2 * m]? - 2.0 * x[i + m]? + x[i]?)
+}
+
+/*
+ * Third difference of phase data, with averaging factor m
+ */
+fn third_diff(x: &[Option<f64>], i: usize, m: usize) -> Option<f64> {
+    Some(x[i + 3 * m]? - 3.0 * x[i + 2 * m]? + 3.0 * x[i + m]? - x[i]?)
+}
+
+fn adev(x: &[Option<f64>], m: usize, tau: f64) -> Option<f64> {
+    let n = x.len();
+    let (sum, count) = (0..n - 2 * m)
+        .filter_map(|i| second_diff(x, i, m))
+        .fold((0.0, 0), |(sum, count), d| (sum + d.powi(2), count + 1));
+    if count > 0 {
+        Some((sum / (2.0 * tau.powi(2) * count as f64)).sqrt())
+    } else {
+        None
+    }
+}
+
+fn mdev(x: &[Option<f64>], m: usize, tau: f64) -> Option<f64> {
+    let n = x.len();
+    let mut sum = 0.0_f64;
+    let mut count = 0;
+    for j in 0..=n - 3 * m {
+        let inner: Option<f64> = (j..j + m).map(|i| second_diff(x, i, m)).sum();
+        if let Some(inner) = inner {
+            sum += inner.powi(2);
+            count += 1;
+        }
+    }
+    if count > 0 {
+        Some((sum / (2.0 * (m as f64).powi(2) * tau.powi(2) * count as f64)).sqrt())
+    } else {
+        None
+    }
+}
+
+fn hdev(x: &[Option<f64>], m: usize, tau: f64) -> Option<f64> {
+    let n = x.len();
+    let (sum, count) = (0..n - 3 * m)
+        .filter_map(|i| third_diff(x, i, m))
+        .fold((0.0, 0), |(sum, count), d| (sum + d.powi(2), count + 1));
+    if count > 0 {
+        Some((sum / (6.0 * tau.powi(2) * count as f64)).sqrt())
+    } else {
+        None
+    }
+}
+
+/// Evaluates desired frequency stability estimator, on given phase (time offset)
+/// data expressed in seconds. Data is projected onto its dominant sampling
+/// interval τ0: missing samples are considered as data gaps, and estimator terms
+/// that would involve one of them are not taken into account.
+/// Returns the deviation for the standard octave τ series (τ = 2^n τ0).
+pub fn deviation(series: &BTreeMap<Epoch, f64>, dev: Deviation) -> Vec<(Duration, f64)> {
+    let mut ret: Vec<(Duration, f64)> = Vec::new();
+    let (tau0, x) = match regular_grid(series) {
+        Some(grid) => grid,
+        None => return ret,
+    };
+    let n = x.len();
+    let mut m = 1;
+    loop {
+        let feasible = match dev {
+            Deviation::Adev => 2 * m < n,
+            Deviation::Mdev | Deviation::Tdev | Deviation::Hdev => 3 * m < n,
+        };
+        if !feasible {
+            break;
+        }
+        let tau = tau0 * m as f64;
+        let tau_s = tau.to_seconds();
+        let value = match dev {
+            Deviation::Adev => adev(&x, m, tau_s),
+            Deviation::Mdev => mdev(&x, m, tau_s),
+            Deviation::Tdev => mdev(&x, m, tau_s).map(|mdev| tau_s / 3.0_f64.sqrt() * mdev),
+            Deviation::Hdev => hdev(&x, m, tau_s),
+        };
+        if let Some(value) = value {
+            ret.push((tau, value));
+        }
+        m *= 2;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn series(phase: &[f64]) -> BTreeMap<Epoch, f64> {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2022, 1, 1);
+        phase
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (t0 + Duration::from_seconds(i as f64), *x))
+            .collect()
+    }
+    #[test]
+    fn frequency_offset() {
+        // a pure frequency offset (linear phase) is perfectly stable
+        let phase: Vec<f64> = (0..64).map(|i| 1.0E-9 * i as f64).collect();
+        for dev in Deviation::ALL {
+            let values = deviation(&series(&phase), dev);
+            assert_eq!(values.len(), 5, "{}", dev);
+            for (_, value) in values {
+                assert!(value.abs() < 1.0E-15, "{}", dev);
+            }
+        }
+    }
+    #[test]
+    fn frequency_jump() {
+        // y = 0, 0, 1, 1 [s/s] sampled @ 1s: ADEV(1s)
+        let phase = [0.0, 0.0, 0.0, 1.0, 2.0];
+        let values = deviation(&series(&phase), Deviation::Adev);
+        assert_eq!(values[0].0, Duration::from_seconds(1.0));
+        // second differences: 0, 1, 0
+        assert!((values[0].1 - (1.0_f64 / 6.0).sqrt()).abs() < 1.0E-12);
+        // m=1 MDEV equals ADEV
+        let mdev = deviation(&series(&phase), Deviation::Mdev);
+        assert!((mdev[0].1 - values[0].1).abs() < 1.0E-12);
+    }
+    #[test]
+    fn data_gaps() {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2022, 1, 1);
+        let mut data = BTreeMap::new();
+        for i in 0..32 {
+            if i != 10 {
+                data.insert(t0 + Duration::from_seconds(i as f64 * 30.0), 0.0);
+            }
+        }
+        data.insert(t0 + Duration::from_seconds(10.0 * 30.0), 1.0E3);
+        let with_outlier = deviation(&data, Deviation::Adev);
+        assert!(with_outlier[0].1 > 0.0);
+        data.remove(&(t0 + Duration::from_seconds(10.0 * 30.0)));
+        let values = deviation(&data, Deviation::Adev);
+        assert_eq!(values[0].0, Duration::from_seconds(30.0));
+        for (_, value) in values {
+            assert_eq!(value, 0.0, "data gap should be dropped");
+        }
+    }
+}
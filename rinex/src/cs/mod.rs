@@ -1,15 +1,21 @@
-use crate::{prelude::*, Carrier};
-use std::collections::HashMap;
+//! Cycle slip detection and repair
+use crate::observation::{Combine, LliFlags, Record};
+use crate::{prelude::*, Carrier, Observable, SPEED_OF_LIGHT};
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Default, Debug, Clone, PartialEq)]
+mod repair;
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum CsStrategy {
     /// Ultimate detection method, not particularly more intense
-    /// than others, but requires modern Multi carrier frequencies
+    /// than others, but requires modern Multi carrier frequencies.
+    /// Geometry free combinations are formed against several carriers,
+    /// which allows identifying the carrier that actually slipped.
     #[default]
     GfAdvanced,
     /// Simple GF based CS detection method.
     /// This does not work well in case of high ionospheric
-    /// activity
+    /// activity, and slips cannot be attributed to one carrier or the other
     GfSimple,
     /// MW recombination based CS detection method
     Mw,
@@ -21,6 +27,17 @@ pub enum CsStrategy {
     SingleFrequency,
 }
 
+/*
+ * Strategies, by order of preference
+ */
+const PREFERENCE: [CsStrategy; 5] = [
+    CsStrategy::GfAdvanced,
+    CsStrategy::GfSimple,
+    CsStrategy::Mw,
+    CsStrategy::PhaseDoppler,
+    CsStrategy::SingleFrequency,
+];
+
 #[derive(Default, Debug, Clone, PartialEq)]
 pub enum CsSelectionMethod {
     /// Selects most suited method automatically
@@ -32,383 +49,730 @@ pub enum CsSelectionMethod {
     Manual(CsStrategy),
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Clone)]
 pub struct CsDetector {
     /// CS detection strategy
+    #[deprecated(note = "strategies are selected per Sv, through `method`")]
     pub strategy: CsStrategy,
-    /// CS strategy selection method
+    /// CS strategy selection method.
+    /// Strategies are selected for each individual Sv,
+    /// depending on the signals it was observed on.
     pub method: CsSelectionMethod,
+    /// Geometry free jump threshold [m], between two successive epochs
+    pub gf_threshold: f64,
+    /// Melbourne-Wübbena threshold [wide lane cycles],
+    /// with respect to the running average of the current arc
+    pub mw_threshold: f64,
+    /// Phase against integrated doppler threshold [cycles.s⁻¹]:
+    /// doppler observations are noisy, the threshold is proportional
+    /// to the sampling interval
+    pub doppler_threshold: f64,
+    /// Code minus phase jump threshold [m], between two successive epochs
+    pub cmc_threshold: f64,
+    /// Data gap above which a new arc starts. Gaps are not reported as slips
+    pub max_gap: Duration,
+    /// Number of epochs averaged on each side of a slip, when repairing it
+    pub repair_window: usize,
+    /// Maximal distance to the nearest integer [cycles],
+    /// for a slip estimate to be accepted when repairing
+    pub repair_tolerance: f64,
 }
 
-impl CsDetector {
-    /// Returns list of Epoch where detector determined a CS happened
-    /// rnx: should be Observation RINEX
-    pub fn cs_detection(&mut self, rnx: &Rinex) -> HashMap<Carrier, HashMap<Sv, Vec<Epoch>>> {
-        let ret: HashMap<Carrier, HashMap<Sv, Vec<Epoch>>> = HashMap::new();
-        let _permutation_allowed = match self.method {
-            CsSelectionMethod::Auto | CsSelectionMethod::Prefered(_) => true,
-            _ => false,
-        };
+impl Default for CsDetector {
+    #[allow(deprecated)]
+    fn default() -> Self {
+        Self {
+            strategy: CsStrategy::default(),
+            method: CsSelectionMethod::default(),
+            gf_threshold: 0.15,
+            mw_threshold: 4.0,
+            doppler_threshold: 1.5,
+            cmc_threshold: 5.0,
+            max_gap: Duration::from_seconds(300.0),
+            repair_window: 20,
+            repair_tolerance: 0.35,
+        }
+    }
+}
 
-        match self.method {
-            CsSelectionMethod::Auto
-            | CsSelectionMethod::Prefered(CsStrategy::GfAdvanced)
-            | CsSelectionMethod::Prefered(CsStrategy::GfSimple)
-            | CsSelectionMethod::Manual(CsStrategy::GfAdvanced)
-            | CsSelectionMethod::Manual(CsStrategy::GfSimple) => {
-                /*
-                 * Compute GF recombinations
-                 */
-                let iono = rnx.observation_iono_detector();
-                // op is feasible if at least two independent phase combinations were created
-                let mut _is_feasible = false;
-                let mut candidates: Vec<String> = Vec::with_capacity(iono.len());
-                let mut confirmed: Vec<String> = Vec::with_capacity(iono.len());
-                for (op, _) in &iono {
-                    let items: Vec<&str> = op.split("-").collect();
-                    let (_lhs_op, rhs_op) = (items[0], items[1]);
-                    if candidates.contains(&rhs_op.to_string())
-                        && !confirmed.contains(&rhs_op.to_string())
-                    {
-                        confirmed.push(rhs_op.to_string());
-                    } else {
-                        candidates.push(rhs_op.to_string());
+/// (phase carriers, phase carriers with doppler, phase carriers with code)
+type Signals = (Vec<Carrier>, Vec<Carrier>, Vec<Carrier>);
+
+/*
+ * Strategies feasible for each Sv, over the entire record
+ */
+fn feasibility(record: &Record) -> HashMap<Sv, Vec<CsStrategy>> {
+    let mut signals: HashMap<Sv, Signals> = HashMap::new();
+    for (_, svs) in record.values() {
+        for (sv, observations) in svs {
+            let entry = signals.entry(*sv).or_default();
+            for observable in observations.keys() {
+                if !observable.is_phase_observable() {
+                    continue;
+                }
+                let carrier = match observable.carrier(sv.constellation) {
+                    Ok(carrier) => carrier,
+                    Err(_) => continue,
+                };
+                if !entry.0.contains(&carrier) {
+                    entry.0.push(carrier);
+                }
+                for rhs in observations.keys() {
+                    if rhs.carrier(sv.constellation) != Ok(carrier) {
+                        continue;
+                    }
+                    if rhs.is_doppler_observable() && !entry.1.contains(&carrier) {
+                        entry.1.push(carrier);
+                    }
+                    if rhs.is_pseudorange_observable() && !entry.2.contains(&carrier) {
+                        entry.2.push(carrier);
                     }
                 }
-
-                println!(
-                    "cs_detection - combinations: {:?}",
-                    iono.keys().map(|s| format!("{} ", s)).collect::<String>()
-                ); //DEBUG
-                println!("{:?} - confirmed: {:?}", CsStrategy::GfSimple, confirmed); //DEBUG
-
-                // for each iono confirmed candidates,
-                //  determine if a 3rd phase combination exists
-                for op in confirmed {
-                    let ops: Vec<_> = iono
-                        .keys()
-                        .filter_map(|k| if k.contains(&op) { Some(k) } else { None })
-                        .collect();
-                    if ops.len() > 0 {
-                        println!("{:?} - is possible for \"{}\"", CsStrategy::GfAdvanced, op);
-                        // DEBUG
+            }
+        }
+    }
+    signals
+        .iter()
+        .map(|(sv, (phase, doppler, code))| {
+            let mut feasible: Vec<CsStrategy> = Vec::new();
+            match sv.constellation {
+                Constellation::Glonass => {
+                    // FDMA: carrier frequencies are not known at this point
+                    if !doppler.is_empty() {
+                        feasible.push(CsStrategy::PhaseDoppler);
                     }
+                    return (*sv, feasible);
+                },
+                Constellation::Geo | Constellation::SBAS(_) => {
+                    return (*sv, feasible);
+                },
+                _ => {},
+            }
+            if phase.len() > 2 {
+                feasible.push(CsStrategy::GfAdvanced);
+            }
+            if phase.len() > 1 {
+                feasible.push(CsStrategy::GfSimple);
+                if code.len() > 1 {
+                    feasible.push(CsStrategy::Mw);
                 }
-            },
-            _ => {},
+            }
+            if !doppler.is_empty() {
+                feasible.push(CsStrategy::PhaseDoppler);
+            }
+            if !code.is_empty() {
+                feasible.push(CsStrategy::SingleFrequency);
+            }
+            (*sv, feasible)
+        })
+        .collect()
+}
+
+/*
+ * Epochs where two successive values differ by more than threshold.
+ * A new arc starts after a data gap.
+ */
+fn jumps(
+    series: &BTreeMap<(Epoch, EpochFlag), f64>,
+    threshold: f64,
+    max_gap: Duration,
+) -> Vec<Epoch> {
+    let mut ret: Vec<Epoch> = Vec::new();
+    let mut prev: Option<(Epoch, f64)> = None;
+    for ((t, _), value) in series {
+        if let Some((prev_t, prev_value)) = prev {
+            if *t - prev_t <= max_gap && (value - prev_value).abs() > threshold {
+                ret.push(*t);
+            }
         }
-        ret
+        prev = Some((*t, *value));
     }
+    ret
 }
+
 /*
-    fn single_frequency_cs_detection(
-        &self,
-        sample_rate: Duration,
-        opts: CsOpts,
-    ) -> BTreeMap<Epoch, HashMap<Sv, String>> {
-        let mut ret: BTreeMap<Epoch, HashMap<Sv, String>> = BTreeMap::new();
-        // 1. Form Phase/PR recombinations
-        // 2. Study recombination evolution over time
-        let mut prev_data: HashMap<Sv, (Epoch, f64)> = HashMap::new();
-        if let Some(r) = self.record.as_obs() {
-            for ((epoch, _), (_, svs)) in r {
-                for (sv, observations) in svs {
-                    for (lhs_code, lhs_data) in observations {
-                        let lhs_carrier = &lhs_code[1..1];
-                        if is_phase_observation(lhs_code) {
-                            // locate a PR OBS
-                            for (rhs_code, rhs_data) in observations {
-                                let rhs_carrier = &rhs_code[1..];
-                                if lhs_carrier == rhs_carrier {
-                                    if is_pseudorange_observation(rhs_code) {
-                                        let recomb_y = lhs_data.obs - rhs_data.obs;
-                                        //let op = rhs_code[1..].to_owned() + &lhs_code[1..];
-                                        if let Some((p_epoch, p_data)) = prev_data.get_mut(sv) {
-                                            let dy = recomb_y - *p_data;
-                                            let dt = *epoch - *p_epoch;
-                                            if dy.abs() > opts.sensitivity(dt, sample_rate) {
-                                                println!(
-                                                    "{} (dt={}) - GAP DETECTED |{} - {}| = {}",
-                                                    epoch,
-                                                    dt,
-                                                    recomb_y,
-                                                    p_data,
-                                                    dy.abs()
-                                                ); //DEBUG
-                                            }
-                                        } else {
-                                            prev_data.insert(*sv, (*epoch, recomb_y));
-                                        }
-                                    }
-                                }
-                            }
-                        }
+ * Epochs where the value departs from the running average of the current arc,
+ * by more than max(threshold, 4σ). A new arc starts on each detection
+ * and after a data gap.
+ */
+fn outliers(
+    series: &BTreeMap<(Epoch, EpochFlag), f64>,
+    threshold: f64,
+    max_gap: Duration,
+) -> Vec<Epoch> {
+    let mut ret: Vec<Epoch> = Vec::new();
+    // (last epoch, number of samples, mean, sum of squared deviations)
+    let mut arc: Option<(Epoch, f64, f64, f64)> = None;
+    for ((t, _), value) in series {
+        let restart = match arc {
+            Some((last, n, mean, m2)) => {
+                if *t - last > max_gap {
+                    true
+                } else {
+                    let sigma = if n > 1.0 {
+                        (m2 / (n - 1.0)).sqrt()
+                    } else {
+                        0.0
+                    };
+                    if (value - mean).abs() > threshold.max(4.0 * sigma) {
+                        ret.push(*t);
+                        true
+                    } else {
+                        false
                     }
                 }
-            }
+            },
+            None => true,
+        };
+        if restart {
+            arc = Some((*t, 1.0, *value, 0.0));
+        } else if let Some((last, n, mean, m2)) = arc.as_mut() {
+            // Welford
+            *last = *t;
+            *n += 1.0;
+            let delta = value - *mean;
+            *mean += delta / *n;
+            *m2 += delta * (value - *mean);
         }
-        ret
     }
-*/
+    ret
+}
+
+/// Combined signals, per Sv, over time
+type Combinations =
+    HashMap<(Observable, Observable), BTreeMap<Sv, BTreeMap<(Epoch, EpochFlag), f64>>>;
 
 /*
-/*
- * Selects an algorithm for each vehicle and each observation
+ * Melbourne-Wübbena combinations [m]: wide lane phase minus narrow lane code.
+ * Geometry, clocks and ionosphere cancel out, the wide lane ambiguity remains.
  */
-pub (crate)fn select_algorithm(&self, consistency: u8, sv: Sv, record: &Record) -> HashMap<Sv, HashMap<String, bool>> {
-    let mut count: HashMap<String, (u32, u32)>; // (nb_epoch_seen, total_nb_epoch) per code
-    let total_epochs = record.len();
-    for ((epoch, _), (_, svs)) in record {
-        for (sv, observations) in svs {
-            let mut remaining = observations.keys().collect();
-            for (code, (seen, total)) in count {
-                if let Some(_) = observations.get(code) {
-                    // code is seen again
-                    seen += 1;
-                    remaining.remove(code);
+fn melbourne_wubbena(record: &Record) -> Combinations {
+    let mut ret: Combinations = HashMap::new();
+    for (epoch, (_, vehicles)) in record {
+        for (sv, observations) in vehicles {
+            for (lhs_observable, lhs_data) in observations {
+                if !lhs_observable.is_phase_observable() {
+                    continue; // phase against phase, code is then associated
                 }
-                total += 1;
-            }
-            for k in remaining {
-                if count.get(k).is_none() {
-                    // new code, never before seen
-                    count.insert(k.to_string(), (1, 1));
+                let lhs_code = lhs_observable.to_string();
+                let lhs_carrier = &lhs_code[1..2];
+
+                // determine another carrier
+                let rhs_carrier = match lhs_carrier {
+                    // this will restrict combinations to
+                    "1" => "2", // 1 against 2
+                    _ => "1",   // M > 1 against 1
+                };
+
+                // locate a reference phase against another carrier
+                let mut reference: Option<(Observable, f64)> = None;
+                for (ref_observable, ref_data) in observations {
+                    if !ref_observable.is_phase_observable() {
+                        continue;
+                    }
+                    let refcode = ref_observable.to_string();
+                    let carrier_code = &refcode[1..2];
+                    if carrier_code == rhs_carrier {
+                        reference = Some((ref_observable.clone(), ref_data.obs));
+                        break; // DONE searching
+                    }
                 }
-            }
-        }
 
-        /*
-         * if all codes are already above threshold
-         * => end this loop early
-         * ==> makes decision process quickness related to designed consistency
-         */
-        let mut above = true;
-        for (code, (seen, _)) in count {
-            above &= (seen * 100 / total_epochs as u32) > consistency as u32;
-        }
-        if above {
-            break;
+                let (ref_observable, ref_data) = match reference {
+                    Some(reference) => reference,
+                    None => continue,
+                };
+
+                // locate pseudo ranges, on both carriers:
+                // same code is preferred
+                let pseudo_range = |phase: &Observable| -> Option<f64> {
+                    let phase_code = phase.to_string();
+                    let same_code = "C".to_owned() + &phase_code[1..];
+                    let mut ret: Option<f64> = None;
+                    for (observable, data) in observations {
+                        if !observable.is_pseudorange_observable() {
+                            continue;
+                        }
+                        let code = observable.to_string();
+                        if code == same_code {
+                            return Some(data.obs);
+                        }
+                        if code[1..2] == phase_code[1..2] && ret.is_none() {
+                            ret = Some(data.obs);
+                        }
+                    }
+                    ret
+                };
+
+                let (lhs_pr, ref_pr) =
+                    match (pseudo_range(lhs_observable), pseudo_range(&ref_observable)) {
+                        (Some(lhs_pr), Some(ref_pr)) => (lhs_pr, ref_pr),
+                        _ => continue,
+                    };
+
+                let (lhs_freq, ref_freq) = match (
+                    lhs_observable.carrier(sv.constellation),
+                    ref_observable.carrier(sv.constellation),
+                ) {
+                    (Ok(lhs), Ok(rhs)) => (lhs.frequency(), rhs.frequency()),
+                    _ => continue,
+                };
+
+                // wide lane phase - narrow lane code
+                let mw = SPEED_OF_LIGHT * (lhs_data.obs - ref_data) / (lhs_freq - ref_freq)
+                    - (lhs_freq * lhs_pr + ref_freq * ref_pr) / (lhs_freq + ref_freq);
+
+                if let Some(data) = ret.get_mut(&(lhs_observable.clone(), ref_observable.clone())) {
+                    if let Some(data) = data.get_mut(sv) {
+                        data.insert(*epoch, mw);
+                    } else {
+                        let mut bmap: BTreeMap<(Epoch, EpochFlag), f64> = BTreeMap::new();
+                        bmap.insert(*epoch, mw);
+                        data.insert(*sv, bmap);
+                    }
+                } else {
+                    // new combination
+                    let mut inject = true; // insert only if not already combined to some other signal
+                    for (lhs, rhs) in ret.keys() {
+                        if lhs == lhs_observable {
+                            inject = false;
+                            break;
+                        }
+                        if rhs == lhs_observable {
+                            inject = false;
+                            break;
+                        }
+                    }
+                    if inject {
+                        let mut bmap: BTreeMap<(Epoch, EpochFlag), f64> = BTreeMap::new();
+                        bmap.insert(*epoch, mw);
+                        let mut map: BTreeMap<Sv, BTreeMap<(Epoch, EpochFlag), f64>> =
+                            BTreeMap::new();
+                        map.insert(*sv, bmap);
+                        ret.insert((lhs_observable.clone(), ref_observable), map);
+                    }
+                }
+            }
         }
     }
-    count.iter()
-        .map(|(code, (seen, total))| {
-            (*code, (seen * 100 / total) > consistency as u32)
-        })
-        .collect()
+    ret
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum CsAlgorithm {
-    /// Geometry Free reombination based method.
-    /// Is only feasible if carrier phase was estimated for at least
-    /// 3 carrier signals.
-    GfCombinationAdvanced,
-    /// Geometry Free reombination based method.
-    /// This method is possible on modern RINEX where carrier phase was estimated
-    /// for at least two carrier signals.
-    /// The lower the sampling rate, the better this method will work.
-    /// This is the prefered method amongst all other
-    GfCombinationSimple,
-    /// Melbourne Wubbena recombination based method.
-    /// This method is possible on modern RINEX where carrier phase was estimated
-    /// for at least two carrier signals.
-    /// This is the preferred method.
-    MwCombination,
-    /// Preferred Fallback method for old RINEX context
-    /// or 1D contexts.
-    SingleFrequencyDoppler,
-    /// Fallback method, always feasible even on old RINEX
-    /// or single channel receivers.
-    /// Highly subject to parameters fine tuning
-    SingleFrequencyRecombination,
-}
-
-impl Default for CsAlgorithm {
-    fn default() -> Self {
-        Self::GfCombination
+/*
+ * Inserts a detection
+ */
+fn report(
+    ret: &mut HashMap<Carrier, HashMap<Sv, Vec<Epoch>>>,
+    carrier: Carrier,
+    sv: Sv,
+    epoch: Epoch,
+) {
+    let epochs = ret.entry(carrier).or_default().entry(sv).or_default();
+    if !epochs.contains(&epoch) {
+        epochs.push(epoch);
     }
 }
 
-impl Into<u8> for CsAlgorithm {
-    fn into(&self) -> u8 {
-        match self {
-            Self::GfCombinationAdvanced => 255,
-            Self::GfCombinationSimple => 255,
-            Self::MwCombinationMultipath => 128,
-            Self::MwCombination => 64,
-            Self::SingleFrequencyDoppler => 8,
-            Self::SingleFrequencyRecombination => 1,
+impl CsDetector {
+    /// Returns detector with given selection method
+    pub fn with_method(&self, method: CsSelectionMethod) -> Self {
+        let mut s = self.clone();
+        s.method = method;
+        s
+    }
+    /// Returns detector with given geometry free threshold [m]
+    pub fn with_gf_threshold(&self, threshold: f64) -> Self {
+        let mut s = self.clone();
+        s.gf_threshold = threshold;
+        s
+    }
+    /// Returns detector with given Melbourne-Wübbena threshold [wide lane cycles]
+    pub fn with_mw_threshold(&self, threshold: f64) -> Self {
+        let mut s = self.clone();
+        s.mw_threshold = threshold;
+        s
+    }
+    /// Returns detector with given phase / doppler threshold [cycles.s⁻¹]
+    pub fn with_doppler_threshold(&self, threshold: f64) -> Self {
+        let mut s = self.clone();
+        s.doppler_threshold = threshold;
+        s
+    }
+    /// Returns detector with given code minus phase threshold [m]
+    pub fn with_cmc_threshold(&self, threshold: f64) -> Self {
+        let mut s = self.clone();
+        s.cmc_threshold = threshold;
+        s
+    }
+    /// Returns detector with given maximal data gap
+    pub fn with_max_gap(&self, max_gap: Duration) -> Self {
+        let mut s = self.clone();
+        s.max_gap = max_gap;
+        s
+    }
+    /// Returns detector with given repair window [epochs]
+    pub fn with_repair_window(&self, window: usize) -> Self {
+        let mut s = self.clone();
+        s.repair_window = window;
+        s
+    }
+    /// Returns detector with given repair tolerance [cycles]
+    pub fn with_repair_tolerance(&self, tolerance: f64) -> Self {
+        let mut s = self.clone();
+        s.repair_tolerance = tolerance;
+        s
+    }
+    /*
+     * Selects a strategy, among feasible strategies
+     */
+    fn select(&self, feasible: &[CsStrategy]) -> Option<CsStrategy> {
+        let auto = || PREFERENCE.iter().find(|s| feasible.contains(s)).copied();
+        match self.method {
+            CsSelectionMethod::Auto => auto(),
+            CsSelectionMethod::Prefered(strategy) => {
+                if feasible.contains(&strategy) {
+                    Some(strategy)
+                } else {
+                    auto()
+                }
+            },
+            CsSelectionMethod::Manual(strategy) => {
+                if feasible.contains(&strategy) {
+                    Some(strategy)
+                } else {
+                    None
+                }
+            },
         }
     }
-}
-
-//impl PartialOrd for CsAlgorithm {}
+    /// Returns list of Epoch where detector determined a CS happened,
+    /// per carrier signal and per Sv.
+    /// rnx: should be Observation RINEX
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::cs::{CsDetector, CsSelectionMethod, CsStrategy};
+    /// let rnx = Rinex::from_file("../test_resources/OBS/V3/DUTH0630.22O")
+    ///     .unwrap();
+    /// let detector = CsDetector::default()
+    ///     .with_method(CsSelectionMethod::Prefered(CsStrategy::Mw));
+    /// for (carrier, vehicles) in detector.cs_detection(&rnx) {
+    ///     for (sv, epochs) in vehicles {
+    ///         // slips detected on this signal, for this Sv
+    ///     }
+    /// }
+    /// ```
+    pub fn cs_detection(&self, rnx: &Rinex) -> HashMap<Carrier, HashMap<Sv, Vec<Epoch>>> {
+        match rnx.record.as_obs() {
+            Some(record) => self.detection(record),
+            None => HashMap::new(),
+        }
+    }
+    /*
+     * Runs all selected strategies on given record
+     */
+    fn detection(&self, record: &Record) -> HashMap<Carrier, HashMap<Sv, Vec<Epoch>>> {
+        let mut ret: HashMap<Carrier, HashMap<Sv, Vec<Epoch>>> = HashMap::new();
+        let strategies: HashMap<Sv, CsStrategy> = feasibility(record)
+            .iter()
+            .filter_map(|(sv, feasible)| self.select(feasible).map(|s| (*sv, s)))
+            .collect();
+        let uses =
+            |strategies_: &[CsStrategy]| strategies.values().any(|s| strategies_.contains(s));
 
-impl CsAlgorithm {
-    /// Returns true if self is feasible, on given context
-    pub (crate)fn is_feasible(&self, context: &HashMap<String, ObservationData>) -> bool {
-        match self {
-            Self::SingleFrequencyRecombination => {
-                for (code, _) in context {
-                    let carrier_nb = &code[1..1];
-                    if is_phase_carrier_obs_code!(code) {
-                        // search for a PR code
-                        for (others, _) in context {
-                            let other_carrier = &code[1..1];
-                            if others != code { // different code
-                                if carrier_nb == other_carrier { // same carrier
-                                    if is_pseudo_range_obs_code!(code) {
-                                        return true;
-                                    }
-                                }
-                            }
+        if uses(&[CsStrategy::GfAdvanced, CsStrategy::GfSimple]) {
+            self.gf_detection(record, &strategies, &mut ret);
+        }
+        if uses(&[CsStrategy::Mw]) {
+            self.mw_detection(record, &strategies, &mut ret);
+        }
+        if uses(&[CsStrategy::PhaseDoppler, CsStrategy::SingleFrequency]) {
+            self.single_frequency_detection(record, &strategies, &mut ret);
+        }
+        for svs in ret.values_mut() {
+            for epochs in svs.values_mut() {
+                epochs.sort();
+            }
+        }
+        ret
+    }
+    /// Runs [Self::cs_detection] and writes every detection back into
+    /// the LLI flags (LliFlags::LOCK_LOSS) of the phase observations of
+    /// the slipped signal, so further processing resets its state.
+    pub fn cs_detection_mut(&self, rnx: &mut Rinex) -> HashMap<Carrier, HashMap<Sv, Vec<Epoch>>> {
+        let ret = self.cs_detection(rnx);
+        if let Some(record) = rnx.record.as_mut_obs() {
+            for ((t, _), (_, svs)) in record.iter_mut() {
+                for (sv, observations) in svs.iter_mut() {
+                    for (observable, data) in observations.iter_mut() {
+                        if !observable.is_phase_observable() {
+                            continue;
                         }
-                    } else if is_pseudo_range_obs_code!(code) {
-                        // search for a PH code
-                        for (others, _) in context {
-                            let other_carrier = &code[1..1];
-                            if others != code { // different code
-                                if carrier_nb == other_carrier { // same carrier
-                                    if is_phase_carrier_obs_code!(code) {
-                                        return true;
-                                    }
-                                }
-                            }
+                        let slipped = observable
+                            .carrier(sv.constellation)
+                            .ok()
+                            .and_then(|carrier| ret.get(&carrier))
+                            .and_then(|svs| svs.get(sv))
+                            .map(|epochs| epochs.binary_search(t).is_ok())
+                            .unwrap_or(false);
+                        if slipped {
+                            data.lli = Some(
+                                data.lli.unwrap_or(LliFlags::OK_OR_UNKNOWN) | LliFlags::LOCK_LOSS,
+                            );
                         }
                     }
                 }
-            },
-            Self::SingleFrequencyDoppler => {
-                let single_freq_ok = CsAlgorithm::SingleFrequency.is_feasible(context);
-                if !single_freq_ok {
-                    return false;
-                }
-                // search for doppler obs
-                for (code, _) in context {
-                    if is_doppler_obs_code!(code) {
-                        return true;
+            }
+        }
+        ret
+    }
+    /*
+     * Geometry free detection. With several combinations (GfAdvanced),
+     * the slip is attributed to the carrier shared by all jumping combinations,
+     * otherwise both carriers are reported.
+     */
+    fn gf_detection(
+        &self,
+        record: &Record,
+        strategies: &HashMap<Sv, CsStrategy>,
+        ret: &mut HashMap<Carrier, HashMap<Sv, Vec<Epoch>>>,
+    ) {
+        // per Sv: pairs present at each epoch, jumping pairs at each epoch
+        let mut present: HashMap<Sv, BTreeMap<Epoch, Vec<(Carrier, Carrier)>>> = HashMap::new();
+        let mut jumping: HashMap<Sv, BTreeMap<Epoch, Vec<(Carrier, Carrier)>>> = HashMap::new();
+        for ((lhs, rhs), vehicles) in record.geo_free() {
+            if !lhs.is_phase_observable() {
+                continue;
+            }
+            for (sv, series) in vehicles {
+                let advanced = match strategies.get(&sv) {
+                    Some(CsStrategy::GfAdvanced) => true,
+                    Some(CsStrategy::GfSimple) => false,
+                    _ => continue,
+                };
+                let pair = match (lhs.carrier(sv.constellation), rhs.carrier(sv.constellation)) {
+                    (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+                    _ => continue,
+                };
+                for t in jumps(&series, self.gf_threshold, self.max_gap) {
+                    if advanced {
+                        jumping
+                            .entry(sv)
+                            .or_default()
+                            .entry(t)
+                            .or_default()
+                            .push(pair);
+                    } else {
+                        report(ret, pair.0, sv, t);
+                        report(ret, pair.1, sv, t);
                     }
                 }
-            },
-            Self::GfCombinationSimple => {
-                // identify at least 2 Carrier Phase
-                let mut nb_phase = 0;
-                for (code, _) in context {
-                    if is_phase_carrier_obs_code!(code) {
-                        nb_phase += 1;
+                if advanced {
+                    for (t, _) in series.keys() {
+                        present
+                            .entry(sv)
+                            .or_default()
+                            .entry(*t)
+                            .or_default()
+                            .push(pair);
                     }
                 }
-            },
-            Self::GfCombinationAdvanced => {
-                let simple_ok = CsAlgorithm::GfCombinationSimple.is_feasible(context);
-                if !simple_ok {
-                    return false;
+            }
+        }
+        for (sv, epochs) in jumping {
+            for (t, jumped) in epochs {
+                let others: Vec<(Carrier, Carrier)> = present
+                    .get(&sv)
+                    .and_then(|epochs| epochs.get(&t))
+                    .map(|pairs| {
+                        pairs
+                            .iter()
+                            .filter(|p| !jumped.contains(p))
+                            .copied()
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let mut carriers: Vec<Carrier> = Vec::new();
+                for (lhs, rhs) in &jumped {
+                    for carrier in [*lhs, *rhs] {
+                        if !carriers.contains(&carrier) {
+                            carriers.push(carrier);
+                        }
+                    }
                 }
-                // identify at least 3 different PH observations
-                let mut phase_codes = 0;
-                for (code, _) in context {
-                    if is_phase_carrier_obs_code!(code) {
-                        phase_codes += 1;
+                // carriers that are part of a steady combination did not slip
+                let mut flagged: Vec<Carrier> = carriers
+                    .iter()
+                    .filter(|c| !others.iter().any(|(lhs, rhs)| lhs == *c || rhs == *c))
+                    .copied()
+                    .collect();
+                // a carrier shared by all jumping combinations explains them all
+                if jumped.len() > 1 {
+                    let shared: Vec<Carrier> = flagged
+                        .iter()
+                        .filter(|c| jumped.iter().all(|(lhs, rhs)| lhs == *c || rhs == *c))
+                        .copied()
+                        .collect();
+                    if !shared.is_empty() {
+                        flagged = shared;
                     }
                 }
-                return phase_codes > 2;
-            },
-            Self::MwCombination => {
-
+                if flagged.is_empty() {
+                    flagged = carriers;
+                }
+                for carrier in flagged {
+                    report(ret, carrier, sv, t);
+                }
             }
-            _ => false,
         }
-        false
     }
-}
-*/
-
-/*
-#[derive(Debug, Clone)]
-pub enum CsMethod {
-    /// The calculator decides which method to use,
-    /// by order of preference and decides to use this method
-    /// for all vehicles in the provided context
-    Auto,
-    /// The calculator elects 1 method that suites best
-    /// every single vehicle context
-    AutoSv,
-    /// The calculator uses given algorithm for every single Sv
-    Prefered(CsAlgorithm),
-    /// The calculator uses given algorithm if 1 method
-    /// has not been determined as better suited.
-    PreferedSv(CsAlgorithm),
-}*/
-
-/*
-#[derive(Debug, Clone)]
-pub struct OptsThreshold {
-    pub a0: f64,
-    pub a1: f64,
-}
-
-impl Default for OptsThreshold {
-    /// Builds defaults OptsThreshold, best suited for L1/L2 studies
-    /// with ~1min observation interval
-    fn default() -> Self {
-        let a0 = 3.0 * (Carrier::L2.carrier_wavelength() - Carrier::L1.carrier_wavelength()) / 2.0;
-        Self { a0, a1: a0 / 2.0 }
+    /*
+     * Melbourne-Wübbena detection: both carriers are reported
+     */
+    fn mw_detection(
+        &self,
+        record: &Record,
+        strategies: &HashMap<Sv, CsStrategy>,
+        ret: &mut HashMap<Carrier, HashMap<Sv, Vec<Epoch>>>,
+    ) {
+        for ((lhs, rhs), vehicles) in melbourne_wubbena(record) {
+            for (sv, series) in vehicles {
+                if strategies.get(&sv) != Some(&CsStrategy::Mw) {
+                    continue;
+                }
+                let pair = match (lhs.carrier(sv.constellation), rhs.carrier(sv.constellation)) {
+                    (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+                    _ => continue,
+                };
+                let wide_lane = SPEED_OF_LIGHT / (pair.0.frequency() - pair.1.frequency()).abs();
+                for t in outliers(&series, self.mw_threshold * wide_lane, self.max_gap) {
+                    report(ret, pair.0, sv, t);
+                    report(ret, pair.1, sv, t);
+                }
+            }
+        }
     }
-}
-
-#[derive(Debug, Clone)]
-pub struct CsOpts {
-    // /// Decision method
-    // pub method: CsMethod,
-    // /// Percentage of epoch of the total record
-    // /// the method should remain feasible for it to be elected.
-    // /// A lower percentage gives quicker election process.
-    // pub method_consistency_pct: u8,
-    /// Sample rate dependent threshold,
-    /// in the form a0 - a1 e^-dt
-    /// to control the detector sensitivity against symbol rate
-    pub threshold: OptsThreshold,
-}
-
-impl Default for CsOpts {
-    fn default() -> Self {
-        Self {
-            /*method: CsMethod::Auto,
-            method_consistency_pct: 33,
-            threshold: OptsThreshold::default(),*/
+    /*
+     * Single frequency detections: phase against doppler
+     * or phase against pseudo range, on each carrier signal
+     */
+    fn single_frequency_detection(
+        &self,
+        record: &Record,
+        strategies: &HashMap<Sv, CsStrategy>,
+        ret: &mut HashMap<Carrier, HashMap<Sv, Vec<Epoch>>>,
+    ) {
+        // (epoch, phase [cycles], doppler [Hz] or code minus phase [m])
+        let mut prev: HashMap<(Sv, Observable), (Epoch, f64, f64)> = HashMap::new();
+        for ((t, _), (_, svs)) in record {
+            for (sv, observations) in svs {
+                let doppler = match strategies.get(sv) {
+                    Some(CsStrategy::PhaseDoppler) => true,
+                    Some(CsStrategy::SingleFrequency) => false,
+                    _ => continue,
+                };
+                for (observable, data) in observations {
+                    if !observable.is_phase_observable() {
+                        continue;
+                    }
+                    let carrier = match observable.carrier(sv.constellation) {
+                        Ok(carrier) => carrier,
+                        Err(_) => continue,
+                    };
+                    // associated observation: same code is preferred
+                    let code = observable.to_string();
+                    let same_code = if doppler { "D" } else { "C" }.to_owned() + &code[1..];
+                    let associated = observations
+                        .iter()
+                        .filter(|(rhs, _)| {
+                            let kind = if doppler {
+                                rhs.is_doppler_observable()
+                            } else {
+                                rhs.is_pseudorange_observable()
+                            };
+                            kind && rhs.carrier(sv.constellation) == Ok(carrier)
+                        })
+                        .min_by_key(|(rhs, _)| rhs.to_string() != same_code)
+                        .map(|(_, data)| data.obs);
+                    let associated = match associated {
+                        Some(associated) => associated,
+                        None => continue,
+                    };
+                    let value = if doppler {
+                        associated
+                    } else {
+                        associated - data.obs * carrier.wavelength()
+                    };
+                    let key = (*sv, observable.clone());
+                    if let Some((prev_t, prev_phase, prev_value)) = prev.get(&key) {
+                        let dt = *t - *prev_t;
+                        if dt <= self.max_gap {
+                            let slipped = if doppler {
+                                // positive doppler: approaching vehicle, decreasing phase
+                                let predicted = -(prev_value + value) / 2.0 * dt.to_seconds();
+                                let residual = data.obs - prev_phase - predicted;
+                                residual.abs() > self.doppler_threshold * dt.to_seconds()
+                            } else {
+                                (value - prev_value).abs() > self.cmc_threshold
+                            };
+                            if slipped {
+                                report(ret, carrier, *sv, *t);
+                            }
+                        }
+                    }
+                    prev.insert(key, (*t, data.obs, value));
+                }
+            }
         }
     }
 }
 
-impl CsOpts {
-    /*pub fn with_method(&self, method: CsMethod) -> Self {
-        let mut s = self.clone();
-        s.method = method;
-        s
-    }*/
-    /*
-    pub fn with_threshold(&self, threshold: OptsThreshold) -> Self {
-        let mut s = self.clone();
-        s.threshold = threshold;
-        s
+#[cfg(test)]
+mod test {
+    use super::*;
+    fn series(values: &[(f64, f64)]) -> BTreeMap<(Epoch, EpochFlag), f64> {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        values
+            .iter()
+            .map(|(dt, value)| ((t0 + Duration::from_seconds(*dt), EpochFlag::Ok), *value))
+            .collect()
     }
-    */
-    /*
-    /// Returns detector sensitivity at current context, in meter,
-    /// based on given (a0, a1) parameters
-    pub fn sensitivity(&self, dt: Duration, sample_rate: Duration) -> f64 {
-        self.threshold.a0
-            - self.threshold.a1
-                * (-dt.to_unit(hifitime::Unit::Second)
-                    / sample_rate.to_unit(hifitime::Unit::Second))
-                .exp()
+    #[test]
+    fn jump_detection() {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        let max_gap = Duration::from_seconds(300.0);
+        let s = series(&[
+            (0.0, 1.00),
+            (30.0, 1.01),
+            (60.0, 1.50), // slip
+            (90.0, 1.51),
+            (1000.0, 3.0), // gap: new arc
+            (1030.0, 3.01),
+        ]);
+        assert_eq!(
+            jumps(&s, 0.15, max_gap),
+            vec![t0 + Duration::from_seconds(60.0)]
+        );
+        let mut values: Vec<(f64, f64)> = (0..20)
+            .map(|i| (30.0 * i as f64, if i % 2 == 0 { 0.1 } else { -0.1 }))
+            .collect();
+        values.push((600.0, 5.0)); // slip
+        values.push((630.0, 5.1));
+        let s = series(&values);
+        assert_eq!(
+            outliers(&s, 1.0, max_gap),
+            vec![t0 + Duration::from_seconds(600.0)]
+        );
+    }
+    #[test]
+    fn strategy_selection() {
+        let feasible = vec![CsStrategy::GfSimple, CsStrategy::Mw];
+        let detector = CsDetector::default();
+        assert_eq!(detector.select(&feasible), Some(CsStrategy::GfSimple));
+        let detector = detector.with_method(CsSelectionMethod::Prefered(CsStrategy::Mw));
+        assert_eq!(detector.select(&feasible), Some(CsStrategy::Mw));
+        let detector = detector.with_method(CsSelectionMethod::Prefered(CsStrategy::PhaseDoppler));
+        assert_eq!(detector.select(&feasible), Some(CsStrategy::GfSimple));
+        let detector = detector.with_method(CsSelectionMethod::Manual(CsStrategy::PhaseDoppler));
+        assert_eq!(detector.select(&feasible), None);
     }
-    */
-    /*
-    /// Returns detector sensitivity [in ideal data scenario], in meter,
-    /// based on given (a0, a1) parameters
-    pub fn best_sensitivity(&self) -> f64 {
-        self.threshold.a0 - self.threshold.a1 * (-1.0_f64).exp()
-    }*/
 }
-*/
@@ -0,0 +1,452 @@
+//! Cycle slip repair
+use super::CsDetector;
+use crate::observation::{LliFlags, Record};
+use crate::{prelude::*, Carrier, SPEED_OF_LIGHT};
+use std::collections::{BTreeSet, HashMap};
+
+/*
+ * Phase [cycles] and pseudo range [m] observations of one Sv, at one epoch
+ */
+#[derive(Debug, Clone)]
+struct Sample {
+    key: (Epoch, EpochFlag),
+    phase: HashMap<Observable, f64>,
+    code: HashMap<Carrier, f64>,
+}
+
+impl Sample {
+    fn t(&self) -> Epoch {
+        self.key.0
+    }
+    /*
+     * Geometry free combination [m] of two phase signals
+     */
+    fn gf(&self, lhs: &(Carrier, Observable), rhs: &(Carrier, Observable)) -> Option<f64> {
+        let l_lhs = self.phase.get(&lhs.1)?;
+        let l_rhs = self.phase.get(&rhs.1)?;
+        Some(lhs.0.wavelength() * l_lhs - rhs.0.wavelength() * l_rhs)
+    }
+    /*
+     * Melbourne-Wübbena combination [m] of two signals
+     */
+    fn mw(&self, lhs: &(Carrier, Observable), rhs: &(Carrier, Observable)) -> Option<f64> {
+        let l_lhs = self.phase.get(&lhs.1)?;
+        let l_rhs = self.phase.get(&rhs.1)?;
+        let c_lhs = self.code.get(&lhs.0)?;
+        let c_rhs = self.code.get(&rhs.0)?;
+        let (f_lhs, f_rhs) = (lhs.0.frequency(), rhs.0.frequency());
+        Some(
+            SPEED_OF_LIGHT * (l_lhs - l_rhs) / (f_lhs - f_rhs)
+                - (f_lhs * c_lhs + f_rhs * c_rhs) / (f_lhs + f_rhs),
+        )
+    }
+}
+
+/*
+ * Value of the linear trend of given (x, y) points, at x
+ */
+fn predict(points: &[(f64, f64)], x: f64) -> Option<f64> {
+    let n = points.len() as f64;
+    match points.len() {
+        0 => None,
+        1 => Some(points[0].1),
+        _ => {
+            let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+            let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+            let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+            let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+            if sxx > 0.0 {
+                Some(mean_y + sxy / sxx * (x - mean_x))
+            } else {
+                Some(mean_y)
+            }
+        },
+    }
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/*
+ * Rounds a float estimate, if it lies close enough to an integer
+ */
+fn fix(float: f64, tolerance: f64) -> Option<i64> {
+    let integer = float.round();
+    if (float - integer).abs() <= tolerance {
+        Some(integer as i64)
+    } else {
+        None
+    }
+}
+
+/*
+ * Most frequent phase observable of each carrier, sorted by decreasing frequency
+ */
+fn primary_signals(record: &Record, sv: Sv) -> Vec<(Carrier, Observable)> {
+    let mut counts: HashMap<(Carrier, Observable), usize> = HashMap::new();
+    for ((_, flag), (_, svs)) in record {
+        if *flag == EpochFlag::CycleSlip {
+            continue;
+        }
+        if let Some(observations) = svs.get(&sv) {
+            for observable in observations.keys() {
+                if !observable.is_phase_observable() {
+                    continue;
+                }
+                if let Ok(carrier) = observable.carrier(sv.constellation) {
+                    *counts.entry((carrier, observable.clone())).or_default() += 1;
+                }
+            }
+        }
+    }
+    let mut primaries: Vec<(Carrier, Observable)> = Vec::new();
+    let mut carriers: Vec<Carrier> = counts.keys().map(|(carrier, _)| *carrier).collect();
+    carriers.sort_by(|a, b| b.frequency().total_cmp(&a.frequency()));
+    carriers.dedup();
+    for carrier in carriers {
+        let primary = counts
+            .iter()
+            .filter(|((c, _), _)| *c == carrier)
+            .max_by(|((_, a), n_a), ((_, b), n_b)| n_a.cmp(n_b).then(b.cmp(a)))
+            .map(|((_, observable), _)| observable.clone());
+        if let Some(observable) = primary {
+            primaries.push((carrier, observable));
+        }
+    }
+    primaries
+}
+
+/*
+ * Time series of one Sv, EpochFlag::CycleSlip records excluded
+ */
+fn samples(record: &Record, sv: Sv, primaries: &[(Carrier, Observable)]) -> Vec<Sample> {
+    let mut ret: Vec<Sample> = Vec::new();
+    for ((t, flag), (_, svs)) in record {
+        if *flag == EpochFlag::CycleSlip {
+            continue;
+        }
+        let observations = match svs.get(&sv) {
+            Some(observations) => observations,
+            None => continue,
+        };
+        let phase: HashMap<Observable, f64> = observations
+            .iter()
+            .filter(|(observable, _)| observable.is_phase_observable())
+            .map(|(observable, data)| (observable.clone(), data.obs))
+            .collect();
+        if phase.is_empty() {
+            continue;
+        }
+        let mut code: HashMap<Carrier, f64> = HashMap::new();
+        for (carrier, observable) in primaries {
+            // pseudo range: same code is preferred
+            let same_code = "C".to_owned() + &observable.to_string()[1..];
+            let pr = observations
+                .iter()
+                .filter(|(rhs, _)| {
+                    rhs.is_pseudorange_observable() && rhs.carrier(sv.constellation) == Ok(*carrier)
+                })
+                .min_by_key(|(rhs, _)| rhs.to_string() != same_code)
+                .map(|(_, data)| data.obs);
+            if let Some(pr) = pr {
+                code.insert(*carrier, pr);
+            }
+        }
+        ret.push(Sample {
+            key: (*t, *flag),
+            phase,
+            code,
+        });
+    }
+    ret
+}
+
+impl CsDetector {
+    /// Detects and repairs cycle slips, directly on an Observation record.
+    /// Slips are searched among the detections of [Self::cs_detection],
+    /// the phase observations flagged with LliFlags::LOCK_LOSS,
+    /// and the vehicles reported by EpochFlag::CycleSlip events.
+    ///
+    /// The wide lane slip is estimated from the jump in the Melbourne-Wübbena
+    /// combination, the slip on the first carrier from the jump in the
+    /// Geometry Free combination, each side of the slip being averaged
+    /// (resp. extrapolated) over [Self::repair_window] epochs.
+    /// With a third carrier, its own wide lane combination is solved
+    /// and cross checked against the extra Geometry Free combination.
+    /// Other phase signals on the same carrier are then repaired against
+    /// the repaired one.
+    ///
+    /// Integer slips are removed from the `Phase` observations, until the end
+    /// of the arc, and the loss of lock indication is cleared.
+    /// Slips that could not be fixed to an integer value remain
+    /// flagged with LliFlags::LOCK_LOSS.
+    /// Single frequency vehicles, Glonass and geostationary vehicles
+    /// are not repaired.
+    /// EpochFlag::CycleSlip records are not observations: they are left untouched.
+    ///
+    /// Returns the repaired slips [cycles], per phase observable and per Sv.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::cs::CsDetector;
+    /// let mut rnx = Rinex::from_file("../test_resources/OBS/V3/DUTH0630.22O")
+    ///     .unwrap();
+    /// let record = rnx.record.as_mut_obs()
+    ///     .unwrap();
+    /// let repaired = CsDetector::default()
+    ///     .cs_repair_mut(record);
+    /// for (observable, vehicles) in repaired {
+    ///     for (sv, slips) in vehicles {
+    ///         for (epoch, cycles) in slips {
+    ///             // this amount of cycles was removed from epoch onwards
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn cs_repair_mut(
+        &self,
+        record: &mut Record,
+    ) -> HashMap<Observable, HashMap<Sv, Vec<(Epoch, i64)>>> {
+        let mut ret: HashMap<Observable, HashMap<Sv, Vec<(Epoch, i64)>>> = HashMap::new();
+        // candidates, per Sv
+        let mut candidates: HashMap<Sv, BTreeSet<Epoch>> = HashMap::new();
+        for svs in self.detection(record).values() {
+            for (sv, epochs) in svs {
+                candidates.entry(*sv).or_default().extend(epochs.iter());
+            }
+        }
+        for ((t, flag), (_, svs)) in record.iter() {
+            for (sv, observations) in svs {
+                let slipped = *flag == EpochFlag::CycleSlip
+                    || observations.iter().any(|(observable, data)| {
+                        observable.is_phase_observable()
+                            && data
+                                .lli
+                                .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                                .unwrap_or(false)
+                    });
+                if slipped {
+                    candidates.entry(*sv).or_default().insert(*t);
+                }
+            }
+        }
+
+        for (sv, epochs) in candidates {
+            match sv.constellation {
+                Constellation::Glonass | Constellation::Geo | Constellation::SBAS(_) => continue,
+                _ => {},
+            }
+            let primaries = primary_signals(record, sv);
+            if primaries.len() < 2 {
+                continue;
+            }
+            let mut samples = samples(record, sv, &primaries);
+            if samples.is_empty() {
+                continue;
+            }
+            // arc boundaries: first index of each arc
+            let mut boundaries: BTreeSet<usize> = BTreeSet::new();
+            boundaries.insert(0);
+            for i in 1..samples.len() {
+                if samples[i].t() - samples[i - 1].t() > self.max_gap {
+                    boundaries.insert(i);
+                }
+            }
+            // slips: first sample at or after each candidate
+            let slips: BTreeSet<usize> = epochs
+                .iter()
+                .map(|t| samples.partition_point(|s| s.t() < *t))
+                .filter(|i| *i < samples.len() && !boundaries.contains(i))
+                .collect();
+            let mut status: Vec<(usize, bool)> = Vec::with_capacity(slips.len());
+            for i in slips.iter() {
+                let i = *i;
+                let arc_start = *boundaries.range(..=i).next_back().unwrap();
+                let arc_end = boundaries
+                    .range(i + 1..)
+                    .next()
+                    .copied()
+                    .unwrap_or(samples.len());
+                let prev = slips.range(..i).next_back().copied().unwrap_or(arc_start);
+                let next = slips.range(i + 1..).next().copied().unwrap_or(arc_end);
+                let lo = prev
+                    .max(arc_start)
+                    .max(i.saturating_sub(self.repair_window));
+                let hi = next.min(arc_end).min(i + self.repair_window);
+                let slip = match self.estimate(
+                    &samples[lo..i],
+                    &samples[i..hi],
+                    &primaries,
+                    sv.constellation,
+                ) {
+                    Some(slip) => slip,
+                    None => {
+                        status.push((i, false));
+                        continue;
+                    },
+                };
+                for (observable, cycles) in slip {
+                    if cycles == 0 {
+                        continue;
+                    }
+                    for sample in samples[i..arc_end].iter_mut() {
+                        if let Some(value) = sample.phase.get_mut(&observable) {
+                            *value -= cycles as f64;
+                        }
+                    }
+                    ret.entry(observable)
+                        .or_default()
+                        .entry(sv)
+                        .or_default()
+                        .push((samples[i].t(), cycles));
+                }
+                status.push((i, true));
+            }
+            // write back
+            let status: HashMap<(Epoch, EpochFlag), bool> = status
+                .iter()
+                .map(|(i, repaired)| (samples[*i].key, *repaired))
+                .collect();
+            for sample in samples {
+                let observations = match record
+                    .get_mut(&sample.key)
+                    .and_then(|(_, svs)| svs.get_mut(&sv))
+                {
+                    Some(observations) => observations,
+                    None => continue,
+                };
+                let repaired = status.get(&sample.key);
+                for (observable, data) in observations.iter_mut() {
+                    if let Some(value) = sample.phase.get(observable) {
+                        data.obs = *value;
+                        let lli = data.lli.unwrap_or(LliFlags::OK_OR_UNKNOWN);
+                        match repaired {
+                            Some(true) => data.lli = Some(lli - LliFlags::LOCK_LOSS),
+                            Some(false) => data.lli = Some(lli | LliFlags::LOCK_LOSS),
+                            None => {},
+                        }
+                    }
+                }
+            }
+        }
+        for vehicles in ret.values_mut() {
+            for slips in vehicles.values_mut() {
+                slips.sort();
+            }
+        }
+        ret
+    }
+    /*
+     * Estimates the integer slip of each phase signal,
+     * between the samples preceding the slip and the samples following it.
+     * Returns None if one of the slips could not be fixed.
+     */
+    fn estimate(
+        &self,
+        before: &[Sample],
+        after: &[Sample],
+        primaries: &[(Carrier, Observable)],
+        constellation: Constellation,
+    ) -> Option<Vec<(Observable, i64)>> {
+        let t = after.first()?.t();
+        let tolerance = self.repair_tolerance;
+        // jump of a combination: averaged MW, extrapolated GF
+        let mw_jump = |lhs: &(Carrier, Observable), rhs: &(Carrier, Observable)| -> Option<f64> {
+            let mw = |samples: &[Sample]| -> Vec<f64> {
+                samples.iter().filter_map(|s| s.mw(lhs, rhs)).collect()
+            };
+            Some(mean(&mw(after))? - mean(&mw(before))?)
+        };
+        let gf_jump = |lhs: &(Carrier, Observable), rhs: &(Carrier, Observable)| -> Option<f64> {
+            let gf = |samples: &[Sample]| -> Vec<(f64, f64)> {
+                samples
+                    .iter()
+                    .filter_map(|s| s.gf(lhs, rhs).map(|gf| ((s.t() - t).to_seconds(), gf)))
+                    .collect()
+            };
+            Some(predict(&gf(after), 0.0)? - predict(&gf(before), 0.0)?)
+        };
+
+        let (first, second) = (&primaries[0], &primaries[1]);
+        let (lambda_1, lambda_2) = (first.0.wavelength(), second.0.wavelength());
+        let wide_lane = SPEED_OF_LIGHT / (first.0.frequency() - second.0.frequency());
+        let n_w = fix(mw_jump(first, second)? / wide_lane, tolerance)?;
+        let n_1 = fix(
+            (gf_jump(first, second)? - lambda_2 * n_w as f64) / (lambda_1 - lambda_2),
+            tolerance,
+        )?;
+        let mut ret: Vec<(Observable, i64)> =
+            vec![(first.1.clone(), n_1), (second.1.clone(), n_1 - n_w)];
+
+        for other in primaries.iter().skip(2) {
+            // signal not tracked around this slip
+            let tracked = |samples: &[Sample]| samples.iter().any(|s| s.gf(first, other).is_some());
+            if !tracked(before) || !tracked(after) {
+                continue;
+            }
+            let lambda = other.0.wavelength();
+            let wide_lane = SPEED_OF_LIGHT / (first.0.frequency() - other.0.frequency());
+            let n_w = fix(mw_jump(first, other)? / wide_lane, tolerance)?;
+            // cross check
+            let n = (gf_jump(first, other)? - lambda * n_w as f64) / (lambda_1 - lambda);
+            if (n - n_1 as f64).abs() > tolerance {
+                return None;
+            }
+            ret.push((other.1.clone(), n_1 - n_w));
+        }
+
+        // other signals, against the repaired signal of the same carrier
+        let current = after.first()?;
+        for observable in current.phase.keys() {
+            if ret.iter().any(|(repaired, _)| repaired == observable) {
+                continue;
+            }
+            let carrier = match observable.carrier(constellation) {
+                Ok(carrier) => carrier,
+                Err(_) => continue,
+            };
+            let (primary, n) = match primaries
+                .iter()
+                .find(|(c, _)| *c == carrier)
+                .and_then(|(_, p)| ret.iter().find(|(repaired, _)| repaired == p))
+            {
+                Some((primary, n)) => (primary.clone(), *n),
+                None => continue,
+            };
+            let diff = |s: &Sample| -> Option<f64> {
+                Some(s.phase.get(observable)? - s.phase.get(&primary)?)
+            };
+            let before = match before.iter().rev().find_map(diff) {
+                Some(before) => before,
+                None => continue,
+            };
+            let jump = diff(current)? - before;
+            ret.push((observable.clone(), n + fix(jump, tolerance)?));
+        }
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn linear_prediction() {
+        let points: Vec<(f64, f64)> = (1..10)
+            .map(|i| (-30.0 * i as f64, 1.0 - 0.01 * i as f64))
+            .collect();
+        assert!((predict(&points, 0.0).unwrap() - 1.0).abs() < 1.0E-9);
+        assert_eq!(predict(&points[..1], 0.0), Some(0.99));
+        assert_eq!(predict(&[], 0.0), None);
+    }
+    #[test]
+    fn integer_fix() {
+        assert_eq!(fix(3.1, 0.35), Some(3));
+        assert_eq!(fix(-2.8, 0.35), Some(-3));
+        assert_eq!(fix(4.5, 0.35), None);
+    }
+}
@@ -1,18 +1,16 @@
+//! Differential processing between two receivers
 use super::*;
-//use navigation::*;
+use crate::observation::{LliFlags, ObservationData, Record};
+use crate::preprocessing::Decimate;
+use std::collections::{BTreeMap, HashMap};
 use thiserror::Error;
-//use crate::observation::record::ObservationData;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("`base` data must be Observation RINEX for this operation")]
     NotObservationBase,
-    #[error("`base` data must be Navigation RINEX for this operation")]
-    NotNavigationBase,
     #[error("`rover` must be Observation RINEX for this operation")]
     NotObservationRover,
-    #[error("`rover` must be Navigation RINEX for this operation")]
-    NotNavigationRover,
     #[error("failed to parse RINEX data")]
     RinexError(#[from] super::Error),
 }
@@ -31,176 +29,400 @@ pub struct DiffContext {
     pub base: Rinex,
     /// "rover": other RINEX
     pub rover: Rinex,
+    /// Reference vehicles for double differences, per constellation.
+    /// When not defined (or not observed), the reference vehicle is
+    /// selected automatically.
+    pub references: HashMap<Constellation, Sv>,
+    /// Cycle slip events ([EpochFlag::CycleSlip]) declared by the rover,
+    /// that we do not retain in the record
+    cycle_slips: Vec<Epoch>,
+}
+
+/*
+ * Combines the LLI flags of both receivers
+ */
+fn lli(base: Option<LliFlags>, rover: Option<LliFlags>) -> Option<LliFlags> {
+    match (base, rover) {
+        (Some(base), Some(rover)) => Some(base | rover),
+        (Some(lli), None) | (None, Some(lli)) => Some(lli),
+        (None, None) => None,
+    }
+}
+
+/*
+ * Difference of two observations
+ */
+fn diff(lhs: &ObservationData, rhs: &ObservationData) -> ObservationData {
+    ObservationData {
+        obs: lhs.obs - rhs.obs,
+        lli: lli(rhs.lli, lhs.lli),
+        snr: match (lhs.snr, rhs.snr) {
+            (Some(lhs), Some(rhs)) => Some(lhs.min(rhs)),
+            _ => None,
+        },
+    }
 }
 
 impl DiffContext {
-    /// Builds a new DiffContext from two mutable RINEX.
+    /// Builds a new DiffContext from two Observation RINEX.
     /// Sample rate is reworked to perfectly match:
     /// non shared epochs are dropped, to facilitate further processing.
-    /// For Observation context: we only retain Phase and Pseudo Range observations,
-    /// as we don't know of Differential analysis involving other observations.
-    /// For Navigation context: we only retain Ephemeris frames,
-    /// as we don't know of Differential analysis involving other frames.
-    pub fn new(base: &Rinex, rover: &Rinex) -> Self {
-        let base = base.clone();
-        let rover = rover.clone();
-        /*
-            base.decim_match_mut(&rover);
-            rover.decim_match_mut(&base);
-            // match /adjust sample rates
-            // For Navigation RINEX
-            //  retain ephemeris frames only
-            base.retain_navigation_ephemeris_mut();
-            rover.retain_navigation_ephemeris_mut();
-            // For Observation RINEX
-            //  retain Phase and Pseudo Range observations only
-            //    and only shared vehicles
-            if let Some(record) = base.record.as_mut_obs() {
-                record.retain(|e, (_, vehicles)| {
-                    vehicles.retain(|sv, observations| {
-                        if let Some(obs_rov) = rover.record.as_obs() {
-                            let (_, rov_vehicles) = obs_rov.get(e)
-                                .unwrap();
-                            let mut shared = false;
-                            for (rov_sv, _) in rov_vehicles {
-                                shared |= rov_sv == sv;
-                            }
-                            if shared {
-                                observations.retain(|code, _| {
-                                    is_pseudo_range_obs_code!(code) || is_phase_carrier_obs_code!(code)
-                                });
-                                observations.len() > 0
-                            } else {
-                                false
-                            }
-                        } else if let Some(nav_rov) = rover.record.as_nav() {
-                            let mut shared = false;
-                            let rov_classes = nav_rov.get(e)
-                                .unwrap();
-                            for (rov_class, rov_frames) in rov_classes {
-                                for rov_frame in rov_frames {
-                                    let (_, rov_sv, _) = rov_frame.as_eph()
-                                        .unwrap();
-                                    shared |= rov_sv == sv;
-                                }
-                            }
-                            if shared {
-                                observations.retain(|code, _| {
-                                    is_pseudo_range_obs_code!(code) || is_phase_carrier_obs_code!(code)
-                                });
-                                observations.len() > 0
-                            } else {
-                                false
-                            }
-                        } else {
-                            false
-                        }
-                    });
-                    vehicles.len() > 0
-                });
-            }
-            // For Navigation (ephemeris..) RINEX
-            //  retain only shared vehicles
-            else if let Some(record) = base.record.as_mut_nav() {
-                record.retain(|e, classes| {
-                    classes.retain(|class, frames| {
-                        frames.retain(|fr| {
-                            let mut shared = false;
-                            let (_, sv, _) = fr.as_eph()
-                                .unwrap();
-                            if let Some(obs_rec) = rover.record.as_obs() {
-                                let (_, obs_vehicles) = obs_rec.get(e)
-                                    .unwrap();
-                                for (obs_sv, _) in obs_vehicles {
-                                    shared |= obs_sv == sv;
-                                }
-                            } else if let Some(nav_rec) = rover.record.as_nav() {
-                                let classes = nav_rec.get(e)
-                                    .unwrap();
-                                for (class, frames) in classes {
-                                    for fr in frames {
-                                        let (_, nav_sv, _) = fr.as_eph()
-                                            .unwrap();
-                                        shared |= nav_sv == sv;
-                                    }
-                                }
-                            }
-                            shared
-                        });
-                        frames.len() > 0
-                    });
-                    classes.len() > 0
-                });
-            }
-            /*
-             * at this point "base" is ready for processing,
-             * let's strip "rover" to identical vehicles to
-             * to speed up and eventually facilitate further operations
-             */
-            if let Some(record) = rover.record.as_mut_obs() {
-                record.retain(|e, (_, vehicles)| {
-                    vehicles.retain(|sv, _| {
-                        let mut shared = false;
-                        if let Some(obs_base) = base.record.as_obs() {
-                            let (_, base_vehicles) = obs_base.get(e)
-                                .unwrap();
-                            for (base_sv, _) in base_vehicles {
-                                shared |= base_sv == sv;
-                            }
-                        } else if let Some(nav_base) = base.record.as_nav() {
-                            let base_classes = nav_base.get(e)
-                                .unwrap();
-                            for (class, frames) in base_classes {
-                                for fr in frames {
-                                    let (_, nav_sv, _) = fr.as_eph()
-                                        .unwrap();
-                                    shared |= nav_sv == sv;
-                                }
-                            }
-                        }
-                        false
-                    });
-                    vehicles.len() > 0
-                });
-            } else if let Some(record) = rover.record.as_mut_nav() {
-                record.retain(|e, classes| {
-                    classes.retain(|class, frames| {
-                        frames.retain(|fr| {
-                            let mut shared = false;
-                            let (_, sv, _) = fr.as_eph()
-                                .unwrap();
-                            if let Some(obs_base) = base.record.as_obs() {
-                                let (_, base_vehicles) = obs_base.get(e)
-                                    .unwrap();
-                                for (base_sv, _) in base_vehicles {
-                                    shared |= sv == base_sv;
-                                }
-                            } else if let Some(nav_base) = base.record.as_nav() {
-                                let nav_classes = nav_base.get(e)
-                                    .unwrap();
-                                for (class, frames) in nav_classes {
-                                    for fr in frames {
-                                        let (_, base_sv, _) = fr.as_eph()
-                                            .unwrap();
-                                        shared |= sv == base_sv;
-                                    }
-                                }
-                            }
-                            shared
-                        });
-                        frames.len() > 0
-                    });
-                    classes.len() > 0
+    /// Epochs that do not describe a sane observation (see [EpochFlag::is_ok])
+    /// are dropped as well.
+    /// We only retain Phase and Pseudo Range observations,
+    /// as we don't know of Differential analysis involving other observations,
+    /// and only vehicles and observables that both receivers share, at each epoch.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::differential::DiffContext;
+    /// let base = Rinex::from_file("../test_resources/OBS/V3/LARM0630.22O")
+    ///     .unwrap();
+    /// let rover = Rinex::from_file("../test_resources/OBS/V3/NOA10630.22O")
+    ///     .unwrap();
+    /// let ctx = DiffContext::new(&base, &rover)
+    ///     .unwrap();
+    /// assert_eq!(ctx.base.epoch().count(), ctx.rover.epoch().count());
+    /// ```
+    pub fn new(base: &Rinex, rover: &Rinex) -> Result<Self, Error> {
+        if !base.is_observation_rinex() {
+            return Err(Error::NotObservationBase);
+        }
+        if !rover.is_observation_rinex() {
+            return Err(Error::NotObservationRover);
+        }
+        let cycle_slips: Vec<Epoch> = rover.epoch_cs().collect();
+        let mut base = base.clone();
+        let mut rover = rover.clone();
+        // match sample rates
+        base.decimate_match_mut(&rover);
+        rover.decimate_match_mut(&base);
+
+        let base_rec = base.record.as_mut_obs().unwrap();
+        let rover_rec = rover.record.as_mut_obs().unwrap();
+        base_rec.retain(|(_, flag), _| flag.is_ok());
+        rover_rec.retain(|(_, flag), _| flag.is_ok());
+        // retain shared vehicles and shared Phase / Pseudo Range observations
+        for (k, (_, svs)) in base_rec.iter_mut() {
+            let (_, rover_svs) = rover_rec.get_mut(k).unwrap();
+            svs.retain(|sv, observations| {
+                let rover_obs = match rover_svs.get(sv) {
+                    Some(rover_obs) => rover_obs,
+                    None => return false,
+                };
+                observations.retain(|observable, _| {
+                    (observable.is_phase_observable() || observable.is_pseudorange_observable())
+                        && rover_obs.contains_key(observable)
                 });
-            }
-        */
-        Self { base, rover }
+                !observations.is_empty()
+            });
+            rover_svs.retain(|sv, observations| {
+                let base_obs = match svs.get(sv) {
+                    Some(base_obs) => base_obs,
+                    None => return false,
+                };
+                observations.retain(|observable, _| base_obs.contains_key(observable));
+                !observations.is_empty()
+            });
+        }
+        base_rec.retain(|_, (_, svs)| !svs.is_empty());
+        rover_rec.retain(|_, (_, svs)| !svs.is_empty());
+        Ok(Self {
+            base,
+            rover,
+            references: HashMap::new(),
+            cycle_slips,
+        })
     }
 
     /// Builds Self from two local files
     pub fn from_files(fp: &str, rover_fp: &str) -> Result<Self, Error> {
         let rnx = Rinex::from_file(fp)?;
         let rover = Rinex::from_file(rover_fp)?;
-        Ok(Self::new(&rnx, &rover))
+        Self::new(&rnx, &rover)
+    }
+
+    /// Returns a DiffContext where given vehicle is the prefered reference
+    /// of its constellation, for double differences
+    pub fn with_reference(&self, sv: Sv) -> Self {
+        let mut s = self.clone();
+        s.references.insert(sv.constellation, sv);
+        s
+    }
+
+    /*
+     * Forms a new Observation RINEX from given record, rover header is retained
+     */
+    fn rinex(&self, record: Record) -> Rinex {
+        let mut header = self.rover.header.clone();
+        if let Some(obs) = header.obs.as_mut() {
+            for codes in obs.codes.values_mut() {
+                codes.retain(|observable| {
+                    observable.is_phase_observable() || observable.is_pseudorange_observable()
+                });
+            }
+        }
+        Rinex::new(header, record::Record::ObsRecord(record))
+    }
+
+    /// Returns single differences (rover - base) of Phase and Pseudo Range observations,
+    /// per epoch and per vehicle, as a new Observation RINEX.
+    /// Satellite clock offsets and satellite related delays cancel out.
+    /// Phase differences are expressed in carrier cycles,
+    /// Pseudo Range differences in meters. Loss of lock in either
+    /// receiver is reported. Receiver clock offsets are differenced as well, when both are known.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::differential::DiffContext;
+    /// let ctx = DiffContext::from_files(
+    ///     "../test_resources/OBS/V3/LARM0630.22O",
+    ///     "../test_resources/OBS/V3/NOA10630.22O")
+    ///     .unwrap();
+    /// let sd = ctx.single_differences();
+    /// assert!(sd.is_observation_rinex());
+    /// ```
+    pub fn single_differences(&self) -> Rinex {
+        self.rinex(self.single_differences_record())
+    }
+
+    fn single_differences_record(&self) -> Record {
+        let mut record = Record::new();
+        let base = self.base.record.as_obs().unwrap();
+        let rover = self.rover.record.as_obs().unwrap();
+        for (k, (rover_clk, rover_svs)) in rover {
+            let (base_clk, base_svs) = match base.get(k) {
+                Some(base) => base,
+                None => continue,
+            };
+            let clk = match (rover_clk, base_clk) {
+                (Some(rover), Some(base)) => Some(rover - base),
+                _ => None,
+            };
+            let mut svs: BTreeMap<Sv, HashMap<Observable, ObservationData>> = BTreeMap::new();
+            for (sv, rover_obs) in rover_svs {
+                let base_obs = match base_svs.get(sv) {
+                    Some(base_obs) => base_obs,
+                    None => continue,
+                };
+                let observations: HashMap<Observable, ObservationData> = rover_obs
+                    .iter()
+                    .filter_map(|(observable, data)| {
+                        base_obs
+                            .get(observable)
+                            .map(|base| (observable.clone(), diff(data, base)))
+                    })
+                    .collect();
+                if !observations.is_empty() {
+                    svs.insert(*sv, observations);
+                }
+            }
+            if !svs.is_empty() {
+                record.insert(*k, (clk, svs));
+            }
+        }
+        record
+    }
+
+    /// Returns the reference vehicle of each constellation, at each epoch,
+    /// used in [Self::double_differences].
+    /// A user defined reference (see [Self::with_reference]) is always prefered,
+    /// when observed. Otherwise, the current reference is retained as long
+    /// as it is observed, then replaced by the vehicle that
+    /// will remain observed the longest.
+    pub fn reference_vehicles(&self) -> BTreeMap<(Epoch, EpochFlag), HashMap<Constellation, Sv>> {
+        self.references_from(&self.single_differences_record())
+    }
+
+    fn references_from(
+        &self,
+        sd: &Record,
+    ) -> BTreeMap<(Epoch, EpochFlag), HashMap<Constellation, Sv>> {
+        let mut ret: BTreeMap<(Epoch, EpochFlag), HashMap<Constellation, Sv>> = BTreeMap::new();
+        let epochs: Vec<(Epoch, EpochFlag)> = sd.keys().copied().collect();
+        let mut current: HashMap<Constellation, Sv> = HashMap::new();
+        for (i, k) in epochs.iter().enumerate() {
+            let (_, svs) = &sd[k];
+            let mut refs: HashMap<Constellation, Sv> = HashMap::new();
+            for sv in svs.keys() {
+                if refs.contains_key(&sv.constellation) {
+                    continue;
+                }
+                let observed = |sv: &Sv| svs.contains_key(sv);
+                let reference = if let Some(sv) = self
+                    .references
+                    .get(&sv.constellation)
+                    .filter(|sv| observed(sv))
+                {
+                    *sv
+                } else if let Some(sv) = current.get(&sv.constellation).filter(|sv| observed(sv)) {
+                    *sv
+                } else {
+                    // longest upcoming continuous tracking
+                    *svs.keys()
+                        .filter(|rhs| rhs.constellation == sv.constellation)
+                        .max_by_key(|rhs| {
+                            let n = epochs[i..]
+                                .iter()
+                                .take_while(|k| sd[k].1.contains_key(rhs))
+                                .count();
+                            // lowest PRN on ties
+                            (n, std::cmp::Reverse(**rhs))
+                        })
+                        .unwrap()
+                };
+                refs.insert(sv.constellation, reference);
+            }
+            current = refs.clone();
+            ret.insert(*k, refs);
+        }
+        ret
+    }
+
+    /// Returns double differences (between receivers and against the reference vehicle
+    /// of each constellation, see [Self::reference_vehicles]) of Phase and Pseudo Range
+    /// observations, per epoch and per vehicle, as a new Observation RINEX.
+    /// Receiver clock offsets cancel out as well, leaving geometry,
+    /// residual atmospheric delays and phase ambiguities.
+    /// Reference vehicles do not appear in the results.
+    /// Phase double differences are expressed in carrier cycles:
+    /// Glonass double differences are not integer because of the FDMA scheme.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::differential::DiffContext;
+    /// let ctx = DiffContext::from_files(
+    ///     "../test_resources/OBS/V3/LARM0630.22O",
+    ///     "../test_resources/OBS/V3/NOA10630.22O")
+    ///     .unwrap();
+    /// let dd = ctx.double_differences();
+    /// let references = ctx.reference_vehicles();
+    /// ```
+    pub fn double_differences(&self) -> Rinex {
+        self.rinex(self.double_differences_record())
+    }
+
+    fn double_differences_record(&self) -> Record {
+        let sd = self.single_differences_record();
+        let references = self.references_from(&sd);
+        self.double_differences_from(&sd, &references)
+    }
+
+    fn double_differences_from(
+        &self,
+        sd: &Record,
+        references: &BTreeMap<(Epoch, EpochFlag), HashMap<Constellation, Sv>>,
+    ) -> Record {
+        let mut record = Record::new();
+        for (k, (_, svs)) in sd {
+            let refs = &references[k];
+            let mut dd: BTreeMap<Sv, HashMap<Observable, ObservationData>> = BTreeMap::new();
+            for (sv, observations) in svs {
+                let reference = match refs.get(&sv.constellation) {
+                    Some(reference) if reference != sv => reference,
+                    _ => continue,
+                };
+                let ref_obs = &svs[reference];
+                let observations: HashMap<Observable, ObservationData> = observations
+                    .iter()
+                    .filter_map(|(observable, data)| {
+                        ref_obs
+                            .get(observable)
+                            .map(|reference| (observable.clone(), diff(data, reference)))
+                    })
+                    .collect();
+                if !observations.is_empty() {
+                    dd.insert(*sv, observations);
+                }
+            }
+            if !dd.is_empty() {
+                record.insert(*k, (None, dd));
+            }
+        }
+        record
+    }
+
+    /// Returns epochs where a cycle slip declared by the rover
+    /// (loss of lock or [EpochFlag::CycleSlip] event) is confirmed
+    /// by a phase double difference jump of at least half a cycle.
+    /// Double differences still carry the geometry, which varies way
+    /// more than that between two epochs on long baselines:
+    /// we compare each double difference to its quadratic extrapolation
+    /// from the three previous epochs, sharing the same reference vehicle.
+    /// Slips declared in the first epochs of each vehicle cannot be confirmed.
+    pub fn confirmed_cycle_slips(&self) -> Vec<Epoch> {
+        let mut declared: Vec<Epoch> = self.cycle_slips.clone();
+        if let Some(record) = self.rover.record.as_obs() {
+            for ((t, _), (_, svs)) in record {
+                let lock_loss = svs.values().any(|observations| {
+                    observations.iter().any(|(observable, data)| {
+                        observable.is_phase_observable()
+                            && data
+                                .lli
+                                .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                                .unwrap_or(false)
+                    })
+                });
+                if lock_loss {
+                    declared.push(*t);
+                }
+            }
+        }
+        let sd = self.single_differences_record();
+        let references = self.references_from(&sd);
+        let dd = self.double_differences_from(&sd, &references);
+        let epochs: Vec<&(Epoch, EpochFlag)> = dd.keys().collect();
+        let mut ret: Vec<Epoch> = Vec::new();
+        for (i, k) in epochs.iter().enumerate() {
+            if !declared.contains(&k.0) {
+                continue;
+            }
+            let (_, svs) = &dd[*k];
+            let jump = svs.iter().any(|(sv, observations)| {
+                let reference = references[*k].get(&sv.constellation);
+                observations.iter().any(|(observable, data)| {
+                    if !observable.is_phase_observable() {
+                        return false;
+                    }
+                    // previous samples, with the same reference vehicle
+                    let samples: Vec<(f64, f64)> = epochs[..i]
+                        .iter()
+                        .rev()
+                        .take_while(|prev| references[**prev].get(&sv.constellation) == reference)
+                        .filter_map(|prev| {
+                            let data = dd[*prev].1.get(sv)?.get(observable)?;
+                            Some(((prev.0 - k.0).to_seconds(), data.obs))
+                        })
+                        .take(3)
+                        .collect();
+                    match extrapolate(&samples) {
+                        Some(predicted) => (data.obs - predicted).abs() > 0.5,
+                        None => false,
+                    }
+                })
+            });
+            if jump {
+                ret.push(k.0);
+            }
+        }
+        ret
+    }
+}
+
+/*
+ * Evaluates at t = 0 the quadratic polynomial passing through
+ * three (t, value) samples (Lagrange form)
+ */
+fn extrapolate(samples: &[(f64, f64)]) -> Option<f64> {
+    if samples.len() < 3 {
+        return None;
+    }
+    let mut ret = 0.0;
+    for (i, (t_i, x_i)) in samples.iter().enumerate() {
+        let mut weight = 1.0;
+        for (j, (t_j, _)) in samples.iter().enumerate() {
+            if i != j {
+                weight *= -t_j / (t_i - t_j);
+            }
+        }
+        ret += weight * x_i;
     }
+    Some(ret)
 }
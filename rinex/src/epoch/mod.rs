@@ -1,5 +1,6 @@
+use crate::constellation::Constellation;
 use crate::types::Type;
-use hifitime::Epoch;
+use hifitime::{Duration, Epoch, TimeScale};
 use std::str::FromStr;
 use thiserror::Error;
 
@@ -39,6 +40,26 @@ pub(crate) fn now() -> Epoch {
     Epoch::now().unwrap_or(Epoch::from_gregorian_utc_at_midnight(2000, 01, 01))
 }
 
+/*
+ * Epochs are parsed with a UTC label, while RINEX epochs are described
+ * in the GNSS timescale of given constellation (Glonass being UTC).
+ * Returns the actual instant, for such an epoch.
+ */
+pub(crate) fn gnss_instant(t: Epoch, constellation: Constellation) -> Epoch {
+    if t.time_scale != TimeScale::UTC {
+        return t;
+    }
+    // TAI - UTC
+    let leap = t.leap_seconds(true).unwrap_or(0.0);
+    // TAI - GPST = 19s, TAI - BDT = 33s
+    let offset = match constellation {
+        Constellation::Glonass => return t,
+        Constellation::BeiDou => leap - 33.0,
+        _ => leap - 19.0,
+    };
+    t - Duration::from_seconds(offset)
+}
+
 /*
  * Formats given epoch to string, matching standard specifications
  */
@@ -101,10 +122,7 @@ pub(crate) fn format(epoch: Epoch, flag: Option<EpochFlag>, t: Type, revision: u
                 format!("{:04} {:02} {:02} {:02} {:02} {:02}", y, m, d, hh, mm, ss)
             }
         },
-        Type::IonosphereMaps => format!(
-            "{:04}   {:>2}    {:>2}    {:>2}    {:>2}    {:>2}",
-            y, m, d, hh, mm, ss
-        ),
+        Type::IonosphereMaps => format!("{:6}{:6}{:6}{:6}{:6}{:6}", y, m, d, hh, mm, ss),
         _ => {
             if revision < 3 {
                 // old RINEX wants 2 digit YY field
@@ -207,7 +225,20 @@ pub(crate) fn parse(s: &str) -> Result<(Epoch, EpochFlag), Error> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use hifitime::TimeScale;
+    use hifitime::GPST_REF_EPOCH;
+    #[test]
+    fn gnss_instants() {
+        let t = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        // thursday 00:00:00 GPST
+        let gpst = gnss_instant(t, Constellation::GPS);
+        assert_eq!((gpst - GPST_REF_EPOCH).to_seconds() % 604800.0, 345600.0);
+        assert_eq!(gnss_instant(t, Constellation::Galileo), gpst);
+        let bdt = gnss_instant(t, Constellation::BeiDou);
+        assert_eq!(bdt - gpst, Duration::from_seconds(14.0));
+        assert_eq!(gnss_instant(t, Constellation::Glonass), t);
+        let tai = Epoch::from_gregorian_tai_at_midnight(2020, 6, 25);
+        assert_eq!(gnss_instant(tai, Constellation::GPS), tai);
+    }
     #[test]
     fn epoch_parse_nav_v2() {
         let e = parse("20 12 31 23 45  0.0");
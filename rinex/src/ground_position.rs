@@ -32,6 +32,36 @@ impl std::fmt::Display for GroundPosition {
     }
 }
 
+/*
+ * ECEF to local ENU frame rotation matrix, at given geodetic coordinates [rad]
+ */
+#[cfg(feature = "nav")]
+pub(crate) fn ecef2enu(lat: f64, lon: f64) -> [[f64; 3]; 3] {
+    [
+        [-lon.sin(), lon.cos(), 0.0],
+        [-lat.sin() * lon.cos(), -lat.sin() * lon.sin(), lat.cos()],
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()],
+    ]
+}
+
+/*
+ * Sv elevation and azimuth angles [°], seen from given receiver position (ECEF)
+ */
+#[cfg(feature = "nav")]
+pub(crate) fn elevation_azimuth(rcvr: (f64, f64, f64), sv: (f64, f64, f64)) -> (f64, f64) {
+    let (lat, lon, _) = ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, Ellipsoid::WGS84);
+    let los = (sv.0 - rcvr.0, sv.1 - rcvr.1, sv.2 - rcvr.2);
+    let norm = (los.0.powi(2) + los.1.powi(2) + los.2.powi(2)).sqrt();
+    let r = ecef2enu(lat, lon);
+    let enu: Vec<f64> = r
+        .iter()
+        .map(|row| (row[0] * los.0 + row[1] * los.1 + row[2] * los.2) / norm)
+        .collect();
+    let elev = enu[2].clamp(-1.0, 1.0).asin().to_degrees();
+    let azim = enu[0].atan2(enu[1]).to_degrees().rem_euclid(360.0);
+    (elev, azim)
+}
+
 #[cfg(feature = "qc")]
 use crate::quality::HtmlReport;
 
@@ -5,7 +5,9 @@ use crate::{
     antex, clocks,
     ground_position::GroundPosition,
     hardware::{Antenna, Rcvr, SvAntenna},
-    ionex, leap, meteo, observation,
+    ionex, leap, meteo,
+    navigation::{record::fmt_nav_float, IonMessage, KbModel, NgModel, StoMessage},
+    observation,
     observation::Crinex,
     reader::BufferedReader,
     types::{Type, TypeError},
@@ -129,8 +131,6 @@ pub struct Header {
     pub glo_channels: HashMap<Sv, i8>,
     /// optionnal leap seconds infos
     pub leap: Option<leap::Leap>,
-    // /// Optionnal system time correction
-    // pub time_corrections: Option<gnss_time::Correction>,
     /// Station approximate coordinates
     pub ground_position: Option<GroundPosition>,
     /// Optionnal observation wavelengths
@@ -145,6 +145,16 @@ pub struct Header {
     pub gps_utc_delta: Option<u32>,
     /// Optionnal data scaling
     pub data_scaling: Option<f64>,
+    /// Broadcast ionospheric model coefficients, per constellation,
+    /// as found in V2 and V3 NAV headers. NAV V4 streams these
+    /// as [crate::navigation::IonMessage] frames instead.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub ionod_corrections: HashMap<Constellation, IonMessage>,
+    /// System time corrections, per time system (for example "GPUT"),
+    /// with their reference [Epoch], as found in V2 and V3 NAV headers.
+    /// NAV V4 streams these as [crate::navigation::StoMessage] frames instead.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub time_corrections: HashMap<String, (Epoch, StoMessage)>,
     /// Optionnal Receiver information
     #[cfg_attr(feature = "serde", serde(default))]
     pub rcvr: Option<Rcvr>,
@@ -229,6 +239,8 @@ impl Default for Header {
             ground_position: None,
             wavelengths: None,
             data_scaling: None,
+            ionod_corrections: HashMap::new(),
+            time_corrections: HashMap::new(),
             sampling_interval: None,
             obs: None,
             meteo: None,
@@ -264,8 +276,12 @@ impl Header {
         let mut leap: Option<leap::Leap> = None;
         let mut sampling_interval: Option<Duration> = None;
         let mut ground_position: Option<GroundPosition> = None;
+        let mut ionod_corrections: HashMap<Constellation, IonMessage> = HashMap::new();
+        let mut time_corrections: HashMap<String, (Epoch, StoMessage)> = HashMap::new();
         // RINEX specific fields
         let mut current_constell: Option<Constellation> = None;
+        let mut current_scaling: Option<(Constellation, f64)> = None;
+        let mut current_phase_shift: Option<(Constellation, Observable, f64)> = None;
         let mut observation = observation::HeaderFields::default();
         let mut meteo = meteo::HeaderFields::default();
         let mut clocks = clocks::HeaderFields::default();
@@ -464,11 +480,38 @@ impl Header {
                 let (system, rem) = content.split_at(2);
                 let (_program, _url) = rem.split_at(18);
                 if let Ok(gnss) = Constellation::from_str(system.trim()) {
-                    observation.with_dcb_compensation(gnss);
+                    observation = observation.with_dcb_compensation(gnss);
                 }
             } else if marker.contains("SYS / SCALE FACTOR") {
-                /*let (system, rem) = content.split_at(2);
-                let (factor, rem) = rem.split_at(5);*/
+                let (system, rem) = content.split_at(1);
+                let (factor, rem) = rem.split_at(5);
+                let (_, rem) = rem.split_at(4);
+                let observables: Vec<Observable> = rem
+                    .split_ascii_whitespace()
+                    .filter_map(|code| Observable::from_str(code).ok())
+                    .collect();
+                if let Ok(c) = Constellation::from_1_letter_code(system) {
+                    // new system: continuation lines have blank system and factor
+                    current_scaling = match f64::from_str(factor.trim()) {
+                        Ok(factor) => Some((c, factor)),
+                        _ => None,
+                    };
+                    if let Some((c, factor)) = current_scaling {
+                        if observables.is_empty() {
+                            // applies to all observables of this system
+                            if let Some(codes) = observation.codes.get(&c).cloned() {
+                                for observable in codes {
+                                    observation = observation.with_scaling(c, observable, factor);
+                                }
+                            }
+                        }
+                    }
+                }
+                if let Some((c, factor)) = current_scaling {
+                    for observable in observables {
+                        observation = observation.with_scaling(c, observable, factor);
+                    }
+                }
             } else if marker.contains("SENSOR MOD/TYPE/ACC") {
                 if let Ok(sensor) = meteo::sensor::Sensor::from_str(content) {
                     meteo.sensors.push(sensor)
@@ -587,13 +630,46 @@ impl Header {
                     observation.clock_offset_applied = n > 0;
                 }
             } else if marker.contains("# OF SATELLITES") {
-                // ---> we don't need this info,
-                //     user can determine it by analyzing the record
+                // OBS: we don't need this info,
+                //      user can determine it by analyzing the record
+                if let Ok(u) = content.trim().parse::<u32>() {
+                    ionex = ionex.with_nb_satellites(u)
+                }
             } else if marker.contains("PRN / # OF OBS") {
                 // ---> we don't need this info,
                 //     user can determine it by analyzing the record
             } else if marker.contains("SYS / PHASE SHIFT") {
-                //TODO
+                let (system, rem) = content.split_at(1);
+                let (code, rem) = rem.split_at(5);
+                let (correction, rem) = rem.split_at(8);
+                let (_, rem) = rem.split_at(4);
+                let vehicles: Vec<Sv> = rem
+                    .split_ascii_whitespace()
+                    .filter_map(|sv| Sv::from_str(sv).ok())
+                    .collect();
+                if let Ok(c) = Constellation::from_1_letter_code(system) {
+                    // new observable: continuation lines only list vehicles.
+                    // Blank correction: unknown, or not applied
+                    current_phase_shift = match (
+                        Observable::from_str(code.trim()),
+                        f64::from_str(correction.trim()),
+                    ) {
+                        (Ok(observable), Ok(shift)) => Some((c, observable, shift)),
+                        _ => None,
+                    };
+                    if let Some((c, observable, shift)) = &current_phase_shift {
+                        if vehicles.is_empty() {
+                            observation =
+                                observation.with_phase_shift(*c, observable.clone(), None, *shift);
+                        }
+                    }
+                }
+                if let Some((c, observable, shift)) = &current_phase_shift {
+                    for sv in vehicles {
+                        observation =
+                            observation.with_phase_shift(*c, observable.clone(), Some(sv), *shift);
+                    }
+                }
             } else if marker.contains("SYS / PVCS APPLIED") {
                 // RINEX::ClockData specific
                 // + satellite system (G/R/E/C/I/J/S)
@@ -718,25 +794,73 @@ impl Header {
                     }
                 }
             } else if marker.contains("GLONASS COD/PHS/BIS") {
-                //TODO
+                // blank bias: unknown
+                for i in 0..num_integer::div_ceil(content.len(), 13) {
+                    let item = &content[i * 13..std::cmp::min((i + 1) * 13, content.len())];
+                    if item.len() < 5 {
+                        continue;
+                    }
+                    let (code, bias) = item.split_at(5);
+                    if let (Ok(observable), Ok(bias)) = (
+                        Observable::from_str(code.trim()),
+                        f64::from_str(bias.trim()),
+                    ) {
+                        observation = observation.with_glo_bias(observable, bias);
+                    }
+                }
             } else if marker.contains("ION ALPHA") {
-                //TODO
+                // V2 GPS Klobuchar model
                 //0.7451D-08 -0.1490D-07 -0.5960D-07  0.1192D-06          ION ALPHA
+                if let Some(alpha) = parse_ionod_coefs(content.split_at(2).1) {
+                    kb_model(&mut ionod_corrections, Constellation::GPS).alpha = alpha;
+                }
             } else if marker.contains("ION BETA") {
-                //TODO
+                // V2 GPS Klobuchar model
                 //0.9011D+05 -0.6554D+05 -0.1311D+06  0.4588D+06          ION BETA
+                if let Some(beta) = parse_ionod_coefs(content.split_at(2).1) {
+                    kb_model(&mut ionod_corrections, Constellation::GPS).beta = beta;
+                }
             } else if marker.contains("IONOSPHERIC CORR") {
-                // TODO
+                // V3 description
                 // GPSA 0.1025E-07 0.7451E-08 -0.5960E-07 -0.5960E-07
                 // GPSB 0.1025E-07 0.7451E-08 -0.5960E-07 -0.5960E-07
+                // GAL  6.6250e+01 -1.6410e-01 -2.4720e-03  0.0000e+00
+                let (system, rem) = content.split_at(5);
+                if let Some(coefs) = parse_ionod_coefs(rem) {
+                    let system = system.trim();
+                    if system.eq("GAL") {
+                        ionod_corrections.insert(
+                            Constellation::Galileo,
+                            IonMessage::NequickGModel(NgModel {
+                                a: (coefs.0, coefs.1, coefs.2),
+                                ..Default::default()
+                            }),
+                        );
+                    } else if system.len() == 4 {
+                        let (gnss, kind) = system.split_at(3);
+                        let constellation = match gnss {
+                            "GPS" => Some(Constellation::GPS),
+                            "QZS" => Some(Constellation::QZSS),
+                            "BDS" => Some(Constellation::BeiDou),
+                            "IRN" => Some(Constellation::IRNSS),
+                            _ => None,
+                        };
+                        if let Some(constellation) = constellation {
+                            let model = kb_model(&mut ionod_corrections, constellation);
+                            match kind {
+                                "A" => model.alpha = coefs,
+                                "B" => model.beta = coefs,
+                                _ => {},
+                            }
+                        }
+                    }
+                }
             } else if marker.contains("TIME SYSTEM CORR") {
+                // V3 description
                 // GPUT 0.2793967723E-08 0.000000000E+00 147456 1395
-                /*
-                 * V3 Time System correction description
-                 */
-                //if let Ok((ts, ts, corr)) = gnss_time::decode_time_system_corr(content) {
-                //    time_corrections.insert(ts, (ts, corr));
-                //}
+                if let Some((epoch, sto)) = parse_time_system_corr(content) {
+                    time_corrections.insert(sto.system.clone(), (epoch, sto));
+                }
             } else if marker.contains("TIME SYSTEM ID") {
                 let timescale = content.trim();
                 if let Ok(ts) = TimeScale::from_str(content.trim()) {
@@ -751,8 +875,17 @@ impl Header {
                     }
                 }
             } else if marker.contains("DELTA-UTC") {
-                //TODO
+                // V2 GPS description
                 //0.931322574615D-09 0.355271367880D-14   233472     1930 DELTA-UTC: A0,A1,T,W
+                if let Some((epoch, sto)) = parse_delta_utc(content) {
+                    time_corrections.insert(sto.system.clone(), (epoch, sto));
+                }
+            } else if marker.contains("CORR TO SYSTEM TIME") {
+                // V2 GLONASS description
+                //  2021     1     1   -1.862645149231D-09                    CORR TO SYSTEM TIME
+                if let Some((epoch, sto)) = parse_corr_to_system_time(content) {
+                    time_corrections.insert(sto.system.clone(), (epoch, sto));
+                }
             } else if marker.contains("DESCRIPTION") {
                 // IONEX description
                 // <o
@@ -770,19 +903,26 @@ impl Header {
                 if let Ok(f) = f32::from_str(content.trim()) {
                     ionex = ionex.with_base_radius(f);
                 }
-            } else if marker.contains("MAPPING FUCTION") {
+            } else if marker.contains("MAPPING FUNCTION") || marker.contains("MAPPING FUCTION") {
                 if let Ok(mf) = ionex::MappingFunction::from_str(content.trim()) {
                     ionex = ionex.with_mapping_function(mf);
                 }
-            } else if marker.contains("# OF STATIONS") {
-                // IONEX
+            } else if marker.contains("EPOCH OF FIRST MAP") {
+                if let Some(epoch) = ionex::parse_epoch(content) {
+                    ionex.epoch_of_first_map = Some(epoch);
+                }
+            } else if marker.contains("EPOCH OF LAST MAP") {
+                if let Some(epoch) = ionex::parse_epoch(content) {
+                    ionex.epoch_of_last_map = Some(epoch);
+                }
+            } else if marker.contains("# OF MAPS IN FILE") {
                 if let Ok(u) = u32::from_str_radix(content.trim(), 10) {
-                    ionex = ionex.with_nb_stations(u)
+                    ionex = ionex.with_nb_maps(u)
                 }
-            } else if marker.contains("# OF SATELLITES") {
+            } else if marker.contains("# OF STATIONS") {
                 // IONEX
                 if let Ok(u) = u32::from_str_radix(content.trim(), 10) {
-                    ionex = ionex.with_nb_satellites(u)
+                    ionex = ionex.with_nb_stations(u)
                 }
             /*
              * Initial TEC map scaling
@@ -846,7 +986,27 @@ impl Header {
                 }
             } else if marker.contains("PRN / BIAS / RMS") {
                 // differential PR code analysis
-                //TODO
+                let (sv, rem) = content.split_at(6);
+                let (constell, prn) = sv.split_at(3).1.split_at(1);
+                let constellation = match constell.trim().len() {
+                    0 => Ok(Constellation::GPS),
+                    _ => Constellation::from_1_letter_code(constell),
+                };
+                if let (Ok(constellation), Ok(prn), Some((bias, rms))) = (
+                    constellation,
+                    prn.trim().parse::<u8>(),
+                    ionex::parse_bias(rem),
+                ) {
+                    let sv = Sv::new(constellation, prn);
+                    ionex = ionex.with_dcb(ionex::BiasSource::SpaceVehicle(sv), (bias, rms));
+                }
+            } else if marker.contains("STATION / BIAS / RMS") {
+                let (station, rem) = content.split_at(10);
+                let station = station.split_at(6).1.trim();
+                if let Some((bias, rms)) = ionex::parse_bias(rem) {
+                    let station = ionex::BiasSource::Station(station.to_string());
+                    ionex = ionex.with_dcb(station, (bias, rms));
+                }
             }
         }
 
@@ -874,6 +1034,8 @@ impl Header {
             gps_utc_delta: None,
             sampling_interval,
             data_scaling: None,
+            ionod_corrections,
+            time_corrections,
             rcvr_antenna,
             sv_antenna,
             // RINEX specific
@@ -1002,6 +1164,20 @@ impl Header {
                     None
                 }
             },
+            ionod_corrections: {
+                let mut corrections = header.ionod_corrections.clone();
+                for (constellation, model) in &self.ionod_corrections {
+                    corrections.insert(*constellation, model.clone());
+                }
+                corrections
+            },
+            time_corrections: {
+                let mut corrections = header.time_corrections.clone();
+                for (system, correction) in &self.time_corrections {
+                    corrections.insert(system.clone(), correction.clone());
+                }
+                corrections
+            },
             constellation: {
                 if let Some(c0) = self.constellation {
                     if let Some(c1) = header.constellation {
@@ -1095,7 +1271,13 @@ impl Header {
                             },
                             clock_offset_applied: d0.clock_offset_applied
                                 && d1.clock_offset_applied,
-                            scalings: HashMap::new(),      //TODO
+                            scalings: HashMap::new(), //TODO
+                            phase_shifts: {
+                                let mut merged = d0.clone();
+                                merged.merge_phase_shifts_mut(d1)?;
+                                merged.phase_shifts
+                            },
+                            glo_biases: d0.glo_biases.clone(),
                             dcb_compensations: Vec::new(), //TODO
                         })
                     } else {
@@ -1234,6 +1416,16 @@ impl Header {
                             map_dimension: d0.map_dimension,
                             base_radius: d0.base_radius,
                             grid: d0.grid.clone(),
+                            epoch_of_first_map: match (d0.epoch_of_first_map, d1.epoch_of_first_map)
+                            {
+                                (Some(t0), Some(t1)) => Some(std::cmp::min(t0, t1)),
+                                (t0, t1) => t0.or(t1),
+                            },
+                            epoch_of_last_map: match (d0.epoch_of_last_map, d1.epoch_of_last_map) {
+                                (Some(t0), Some(t1)) => Some(std::cmp::max(t0, t1)),
+                                (t0, t1) => t0.or(t1),
+                            },
+                            nb_maps: std::cmp::max(d0.nb_maps, d1.nb_maps),
                             elevation_cutoff: d0.elevation_cutoff,
                             observables: {
                                 if let Some(obs) = &d0.observables {
@@ -1291,6 +1483,14 @@ impl Header {
             .with_constellation(Constellation::Mixed)
     }
 
+    /// Creates a Basic Header structure for IONEX
+    pub fn basic_ionex() -> Self {
+        Self::default()
+            .with_type(Type::IonosphereMaps)
+            .with_version(Version::new(1, 0))
+            .with_ionex_fields(ionex::HeaderFields::default())
+    }
+
     /// Creates Basic Header structure
     /// for Compact RINEX with Mixed Constellation context
     pub fn basic_crinex() -> Self {
@@ -1366,6 +1566,12 @@ impl Header {
         s.obs = Some(fields);
         s
     }
+    /// Adds IONEX specific fields
+    pub fn with_ionex_fields(&self, fields: ionex::HeaderFields) -> Self {
+        let mut s = self.clone();
+        s.ionex = Some(fields);
+        s
+    }
 }
 
 impl std::fmt::Display for Header {
@@ -1378,11 +1584,16 @@ impl std::fmt::Display for Header {
             }
         }
         // RINEX VERSION / TYPE
-        write!(
-            f,
-            "{:6}.{:02}           ",
-            self.version.major, self.version.minor
-        )?;
+        if self.rinex_type == Type::IonosphereMaps {
+            let version = format!("{}.{}", self.version.major, self.version.minor);
+            write!(f, "{:>8}{:12}", version, "")?;
+        } else {
+            write!(
+                f,
+                "{:6}.{:02}           ",
+                self.version.major, self.version.minor
+            )?;
+        }
         match self.rinex_type {
             Type::NavigationData => {
                 match self.constellation {
@@ -1419,7 +1630,15 @@ impl std::fmt::Display for Header {
                 write!(f, "{:<20}", "RINEX VERSION / TYPE\n")?;
             },
             Type::AntennaData => todo!(),
-            Type::IonosphereMaps => todo!(),
+            Type::IonosphereMaps => {
+                let system = match &self.ionex {
+                    Some(ionex) => ionex.reference.to_string(),
+                    None => ionex::RefSystem::default().to_string(),
+                };
+                write!(f, "{:<20}", "IONOSPHERE MAPS")?;
+                write!(f, "{:<20}", system)?;
+                writeln!(f, "IONEX VERSION / TYPE")?;
+            },
         }
         // COMMENTS
         for comment in self.comments.iter() {
@@ -1477,6 +1696,16 @@ impl std::fmt::Display for Header {
                 write!(f, "{}", "ANTENNA: DELTA H/E/N\n")?
             }
         }
+        // APPROX POSITION
+        let antenna_coords = self.rcvr_antenna.as_ref().and_then(|a| a.coords);
+        if let (None, Some(position)) = (antenna_coords, &self.ground_position) {
+            let (x, y, z) = position.to_ecef_wgs84();
+            writeln!(
+                f,
+                "{:14.4}{:14.4}{:14.4}{:18}APPROX POSITION XYZ",
+                x, y, z, ""
+            )?;
+        }
         // RCVR
         if let Some(rcvr) = &self.rcvr {
             write!(f, "{:<20}", rcvr.sn)?;
@@ -1497,46 +1726,23 @@ impl std::fmt::Display for Header {
                     match self.version.major {
                         1 | 2 => {
                             // old revisions
-                            for (_, observables) in obs.codes.iter() {
-                                write!(f, "{:6}", observables.len())?;
-                                let mut descriptor = String::new();
-                                for i in 0..observables.len() {
-                                    if (i % 9) == 0 && i > 0 {
-                                        //ADD LABEL
-                                        descriptor.push_str("# / TYPES OF OBSERV\n");
-                                        descriptor.push_str(&format!("{:<6}", ""));
-                                        //TAB
+                            // full cycle ambiguities, unless specified otherwise
+                            let (l1, l2) = self.wavelengths.unwrap_or((1, 1));
+                            writeln!(f, "{:6}{:6}{:<48}WAVELENGTH FACT L1/2", l1, l2, "")?;
+                            // header contains obs.codes[] copied for every possible constellation system
+                            // because we have no means to known which ones are to be encountered
+                            // in this great/magnificent RINEX2 format.
+                            // On the other hand, we're expected to only declare a single #/TYPESOFOBSERV label
+                            if let Some(observables) = obs.codes.values().next() {
+                                let mut line = format!("{:6}", observables.len());
+                                for (i, observable) in observables.iter().enumerate() {
+                                    if i > 0 && i % 9 == 0 {
+                                        writeln!(f, "{:<60}# / TYPES OF OBSERV", line)?;
+                                        line = format!("{:6}", "");
                                     }
-                                    // <!> this will not work if observable
-                                    //     does not fit on 2 characters
-                                    descriptor.push_str(&format!("    {}", observables[i]));
-                                }
-                                //ADD BLANK on last line
-                                if observables.len() <= 9 {
-                                    // fits on one line
-                                    descriptor.push_str(&format!(
-                                        "{:<width$}",
-                                        "",
-                                        width = 80 - descriptor.len()
-                                    ));
-                                } else {
-                                    let nb_lines = observables.len() / 9;
-                                    let blanking = 80 - (descriptor.len() - 80 * nb_lines); //98 = 80 + # / TYPESOFOBSERV
-                                    descriptor.push_str(&format!(
-                                        "{:<width$}",
-                                        "",
-                                        width = blanking
-                                    ));
+                                    line.push_str(&format!("{:>6}", observable.to_string()));
                                 }
-                                //ADD LABEL
-                                descriptor.push_str("# / TYPES OF OBSERV\n");
-                                write!(f, "{}", descriptor)?;
-                                // NOTE ON THIS BREAK
-                                //      header contains obs.codes[] copied for every possible constellation system
-                                //      because we have no means to known which ones are to be encountered
-                                //      in this great/magnificent RINEX2 format.
-                                //      On the other hand, we're expected to only declare a single #/TYPESOFOBSERV label
-                                break;
+                                writeln!(f, "{:<60}# / TYPES OF OBSERV", line)?;
                             }
                         },
                         _ => {
@@ -1562,14 +1768,180 @@ impl std::fmt::Display for Header {
                                 line.push_str("SYS / # / OBS TYPES\n");
                                 write!(f, "{}", line)?
                             }
+                            // DCBS compensations: correction program and source are not known
+                            for constell in obs.dcb_compensations.iter() {
+                                writeln!(
+                                    f,
+                                    "{:<60}SYS / DCBS APPLIED",
+                                    constell.to_1_letter_code()
+                                )?;
+                            }
+                            // Data scalings, grouped by factor
+                            let mut constellations: Vec<_> = obs.scalings.keys().collect();
+                            constellations.sort();
+                            for constell in constellations {
+                                let codes = match obs.codes.get(constell) {
+                                    Some(codes) => codes,
+                                    None => continue,
+                                };
+                                let mut factors: Vec<u32> = Vec::new();
+                                for code in codes {
+                                    if let Some(factor) = obs.scalings[constell].get(code) {
+                                        if !factors.contains(&(*factor as u32)) {
+                                            factors.push(*factor as u32);
+                                        }
+                                    }
+                                }
+                                for factor in factors {
+                                    let scaled: Vec<_> = codes
+                                        .iter()
+                                        .filter(|code| {
+                                            obs.scalings[constell].get(code).map(|f| *f as u32)
+                                                == Some(factor)
+                                        })
+                                        .collect();
+                                    let mut line = format!(
+                                        "{} {:4}  {:2}",
+                                        constell.to_1_letter_code(),
+                                        factor,
+                                        scaled.len()
+                                    );
+                                    for (i, code) in scaled.iter().enumerate() {
+                                        if i > 0 && i % 12 == 0 {
+                                            writeln!(f, "{:<60}SYS / SCALE FACTOR", line)?;
+                                            line = format!("{:10}", "");
+                                        }
+                                        line.push_str(&format!(" {:<3}", code.to_string()));
+                                    }
+                                    writeln!(f, "{:<60}SYS / SCALE FACTOR", line)?;
+                                }
+                            }
+                            // Phase shifts (mandatory since RINEX3.01):
+                            // blank when unknown
+                            if self.version > Version::new(3, 0) {
+                                let mut constellations: Vec<_> = obs.codes.keys().collect();
+                                constellations.sort();
+                                for constell in constellations {
+                                    for code in obs.codes[constell].iter() {
+                                        if !code.is_phase_observable() {
+                                            continue;
+                                        }
+                                        let shifts = obs
+                                            .phase_shifts
+                                            .get(constell)
+                                            .and_then(|shifts| shifts.get(code));
+                                        let shifts = match shifts {
+                                            Some(shifts) => shifts,
+                                            None => {
+                                                writeln!(
+                                                    f,
+                                                    "{:<60}SYS / PHASE SHIFT",
+                                                    format!(
+                                                        "{} {}",
+                                                        constell.to_1_letter_code(),
+                                                        code
+                                                    )
+                                                )?;
+                                                continue;
+                                            },
+                                        };
+                                        // constellation wide correction first,
+                                        // then vehicles that share the same correction
+                                        let mut entries: Vec<(f64, Vec<Sv>)> = Vec::new();
+                                        if let Some(shift) = shifts.get(&None) {
+                                            entries.push((*shift, Vec::new()));
+                                        }
+                                        let mut vehicles: Vec<_> = shifts
+                                            .iter()
+                                            .filter_map(|(sv, shift)| Some(((*sv)?, *shift)))
+                                            .collect();
+                                        vehicles.sort_by_key(|(sv, _)| *sv);
+                                        for (sv, shift) in vehicles {
+                                            match entries
+                                                .iter_mut()
+                                                .find(|(s, svs)| *s == shift && !svs.is_empty())
+                                            {
+                                                Some((_, svs)) => svs.push(sv),
+                                                None => entries.push((shift, vec![sv])),
+                                            }
+                                        }
+                                        for (shift, vehicles) in entries {
+                                            let mut line = format!(
+                                                "{} {:<3} {:8.5}  {:2}",
+                                                constell.to_1_letter_code(),
+                                                code.to_string(),
+                                                shift,
+                                                vehicles.len()
+                                            );
+                                            if vehicles.is_empty() {
+                                                line.truncate(line.len() - 2);
+                                            }
+                                            for (i, sv) in vehicles.iter().enumerate() {
+                                                if i > 0 && i % 10 == 0 {
+                                                    writeln!(f, "{:<60}SYS / PHASE SHIFT", line)?;
+                                                    line = format!("{:18}", "");
+                                                }
+                                                line.push_str(&format!(" {}", sv));
+                                            }
+                                            writeln!(f, "{:<60}SYS / PHASE SHIFT", line)?;
+                                        }
+                                    }
+                                }
+                            }
+                            // Glonass FDMA channels
+                            let mut channels: Vec<_> = self.glo_channels.iter().collect();
+                            channels.sort();
+                            for (index, chunk) in channels.chunks(8).enumerate() {
+                                let mut line = match index {
+                                    0 => format!("{:3} ", channels.len()),
+                                    _ => format!("{:4}", ""),
+                                };
+                                for (sv, channel) in chunk {
+                                    line.push_str(&format!("{} {:2} ", sv, channel));
+                                }
+                                writeln!(f, "{:<60}GLONASS SLOT / FRQ #", line)?;
+                            }
+                            // Glonass code-phase biases, blank when unknown
+                            if self.version >= Version::new(3, 2)
+                                && obs.codes.contains_key(&Constellation::Glonass)
+                            {
+                                let mut biases: Vec<(String, Option<f64>)> = obs
+                                    .glo_biases
+                                    .iter()
+                                    .map(|(code, bias)| (code.to_string(), Some(*bias)))
+                                    .collect();
+                                biases.sort_by(|(a, _), (b, _)| a.cmp(b));
+                                if biases.is_empty() {
+                                    biases = ["C1C", "C1P", "C2C", "C2P"]
+                                        .iter()
+                                        .map(|code| (code.to_string(), None))
+                                        .collect();
+                                }
+                                for chunk in biases.chunks(4) {
+                                    let mut line = String::new();
+                                    for (code, bias) in chunk {
+                                        match bias {
+                                            Some(bias) => {
+                                                line.push_str(&format!(" {:<3} {:8.3}", code, bias))
+                                            },
+                                            None => {
+                                                line.push_str(&format!(" {:<3} {:8}", code, ""))
+                                            },
+                                        }
+                                    }
+                                    writeln!(f, "{:<60}GLONASS COD/PHS/BIS", line)?;
+                                }
+                            }
                         },
                     }
+                    if obs.clock_offset_applied {
+                        writeln!(f, "{:6}{:<54}RCV CLOCK OFFS APPL", 1, "")?;
+                    }
                 }
             }, //ObservationData observables description
             Type::MeteoData => {
                 if let Some(obs) = &self.meteo {
-                    write!(f, "{:6}", obs.codes.len())?;
-                    let mut description = String::new();
+                    let mut description = format!("{:6}", obs.codes.len());
                     for i in 0..obs.codes.len() {
                         if (i % 9) == 0 && i > 0 {
                             description.push_str("# / TYPES OF OBSERV\n");
@@ -1582,7 +1954,7 @@ impl std::fmt::Display for Header {
                     description.push_str(&format!(
                         "{:<width$}",
                         "",
-                        width = 54 - description.len()
+                        width = 60 - description.len()
                     ));
                     description.push_str("# / TYPES OF OBSERV\n");
                     write!(f, "{}", description)?
@@ -1592,8 +1964,87 @@ impl std::fmt::Display for Header {
         }
         // Must take place after list of Observables:
         //TODO: scale factor, if any
-        //TODO: DCBS compensation, if any
         //TODO: PCVs compensation, if any
+        // IONOSPHERIC CORR: NAV V4 streams these as frames
+        let mut ionod_corrections: Vec<_> = self.ionod_corrections.iter().collect();
+        ionod_corrections.sort_by_key(|(constellation, _)| **constellation);
+        if self.version.major > 3 {
+            ionod_corrections.clear();
+        }
+        for (constellation, model) in ionod_corrections {
+            if let Some(kb) = model.as_klobuchar() {
+                if self.version.major < 3 {
+                    if *constellation == Constellation::GPS {
+                        let (a0, a1, a2, a3) = kb.alpha;
+                        let (b0, b1, b2, b3) = kb.beta;
+                        for (coefs, marker) in [
+                            ([a0, a1, a2, a3], "ION ALPHA"),
+                            ([b0, b1, b2, b3], "ION BETA"),
+                        ] {
+                            let line: String = coefs.iter().map(|c| fmt_ionod_coef(*c)).collect();
+                            writeln!(f, "  {:<58}{}", line, marker)?;
+                        }
+                    }
+                    continue;
+                }
+                let system = match constellation {
+                    Constellation::GPS => "GPS",
+                    Constellation::QZSS => "QZS",
+                    Constellation::BeiDou => "BDS",
+                    Constellation::IRNSS => "IRN",
+                    _ => continue,
+                };
+                let (a0, a1, a2, a3) = kb.alpha;
+                let (b0, b1, b2, b3) = kb.beta;
+                for (coefs, kind) in [([a0, a1, a2, a3], 'A'), ([b0, b1, b2, b3], 'B')] {
+                    let line: String = coefs.iter().map(|c| fmt_ionod_coef(*c)).collect();
+                    writeln!(f, "{}{} {:<55}IONOSPHERIC CORR", system, kind, line)?;
+                }
+            } else if let Some(ng) = model.as_nequick_g() {
+                if self.version.major > 2 {
+                    let (a0, a1, a2) = ng.a;
+                    let line: String = [a0, a1, a2, 0.0]
+                        .iter()
+                        .map(|c| fmt_ionod_coef(*c))
+                        .collect();
+                    writeln!(f, "GAL  {:<55}IONOSPHERIC CORR", line)?;
+                }
+            }
+        }
+        // TIME SYSTEM CORR: NAV V4 streams these as frames
+        let mut time_corrections: Vec<_> = self.time_corrections.values().collect();
+        time_corrections.sort_by(|(_, a), (_, b)| a.system.cmp(&b.system));
+        if self.version.major > 3 {
+            time_corrections.clear();
+        }
+        for (epoch, sto) in time_corrections {
+            if self.version.major > 2 {
+                let (week, seconds) = time_system_week(&sto.system, *epoch);
+                let line = format!(
+                    "{:<4} {}{} {:6} {:4}",
+                    sto.system,
+                    fmt_nav_float(sto.a.0, 17, 10),
+                    fmt_nav_float(sto.a.1, 16, 9),
+                    seconds,
+                    week
+                );
+                writeln!(f, "{:<60}TIME SYSTEM CORR", line)?;
+            } else if sto.system.eq("GPUT") {
+                let (week, seconds) = time_system_week(&sto.system, *epoch);
+                let line = format!(
+                    "   {}{}{:9}{:9}",
+                    fmt_nav_float(sto.a.0, 19, 12),
+                    fmt_nav_float(sto.a.1, 19, 12),
+                    seconds,
+                    week
+                );
+                writeln!(f, "{:<60}DELTA-UTC: A0,A1,T,W", line.replace('E', "D"))?;
+            } else if sto.system.eq("GLUT") {
+                let (y, m, d, _, _, _, _) = epoch.to_gregorian_utc();
+                let line = format!("{:6}{:6}{:6}   {}", y, m, d, fmt_nav_float(sto.a.0, 19, 12));
+                writeln!(f, "{:<60}CORR TO SYSTEM TIME", line.replace('E', "D"))?;
+            }
+        }
         // LEAP
         if let Some(leap) = &self.leap {
             let mut line = String::new();
@@ -1654,41 +2105,7 @@ impl std::fmt::Display for Header {
         }
         // Custom IONEX fields
         if let Some(ionex) = &self.ionex {
-            //TODO:
-            //  EPOCH OF FIRST and LAST MAP
-            //   with epoch::format(Ionex)
-            let _ = write!(f, "{:6}           MAP DIMENSION\n", ionex.map_dimension);
-            let h = &ionex.grid.height;
-            let _ = write!(
-                f,
-                "{} {}  {}     HGT1 / HGT2 / DHGT\n",
-                h.start, h.end, h.spacing
-            );
-            let lat = &ionex.grid.latitude;
-            let _ = write!(
-                f,
-                "{} {}  {}     LAT1 / LON2 / DLAT\n",
-                lat.start, lat.end, lat.spacing
-            );
-            let lon = &ionex.grid.longitude;
-            let _ = write!(
-                f,
-                "{} {}  {}     LON1 / LON2 / DLON\n",
-                lon.start, lon.end, lon.spacing
-            );
-            let _ = write!(f, "{}         ELEVATION CUTOFF\n", ionex.elevation_cutoff);
-            if let Some(func) = &ionex.mapping {
-                let _ = write!(f, "{:?}         MAPPING FUNCTION\n", func);
-            } else {
-                let _ = write!(f, "NONE         MAPPING FUNCTION\n");
-            }
-            let _ = write!(f, "{}               EXPONENT\n", ionex.exponent);
-            if let Some(desc) = &ionex.description {
-                for line in 0..desc.len() / 60 {
-                    let max = std::cmp::min((line + 1) * 60, desc.len());
-                    let _ = write!(f, "{}                COMMENT\n", &desc[line * 60..max]);
-                }
-            }
+            write!(f, "{}", ionex)?;
         }
         // END OF HEADER
         write!(f, "{:>74}", "END OF HEADER\n")
@@ -1734,6 +2151,17 @@ impl Merge for Header {
         merge::merge_mut_option(&mut self.sv_antenna, &rhs.sv_antenna);
         merge::merge_mut_option(&mut self.ground_position, &rhs.ground_position);
         merge::merge_mut_option(&mut self.wavelengths, &rhs.wavelengths);
+        for (constellation, model) in &rhs.ionod_corrections {
+            if !self.ionod_corrections.contains_key(constellation) {
+                self.ionod_corrections.insert(*constellation, model.clone());
+            }
+        }
+        for (system, correction) in &rhs.time_corrections {
+            if !self.time_corrections.contains_key(system) {
+                self.time_corrections
+                    .insert(system.clone(), correction.clone());
+            }
+        }
 
         // RINEX specific operation
         if let Some(lhs) = &mut self.antex {
@@ -1760,6 +2188,7 @@ impl Merge for Header {
         if let Some(lhs) = &mut self.obs {
             if let Some(rhs) = &rhs.obs {
                 merge::merge_mut_option(&mut lhs.crinex, &rhs.crinex);
+                lhs.merge_phase_shifts_mut(rhs)?;
                 merge::merge_mut_unique_map2d(&mut lhs.codes, &rhs.codes);
                 lhs.clock_offset_applied |= rhs.clock_offset_applied;
             }
@@ -1784,6 +2213,13 @@ impl Merge for Header {
                 if lhs.base_radius != rhs.base_radius {
                     return Err(merge::Error::IonexBaseRadiusMismatch);
                 }
+                if let (Some(a), Some(b)) = (&lhs.mapping, &rhs.mapping) {
+                    if a != b {
+                        return Err(merge::Error::IonexMappingFunctionMismatch);
+                    }
+                }
+                // maps are scaled when parsed: retain the finest scaling
+                lhs.exponent = std::cmp::min(lhs.exponent, rhs.exponent);
                 merge::merge_mut_option(&mut lhs.description, &rhs.description);
                 merge::merge_mut_option(&mut lhs.mapping, &rhs.mapping);
                 if lhs.elevation_cutoff == 0.0 {
@@ -1793,6 +2229,14 @@ impl Merge for Header {
                 merge::merge_mut_option(&mut lhs.observables, &rhs.observables);
                 lhs.nb_stations = std::cmp::max(lhs.nb_stations, rhs.nb_stations);
                 lhs.nb_satellites = std::cmp::max(lhs.nb_satellites, rhs.nb_satellites);
+                lhs.epoch_of_first_map = match (lhs.epoch_of_first_map, rhs.epoch_of_first_map) {
+                    (Some(t0), Some(t1)) => Some(std::cmp::min(t0, t1)),
+                    (t0, t1) => t0.or(t1),
+                };
+                lhs.epoch_of_last_map = match (lhs.epoch_of_last_map, rhs.epoch_of_last_map) {
+                    (Some(t0), Some(t1)) => Some(std::cmp::max(t0, t1)),
+                    (t0, t1) => t0.or(t1),
+                };
                 for (b, dcb) in &rhs.dcbs {
                     lhs.dcbs.insert(b.clone(), *dcb);
                 }
@@ -1802,8 +2246,150 @@ impl Merge for Header {
     }
 }
 
+/*
+ * Parses 4 ionospheric model coefficients, formatted as 4D12.4
+ */
+fn parse_ionod_coefs(content: &str) -> Option<(f64, f64, f64, f64)> {
+    let mut coefs = [0.0_f64; 4];
+    for (i, coef) in coefs.iter_mut().enumerate() {
+        let item = content.get(i * 12..(i + 1) * 12)?;
+        *coef = f64::from_str(item.trim().replace('D', "E").as_str()).ok()?;
+    }
+    Some((coefs[0], coefs[1], coefs[2], coefs[3]))
+}
+
+/*
+ * Returns the MJD of the week counter origin of given time system:
+ * BeiDou systems count BDT weeks, others count GPS weeks
+ */
+fn time_system_origin(system: &str) -> i64 {
+    if system.starts_with("BD") {
+        53736
+    } else {
+        44244
+    }
+}
+
+/*
+ * Converts given time system reference epoch to (week, seconds of week).
+ * Epochs are labelled in their own timescale: week arithmetic
+ * is performed on the calendar labels, not across leap seconds.
+ */
+pub(crate) fn time_system_week(system: &str, epoch: Epoch) -> (u32, u32) {
+    let (y, m, d, hh, mm, ss, _) = epoch.to_gregorian_utc();
+    let mjd = Epoch::from_gregorian_utc_at_midnight(y, m, d)
+        .to_mjd_utc_days()
+        .round() as i64;
+    let days = mjd - time_system_origin(system);
+    let seconds = days.rem_euclid(7) * 86400 + hh as i64 * 3600 + mm as i64 * 60 + ss as i64;
+    (days.div_euclid(7) as u32, seconds as u32)
+}
+
+/*
+ * Builds a time system correction, referenced to given week and seconds of week
+ */
+pub(crate) fn time_system_correction(
+    system: &str,
+    a: (f64, f64),
+    week: u32,
+    seconds: u32,
+) -> (Epoch, StoMessage) {
+    let days = time_system_origin(system) + 7 * week as i64 + (seconds / 86400) as i64;
+    let epoch = Epoch::from_mjd_utc(days as f64) + Duration::from_seconds((seconds % 86400) as f64);
+    let sto = StoMessage {
+        system: system.to_string(),
+        utc: String::new(),
+        t_tm: seconds,
+        a: (a.0, a.1, 0.0),
+    };
+    (epoch, sto)
+}
+
+/*
+ * Parses a float, formatted with either D or E exponent
+ */
+fn parse_nav_float(content: &str) -> Option<f64> {
+    f64::from_str(content.trim().replace('D', "E").as_str()).ok()
+}
+
+/*
+ * Parses V3 TIME SYSTEM CORR: A4,1X,D17.10,D16.9,1X,I6,1X,I4
+ */
+fn parse_time_system_corr(content: &str) -> Option<(Epoch, StoMessage)> {
+    let system = content.get(0..4)?.trim();
+    let a0 = parse_nav_float(content.get(5..22)?)?;
+    let a1 = parse_nav_float(content.get(22..38)?)?;
+    let seconds = u32::from_str(content.get(38..45)?.trim()).ok()?;
+    let week = u32::from_str(content.get(45..50)?.trim()).ok()?;
+    Some(time_system_correction(system, (a0, a1), week, seconds))
+}
+
+/*
+ * Parses V2 GPS DELTA-UTC: 3X,2D19.12,2I9
+ */
+fn parse_delta_utc(content: &str) -> Option<(Epoch, StoMessage)> {
+    let a0 = parse_nav_float(content.get(3..22)?)?;
+    let a1 = parse_nav_float(content.get(22..41)?)?;
+    let seconds = u32::from_str(content.get(41..50)?.trim()).ok()?;
+    let week = u32::from_str(content.get(50..59)?.trim()).ok()?;
+    Some(time_system_correction("GPUT", (a0, a1), week, seconds))
+}
+
+/*
+ * Parses V2 GLONASS CORR TO SYSTEM TIME: 3I6,3X,D19.12
+ */
+fn parse_corr_to_system_time(content: &str) -> Option<(Epoch, StoMessage)> {
+    let y = i32::from_str(content.get(0..6)?.trim()).ok()?;
+    let m = u8::from_str(content.get(6..12)?.trim()).ok()?;
+    let d = u8::from_str(content.get(12..18)?.trim()).ok()?;
+    let a0 = parse_nav_float(content.get(21..40)?)?;
+    let epoch = Epoch::from_gregorian_utc_at_midnight(y, m, d);
+    let (week, seconds) = time_system_week("GLUT", epoch);
+    Some(time_system_correction("GLUT", (a0, 0.0), week, seconds))
+}
+
+/*
+ * Returns Klobuchar model being built for given constellation
+ */
+fn kb_model(
+    models: &mut HashMap<Constellation, IonMessage>,
+    constellation: Constellation,
+) -> &mut KbModel {
+    let model = models
+        .entry(constellation)
+        .or_insert(IonMessage::KlobucharModel(KbModel::default()));
+    if model.as_klobuchar().is_none() {
+        *model = IonMessage::KlobucharModel(KbModel::default());
+    }
+    match model {
+        IonMessage::KlobucharModel(model) => model,
+        _ => unreachable!(),
+    }
+}
+
+/*
+ * Formats an ionospheric model coefficient as D12.4
+ */
+fn fmt_ionod_coef(value: f64) -> String {
+    fmt_nav_float(value, 12, 4)
+}
+
 #[cfg(test)]
 mod test {
+    use super::*;
+    #[test]
+    fn ionod_coefs() {
+        let (a0, a1, a2, a3) =
+            parse_ionod_coefs("  0.7451D-08 -0.1490D-07 -0.5960D-07  0.1192D-06").unwrap();
+        assert_eq!(
+            (a0, a1, a2, a3),
+            (0.7451E-8, -0.1490E-7, -0.5960E-7, 0.1192E-6)
+        );
+        assert!(parse_ionod_coefs("   1.1176e-08  2.9802e-08").is_none());
+        assert_eq!(fmt_ionod_coef(7.451E-9), "  7.4510E-09");
+        assert_eq!(fmt_ionod_coef(-131072.0), " -1.3107E+05");
+        assert_eq!(fmt_ionod_coef(0.0), "  0.0000E+00");
+    }
     #[test]
     fn test_from_b_fmt_month() {
         assert_eq!(from_b_fmt_month!("Jan"), 1);
@@ -50,12 +50,40 @@ impl GridLinspace {
     }
     // Returns grid length, in terms of data points
     pub fn length(&self) -> usize {
-        (self.end / self.spacing).floor() as usize
+        if self.spacing == 0.0 {
+            1
+        } else {
+            ((self.end - self.start) / self.spacing).round() as usize + 1
+        }
     }
     /// Returns true if self is a single point space
     pub fn is_single_point(&self) -> bool {
         (self.end == self.start) && self.spacing == 0.0
     }
+    /*
+     * Locates given coordinate in this space:
+     * returns the index of the preceding grid point
+     * and the normalized distance to it, in [0, 1].
+     * Returns None outside of this space.
+     */
+    pub(crate) fn locate(&self, coord: f64) -> Option<(usize, f64)> {
+        if self.spacing == 0.0 {
+            return if coord == self.start {
+                Some((0, 0.0))
+            } else {
+                None
+            };
+        }
+        let x = (coord - self.start) / self.spacing;
+        let last = (self.length() - 1) as f64;
+        if x < -1.0E-9 || x > last + 1.0E-9 {
+            return None;
+        }
+        let x = x.clamp(0.0, last);
+        // last point: interpolate from the previous node
+        let index = (x.floor() as usize).min(self.length().saturating_sub(2));
+        Some((index, x - index as f64))
+    }
 }
 
 impl From<(f64, f64, f64)> for GridLinspace {
@@ -94,6 +122,15 @@ impl Grid {
     pub fn is_2d_grid(&self) -> bool {
         self.height.is_single_point()
     }
+    /*
+     * Index of given grid node, in a [crate::ionex::Map]:
+     * maps are described per height layer,
+     * then latitude band, then longitude
+     */
+    pub(crate) fn index(&self, height: usize, latitude: usize, longitude: usize) -> usize {
+        let (nlat, nlon) = (self.latitude.length(), self.longitude.length());
+        (height * nlat + latitude) * nlon + longitude
+    }
 }
 
 #[cfg(test)]
@@ -113,5 +150,17 @@ mod test {
         let grid = GridLinspace::new(1.0, 10.0, 1.0).unwrap();
         assert_eq!(grid.length(), 10);
         assert_eq!(grid.is_single_point(), false);
+        assert_eq!(grid.locate(1.0), Some((0, 0.0)));
+        assert_eq!(grid.locate(2.5), Some((1, 0.5)));
+        assert_eq!(grid.locate(10.0), Some((8, 1.0)));
+        assert!(grid.locate(0.5).is_none());
+        assert!(grid.locate(10.5).is_none());
+        let grid = GridLinspace::new(87.5, -87.5, -2.5).unwrap();
+        assert_eq!(grid.length(), 71);
+        assert_eq!(grid.locate(86.25), Some((0, 0.5)));
+        assert_eq!(grid.locate(-87.5), Some((69, 1.0)));
+        let grid = GridLinspace::from((350.0, 350.0, 0.0));
+        assert_eq!(grid.length(), 1);
+        assert_eq!(grid.locate(350.0), Some((0, 0.0)));
     }
 }
@@ -0,0 +1,174 @@
+//! IONEX maps interpolation
+use super::{Grid, Map, Record};
+use crate::prelude::*;
+
+/// Earth rotation rate, in degrees per second,
+/// used in the "rotated maps" temporal interpolation
+const EARTH_ROTATION_DEG_S: f64 = 360.0 / 86400.0;
+
+/*
+ * Returns map value at given grid node, in O(1) when the map
+ * strictly follows the grid definition
+ */
+fn node(map: &Map, grid: &Grid, latitude: usize, longitude: usize) -> Option<f64> {
+    let lat = grid.latitude.start + grid.latitude.spacing * latitude as f64;
+    let lon = grid.longitude.start + grid.longitude.spacing * longitude as f64;
+    let matches = |p: &super::record::MapPoint| {
+        (p.latitude - lat).abs() < 1.0E-6 && (p.longitude - lon).abs() < 1.0E-6
+    };
+    match map.get(grid.index(0, latitude, longitude)) {
+        Some(p) if matches(p) => Some(p.value),
+        _ => {
+            // this map does not follow the grid definition (merged or partial map)
+            map.iter().find(|p| matches(p)).map(|p| p.value)
+        },
+    }
+}
+
+/*
+ * 4-point bilinear interpolation of given map,
+ * at given latitude and longitude [°] (IONEX specs §2.4)
+ */
+pub(crate) fn bilinear(map: &Map, grid: &Grid, lat: f64, lon: f64) -> Option<f64> {
+    let lon_grid = &grid.longitude;
+    let span = (lon_grid.end - lon_grid.start).abs();
+    let lon = if (span - 360.0).abs() < 1.0E-6 {
+        // global map: wrap around
+        let west = lon_grid.start.min(lon_grid.end);
+        west + (lon - west).rem_euclid(360.0)
+    } else {
+        lon
+    };
+    let (i, q) = grid.latitude.locate(lat)?;
+    let (j, p) = lon_grid.locate(lon)?;
+    let (i1, j1) = (
+        (i + 1).min(grid.latitude.length() - 1),
+        (j + 1).min(lon_grid.length() - 1),
+    );
+    let e00 = node(map, grid, i, j)?;
+    let e10 = node(map, grid, i, j1)?;
+    let e01 = node(map, grid, i1, j)?;
+    let e11 = node(map, grid, i1, j1)?;
+    Some((1.0 - p) * (1.0 - q) * e00 + p * (1.0 - q) * e10 + q * (1.0 - p) * e01 + p * q * e11)
+}
+
+/*
+ * Interpolates TEC and RMS values at given Epoch and coordinates,
+ * using the "rotated maps" method in between two consecutive maps
+ */
+pub(crate) fn tec_at(
+    record: &Record,
+    grid: &Grid,
+    t: Epoch,
+    lat: f64,
+    lon: f64,
+) -> Option<(f64, Option<f64>)> {
+    let (t0, (tec0, rms0, _)) = record.range(..=t).next_back()?;
+    if *t0 == t {
+        let tec = bilinear(tec0, grid, lat, lon)?;
+        let rms = rms0.as_ref().and_then(|rms| bilinear(rms, grid, lat, lon));
+        return Some((tec, rms));
+    }
+    let (t1, (tec1, rms1, _)) = record.range(t..).next()?;
+    let dt = (*t1 - *t0).to_seconds();
+    let (w0, w1) = ((*t1 - t).to_seconds() / dt, (t - *t0).to_seconds() / dt);
+    // the ionosphere is rather fixed with respect to the Sun
+    let lon0 = lon + (t - *t0).to_seconds() * EARTH_ROTATION_DEG_S;
+    let lon1 = lon + (t - *t1).to_seconds() * EARTH_ROTATION_DEG_S;
+    let tec = w0 * bilinear(tec0, grid, lat, lon0)? + w1 * bilinear(tec1, grid, lat, lon1)?;
+    let rms = match (rms0, rms1) {
+        (Some(rms0), Some(rms1)) => {
+            match (
+                bilinear(rms0, grid, lat, lon0),
+                bilinear(rms1, grid, lat, lon1),
+            ) {
+                (Some(rms0), Some(rms1)) => Some(w0 * rms0 + w1 * rms1),
+                _ => None,
+            }
+        },
+        _ => None,
+    };
+    Some((tec, rms))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::record::MapPoint;
+    use super::super::GridLinspace;
+    use super::*;
+    fn grid() -> Grid {
+        Grid {
+            latitude: GridLinspace::new(10.0, -10.0, -5.0).unwrap(),
+            longitude: GridLinspace::new(-180.0, 180.0, 5.0).unwrap(),
+            height: GridLinspace::from((350.0, 350.0, 0.0)),
+        }
+    }
+    /*
+     * Builds a map on given grid
+     */
+    fn map(grid: &Grid, f: &dyn Fn(f64, f64) -> f64) -> Map {
+        let mut map = Map::new();
+        for i in 0..grid.latitude.length() {
+            let lat = grid.latitude.start + grid.latitude.spacing * i as f64;
+            for j in 0..grid.longitude.length() {
+                let lon = grid.longitude.start + grid.longitude.spacing * j as f64;
+                map.push(MapPoint {
+                    latitude: lat,
+                    longitude: lon,
+                    altitude: 350.0,
+                    value: f(lat, lon),
+                });
+            }
+        }
+        map
+    }
+    #[test]
+    fn bilinear_interpolation() {
+        let grid = grid();
+        let f = |lat: f64, lon: f64| 20.0 + 0.5 * lat + 0.1 * (lon + 180.0);
+        let map = map(&grid, &f);
+        // grid nodes
+        assert_eq!(bilinear(&map, &grid, 10.0, -180.0), Some(f(10.0, -180.0)));
+        assert_eq!(bilinear(&map, &grid, -10.0, 175.0), Some(f(-10.0, 175.0)));
+        // 180°E is wrapped to 180°W
+        assert_eq!(bilinear(&map, &grid, -10.0, 180.0), Some(f(-10.0, -180.0)));
+        // bilinear is exact on a linear trend
+        for (lat, lon) in [(7.3, 12.1), (-2.5, -177.5), (0.0, 179.0)] {
+            let value = bilinear(&map, &grid, lat, lon).unwrap();
+            assert!((value - f(lat, lon)).abs() < 1.0E-9);
+        }
+        // longitude wrapping
+        let value = bilinear(&map, &grid, 0.0, 190.0).unwrap();
+        assert!((value - f(0.0, -170.0)).abs() < 1.0E-9);
+        // outside of the grid
+        assert!(bilinear(&map, &grid, 12.0, 0.0).is_none());
+        // map not following the grid definition
+        let mut shuffled = map.clone();
+        shuffled.reverse();
+        let value = bilinear(&shuffled, &grid, 7.3, 12.1).unwrap();
+        assert!((value - f(7.3, 12.1)).abs() < 1.0E-9);
+    }
+    #[test]
+    fn rotated_maps() {
+        let grid = grid();
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2022, 1, 1);
+        let t1 = t0 + Duration::from_hours(2.0);
+        // a feature fixed with respect to the Sun: moves 30° westward in 2h
+        let f0 = |_: f64, lon: f64| lon.to_radians().cos() + 2.0;
+        let f1 = |_: f64, lon: f64| (lon + 30.0).to_radians().cos() + 2.0;
+        let mut record = Record::new();
+        record.insert(t0, (map(&grid, &f0), Some(map(&grid, &|_, _| 1.0)), None));
+        record.insert(t1, (map(&grid, &f1), Some(map(&grid, &|_, _| 3.0)), None));
+        let t = t0 + Duration::from_hours(1.0);
+        let (tec, rms) = tec_at(&record, &grid, t, 0.0, 0.0).unwrap();
+        // rotated maps: both maps agree
+        assert!((tec - f0(0.0, 15.0)).abs() < 1.0E-3, "{}", tec);
+        assert_eq!(rms, Some(2.0));
+        // exact epoch
+        let (tec, rms) = tec_at(&record, &grid, t1, 0.0, 0.0).unwrap();
+        assert!((tec - f1(0.0, 0.0)).abs() < 1.0E-9);
+        assert_eq!(rms, Some(3.0));
+        // outside of the record
+        assert!(tec_at(&record, &grid, t1 + Duration::from_seconds(1.0), 0.0, 0.0).is_none());
+    }
+}
@@ -1,6 +1,7 @@
 //! IONEX module
 use super::Sv;
 use std::collections::HashMap;
+use std::str::FromStr;
 use strum_macros::EnumString;
 
 pub mod record;
@@ -9,12 +10,58 @@ pub use record::{Map, Record};
 pub mod grid;
 pub use grid::{Grid, GridLinspace};
 
+pub(crate) mod interpolation;
+
 pub mod system;
 pub use system::RefSystem;
 
 #[cfg(feature = "serde")]
 use serde::Serialize;
 
+use crate::carrier::Carrier;
+use crate::prelude::Epoch;
+
+/// Mean Earth radius [km], when the base radius is not described
+const MEAN_EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Converts a Total Electron Content [TECu] to the ionospheric delay [m]
+/// it induces on given carrier: pseudo ranges are delayed
+/// and carrier phases advanced by this amount
+pub fn tec_to_delay(tec: f64, carrier: Carrier) -> f64 {
+    40.3E16 * tec / carrier.frequency().powi(2)
+}
+
+/*
+ * Parses an IONEX epoch description (6I6)
+ */
+pub(crate) fn parse_epoch(content: &str) -> Option<Epoch> {
+    let items: Vec<&str> = content.split_ascii_whitespace().collect();
+    if items.len() != 6 {
+        return None;
+    }
+    let y = items[0].parse::<i32>().ok()?;
+    let mut fields = [0_u8; 5];
+    for (field, item) in fields.iter_mut().zip(&items[1..]) {
+        *field = item.parse::<u8>().ok()?;
+    }
+    let [m, d, hh, mm, ss] = fields;
+    Some(Epoch::from_gregorian_utc(y, m, d, hh, mm, ss, 0))
+}
+
+/*
+ * Parses (bias, rms) values, that terminate DCB descriptions
+ */
+pub(crate) fn parse_bias(content: &str) -> Option<(f64, f64)> {
+    let items: Vec<&str> = content.split_ascii_whitespace().collect();
+    let n = items.len();
+    if n < 2 {
+        return None;
+    }
+    let bias = f64::from_str(items[n - 2]).ok()?;
+    let rms = f64::from_str(items[n - 1]).ok()?;
+    Some((bias, rms))
+}
+
 #[derive(Debug, Clone, PartialEq, PartialOrd, EnumString)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 /// Mapping function used in when determining this IONEX
@@ -27,6 +74,15 @@ pub enum MappingFunction {
     QFac,
 }
 
+impl std::fmt::Display for MappingFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::CosZ => f.write_str("COSZ"),
+            Self::QFac => f.write_str("QFAC"),
+        }
+    }
+}
+
 /// Possible source of DCBs
 #[derive(Debug, Clone, PartialEq, PartialOrd, Hash, Eq, EnumString)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -57,6 +113,12 @@ pub struct HeaderFields {
     pub base_radius: f32,
     /// Reference rid definition.
     pub grid: grid::Grid,
+    /// Epoch of first map
+    pub epoch_of_first_map: Option<Epoch>,
+    /// Epoch of last map
+    pub epoch_of_last_map: Option<Epoch>,
+    /// Number of maps in file
+    pub nb_maps: u32,
     /// Minimum elevation angle filter used. In degrees.
     pub elevation_cutoff: f32,
     /// Verbose description of observables used in determination.
@@ -85,6 +147,9 @@ impl Default for HeaderFields {
             elevation_cutoff: 0.0,
             base_radius: 0.0,
             grid: grid::Grid::default(),
+            epoch_of_first_map: None,
+            epoch_of_last_map: None,
+            nb_maps: 0,
             nb_stations: 0,
             nb_satellites: 0,
             dcbs: HashMap::new(),
@@ -135,9 +200,33 @@ impl HeaderFields {
         s
     }
     /// Returns true if this Ionosphere Maps describes
-    /// a theoretical model, not measured data
+    /// a theoretical model, not measured data:
+    /// OBSERVABLES USED is left blank for theoretical models
     pub fn is_theoretical_model(&self) -> bool {
-        self.observables.is_some()
+        self.observables.is_none()
+    }
+    /// Copies self and sets epoch of first and last maps
+    pub fn with_map_epochs(&self, first: Epoch, last: Epoch) -> Self {
+        let mut s = self.clone();
+        s.epoch_of_first_map = Some(first);
+        s.epoch_of_last_map = Some(last);
+        s
+    }
+    /// Copies self and set number of maps
+    pub fn with_nb_maps(&self, n: u32) -> Self {
+        let mut s = self.clone();
+        s.nb_maps = n;
+        s
+    }
+    /*
+     * Copies self and describes the maps of given record
+     */
+    pub(crate) fn with_record_maps(&self, record: &Record) -> Self {
+        let mut s = self.with_nb_maps(record.len() as u32);
+        if let (Some(first), Some(last)) = (record.keys().next(), record.keys().next_back()) {
+            s = s.with_map_epochs(*first, *last);
+        }
+        s
     }
     /// Copies self and set number of stations
     pub fn with_nb_stations(&self, n: u32) -> Self {
@@ -180,6 +269,46 @@ impl HeaderFields {
         s.grid.height = grid;
         s
     }
+    /// Returns the single layer (shell) height [km]: the fixed altitude
+    /// of 2D maps, or the bottom of the height grid of 3D maps
+    pub fn shell_height(&self) -> f64 {
+        self.grid.height.start
+    }
+    /*
+     * Base radius [km]
+     */
+    fn radius(&self) -> f64 {
+        if self.base_radius > 0.0 {
+            self.base_radius as f64
+        } else {
+            MEAN_EARTH_RADIUS_KM
+        }
+    }
+    /// Returns the ionospheric pierce point (latitude, longitude) [°],
+    /// where the line of sight crosses the single layer,
+    /// seen from given receiver coordinates [°], at given Sv elevation and azimuth [°]
+    pub fn pierce_point(&self, lat: f64, lon: f64, elev: f64, azim: f64) -> (f64, f64) {
+        let (lat, lon) = (lat.to_radians(), lon.to_radians());
+        let (elev, azim) = (elev.to_radians(), azim.to_radians());
+        let ratio = self.radius() / (self.radius() + self.shell_height());
+        // earth centered angle
+        let psi = std::f64::consts::FRAC_PI_2 - elev - (ratio * elev.cos()).asin();
+        let lat_p = (lat.sin() * psi.cos() + lat.cos() * psi.sin() * azim.cos()).asin();
+        let lon_p =
+            lon + (psi.sin() * azim.sin() * lat.cos()).atan2(psi.cos() - lat.sin() * lat_p.sin());
+        let lon_p = (lon_p.to_degrees() + 180.0).rem_euclid(360.0) - 180.0;
+        (lat_p.to_degrees(), lon_p)
+    }
+    /// Returns the mapping factor (slant to vertical TEC ratio)
+    /// at given Sv elevation [°]. Both [MappingFunction::CosZ] and
+    /// undescribed mapping functions use the standard single layer model.
+    /// [MappingFunction::QFac] is not described by the specifications
+    /// and is approximated by the single layer model as well.
+    pub fn mapping_factor(&self, elev: f64) -> f64 {
+        let ratio = self.radius() / (self.radius() + self.shell_height());
+        let sin_z = ratio * elev.to_radians().cos();
+        1.0 / (1.0 - sin_z.powi(2)).sqrt()
+    }
     /// Copies & sets Diffenretial Code Bias estimates
     /// for given vehicle
     pub fn with_dcb(&self, src: BiasSource, value: (f64, f64)) -> Self {
@@ -189,11 +318,144 @@ impl HeaderFields {
     }
 }
 
+impl std::fmt::Display for HeaderFields {
+    /// Formats IONEX specific header fields
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(desc) = &self.description {
+            let mut line = String::new();
+            for word in desc.split_ascii_whitespace() {
+                if !line.is_empty() && line.len() + word.len() >= 60 {
+                    writeln!(f, "{:<60}DESCRIPTION", line)?;
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
+            }
+            if !line.is_empty() {
+                writeln!(f, "{:<60}DESCRIPTION", line)?;
+            }
+        }
+        if let Some(epoch) = self.epoch_of_first_map {
+            let epoch = crate::epoch::format(epoch, None, crate::types::Type::IonosphereMaps, 1);
+            writeln!(f, "{:<60}EPOCH OF FIRST MAP", epoch)?;
+        }
+        if let Some(epoch) = self.epoch_of_last_map {
+            let epoch = crate::epoch::format(epoch, None, crate::types::Type::IonosphereMaps, 1);
+            writeln!(f, "{:<60}EPOCH OF LAST MAP", epoch)?;
+        }
+        if self.nb_maps > 0 {
+            writeln!(f, "{:6}{:54}# OF MAPS IN FILE", self.nb_maps, "")?;
+        }
+        let mapping = match &self.mapping {
+            Some(mapping) => mapping.to_string(),
+            None => "NONE".to_string(),
+        };
+        writeln!(f, "{:>6}{:54}MAPPING FUNCTION", mapping, "")?;
+        writeln!(f, "{:8.1}{:52}ELEVATION CUTOFF", self.elevation_cutoff, "")?;
+        if let Some(observables) = &self.observables {
+            writeln!(f, "{:<60}OBSERVABLES USED", observables)?;
+        }
+        if self.nb_stations > 0 {
+            writeln!(f, "{:6}{:54}# OF STATIONS", self.nb_stations, "")?;
+        }
+        if self.nb_satellites > 0 {
+            writeln!(f, "{:6}{:54}# OF SATELLITES", self.nb_satellites, "")?;
+        }
+        writeln!(f, "{:8.1}{:52}BASE RADIUS", self.radius(), "")?;
+        writeln!(f, "{:6}{:54}MAP DIMENSION", self.map_dimension, "")?;
+        for (grid, label) in [
+            (&self.grid.height, "HGT1 / HGT2 / DHGT"),
+            (&self.grid.latitude, "LAT1 / LAT2 / DLAT"),
+            (&self.grid.longitude, "LON1 / LON2 / DLON"),
+        ] {
+            writeln!(
+                f,
+                "  {:6.1}{:6.1}{:6.1}{:40}{}",
+                grid.start, grid.end, grid.spacing, "", label
+            )?;
+        }
+        writeln!(f, "{:6}{:54}EXPONENT", self.exponent, "")?;
+        if !self.dcbs.is_empty() {
+            writeln!(f, "{:<60}START OF AUX DATA", "DIFFERENTIAL CODE BIASES")?;
+            let mut svs: Vec<_> = self
+                .dcbs
+                .iter()
+                .filter_map(|(src, value)| match src {
+                    BiasSource::SpaceVehicle(sv) => Some((sv, value)),
+                    _ => None,
+                })
+                .collect();
+            svs.sort_by_key(|(sv, _)| **sv);
+            for (sv, (bias, rms)) in svs {
+                let sv = format!("{}{:02}", sv.constellation.to_1_letter_code(), sv.prn);
+                writeln!(
+                    f,
+                    "   {}{:10.3}{:10.3}{:34}PRN / BIAS / RMS",
+                    sv, bias, rms, ""
+                )?;
+            }
+            let mut stations: Vec<_> = self
+                .dcbs
+                .iter()
+                .filter_map(|(src, value)| match src {
+                    BiasSource::Station(name) => Some((name, value)),
+                    _ => None,
+                })
+                .collect();
+            stations.sort_by_key(|(name, _)| *name);
+            for (name, (bias, rms)) in stations {
+                writeln!(
+                    f,
+                    "{:6}{:<20}{:10.3}{:10.3}{:14}STATION / BIAS / RMS",
+                    "", name, bias, rms, ""
+                )?;
+            }
+            writeln!(f, "{:<60}END OF AUX DATA", "DIFFERENTIAL CODE BIASES")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::str::FromStr;
     #[test]
+    fn pierce_point() {
+        let ionex = HeaderFields::default()
+            .with_base_radius(6371.0)
+            .with_altitude_grid(GridLinspace::from((350.0, 350.0, 0.0)));
+        assert_eq!(ionex.shell_height(), 350.0);
+        // zenith
+        let (lat, lon) = ionex.pierce_point(45.0, 5.0, 90.0, 0.0);
+        assert!((lat - 45.0).abs() < 1.0E-9 && (lon - 5.0).abs() < 1.0E-9);
+        assert!((ionex.mapping_factor(90.0) - 1.0).abs() < 1.0E-9);
+        // north-bound: ~ 4.8° at 30° elevation
+        let (lat, lon) = ionex.pierce_point(45.0, 5.0, 30.0, 0.0);
+        assert!((lat - 49.82).abs() < 0.01, "{}", lat);
+        assert!((lon - 5.0).abs() < 1.0E-9);
+        // east-bound, across the antimeridian
+        let (lat, lon) = ionex.pierce_point(0.0, 179.0, 30.0, 90.0);
+        assert!(lat.abs() < 1.0E-9);
+        assert!((lon + 176.18).abs() < 0.01, "{}", lon);
+        // mapping factor increases towards the horizon
+        let factor = ionex.mapping_factor(30.0);
+        assert!(factor > 1.7 && factor < 1.8, "{}", factor);
+        assert!(ionex.mapping_factor(10.0) > factor);
+        // 1 TECu ~ 16cm on L1
+        let delay = tec_to_delay(1.0, Carrier::L1);
+        assert!((delay - 0.162).abs() < 1.0E-3);
+    }
+    #[test]
+    fn theoretical_model() {
+        let model = HeaderFields::default().with_observables("");
+        assert!(model.is_theoretical_model());
+        let measured = model.with_observables("One-way carrier phase leveled to code");
+        assert!(!measured.is_theoretical_model());
+    }
+    #[test]
     fn test_mapping_func() {
         let content = "COSZ";
         let func = MappingFunction::from_str(content);
@@ -1,9 +1,11 @@
-use crate::{gnss_time::GnssTime, merge, merge::Merge, prelude::*, split, split::Split};
+use crate::{
+    epoch, gnss_time::GnssTime, merge, merge::Merge, prelude::*, split, split::Split, types::Type,
+};
 
 use super::{grid, GridLinspace};
 
 use hifitime::Duration;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
 use thiserror::Error;
 
@@ -25,6 +27,97 @@ pub(crate) fn is_new_map(line: &str) -> bool {
     is_new_tec_map(line) || is_new_rms_map(line) || is_new_height_map(line)
 }
 
+/*
+ * Stores a parsed map: TEC maps introduce a new epoch,
+ * RMS and height maps are attached to the TEC map of same epoch,
+ * or of same index (1-based) when their epoch is not described
+ */
+pub(crate) fn insert_map(
+    record: &mut Record,
+    epochs: &mut Vec<Epoch>,
+    content: &str,
+    index: usize,
+    epoch: Epoch,
+    map: Map,
+) {
+    let descriptor = content.lines().next().unwrap_or("");
+    let (rms, height) = (is_new_rms_map(descriptor), is_new_height_map(descriptor));
+    if !rms && !height {
+        epochs.push(epoch);
+        record.insert(epoch, (map, None, None));
+        return;
+    }
+    let epoch = if record.contains_key(&epoch) {
+        Some(epoch)
+    } else {
+        index
+            .checked_sub(1)
+            .and_then(|index| epochs.get(index))
+            .copied()
+    };
+    if let Some((_, rms_map, height_map)) = epoch.and_then(|epoch| record.get_mut(&epoch)) {
+        if rms {
+            *rms_map = Some(map);
+        } else {
+            *height_map = Some(map);
+        }
+    }
+}
+
+/*
+ * Formats given map, described at given Epoch, following the grid definition.
+ * `kind` is either "TEC", "RMS" or "HEIGHT".
+ * Grid nodes that are not described are formatted as non available (9999)
+ */
+pub(crate) fn fmt_map(
+    index: usize,
+    epoch: Epoch,
+    map: &Map,
+    kind: &str,
+    header: &super::HeaderFields,
+) -> String {
+    let key = |lat: f64, lon: f64, alt: f64| {
+        (
+            (lat * 1.0E3).round() as i64,
+            (lon * 1.0E3).round() as i64,
+            (alt * 1.0E3).round() as i64,
+        )
+    };
+    let values: HashMap<_, f64> = map
+        .iter()
+        .map(|p| (key(p.latitude, p.longitude, p.altitude), p.value))
+        .collect();
+    let scaling = 10.0_f64.powi(header.exponent as i32);
+    let grid = &header.grid;
+    let mut lines = format!("{:6}{:54}START OF {} MAP\n", index, "", kind);
+    let epoch = epoch::format(epoch, None, Type::IonosphereMaps, 1);
+    lines.push_str(&format!("{:<60}EPOCH OF CURRENT MAP\n", epoch));
+    for i in 0..grid.height.length() {
+        let alt = grid.height.start + grid.height.spacing * i as f64;
+        for j in 0..grid.latitude.length() {
+            let lat = grid.latitude.start + grid.latitude.spacing * j as f64;
+            let lon = &grid.longitude;
+            lines.push_str(&format!(
+                "  {:6.1}{:6.1}{:6.1}{:6.1}{:6.1}{:28}LAT/LON1/LON2/DLON/H\n",
+                lat, lon.start, lon.end, lon.spacing, alt, ""
+            ));
+            for k in 0..lon.length() {
+                let lon = lon.start + lon.spacing * k as f64;
+                let value = match values.get(&key(lat, lon, alt)) {
+                    Some(value) => (value / scaling).round() as i64,
+                    None => 9999,
+                };
+                lines.push_str(&format!("{:5}", value));
+                if (k + 1) % 16 == 0 || k + 1 == grid.longitude.length() {
+                    lines.push('\n');
+                }
+            }
+        }
+    }
+    lines.push_str(&format!("{:6}{:54}END OF {} MAP\n", index, "", kind));
+    lines
+}
+
 /// A Map is a list of estimates for
 /// a given Latitude, Longitude, Altitude
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
@@ -167,23 +260,7 @@ pub(crate) fn parse_map(header: &mut Header, content: &str) -> Result<(usize, Ep
                 ptr = 0;
             } else if marker.contains("EPOCH OF CURRENT MAP") {
                 // time definition
-                let items: Vec<&str> = content.split_ascii_whitespace().collect();
-                if items.len() != 6 {
-                    return Err(Error::EpochDescriptionError);
-                }
-                if let Ok(y) = i32::from_str_radix(items[0].trim(), 10) {
-                    if let Ok(m) = u8::from_str_radix(items[1].trim(), 10) {
-                        if let Ok(d) = u8::from_str_radix(items[2].trim(), 10) {
-                            if let Ok(hh) = u8::from_str_radix(items[3].trim(), 10) {
-                                if let Ok(mm) = u8::from_str_radix(items[4].trim(), 10) {
-                                    if let Ok(ss) = u8::from_str_radix(items[5].trim(), 10) {
-                                        epoch = Epoch::from_gregorian_utc(y, m, d, hh, mm, ss, 0);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+                epoch = super::parse_epoch(content).ok_or(Error::EpochDescriptionError)?;
             } else if marker.contains("EXPONENT") {
                 // scaling redefinition
                 if let Ok(e) = i8::from_str_radix(content.trim(), 10) {
@@ -193,6 +270,11 @@ pub(crate) fn parse_map(header: &mut Header, content: &str) -> Result<(usize, Ep
                 // parsing TEC values
                 for item in line.split_ascii_whitespace().into_iter() {
                     if let Ok(v) = i32::from_str_radix(item.trim(), 10) {
+                        if v == 9999 {
+                            // non available value
+                            ptr += 1;
+                            continue;
+                        }
                         // parse & apply correct scaling
                         let mut value = v as f64;
                         value *= 10.0_f64.powf(ionex.exponent as f64);
@@ -211,6 +293,11 @@ pub(crate) fn parse_map(header: &mut Header, content: &str) -> Result<(usize, Ep
             // parsing TEC values
             for item in line.split_ascii_whitespace().into_iter() {
                 if let Ok(v) = i32::from_str_radix(item.trim(), 10) {
+                    if v == 9999 {
+                        // non available value
+                        ptr += 1;
+                        continue;
+                    }
                     // parse & apply correct scaling
                     let mut value = v as f64;
                     value *= 10.0_f64.powf(ionex.exponent as f64);
@@ -393,11 +480,11 @@ impl Merge for Record {
             if let Some(lhs_maps) = self.get_mut(epoch) {
                 let (lhs_tec, lhs_rms, lhs_h) = lhs_maps;
 
-                map_merge3d_mut(&mut lhs_tec.to_vec(), tec);
+                map_merge3d_mut(lhs_tec, tec);
 
                 if let Some(map) = rms {
                     if let Some(lhs_map) = lhs_rms {
-                        map_merge3d_mut(&mut lhs_map.to_vec(), map);
+                        map_merge3d_mut(lhs_map, map);
                     } else {
                         *lhs_rms = Some(map.to_vec()); // RMS map now provided
                     }
@@ -405,7 +492,7 @@ impl Merge for Record {
 
                 if let Some(map) = h {
                     if let Some(lhs_map) = lhs_h {
-                        map_merge3d_mut(&mut lhs_map.to_vec(), map);
+                        map_merge3d_mut(lhs_map, map);
                     } else {
                         *lhs_h = Some(map.to_vec()); // H map now provided
                     }
@@ -56,6 +56,9 @@ use observable::Observable;
 use observation::Crinex;
 use version::Version;
 
+/// Speed of light in vacuum [m.s⁻¹]
+pub(crate) const SPEED_OF_LIGHT: f64 = 299_792_458.0_f64;
+
 /// Package to include all basic structures
 pub mod prelude {
     pub use crate::constellation::{Augmentation, Constellation};
@@ -79,6 +82,18 @@ pub mod preprocessing {
     pub use crate::algorithm::*;
 }
 
+#[cfg(feature = "positioning")]
+#[cfg_attr(docrs, doc(cfg(feature = "positioning")))]
+pub mod positioning;
+
+#[cfg(feature = "obs")]
+#[cfg_attr(docrs, doc(cfg(feature = "obs")))]
+pub mod cs;
+
+#[cfg(all(feature = "obs", feature = "processing"))]
+#[cfg_attr(docrs, doc(cfg(all(feature = "obs", feature = "processing"))))]
+pub mod differential;
+
 #[cfg(feature = "qc")]
 mod qc;
 
@@ -316,6 +331,163 @@ impl Rinex {
         }
     }
 
+    /// Converts this Observation RINEX to given revision, for example
+    /// legacy RINEX2 to RINEX3 or RINEX4, or the other way around.
+    /// RINEX2 observables only describe the frequency band, `modes` describes the
+    /// signals the receiver actually tracked (see [observation::TrackingModes]).
+    /// Header observables are regenerated and observations renamed accordingly.
+    /// When converting to RINEX2, observations that cannot be described are dropped,
+    /// and when several signals share the same band, we retain the assumed one.
+    /// This has no effect if self is not an Observation RINEX.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::version::Version;
+    /// use rinex::observation::TrackingModes;
+    /// use std::str::FromStr;
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let rinex = rinex.obs_version_conversion(Version::new(3, 4), &TrackingModes::default());
+    /// let codes = &rinex.header.obs.as_ref().unwrap().codes;
+    /// assert!(codes[&Constellation::GPS].contains(&Observable::from_str("C1W").unwrap()));
+    /// // generate a RINEX3 file
+    /// rinex.to_file("delf0010.rnx")
+    ///     .unwrap();
+    /// # std::fs::remove_file("delf0010.rnx").unwrap();
+    /// ```
+    pub fn obs_version_conversion(
+        &self,
+        version: Version,
+        modes: &observation::TrackingModes,
+    ) -> Self {
+        let mut s = self.clone();
+        s.obs_version_conversion_mut(version, modes);
+        s
+    }
+
+    /// [Rinex::obs_version_conversion] mutable implementation
+    pub fn obs_version_conversion_mut(
+        &mut self,
+        version: Version,
+        modes: &observation::TrackingModes,
+    ) {
+        if let Some(record) = self.record.as_mut_obs() {
+            observation::conversion::convert(&mut self.header, record, version, modes);
+        }
+    }
+
+    /// Converts this Navigation RINEX to RINEX4.
+    /// Header ionospheric and system time corrections become
+    /// [navigation::NavFrame::Ion] and [navigation::NavFrame::Sto] frames, tagged to the first vehicle
+    /// of the broadcasting constellation, and ephemerides are tagged with
+    /// their actual [navigation::NavMsgType]. This also converts RINEX2 files,
+    /// for example a GPS and Glonass pair previously merged into a single file.
+    /// This has no effect if self is not a Navigation RINEX.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::navigation::NavMsgType;
+    /// let rinex = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let rinex = rinex.to_nav_v4();
+    /// assert_eq!(rinex.header.version.major, 4);
+    /// assert!(rinex.header.ionod_corrections.is_empty());
+    /// for (_, frames) in rinex.record.as_nav().unwrap() {
+    ///     for frame in frames {
+    ///         if let Some((msg, sv, _)) = frame.as_eph() {
+    ///             if sv.constellation == Constellation::Glonass {
+    ///                 assert_eq!(msg, NavMsgType::FDMA);
+    ///             }
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn to_nav_v4(&self) -> Self {
+        let mut s = self.clone();
+        s.to_nav_v4_mut();
+        s
+    }
+
+    /// [Rinex::to_nav_v4] mutable implementation
+    pub fn to_nav_v4_mut(&mut self) {
+        if let Some(record) = self.record.as_mut_nav() {
+            navigation::conversion::to_v4(&mut self.header, record);
+        }
+    }
+
+    /// Converts this Navigation RINEX to RINEX3.
+    /// The first [navigation::NavFrame::Ion] and [navigation::NavFrame::Sto] frames of each constellation
+    /// and time system become header corrections, other ION, STO and EOP frames
+    /// are dropped, because RINEX3 cannot describe them.
+    /// This also converts RINEX2 files, for example a GPS and Glonass pair
+    /// merged into a single mixed file.
+    /// This has no effect if self is not a Navigation RINEX.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::merge::Merge;
+    /// // GPS (.n) and Glonass (.g) pair
+    /// let gps = Rinex::from_file("../test_resources/NAV/V3/CBW100NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap()
+    ///     .to_nav_v2()
+    ///     .remove(&Constellation::GPS)
+    ///     .unwrap();
+    /// assert_eq!(gps.header.version.major, 2);
+    /// let glo = Rinex::from_file("../test_resources/NAV/V2/amel0010.21g")
+    ///     .unwrap();
+    /// // merged into a single mixed file
+    /// let rinex = gps.merge(&glo)
+    ///     .unwrap()
+    ///     .to_nav_v3();
+    /// assert_eq!(rinex.header.version.major, 3);
+    /// assert_eq!(rinex.header.constellation, Some(Constellation::Mixed));
+    /// // both constellations are described
+    /// assert!(rinex.sv().any(|sv| sv.constellation == Constellation::GPS));
+    /// assert!(rinex.sv().any(|sv| sv.constellation == Constellation::Glonass));
+    /// ```
+    pub fn to_nav_v3(&self) -> Self {
+        let mut s = self.clone();
+        s.to_nav_v3_mut();
+        s
+    }
+
+    /// [Rinex::to_nav_v3] mutable implementation
+    pub fn to_nav_v3_mut(&mut self) {
+        if let Some(record) = self.record.as_mut_nav() {
+            navigation::conversion::to_v3(&mut self.header, record);
+        }
+    }
+
+    /// Splits this Navigation RINEX into single constellation RINEX2 files,
+    /// for example a mixed file into a GPS (.n) and Glonass (.g) pair.
+    /// Constellations RINEX2 cannot describe are dropped, like
+    /// ionospheric and system time corrections not broadcast by
+    /// the constellation of each file. Revision 4 ION and STO frames
+    /// are first converted to header corrections, see [Rinex::to_nav_v3].
+    /// Returns an empty map if self is not a Navigation RINEX.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let rinex = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let split = rinex.to_nav_v2();
+    /// // this file does not contain GPS ephemerides
+    /// assert!(split.get(&Constellation::GPS).is_none());
+    /// let glo = &split[&Constellation::Glonass];
+    /// assert_eq!(glo.header.version.major, 2);
+    /// assert_eq!(glo.header.constellation, Some(Constellation::Glonass));
+    /// ```
+    pub fn to_nav_v2(&self) -> HashMap<Constellation, Self> {
+        let mut ret: HashMap<Constellation, Self> = HashMap::new();
+        if let Some(record) = self.record.as_nav() {
+            for (header, record) in navigation::conversion::to_v2(&self.header, record) {
+                if let Some(constellation) = header.constellation {
+                    ret.insert(
+                        constellation,
+                        Self::new(header, record::Record::NavRecord(record)),
+                    );
+                }
+            }
+        }
+        ret
+    }
+
     /// Returns [`TimeScale`] used in this RINEX
     pub fn timescale(&self) -> Option<TimeScale> {
         /*
@@ -357,6 +529,8 @@ impl Rinex {
                     clock_offset_applied: params.clock_offset_applied,
                     dcb_compensations: params.dcb_compensations.clone(),
                     scalings: params.scalings.clone(),
+                    phase_shifts: params.phase_shifts.clone(),
+                    glo_biases: params.glo_biases.clone(),
                 });
         }
     }
@@ -601,6 +775,109 @@ impl Rinex {
         }
     }
 
+    /// Interpolates the Total Electron Content at given Epoch and coordinates
+    /// (latitude, longitude in ddeg°), as described in the IONEX specifications:
+    /// 4-point bilinear interpolation in space, and "rotated maps" interpolation
+    /// in between two consecutive maps, to account for the Earth rotation.
+    /// Returns TEC and RMS (when provided) values in TECu, or None outside
+    /// of the grid or time frame. 3D IONEX are interpolated on their first height layer.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let rinex = Rinex::from_file("../test_resources/IONEX/V1/CKMG0020.22I.gz")
+    ///     .unwrap();
+    /// let t = Epoch::from_gregorian_utc(2022, 1, 2, 0, 30, 0, 0);
+    /// let (tec, rms) = rinex.tec_at(t, 45.0, 2.5)
+    ///     .unwrap();
+    /// assert!(tec > 0.0);
+    /// assert!(rms.is_none()); // no RMS maps in this file
+    /// ```
+    pub fn tec_at(&self, t: Epoch, lat: f64, lon: f64) -> Option<(f64, Option<f64>)> {
+        let grid = &self.header.ionex.as_ref()?.grid;
+        let record = self.record.as_ionex()?;
+        ionex::interpolation::tec_at(record, grid, t, lat, lon)
+    }
+
+    /// Returns the slant ionospheric delay [m] on given carrier, at given Epoch,
+    /// seen from given receiver coordinates [°], for a Sv at given elevation and azimuth [°].
+    /// The vertical TEC is interpolated at the ionospheric pierce point (see [Rinex::tec_at])
+    /// and mapped to the line of sight with the single layer model.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::carrier::Carrier;
+    /// let rinex = Rinex::from_file("../test_resources/IONEX/V1/CKMG0020.22I.gz")
+    ///     .unwrap();
+    /// let t = Epoch::from_gregorian_utc(2022, 1, 2, 12, 0, 0, 0);
+    /// let zenith = rinex.ionex_slant_delay(t, 45.0, 2.5, 90.0, 0.0, Carrier::L1)
+    ///     .unwrap();
+    /// let slant = rinex.ionex_slant_delay(t, 45.0, 2.5, 20.0, 180.0, Carrier::L1)
+    ///     .unwrap();
+    /// assert!(slant > zenith);
+    /// // lower frequencies are further delayed
+    /// let l5 = rinex.ionex_slant_delay(t, 45.0, 2.5, 90.0, 0.0, Carrier::L5)
+    ///     .unwrap();
+    /// assert!(l5 > zenith);
+    /// ```
+    pub fn ionex_slant_delay(
+        &self,
+        t: Epoch,
+        lat: f64,
+        lon: f64,
+        elev: f64,
+        azim: f64,
+        carrier: Carrier,
+    ) -> Option<f64> {
+        let params = self.header.ionex.as_ref()?;
+        let (lat_p, lon_p) = params.pierce_point(lat, lon, elev, azim);
+        let (vtec, _) = self.tec_at(t, lat_p, lon_p)?;
+        let stec = vtec * params.mapping_factor(elev);
+        Some(ionex::tec_to_delay(stec, carrier))
+    }
+
+    /// Returns the TEC difference between these Ionosphere Maps and `rhs`,
+    /// for example a broadcast model (see [Rinex::ionex_from_broadcast]) against IGS maps.
+    /// Every node of our TEC maps is compared to `rhs` interpolated at the same
+    /// Epoch and coordinates (see [Rinex::tec_at]). Nodes not covered by `rhs`,
+    /// and maps left empty, are dropped. RMS and height maps are not preserved.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let rinex = Rinex::from_file("../test_resources/IONEX/V1/CKMG0020.22I.gz")
+    ///     .unwrap();
+    /// let diff = rinex.ionex_diff(&rinex)
+    ///     .unwrap();
+    /// let t = Epoch::from_gregorian_utc(2022, 1, 2, 0, 30, 0, 0);
+    /// let (tec, _) = diff.tec_at(t, 45.0, 2.5)
+    ///     .unwrap();
+    /// assert_eq!(tec, 0.0);
+    /// ```
+    pub fn ionex_diff(&self, rhs: &Self) -> Option<Self> {
+        let mut record = ionex::Record::new();
+        for (t, (tec, _, _)) in self.record.as_ionex()? {
+            let diff: ionex::Map = tec
+                .iter()
+                .filter_map(|point| {
+                    let (value, _) = rhs.tec_at(*t, point.latitude, point.longitude)?;
+                    let mut point = point.clone();
+                    point.value -= value;
+                    Some(point)
+                })
+                .collect();
+            if !diff.is_empty() {
+                record.insert(*t, (diff, None, None));
+            }
+        }
+        if record.is_empty() {
+            return None;
+        }
+        let mut header = self.header.clone();
+        if let Some(ionex) = header.ionex.as_mut() {
+            *ionex = ionex.with_record_maps(&record);
+        }
+        header
+            .comments
+            .push("TEC difference to other maps, interpolated on this grid".to_string());
+        Some(Self::new(header, record::Record::IonexRecord(record)))
+    }
+
     /// Returns true if this is a METEO RINEX
     pub fn is_meteo_rinex(&self) -> bool {
         self.header.rinex_type == types::Type::MeteoData
@@ -707,6 +984,68 @@ impl Rinex {
         ret
     }
 
+    /// Evaluates clock data of given system at desired Epoch.
+    /// Sv clocks are searched in `AS` data, station clocks in `AR` data.
+    /// Bias, rate and acceleration are interpolated between the two surrounding
+    /// samples, and sigmas are propagated, when provided.
+    /// Returns None if this is not a Clock RINEX, if `t` is out of the data set,
+    /// or if interpolation would cross a data gap (exceeding the dominant sampling
+    /// of this clock) or a discontinuity (`DR` data).
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::clocks::System;
+    /// use rinex::sv;
+    /// use std::str::FromStr;
+    /// let rnx = Rinex::from_file("../test_resources/CLK/V2/COD20352.CLK")
+    ///     .unwrap();
+    /// let t0 = rnx.first_epoch().unwrap();
+    /// // 30s products, evaluated at 1s epochs
+    /// for t in TimeSeries::inclusive(t0, t0 + Duration::from_seconds(30.0), Duration::from_seconds(1.0)) {
+    ///     let data = rnx.clock_at(&System::Sv(sv!("G01")), t);
+    ///     assert!(data.is_some());
+    /// }
+    /// ```
+    pub fn clock_at(&self, system: &clocks::System, t: Epoch) -> Option<clocks::Data> {
+        let record = self.record.as_clock()?;
+        let sampling = clocks::record::dominant_sampling(record, system)?;
+        clocks::record::interpolate(record, system, t, sampling)
+    }
+
+    /// Evaluates the frequency stability of given clock, for the standard
+    /// octave τ series. Sv clocks are searched in `AS` data,
+    /// station clocks in `AR` data. Data gaps are taken into account.
+    /// Returns (τ, deviation) pairs, empty if this is not a Clock RINEX.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::clocks::{Deviation, System};
+    /// let rnx = Rinex::from_file("../test_resources/CLK/V2/COD20352.CLK")
+    ///     .unwrap();
+    /// let station = System::Station("PIE1".to_string());
+    /// let adev = rnx.clock_stability(&station, Deviation::Adev);
+    /// assert_eq!(adev[0].0, Duration::from_seconds(30.0));
+    /// ```
+    pub fn clock_stability(
+        &self,
+        system: &clocks::System,
+        deviation: clocks::Deviation,
+    ) -> Vec<(Duration, f64)> {
+        let dtype = match system {
+            clocks::System::Sv(_) => clocks::DataType::AS,
+            clocks::System::Station(_) => clocks::DataType::AR,
+        };
+        let series: BTreeMap<Epoch, f64> = self
+            .record
+            .as_clock()
+            .into_iter()
+            .flat_map(|record| record.iter())
+            .filter_map(|(e, dtypes)| {
+                let data = dtypes.get(&dtype)?.get(system)?;
+                Some((*e, data.bias))
+            })
+            .collect();
+        clocks::stability::deviation(&series, deviation)
+    }
+
     /// Applies given AND mask in place, to all observations.
     /// This has no effect on non observation records.
     /// This also drops observations that did not come with an LLI flag.  
@@ -888,6 +1227,225 @@ impl Rinex {
         s
     }
 
+    /// Compensates pseudo range observations for code biases.
+    /// `bias` returns the bias [m] of given pseudo range observable,
+    /// for given Sv at given Epoch, or None when unknown.
+    /// Known biases are removed, and the constellations they applied to are
+    /// declared as compensated for (see [observation::HeaderFields::dcb_compensation]).
+    /// Use this to apply bias products. SINEX BIA solutions are applied with
+    /// `rinex.apply_code_biases(&sinex)`, which is provided by the `sinex` crate:
+    /// its `sinex::ApplyCodeBiases` trait must be imported.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// // compensate a 1 meter bias on all GPS pseudo ranges
+    /// let rinex = rinex.observation_code_bias_compensation(|_, sv, _| {
+    ///     if sv.constellation == Constellation::GPS {
+    ///         Some(1.0)
+    ///     } else {
+    ///         None
+    ///     }
+    /// });
+    /// let obs = rinex.header.obs.as_ref().unwrap();
+    /// assert!(obs.dcb_compensation(Some(Constellation::GPS)));
+    /// assert!(!obs.dcb_compensation(Some(Constellation::Glonass)));
+    /// ```
+    pub fn observation_code_bias_compensation_mut<F>(&mut self, bias: F)
+    where
+        F: Fn(Epoch, Sv, &Observable) -> Option<f64>,
+    {
+        let mut compensated: Vec<Constellation> = Vec::new();
+        if let Some(r) = self.record.as_mut_obs() {
+            for ((epoch, _), (_, vehicles)) in r.iter_mut() {
+                for (sv, observations) in vehicles.iter_mut() {
+                    for (observable, data) in observations.iter_mut() {
+                        if !observable.is_pseudorange_observable() {
+                            continue;
+                        }
+                        if let Some(bias) = bias(*epoch, *sv, observable) {
+                            data.obs -= bias;
+                            if !compensated.contains(&sv.constellation) {
+                                compensated.push(sv.constellation);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(obs) = &mut self.header.obs {
+            for constellation in compensated {
+                *obs = obs.with_dcb_compensation(constellation);
+            }
+        }
+    }
+    /// Compensates pseudo range observations for code biases,
+    /// immutable implementation, see [Rinex::observation_code_bias_compensation_mut].
+    pub fn observation_code_bias_compensation<F>(&self, bias: F) -> Self
+    where
+        F: Fn(Epoch, Sv, &Observable) -> Option<f64>,
+    {
+        let mut s = self.clone();
+        s.observation_code_bias_compensation_mut(bias);
+        s
+    }
+
+    /// Applies the receiver clock offsets to Observation Data, as described in
+    /// RINEX4 (table 5): epochs that come with a clock offset are shifted in time,
+    /// and the offset is removed from pseudo range and phase observations.
+    /// Doppler shifts are corrected for the clock drift, derived from
+    /// successive offsets. Phases and Doppler shifts are compensated at the
+    /// nominal carrier frequency, which is an approximation for Glonass FDMA signals.
+    /// Header is updated (RCV CLOCK OFFS APPL). This has no effect if the offsets
+    /// were already applied.
+    pub fn apply_receiver_clock_offset_mut(&mut self) {
+        if let Some(obs) = &mut self.header.obs {
+            if obs.clock_offset_applied {
+                return;
+            }
+            obs.clock_offset_applied = true;
+        }
+        if let Some(r) = self.record.as_mut_obs() {
+            observation::record::receiver_clock_offset_compensation(r, 1.0);
+        }
+    }
+    /// Applies the receiver clock offsets to Observation Data,
+    /// immutable implementation, see [Rinex::apply_receiver_clock_offset_mut].
+    /// ```
+    /// use rinex::prelude::*;
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V3/VLNS0010.22O")
+    ///     .unwrap();
+    /// let rinex = rinex.apply_receiver_clock_offset();
+    /// let obs = rinex.header.obs.as_ref().unwrap();
+    /// assert!(obs.clock_offset_applied);
+    /// ```
+    pub fn apply_receiver_clock_offset(&self) -> Self {
+        let mut s = self.clone();
+        s.apply_receiver_clock_offset_mut();
+        s
+    }
+    /// Reverts the receiver clock offsets compensation, restoring the original
+    /// time tags and observations, see [Rinex::apply_receiver_clock_offset_mut].
+    /// This has no effect if the offsets were not applied.
+    pub fn revert_receiver_clock_offset_mut(&mut self) {
+        if let Some(obs) = &mut self.header.obs {
+            if !obs.clock_offset_applied {
+                return;
+            }
+            obs.clock_offset_applied = false;
+        }
+        if let Some(r) = self.record.as_mut_obs() {
+            observation::record::receiver_clock_offset_compensation(r, -1.0);
+        }
+    }
+    /// Reverts the receiver clock offsets compensation,
+    /// immutable implementation, see [Rinex::revert_receiver_clock_offset_mut].
+    pub fn revert_receiver_clock_offset(&self) -> Self {
+        let mut s = self.clone();
+        s.revert_receiver_clock_offset_mut();
+        s
+    }
+
+    /// Aligns phase observations to the reference signal of each frequency band,
+    /// by applying the quarter cycle phase shift corrections of RINEX3.04 (table A23),
+    /// so phases from different tracking modes (for example L2C and L2W) can be combined.
+    /// Vehicles for which the header already declares a correction
+    /// (SYS / PHASE SHIFT) are considered aligned and left untouched.
+    /// Header is updated with the applied corrections.
+    pub fn apply_phase_shift_mut(&mut self) {
+        let declared = match &self.header.obs {
+            Some(obs) => obs.clone(),
+            None => return,
+        };
+        let mut shifts: HashMap<Constellation, HashMap<Observable, f64>> = HashMap::new();
+        for (constellation, codes) in declared.codes.iter() {
+            for code in codes {
+                let constellation_wide = declared
+                    .phase_shifts
+                    .get(constellation)
+                    .and_then(|declared| declared.get(code))
+                    .map(|declared| declared.contains_key(&None))
+                    .unwrap_or(false);
+                if constellation_wide {
+                    continue;
+                }
+                if let Some(shift) =
+                    observation::record::reference_phase_shift(*constellation, code)
+                {
+                    shifts
+                        .entry(*constellation)
+                        .or_default()
+                        .insert(code.clone(), shift);
+                }
+            }
+        }
+        if let Some(obs) = &mut self.header.obs {
+            // vehicle specific declarations still prevail
+            for (constellation, shifts) in shifts.iter() {
+                for (code, shift) in shifts {
+                    *obs = obs.with_phase_shift(*constellation, code.clone(), None, *shift);
+                }
+            }
+        }
+        if let Some(r) = self.record.as_mut_obs() {
+            observation::record::phase_shift_compensation(r, &shifts, &declared);
+        }
+    }
+    /// Aligns phase observations to the reference signals,
+    /// immutable implementation, see [Rinex::apply_phase_shift_mut].
+    /// ```
+    /// use rinex::prelude::*;
+    /// use std::str::FromStr;
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V3/VLNS0010.22O")
+    ///     .unwrap();
+    /// let rinex = rinex.apply_phase_shift();
+    /// let obs = rinex.header.obs.as_ref().unwrap();
+    /// let l2x = Observable::from_str("L2X").unwrap();
+    /// let g01 = Sv::new(Constellation::GPS, 1);
+    /// assert_eq!(obs.phase_shift(&Constellation::GPS, &g01, &l2x), Some(-0.25));
+    /// ```
+    pub fn apply_phase_shift(&self) -> Self {
+        let mut s = self.clone();
+        s.apply_phase_shift_mut();
+        s
+    }
+
+    /// Detects receiver clock jumps: receivers that steer their clock
+    /// in millisecond steps, introduce jumps of an integer number of ms (in light distance)
+    /// in pseudo range and/or phase observations, common to all vehicles.
+    /// Returns the jumps [ms] per observable, at the epoch they were introduced.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use std::str::FromStr;
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let jumps = rinex.observation_clock_jumps();
+    /// let t = Epoch::from_gregorian_utc(2021, 1, 1, 0, 2, 0, 0);
+    /// let c1 = Observable::from_str("C1").unwrap();
+    /// assert_eq!(jumps.get(&t).and_then(|jumps| jumps.get(&c1)), Some(&1));
+    /// ```
+    pub fn observation_clock_jumps(&self) -> BTreeMap<Epoch, HashMap<Observable, i64>> {
+        let mut record = match self.record.as_obs() {
+            Some(r) => r.clone(),
+            None => return BTreeMap::new(),
+        };
+        observation::record::receiver_clock_jumps(&mut record, false)
+    }
+    /// Repairs receiver clock jumps (see [Rinex::observation_clock_jumps]):
+    /// jumps are removed from all following pseudo range and phase observations.
+    pub fn observation_clock_jumps_repair_mut(&mut self) {
+        if let Some(r) = self.record.as_mut_obs() {
+            observation::record::receiver_clock_jumps(r, true);
+        }
+    }
+    /// Repairs receiver clock jumps,
+    /// immutable implementation, see [Rinex::observation_clock_jumps_repair_mut].
+    pub fn observation_clock_jumps_repair(&self) -> Self {
+        let mut s = self.clone();
+        s.observation_clock_jumps_repair_mut();
+        s
+    }
+
     /*
         /// Single step /stage, in high order phase differencing
         /// algorithm, which we use in case of old receiver data / old RINEX
@@ -1185,37 +1743,35 @@ impl Rinex {
         }
     */
 
-    /*
-        /// Returns epochs where a so called "cycle slip" has been confirmed.
-        /// We confirm a cycle slip by computing the double difference
-        /// between self and `rhs` Observation RINEX.
-        /// This does not produce anything if both are not
-        /// Observation RINEX files, ideally sampled at same epochs,
-        /// by two seperate but stationnary receivers. Refer to [diff_mut]
-        /// for further explanations on those computations.
-        ///
-        /// Example:
-        /// ```
-        /// use rinex::*;
-        /// // grab an observation rinex
-        /// let rnx = Rinex::from_file("../test_resources/OBS/V2/aopr0010.17o").unwrap();
-        /// let cycle_slips = rnx.cycle_slips();
-        ///
-        /// // now we will confirm those cycle slip events by computing the double diff,
-        /// // assuming this secondary rinex recorded the same data
-        /// let rnx_b = Rinex::from_file("../test_resources/OBS/V2/npaz3550.21o").unwrap();
-        /// let confirmed_slips = rnx.confirmed_cycle_slips(&rnx_b);
-        /// ```
-        pub fn observation_confirmed_cycle_slip_epochs (&self, rhs: &Self) -> Result<Vec<Epoch>, DiffError> {
-            if !self.is_observation_rinex() || !rhs.is_observation_rinex() {
-                return Err(DiffError::NotObsRinex) ;
-            }
-            let rnx = self.double_diff(rhs);
-            let vec : Vec<Epoch> = Vec::new();
-            Ok(vec)
-        }
-    CYCLE SLIPS CONFIRMATION
-    */
+    /// Returns epochs where a so called "cycle slip" has been confirmed.
+    /// We confirm a cycle slip declared by self (loss of lock or
+    /// [EpochFlag::CycleSlip] event) by computing the double differences
+    /// between self and `rhs` Observation RINEX, `rhs` being the base station.
+    /// Both should be Observation RINEX, ideally sampled at same epochs,
+    /// by two seperate but stationnary receivers. Refer to
+    /// [differential::DiffContext] for further explanations on those computations.
+    ///
+    /// Example:
+    /// ```
+    /// use rinex::prelude::*;
+    /// // grab an observation rinex
+    /// let rnx = Rinex::from_file("../test_resources/OBS/V3/NOA10630.22O").unwrap();
+    ///
+    /// // now we will confirm the cycle slip events by computing the double diff,
+    /// // assuming this secondary rinex recorded the same data
+    /// let rnx_b = Rinex::from_file("../test_resources/OBS/V3/LARM0630.22O").unwrap();
+    /// let confirmed_slips = rnx.observation_confirmed_cycle_slip_epochs(&rnx_b)
+    ///     .unwrap();
+    /// ```
+    #[cfg(all(feature = "obs", feature = "processing"))]
+    #[cfg_attr(docrs, doc(cfg(all(feature = "obs", feature = "processing"))))]
+    pub fn observation_confirmed_cycle_slip_epochs(
+        &self,
+        rhs: &Self,
+    ) -> Result<Vec<Epoch>, differential::Error> {
+        let ctx = differential::DiffContext::new(rhs, self)?;
+        Ok(ctx.confirmed_cycle_slips())
+    }
 
     /// Filters out data that was not produced by given agency / station.
     /// This has no effect on records other than CLK RINEX.
@@ -1252,8 +1808,13 @@ impl Rinex {
     /// Record: refer to supported RINEX types
     pub fn to_file(&self, path: &str) -> Result<(), Error> {
         let mut writer = BufferedWriter::new(path)?;
-        write!(writer, "{}", self.header.to_string())?;
-        self.record.to_file(&self.header, &mut writer)?;
+        let mut header = self.header.clone();
+        if let (Some(ionex), Some(record)) = (header.ionex.as_mut(), self.record.as_ionex()) {
+            // IONEX: maps description must match the record
+            *ionex = ionex.with_record_maps(record);
+        }
+        write!(writer, "{}", header)?;
+        self.record.to_file(&header, &mut writer)?;
         Ok(())
     }
 }
@@ -1891,6 +2452,25 @@ impl Rinex {
             }
         }))
     }
+    /// Evaluates the frequency stability of the receiver clock, for the standard
+    /// octave τ series. Data gaps are taken into account.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::clocks::Deviation;
+    /// let rnx = Rinex::from_file("../test_resources/OBS/V2/npaz3550.21o")
+    ///     .unwrap();
+    /// for (tau, adev) in rnx.recvr_clock_stability(Deviation::Adev) {
+    ///     // tau: observation time
+    ///     // adev: Allan deviation
+    /// }
+    /// ```
+    pub fn recvr_clock_stability(&self, deviation: clocks::Deviation) -> Vec<(Duration, f64)> {
+        let series: BTreeMap<Epoch, f64> = self
+            .recvr_clock()
+            .map(|((e, _), offset)| (e, offset))
+            .collect();
+        clocks::stability::deviation(&series, deviation)
+    }
 }
 
 #[cfg(feature = "nav")]
@@ -2155,6 +2735,81 @@ impl Rinex {
                 }),
         )
     }
+    /// Returns the ephemeris to use for given Sv at desired Epoch,
+    /// along with its reference Epoch (time of clock).
+    /// We select the closest ephemeris in time, within a 4 hour validity period.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::sv;
+    /// use std::str::FromStr;
+    /// let rinex = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let t = Epoch::from_str("2021-01-01T00:30:00 GPST").unwrap();
+    /// if let Some((toc, ephemeris)) = rinex.sv_ephemeris(sv!("G08"), t) {
+    ///     assert!((t - toc).abs() < Duration::from_hours(4.0));
+    /// }
+    /// ```
+    pub fn sv_ephemeris(&self, sv: Sv, t: Epoch) -> Option<(Epoch, &Ephemeris)> {
+        self.ephemeris()
+            .filter_map(|(e, (_, svnn, eph))| {
+                if *svnn == sv && (t - *e).abs() <= Duration::from_hours(4.0) {
+                    Some((*e, eph))
+                } else {
+                    None
+                }
+            })
+            .min_by_key(|(e, _)| (t - *e).abs())
+    }
+    /// Compares broadcast ephemerides to precise products, for every Sv:
+    /// orbits, as provided by an SP3 file, and/or clocks, as provided
+    /// by a Clock RINEX (`AS` data). Returns the broadcast orbit errors
+    /// projected in the radial / along-track / cross-track frame, and the
+    /// broadcast clock error, all expressed in meters, at every precise Epoch.
+    /// Only Keplerian ephemerides are evaluated, Glonass and SBAS are dropped.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let clk = Rinex::from_file("../test_resources/CLK/V2/COD20352.CLK")
+    ///     .unwrap();
+    /// for (sv, epochs) in nav.sv_broadcast_errors(None, Some(&clk)) {
+    ///     for (epoch, err) in epochs {
+    ///         // err.clock: clock error [m]
+    ///         // err.orbit: not available, no precise orbits were provided
+    ///         assert!(err.orbit.is_none());
+    ///     }
+    /// }
+    /// ```
+    pub fn sv_broadcast_errors(
+        &self,
+        orbits: Option<&navigation::PreciseOrbits>,
+        clocks: Option<&Rinex>,
+    ) -> BTreeMap<Sv, BTreeMap<Epoch, navigation::SvError>> {
+        let clocks = clocks.and_then(|clk| clk.record.as_clock());
+        navigation::sisre::sv_errors(self, orbits, clocks)
+    }
+    /// Evaluates the Signal In Space Range Error (SISRE), per constellation,
+    /// by comparing broadcast ephemerides to precise products.
+    /// See [Rinex::sv_broadcast_errors] for more information.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let clk = Rinex::from_file("../test_resources/CLK/V2/COD20352.CLK")
+    ///     .unwrap();
+    /// for (constellation, stats) in nav.sisre(None, Some(&clk)) {
+    ///     // stats.clock_rms: clock error RMS [m]
+    ///     // stats.orbit_sisre: orbit only SISRE [m]
+    ///     // stats.sisre: global SISRE [m]
+    /// }
+    /// ```
+    pub fn sisre(
+        &self,
+        orbits: Option<&navigation::PreciseOrbits>,
+        clocks: Option<&Rinex>,
+    ) -> HashMap<Constellation, navigation::SisreStats> {
+        navigation::sisre::sisre_stats(&self.sv_broadcast_errors(orbits, clocks))
+    }
     /// Returns [`IonMessage`] frames Iterator
     pub fn ionosphere_models(
         &self,
@@ -2228,6 +2883,97 @@ impl Rinex {
             }
         }))
     }
+    /// Renders the ionosphere model broadcast by given `constellation` as IONEX maps,
+    /// on given `grid` and [TimeSeries]: the vertical TEC predicted by the Klobuchar
+    /// model (see [IonMessage::vertical_tec]) is evaluated at every node
+    /// of the first height layer. Each map uses the latest model broadcast
+    /// prior to its Epoch, or the model described in the header (RINEX2 and 3).
+    /// Compare the result to other maps with [Rinex::ionex_diff].
+    /// Fails when NeQuick-G or BDGIM would be required, which are not supported,
+    /// or when no model applies to the [TimeSeries].
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::ionex::{Grid, GridLinspace};
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let grid = Grid {
+    ///     latitude: GridLinspace::new(87.5, -87.5, -2.5).unwrap(),
+    ///     longitude: GridLinspace::new(-180.0, 180.0, 5.0).unwrap(),
+    ///     height: GridLinspace::from((350.0, 350.0, 0.0)),
+    /// };
+    /// let t0 = Epoch::from_gregorian_utc_at_midnight(2021, 1, 1);
+    /// let series = TimeSeries::inclusive(t0, t0 + Duration::from_days(1.0), Duration::from_hours(2.0));
+    /// let ionex = nav.ionex_from_broadcast(Constellation::GPS, &grid, series)
+    ///     .unwrap();
+    /// assert_eq!(ionex.epoch().count(), 13);
+    /// // ionex.to_file("klobuchar.21i");
+    /// ```
+    pub fn ionex_from_broadcast(
+        &self,
+        constellation: Constellation,
+        grid: &ionex::Grid,
+        series: TimeSeries,
+    ) -> Result<Self, navigation::IonModelError> {
+        let models: BTreeMap<Epoch, &IonMessage> = self
+            .ionosphere_models()
+            .filter_map(|(t, (_, sv, model))| {
+                if sv.constellation == constellation {
+                    Some((*t, model))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let default = self.header.ionod_corrections.get(&constellation);
+        let height = grid.height.start;
+        let mut record = ionex::Record::new();
+        for t in series {
+            let model = match models.range(..=t).next_back() {
+                Some((_, model)) => *model,
+                None => match default {
+                    Some(model) => model,
+                    None => continue,
+                },
+            };
+            let mut tec = ionex::Map::new();
+            for i in 0..grid.latitude.length() {
+                let lat = grid.latitude.start + grid.latitude.spacing * i as f64;
+                for j in 0..grid.longitude.length() {
+                    let lon = grid.longitude.start + grid.longitude.spacing * j as f64;
+                    tec.push(ionex::record::MapPoint {
+                        latitude: lat,
+                        longitude: lon,
+                        altitude: height,
+                        value: model.vertical_tec(t, lat, lon, constellation)?,
+                    });
+                }
+            }
+            record.insert(t, (tec, None, None));
+        }
+        if record.is_empty() {
+            return Err(navigation::IonModelError::NoModel);
+        }
+        let fields = ionex::HeaderFields::default()
+            .with_reference_system(ionex::RefSystem::GnssConstellation(constellation))
+            .with_description(&format!(
+                "Klobuchar model broadcast by {}, rendered as vertical TEC maps.",
+                constellation
+            ))
+            .with_base_radius(6371.0)
+            .with_map_dimension(2)
+            .with_latitude_grid(grid.latitude.clone())
+            .with_longitude_grid(grid.longitude.clone())
+            .with_altitude_grid(ionex::GridLinspace::from((height, height, 0.0)))
+            .with_exponent(-1)
+            .with_record_maps(&record);
+        let header = Header::basic_ionex()
+            .with_ionex_fields(fields)
+            .with_comments(vec![
+                "Theoretical model: no observables used".to_string(),
+                "TEC values in 0.1 TECU; 9999, if no value available".to_string(),
+            ]);
+        Ok(Self::new(header, record::Record::IonexRecord(record)))
+    }
     /// Returns [`StoMessage`] frames Iterator
     /// ```
     /// use rinex::prelude::*;
@@ -2280,6 +3026,300 @@ impl Rinex {
     }
 }
 
+/*
+ * Navigation solutions: requires both OBS and NAV RINEX
+ */
+#[cfg(feature = "positioning")]
+#[cfg_attr(docrs, doc(cfg(feature = "positioning")))]
+impl Rinex {
+    /// Resolves the receiver position at every Epoch of this Observation RINEX,
+    /// by Single Point Positioning (iterative weighted least squares)
+    /// on the pseudo ranges, using the ephemerides of given Navigation RINEX.
+    /// Returns per Epoch ECEF position, receiver clock offset per constellation
+    /// (therefore the Inter System Bias), DOPs and post fit residuals.
+    /// Observations are weighted by sin²(elevation). The ionospheric delay
+    /// is compensated with the broadcast Klobuchar model (GPS model is used
+    /// when a constellation does not broadcast its own),
+    /// the tropospheric delay with the [positioning::TropoModel] of `opts`
+    /// (Saastamoinen and Niell by default), for the surface conditions of `opts`
+    /// or a standard atmosphere.
+    /// GPS, Galileo, BeiDou (except GEO vehicles), QZSS and IRNSS
+    /// are supported, Glonass and SBAS vehicles are dropped.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::positioning::SppOpts;
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/CBW100NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let solutions = obs.spp(&nav, SppOpts::default());
+    /// for (epoch, solution) in solutions {
+    ///     let (x, y, z) = solution.position; // ECEF [m]
+    ///     let (reference, dt) = solution.reference_clock().unwrap(); // [s]
+    ///     let gdop = solution.dop.gdop;
+    ///     for (sv, residual) in solution.residuals {
+    ///         // post fit residual [m]
+    ///     }
+    /// }
+    /// ```
+    pub fn spp(
+        &self,
+        nav: &Rinex,
+        opts: positioning::SppOpts,
+    ) -> BTreeMap<Epoch, positioning::Solution> {
+        positioning::spp::solve(self, nav, &opts)
+    }
+    /// Resolves the receiver velocity and clock drift at every Epoch
+    /// of this Observation RINEX, by weighted least squares on the Doppler
+    /// observations (reference signal of each constellation), at the position
+    /// obtained by [Rinex::spp]. Sv velocities are evaluated from the
+    /// ephemerides of given Navigation RINEX. Velocities are expressed in the
+    /// local East, North, Up frame, along with their covariance.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::positioning::SppOpts;
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/CBW100NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// for (epoch, velocity) in obs.doppler_velocity(&nav, SppOpts::default()) {
+    ///     let (east, north, up) = velocity.enu; // [m/s]
+    ///     let (std_e, std_n, std_u) = velocity.std_enu(); // [m/s]
+    ///     let drift = velocity.clock_drift; // [s/s]
+    /// }
+    /// ```
+    pub fn doppler_velocity(
+        &self,
+        nav: &Rinex,
+        opts: positioning::SppOpts,
+    ) -> BTreeMap<Epoch, positioning::Velocity> {
+        positioning::velocity::solve(self, nav, &opts)
+    }
+    /// Resolves the marker position at every Epoch of this Observation RINEX
+    /// by Precise Point Positioning: an extended Kalman filter on the
+    /// ionosphere free code and phase combinations (GPS L1/L2, QZSS L1/L2,
+    /// Galileo E1/E5a), estimating the position, one clock offset per
+    /// constellation, the zenith wet delay and one float ambiguity per
+    /// continuous phase arc. Arcs are broken on loss of lock, on observation
+    /// gaps and on geometry free phase jumps. Phase wind up is compensated.
+    ///
+    /// Sv orbits are interpolated from `orbits` (SP3), clocks from `clocks`
+    /// (Clock RINEX), both falling back to the broadcast ephemerides of `nav`.
+    /// When `antex` is provided, the receiver antenna described in this header
+    /// is compensated for its phase center offset and variations, and
+    /// Sv antenna offsets are applied to precise orbits.
+    /// The filter is initialized from [Rinex::spp].
+    /// Solid Earth tides and code biases are not compensated.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::positioning::PppOpts;
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/CBW100NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let solutions = obs.ppp(&nav, None, None, None, PppOpts::default());
+    /// if let Some((epoch, solution)) = solutions.iter().last() {
+    ///     let (x, y, z) = solution.position; // ECEF [m]
+    ///     let (std_e, std_n, std_u) = solution.std_enu; // [m]
+    ///     let ztd = solution.ztd(); // [m]
+    /// }
+    /// ```
+    pub fn ppp(
+        &self,
+        nav: &Rinex,
+        orbits: Option<&navigation::PreciseOrbits>,
+        clocks: Option<&Rinex>,
+        antex: Option<&Rinex>,
+        opts: positioning::PppOpts,
+    ) -> BTreeMap<Epoch, positioning::PppSolution> {
+        positioning::ppp::solve(self, nav, orbits, clocks, antex, &opts)
+    }
+    /// Resolves the position of this (rover) Observation RINEX at every Epoch
+    /// it shares with the `base` station, by relative positioning (RTK).
+    /// The `base` header must describe its ground position.
+    /// An extended Kalman filter processes code and phase double differences
+    /// (GPS L1/L2, QZSS L1/L2, Galileo E1/E5a) and estimates the rover position
+    /// and one single difference ambiguity per vehicle and carrier.
+    /// Double difference ambiguities are then resolved to integers with LAMBDA
+    /// and validated by the ratio test, see [positioning::RtkOpts].
+    /// Ionospheric residuals are neglected: this is meant for short baselines.
+    /// The filter is initialized from [Rinex::spp].
+    /// Returns an empty set if either the base position is unknown or the
+    /// inputs are not Observation RINEX.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::positioning::RtkOpts;
+    /// let base = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let rover = Rinex::from_file("../test_resources/OBS/V2/zegv0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V3/CBW100NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap();
+    /// let solutions = rover.rtk(&base, &nav, RtkOpts::default());
+    /// for (epoch, solution) in solutions {
+    ///     let (e, n, u) = solution.baseline; // [m]
+    ///     let fixed = solution.is_fixed();
+    /// }
+    /// ```
+    pub fn rtk(
+        &self,
+        base: &Rinex,
+        nav: &Rinex,
+        opts: positioning::RtkOpts,
+    ) -> BTreeMap<Epoch, positioning::RtkSolution> {
+        positioning::rtk::solve(self, base, nav, &opts)
+    }
+    /// Returns slant tropospheric delays [m], for every Epoch and every Sv
+    /// above the horizon, seen from the ground position of this Observation RINEX.
+    /// Sv elevations are evaluated from the broadcast ephemerides of `nav`.
+    /// Zenith delays are evaluated from the `meteo` RINEX conditions (interpolated),
+    /// and otherwise fall back to the standard atmosphere of the selected `model`.
+    /// Returns an empty set if the ground position is unknown.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::positioning::{MappingFunction, TropoModel, ZenithModel};
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V2/cbw10010.21n.gz")
+    ///     .unwrap();
+    /// let model = TropoModel::default()
+    ///     .with_zenith_model(ZenithModel::Unb3m)
+    ///     .with_mapping_function(MappingFunction::Gmf);
+    /// for (epoch, svs) in obs.tropo_delays(&nav, None, model) {
+    ///     for (sv, delay) in svs {
+    ///         // slant delay [m]
+    ///     }
+    /// }
+    /// ```
+    pub fn tropo_delays(
+        &self,
+        nav: &Rinex,
+        meteo: Option<&Rinex>,
+        model: positioning::TropoModel,
+    ) -> BTreeMap<Epoch, BTreeMap<Sv, f64>> {
+        positioning::tropo::slant_delays(self, nav, meteo, &model)
+    }
+    /// Returns slant ionospheric delays [m] interpolated from the `ionex` maps,
+    /// for every Epoch, every Sv above the horizon, and every pseudo range
+    /// and phase observable (on its own carrier), seen from the ground position
+    /// of this Observation RINEX: see [Rinex::ionex_slant_delay].
+    /// Pseudo ranges are delayed, and phases advanced, by these amounts.
+    /// Sv elevations and azimuths are evaluated from the broadcast ephemerides of `nav`.
+    /// Returns an empty set if the ground position is unknown.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V2/cbw10010.21n.gz")
+    ///     .unwrap();
+    /// let ionex = Rinex::from_file("../test_resources/IONEX/V1/CKMG0090.21I.gz")
+    ///     .unwrap();
+    /// for (epoch, svs) in obs.ionex_delays(&nav, &ionex) {
+    ///     for (sv, observables) in svs {
+    ///         for (observable, delay) in observables {
+    ///             // slant delay [m]
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn ionex_delays(
+        &self,
+        nav: &Rinex,
+        ionex: &Rinex,
+    ) -> BTreeMap<Epoch, BTreeMap<Sv, HashMap<Observable, f64>>> {
+        positioning::iono::slant_delays(self, nav, ionex)
+    }
+    /// Estimates the Differential Code Biases `lhs` - `rhs` [ns] of every Sv
+    /// of given constellation, and of the receiver, from this single station.
+    /// Code differences are corrected for the slant ionospheric delays interpolated
+    /// from the `ionex` maps, which may also be a model ionosphere
+    /// rendered from the broadcast coefficients (see [Rinex::ionex_from_broadcast]).
+    /// Observations are weighted by the squared sine of the elevation, below 10° they are dropped.
+    /// Satellite biases are constrained to a zero mean, the receiver bias
+    /// is labelled by the station name. Each bias is returned along its formal
+    /// error (rms), as described in IONEX headers, so the results can feed
+    /// [Rinex::ionex_from_network] directly.
+    /// Returns an empty set if the ground position is unknown.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::ionex::{BiasSource, Grid, GridLinspace};
+    /// use std::str::FromStr;
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V2/cbw10010.21n.gz")
+    ///     .unwrap();
+    /// // model ionosphere, over Western Europe
+    /// let grid = Grid {
+    ///     latitude: GridLinspace::from((70.0, 35.0, -2.5)),
+    ///     longitude: GridLinspace::from((-10.0, 20.0, 5.0)),
+    ///     height: GridLinspace::from((350.0, 350.0, 0.0)),
+    /// };
+    /// let t0 = Epoch::from_gregorian_utc_at_midnight(2021, 1, 1);
+    /// let series = TimeSeries::inclusive(t0, t0 + Duration::from_hours(1.0), Duration::from_hours(1.0));
+    /// let ionex = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+    ///     .unwrap()
+    ///     .ionex_from_broadcast(Constellation::GPS, &grid, series)
+    ///     .unwrap();
+    /// let (p1, p2) = (Observable::from_str("P1").unwrap(), Observable::from_str("P2").unwrap());
+    /// let dcbs = obs.dcb_estimation(&nav, &ionex, Constellation::GPS, &p1, &p2);
+    /// for (source, (bias, rms)) in dcbs {
+    ///     if let BiasSource::SpaceVehicle(sv) = source {
+    ///         // P1-P2 [ns]
+    ///     }
+    /// }
+    /// ```
+    pub fn dcb_estimation(
+        &self,
+        nav: &Rinex,
+        ionex: &Rinex,
+        constellation: Constellation,
+        lhs: &Observable,
+        rhs: &Observable,
+    ) -> HashMap<ionex::BiasSource, (f64, f64)> {
+        positioning::dcb::estimate(self, nav, ionex, constellation, lhs, rhs)
+    }
+    /// Generates IONEX maps from a `network` of Observation RINEX,
+    /// using the broadcast ephemerides of `nav`.
+    /// Slant TEC is obtained from the geometry free phase combination,
+    /// leveled to the code per arc and calibrated with the `dcbs`
+    /// (P1-P2 [ns], per Sv and per station, (bias, rms) as described in IONEX headers).
+    /// Stations and vehicles with unknown DCB are skipped,
+    /// they are listed in the header comments of the generated IONEX.
+    /// Vertical TEC at the pierce points is then fitted on the grid,
+    /// once per map interval, see [positioning::IonexOpts].
+    /// Stations need a known ground position.
+    /// Returns None if no map could be fitted.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::positioning::IonexOpts;
+    /// use rinex::ionex::BiasSource;
+    /// use std::collections::HashMap;
+    /// let obs = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let nav = Rinex::from_file("../test_resources/NAV/V2/cbw10010.21n.gz")
+    ///     .unwrap();
+    /// // null biases, for the sake of this example
+    /// let mut dcbs = HashMap::new();
+    /// dcbs.insert(BiasSource::Station("DELF".to_string()), (0.0, 0.0));
+    /// for sv in obs.sv() {
+    ///     dcbs.insert(BiasSource::SpaceVehicle(sv), (0.0, 0.0));
+    /// }
+    /// let opts = IonexOpts::default();
+    /// let ionex = Rinex::ionex_from_network(&[obs], &nav, &dcbs, &opts)
+    ///     .unwrap();
+    /// assert!(ionex.is_ionex());
+    /// // ionex.to_file("delf0010.21i");
+    /// ```
+    pub fn ionex_from_network(
+        network: &[Rinex],
+        nav: &Rinex,
+        dcbs: &HashMap<ionex::BiasSource, (f64, f64)>,
+        opts: &positioning::IonexOpts,
+    ) -> Option<Rinex> {
+        positioning::tec::generate(network, nav, dcbs, opts)
+    }
+}
+
 /*
  * Meteo RINEX specific methods: only available on crate feature.
  * Either specific Iterators, or meaningful data we can extract.
@@ -2544,6 +3584,192 @@ impl Rinex {
             false
         }
     }
+    /// Returns dew point observations, in Celcius degrees,
+    /// see [Rinex::dew_point_mut]
+    pub fn dew_point(&self) -> Box<dyn Iterator<Item = (Epoch, f64)> + '_> {
+        Box::new(self.meteo().flat_map(|(epoch, v)| {
+            v.iter().filter_map(|(k, value)| {
+                if *k == Observable::DewPoint {
+                    Some((*epoch, *value))
+                } else {
+                    None
+                }
+            })
+        }))
+    }
+    /// Returns precipitable water vapor observations, in mm,
+    /// see [Rinex::precipitable_water_vapor_mut]
+    pub fn precipitable_water_vapor(&self) -> Box<dyn Iterator<Item = (Epoch, f64)> + '_> {
+        Box::new(self.meteo().flat_map(|(epoch, v)| {
+            v.iter().filter_map(|(k, value)| {
+                if *k == Observable::PrecipitableWaterVapor {
+                    Some((*epoch, *value))
+                } else {
+                    None
+                }
+            })
+        }))
+    }
+    /*
+     * Declares a derived observable in the Meteo header
+     */
+    fn meteo_declare_observable(&mut self, observable: Observable) {
+        let meteo = self.header.meteo.get_or_insert_with(Default::default);
+        if !meteo.codes.contains(&observable) {
+            meteo.codes.push(observable);
+        }
+    }
+    /// Derives the dew point (DP) from temperature and relative humidity,
+    /// at every epoch where both are observed, see [meteo::products::dew_point].
+    /// Epochs where the humidity is null (or invalid) are left untouched.
+    /// ```
+    /// use rinex::prelude::*;
+    /// let mut rinex = Rinex::from_file("../test_resources/MET/V2/abvi0010.15m")
+    ///     .unwrap();
+    /// rinex.dew_point_mut();
+    /// for (epoch, dew_point) in rinex.dew_point() {
+    ///     println!("ts: {}, dew point: {}°C", epoch, dew_point);
+    /// }
+    /// ```
+    pub fn dew_point_mut(&mut self) {
+        if let Some(record) = self.record.as_mut_meteo() {
+            meteo::products::dew_point_mut(record);
+            self.meteo_declare_observable(Observable::DewPoint);
+        }
+    }
+    /// Derives the weighted mean temperature of the atmosphere (TM),
+    /// from surface temperature (Bevis et al., 1992).
+    pub fn weighted_mean_temperature_mut(&mut self) {
+        if let Some(record) = self.record.as_mut_meteo() {
+            meteo::products::weighted_mean_temperature_mut(record);
+            self.meteo_declare_observable(Observable::WeightedMeanTemperature);
+        }
+    }
+    /// Derives the zenith hydrostatic delay (ZD) from pressure (Saastamoinen),
+    /// at given latitude [°] and ellipsoidal height [m].
+    /// Existing ZD observations are overwritten.
+    pub fn zenith_dry_delay_mut(&mut self, lat: f64, alt: f64) {
+        if let Some(record) = self.record.as_mut_meteo() {
+            meteo::products::zenith_dry_delay_mut(record, lat, alt);
+            self.meteo_declare_observable(Observable::ZenithDryDelay);
+        }
+    }
+    /// Derives the precipitable water vapor (PW) from the wet zenith delay (ZW),
+    /// typically estimated from GNSS observations.
+    /// The weighted mean temperature (TM) is used when present,
+    /// otherwise it is evaluated from surface temperature.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use std::collections::HashMap;
+    /// let mut rinex = Rinex::from_file("../test_resources/MET/V2/abvi0010.15m")
+    ///     .unwrap();
+    /// let t0 = Epoch::from_gregorian_utc_at_midnight(2015, 1, 1);
+    /// // ZWD [mm] from a GNSS solution
+    /// if let Some(record) = rinex.record.as_mut_meteo() {
+    ///     record.get_mut(&t0).unwrap().insert(Observable::ZenithWetDelay, 250.0);
+    /// }
+    /// rinex.precipitable_water_vapor_mut();
+    /// let (t, pwv) = rinex.precipitable_water_vapor().next().unwrap();
+    /// assert_eq!(t, t0);
+    /// assert!(pwv > 38.0 && pwv < 42.0);
+    /// ```
+    pub fn precipitable_water_vapor_mut(&mut self) {
+        if let Some(record) = self.record.as_mut_meteo() {
+            meteo::products::precipitable_water_vapor_mut(record);
+            self.meteo_declare_observable(Observable::PrecipitableWaterVapor);
+        }
+    }
+    /// Interpolates given meteo observable at desired Epoch.
+    /// Returns None outside of the record, within data gaps
+    /// larger than `opts.max_gap`, or if this observable is not measured.
+    /// Values are reported at the sensor level, see [Rinex::meteo_align].
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::meteo::{InterpolationMethod, InterpolationOpts};
+    /// let rinex = Rinex::from_file("../test_resources/MET/V2/abvi0010.15m")
+    ///     .unwrap();
+    /// let t = Epoch::from_gregorian_utc(2015, 1, 1, 0, 0, 30, 0);
+    /// let opts = InterpolationOpts::default()
+    ///     .with_method(InterpolationMethod::Spline)
+    ///     .with_max_gap(Duration::from_seconds(300.0));
+    /// let pressure = rinex.meteo_at(t, &Observable::Pressure, opts)
+    ///     .unwrap();
+    /// assert!((pressure - 1018.65).abs() < 0.01);
+    /// ```
+    pub fn meteo_at(
+        &self,
+        t: Epoch,
+        observable: &Observable,
+        opts: meteo::InterpolationOpts,
+    ) -> Option<f64> {
+        let record = self.record.as_meteo()?;
+        meteo::interpolation::interpolate(record, t, observable, &opts)
+    }
+    /// Builds a Meteo RINEX sampled on given [TimeSeries], typically
+    /// the [Rinex::timeseries] of an Observation RINEX, by interpolation,
+    /// see [Rinex::meteo_at]. Epochs where nothing could be interpolated are omitted.
+    /// When the `antenna` position is known, pressure, temperature and humidity
+    /// measured by sensors of known height are reduced to the antenna level:
+    /// these sensors are then positioned at the antenna in the returned header.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::meteo::InterpolationOpts;
+    /// let meteo = Rinex::from_file("../test_resources/MET/V2/abvi0010.15m")
+    ///     .unwrap();
+    /// let t0 = Epoch::from_gregorian_utc_at_midnight(2015, 1, 1);
+    /// let series = TimeSeries::inclusive(t0, t0 + Duration::from_hours(1.0), Duration::from_seconds(30.0));
+    /// let aligned = meteo.meteo_align(series, None, InterpolationOpts::default());
+    /// // sampling stops at 00:09:00 and resumes at 09:00:00:
+    /// // this gap is larger than the default 1h limit
+    /// assert_eq!(aligned.epoch().count(), 19);
+    /// ```
+    pub fn meteo_align(
+        &self,
+        series: TimeSeries,
+        antenna: Option<GroundPosition>,
+        opts: meteo::InterpolationOpts,
+    ) -> Self {
+        let mut header = self.header.clone();
+        let mut record = meteo::Record::new();
+        let sensors: Vec<meteo::sensor::Sensor> = self
+            .header
+            .meteo
+            .as_ref()
+            .map(|meteo| meteo.sensors.clone())
+            .unwrap_or_default();
+        let observables: Vec<Observable> = self.observable().cloned().collect();
+        let antenna = antenna.map(|antenna| (antenna.to_ecef_wgs84(), antenna.to_geodetic().2));
+        for t in series {
+            let mut values: HashMap<Observable, f64> = HashMap::new();
+            for observable in &observables {
+                if let Some(value) = self.meteo_at(t, observable, opts) {
+                    let sensor = sensors.iter().find(|s| s.observable == *observable);
+                    let value = match (sensor, antenna) {
+                        (Some(sensor), Some((_, height))) => {
+                            sensor.height_correction(value, height)
+                        },
+                        _ => value,
+                    };
+                    values.insert(observable.clone(), value);
+                }
+            }
+            if !values.is_empty() {
+                record.insert(t, values);
+            }
+        }
+        if let (Some(meteo), Some(((x, y, z), height))) = (header.meteo.as_mut(), antenna) {
+            for sensor in meteo.sensors.iter_mut() {
+                let corrected = matches!(
+                    sensor.observable,
+                    Observable::Pressure | Observable::Temperature | Observable::HumidityRate
+                );
+                if corrected && sensor.height().is_some() {
+                    *sensor = sensor.with_position((x, y, z, height));
+                }
+            }
+        }
+        Self::new(header, record::Record::MeteoRecord(record))
+    }
 }
 
 impl Merge for Rinex {
@@ -2553,11 +3779,21 @@ impl Merge for Rinex {
         lhs.merge_mut(rhs)?;
         Ok(lhs)
     }
-    /// Merges `rhs` into `Self` in place
+    /// Merges `rhs` into `Self` in place.
+    /// Records are merged for IONEX and Navigation RINEX,
+    /// only headers are merged for other RINEX types.
     fn merge_mut(&mut self, rhs: &Self) -> Result<(), merge::Error> {
         self.header.merge_mut(&rhs.header)?;
+        if self.is_ionex() || self.is_navigation_rinex() {
+            self.record.merge_mut(&rhs.record)?;
+            if let (Some(ionex), Some(record)) =
+                (self.header.ionex.as_mut(), self.record.as_ionex())
+            {
+                *ionex = ionex.with_record_maps(record);
+            }
+        }
         Ok(())
-        //TODO: needs to reapply
+        //TODO: record merging needs to be reapplied to other RINEX types
         //if self.epoch().len() == 0 {
         //    // self is empty
         //    self.record = rhs.record.clone();
@@ -2846,6 +4082,41 @@ impl IonoDelay for Rinex {
     }
 }
 
+#[cfg(feature = "obs")]
+use observation::{Roti, Scintillation};
+
+#[cfg(feature = "obs")]
+impl Roti for Rinex {
+    fn roti(&self, window: Duration) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> {
+        if let Some(r) = self.record.as_obs() {
+            r.roti(window)
+        } else {
+            panic!("wrong RINEX type");
+        }
+    }
+}
+
+#[cfg(feature = "obs")]
+impl Scintillation for Rinex {
+    fn s4(&self, window: Duration) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> {
+        if let Some(r) = self.record.as_obs() {
+            r.s4(window)
+        } else {
+            panic!("wrong RINEX type");
+        }
+    }
+    fn sigma_phi(
+        &self,
+        window: Duration,
+    ) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> {
+        if let Some(r) = self.record.as_obs() {
+            r.sigma_phi(window)
+        } else {
+            panic!("wrong RINEX type");
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -19,6 +19,10 @@ pub enum Error {
     IonexMapDimensionsMismatch,
     #[error("cannot merge ionex where base radius differs")]
     IonexBaseRadiusMismatch,
+    #[error("cannot merge ionex evaluated with different mapping functions")]
+    IonexMappingFunctionMismatch,
+    #[error("cannot merge observations with different phase shift corrections")]
+    PhaseShiftMismatch,
 }
 
 /*
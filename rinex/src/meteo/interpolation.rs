@@ -0,0 +1,175 @@
+//! Meteo observations interpolation
+use super::Record;
+use crate::prelude::*;
+
+/// Interpolation method
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum InterpolationMethod {
+    /// Linear interpolation between the two surrounding samples
+    #[default]
+    Linear,
+    /// Cubic Hermite spline, with slopes estimated from the
+    /// neighboring samples (Catmull-Rom). Falls back to linear
+    /// interpolation when neighbors are missing.
+    Spline,
+}
+
+/// Meteo interpolation options
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct InterpolationOpts {
+    /// Interpolation method
+    pub method: InterpolationMethod,
+    /// Maximal gap between two samples we interpolate in between.
+    /// No value is interpolated within larger gaps, nor outside the record.
+    pub max_gap: Duration,
+}
+
+impl Default for InterpolationOpts {
+    fn default() -> Self {
+        Self {
+            method: InterpolationMethod::default(),
+            max_gap: Duration::from_hours(1.0),
+        }
+    }
+}
+
+impl InterpolationOpts {
+    /// Returns options with given interpolation method
+    pub fn with_method(&self, method: InterpolationMethod) -> Self {
+        let mut s = *self;
+        s.method = method;
+        s
+    }
+    /// Returns options with given maximal gap
+    pub fn with_max_gap(&self, max_gap: Duration) -> Self {
+        let mut s = *self;
+        s.max_gap = max_gap;
+        s
+    }
+}
+
+/*
+ * Interpolates given observable at Epoch `t`
+ */
+pub(crate) fn interpolate(
+    record: &Record,
+    t: Epoch,
+    observable: &Observable,
+    opts: &InterpolationOpts,
+) -> Option<f64> {
+    let mut before = record
+        .range(..=t)
+        .rev()
+        .filter_map(|(e, values)| values.get(observable).map(|v| (*e, *v)));
+    let mut after = record
+        .range(t..)
+        .filter_map(|(e, values)| values.get(observable).map(|v| (*e, *v)));
+    let (t0, y0) = before.next()?;
+    if t0 == t {
+        return Some(y0);
+    }
+    let (t1, y1) = after.next()?;
+    if t1 - t0 > opts.max_gap {
+        return None;
+    }
+    let h = (t1 - t0).to_seconds();
+    let s = (t - t0).to_seconds() / h;
+    let linear = y0 + (y1 - y0) * s;
+    if opts.method == InterpolationMethod::Linear {
+        return Some(linear);
+    }
+    // neighbors, within the gap limit
+    let prev = before.next().filter(|(e, _)| t0 - *e <= opts.max_gap);
+    let next = after.next().filter(|(e, _)| *e - t1 <= opts.max_gap);
+    let (prev, next) = match (prev, next) {
+        (Some(prev), Some(next)) => (prev, next),
+        _ => return Some(linear),
+    };
+    // slopes [unit/s]
+    let m0 = (y1 - prev.1) / (t1 - prev.0).to_seconds();
+    let m1 = (next.1 - y0) / (next.0 - t0).to_seconds();
+    let (s2, s3) = (s * s, s * s * s);
+    Some(
+        (2.0 * s3 - 3.0 * s2 + 1.0) * y0
+            + (s3 - 2.0 * s2 + s) * h * m0
+            + (-2.0 * s3 + 3.0 * s2) * y1
+            + (s3 - s2) * h * m1,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+    fn record(samples: &[(f64, f64)]) -> Record {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 1, 1);
+        let mut record = Record::new();
+        for (dt, value) in samples {
+            let mut values = HashMap::new();
+            values.insert(Observable::Temperature, *value);
+            record.insert(t0 + Duration::from_seconds(*dt), values);
+        }
+        record
+    }
+    #[test]
+    fn linear_and_spline() {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 1, 1);
+        // cubic trend, sampled every 10'
+        let f = |t: f64| 10.0 + 2.0E-3 * t - 1.0E-7 * t * t + 1.0E-12 * t * t * t;
+        let samples: Vec<(f64, f64)> = (0..12)
+            .map(|i| (600.0 * i as f64, f(600.0 * i as f64)))
+            .collect();
+        let record = record(&samples);
+        let linear = InterpolationOpts::default();
+        let spline = linear.with_method(InterpolationMethod::Spline);
+        let observable = Observable::Temperature;
+        // samples are returned as is
+        for (dt, value) in &samples {
+            let t = t0 + Duration::from_seconds(*dt);
+            assert_eq!(interpolate(&record, t, &observable, &linear), Some(*value));
+            assert_eq!(interpolate(&record, t, &observable, &spline), Some(*value));
+        }
+        for dt in [1000.0, 2345.0, 4100.0] {
+            let t = t0 + Duration::from_seconds(dt);
+            let lin = interpolate(&record, t, &observable, &linear).unwrap();
+            let spl = interpolate(&record, t, &observable, &spline).unwrap();
+            assert!((spl - f(dt)).abs() < (lin - f(dt)).abs());
+            assert!((spl - f(dt)).abs() < 1.0E-3);
+        }
+        // no extrapolation
+        assert!(interpolate(
+            &record,
+            t0 - Duration::from_seconds(1.0),
+            &observable,
+            &linear
+        )
+        .is_none());
+        assert!(interpolate(
+            &record,
+            t0 + Duration::from_hours(2.0),
+            &observable,
+            &linear
+        )
+        .is_none());
+        // no other observable
+        assert!(interpolate(&record, t0, &Observable::Pressure, &linear).is_none());
+    }
+    #[test]
+    fn gap_limit() {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 1, 1);
+        let record = record(&[(0.0, 1.0), (600.0, 2.0), (4200.0, 3.0)]);
+        let opts = InterpolationOpts::default().with_max_gap(Duration::from_seconds(900.0));
+        let observable = Observable::Temperature;
+        let t = t0 + Duration::from_seconds(300.0);
+        assert_eq!(interpolate(&record, t, &observable, &opts), Some(1.5));
+        let t = t0 + Duration::from_seconds(1200.0);
+        assert!(interpolate(&record, t, &observable, &opts).is_none());
+        let opts = opts.with_max_gap(Duration::from_hours(1.0));
+        assert_eq!(
+            interpolate(&record, t, &observable, &opts),
+            Some(2.1666666666666665)
+        );
+    }
+}
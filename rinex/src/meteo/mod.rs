@@ -1,6 +1,9 @@
 //! Meteo RINEX module
+pub mod interpolation;
+pub mod products;
 pub mod record;
 pub mod sensor;
+pub use interpolation::{InterpolationMethod, InterpolationOpts};
 pub use record::Record;
 
 use crate::Observable;
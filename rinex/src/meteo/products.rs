@@ -0,0 +1,124 @@
+//! Derived meteorological products, for GNSS meteorology
+use super::Record;
+use crate::Observable;
+use std::collections::HashMap;
+
+/// Water density [kg.m⁻³]
+const WATER_DENSITY: f64 = 1000.0;
+/// Specific gas constant of water vapor [J.kg⁻¹.K⁻¹]
+const RV: f64 = 461.5;
+/// Refractivity constants (Bevis et al., 1994) [K.hPa⁻¹] and [K².hPa⁻¹]
+const K2_PRIME: f64 = 22.1;
+const K3: f64 = 3.739E5;
+
+/// Weighted mean temperature of the atmosphere [K],
+/// from surface temperature [°C], using the Bevis et al. (1992) regression
+pub fn weighted_mean_temperature(temperature: f64) -> f64 {
+    70.2 + 0.72 * (temperature + 273.15)
+}
+
+/// Dimensionless conversion factor from zenith wet delay to
+/// precipitable water vapor, at given weighted mean temperature [K]
+pub fn pwv_conversion_factor(tm: f64) -> f64 {
+    1.0E8 / (WATER_DENSITY * RV * (K3 / tm + K2_PRIME))
+}
+
+/// Precipitable water vapor [mm], from zenith wet delay [mm]
+/// and weighted mean temperature [K]
+pub fn precipitable_water_vapor(zwd: f64, tm: f64) -> f64 {
+    pwv_conversion_factor(tm) * zwd
+}
+
+/// Dew point [°C], from temperature [°C] and relative humidity [%],
+/// using the Magnus formula. Returns None for a null or negative humidity,
+/// for which the dew point is not defined.
+pub fn dew_point(temperature: f64, humidity: f64) -> Option<f64> {
+    if humidity <= 0.0 {
+        return None;
+    }
+    let (a, b) = (17.62, 243.12);
+    let gamma = (humidity / 100.0).ln() + a * temperature / (b + temperature);
+    Some(b * gamma / (a - gamma))
+}
+
+/// Zenith hydrostatic delay [mm], from surface pressure [hPa] (Saastamoinen),
+/// at given latitude [°] and ellipsoidal height [m]
+pub fn zenith_hydrostatic_delay(pressure: f64, lat: f64, alt: f64) -> f64 {
+    let f = 1.0 - 2.66E-3 * (2.0 * lat.to_radians()).cos() - 2.8E-7 * alt;
+    2.2768 * pressure / f
+}
+
+/*
+ * Evaluates `f` at every epoch of the record,
+ * and stores the result as the `observable` column
+ */
+fn derive<F: Fn(&HashMap<Observable, f64>) -> Option<f64>>(
+    record: &mut Record,
+    observable: Observable,
+    f: F,
+) {
+    for values in record.values_mut() {
+        if let Some(value) = f(values) {
+            values.insert(observable.clone(), value);
+        }
+    }
+}
+
+pub(crate) fn dew_point_mut(record: &mut Record) {
+    derive(record, Observable::DewPoint, |values| {
+        let temperature = values.get(&Observable::Temperature)?;
+        let humidity = values.get(&Observable::HumidityRate)?;
+        dew_point(*temperature, *humidity)
+    })
+}
+
+pub(crate) fn weighted_mean_temperature_mut(record: &mut Record) {
+    derive(record, Observable::WeightedMeanTemperature, |values| {
+        let temperature = values.get(&Observable::Temperature)?;
+        Some(weighted_mean_temperature(*temperature))
+    })
+}
+
+pub(crate) fn zenith_dry_delay_mut(record: &mut Record, lat: f64, alt: f64) {
+    derive(record, Observable::ZenithDryDelay, |values| {
+        let pressure = values.get(&Observable::Pressure)?;
+        Some(zenith_hydrostatic_delay(*pressure, lat, alt))
+    })
+}
+
+pub(crate) fn precipitable_water_vapor_mut(record: &mut Record) {
+    derive(record, Observable::PrecipitableWaterVapor, |values| {
+        let zwd = values.get(&Observable::ZenithWetDelay)?;
+        let tm = match values.get(&Observable::WeightedMeanTemperature) {
+            Some(tm) => *tm,
+            None => weighted_mean_temperature(*values.get(&Observable::Temperature)?),
+        };
+        Some(precipitable_water_vapor(*zwd, tm))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn products() {
+        // Tm: ~ 280K for a 15°C surface temperature
+        let tm = weighted_mean_temperature(15.0);
+        assert!((tm - 277.67).abs() < 1.0E-2);
+        // PWV ~ ZWD / 6.5
+        let factor = pwv_conversion_factor(tm);
+        assert!(factor > 0.15 && factor < 0.16, "{}", factor);
+        assert!((precipitable_water_vapor(150.0, tm) - 150.0 * factor).abs() < 1.0E-9);
+        // saturated air: dew point is the temperature
+        assert!((dew_point(20.0, 100.0).unwrap() - 20.0).abs() < 1.0E-9);
+        assert!((dew_point(20.0, 50.0).unwrap() - 9.26).abs() < 0.05);
+        assert!(dew_point(20.0, 10.0).unwrap() < dew_point(20.0, 50.0).unwrap());
+        // dry air: undefined
+        assert!(dew_point(20.0, 0.0).is_none());
+        assert!(dew_point(20.0, -1.0).is_none());
+        // ZHD ~ 2.3 m at sea level
+        let zhd = zenith_hydrostatic_delay(1013.25, 45.0, 0.0);
+        assert!((zhd - 2306.9).abs() < 0.1, "{}", zhd);
+        assert!(zenith_hydrostatic_delay(1013.25, 0.0, 0.0) > zhd);
+    }
+}
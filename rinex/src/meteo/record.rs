@@ -69,40 +69,28 @@ pub(crate) fn parse_epoch(
     let (epoch, _) = epoch::parse(&line[0..offset])?;
 
     let codes = &header.meteo.as_ref().unwrap().codes;
-    let nb_codes = codes.len();
-    let nb_lines: usize = num_integer::div_ceil(nb_codes, 8).into();
-    let mut code_index: usize = 0;
-
-    for i in 0..nb_lines {
-        for _ in 0..8 {
-            let code = &codes[code_index];
-            let obs: Option<f64> = match f64::from_str(&line[offset..offset + 7].trim()) {
-                Ok(f) => Some(f),
-                Err(_) => None,
-            };
-
-            if let Some(obs) = obs {
-                map.insert(code.clone(), obs);
-            }
-            code_index += 1;
-            if code_index >= nb_codes {
-                break;
-            }
-
-            offset += 7;
-            if offset >= line.len() {
-                break;
-            }
-        } // 1:8
-
-        if i < nb_lines - 1 {
-            if let Some(l) = lines.next() {
-                line = l;
-            } else {
-                break;
+    for (index, code) in codes.iter().enumerate() {
+        // 8 observations on the epoch line,
+        // then 10 per continuation line (4X,10F7.1)
+        let start = if index < 8 {
+            offset + index * 7
+        } else {
+            if (index - 8) % 10 == 0 {
+                line = match lines.next() {
+                    Some(l) => l,
+                    None => break,
+                };
             }
+            4 + ((index - 8) % 10) * 7
+        };
+        if start >= line.len() {
+            continue;
+        }
+        let end = (start + 7).min(line.len());
+        if let Ok(obs) = f64::from_str(line[start..end].trim()) {
+            map.insert(code.clone(), obs);
         }
-    } // nb lines
+    }
     Ok((epoch, map))
 }
 
@@ -121,16 +109,15 @@ pub(crate) fn fmt_epoch(
         epoch::format(*epoch, None, Type::MeteoData, header.version.major)
     ));
     let observables = &header.meteo.as_ref().unwrap().codes;
-    let mut index = 0;
-    for obscode in observables {
-        index += 1;
+    for (index, obscode) in observables.iter().enumerate() {
+        // continuation lines: 4X,10F7.1
+        if index >= 8 && (index - 8) % 10 == 0 {
+            lines.push_str("\n    ");
+        }
         if let Some(data) = data.get(obscode) {
             lines.push_str(&format!("{:7.1}", data));
         } else {
-            lines.push_str(&format!("       "));
-        }
-        if (index % 8) == 0 {
-            lines.push_str("\n");
+            lines.push_str("       ");
         }
     }
     lines.push_str("\n");
@@ -425,6 +412,9 @@ impl Preprocessing for Record {
     }
 }
 
+#[cfg(feature = "processing")]
+use itertools::Itertools;
+
 #[cfg(feature = "processing")]
 impl Interpolate for Record {
     fn interpolate(&self, series: TimeSeries) -> Self {
@@ -432,8 +422,28 @@ impl Interpolate for Record {
         s.interpolate_mut(series);
         s
     }
-    fn interpolate_mut(&mut self, _series: TimeSeries) {
-        unimplemented!("meteo:record:interpolate_mut()")
+    fn interpolate_mut(&mut self, series: TimeSeries) {
+        let opts = super::InterpolationOpts::default();
+        let observables: Vec<Observable> = self
+            .values()
+            .flat_map(|values| values.keys())
+            .unique()
+            .cloned()
+            .collect();
+        let mut record = Record::new();
+        for t in series {
+            let values: HashMap<Observable, f64> = observables
+                .iter()
+                .filter_map(|observable| {
+                    super::interpolation::interpolate(self, t, observable, &opts)
+                        .map(|value| (observable.clone(), value))
+                })
+                .collect();
+            if !values.is_empty() {
+                record.insert(t, values);
+            }
+        }
+        *self = record;
     }
 }
 
@@ -124,6 +124,34 @@ impl Sensor {
         s.accuracy = Some(accuracy);
         s
     }
+    /// Returns sensor ellipsoidal height [m], if its position is known
+    pub fn height(&self) -> Option<f64> {
+        let (x, y, z, h) = self.position?;
+        if h != 0.0 {
+            Some(h)
+        } else if x != 0.0 || y != 0.0 || z != 0.0 {
+            let (_, _, h) = map_3d::ecef2geodetic(x, y, z, map_3d::Ellipsoid::WGS84);
+            Some(h)
+        } else {
+            None
+        }
+    }
+    /// Reduces a measurement of this sensor to given ellipsoidal height [m],
+    /// using the standard atmosphere lapse rates.
+    /// Only pressure [hPa], temperature [°C] and relative humidity [%]
+    /// are corrected, and only if the sensor height is known.
+    pub fn height_correction(&self, value: f64, height: f64) -> f64 {
+        let dh = match self.height() {
+            Some(h) => height - h,
+            None => return value,
+        };
+        match self.observable {
+            Observable::Pressure => value * (1.0 - 2.26E-5 * dh).powf(5.225),
+            Observable::Temperature => value - 6.5E-3 * dh,
+            Observable::HumidityRate => value * (-6.396E-4 * dh).exp(),
+            _ => value,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -180,4 +208,20 @@ mod test {
         );
         assert_eq!(s.is_ok(), true);
     }
+    #[test]
+    fn height_correction() {
+        let s = Sensor::new(Observable::Pressure);
+        assert_eq!(s.height(), None);
+        assert_eq!(s.height_correction(1013.25, 100.0), 1013.25);
+        let s = s.with_position((0.0, 0.0, 0.0, 100.0));
+        assert_eq!(s.height(), Some(100.0));
+        assert_eq!(s.height_correction(1013.25, 100.0), 1013.25);
+        // about 12 hPa less, 100 m higher
+        let p = s.height_correction(1013.25, 200.0);
+        assert!(p < 1002.0 && p > 1000.0, "{}", p);
+        let t = s.with_observable(Observable::Temperature);
+        assert!((t.height_correction(15.0, 0.0) - 15.65).abs() < 1.0E-9);
+        let ws = s.with_observable(Observable::WindSpeed);
+        assert_eq!(ws.height_correction(3.0, 0.0), 3.0);
+    }
 }
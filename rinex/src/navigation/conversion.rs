@@ -0,0 +1,243 @@
+//! NAV RINEX revisions conversion
+use super::{
+    orbits::{closest_revision, NAV_ORBITS},
+    Ephemeris, IonMessage, NavFrame, NavMsgType, Record,
+};
+use crate::{
+    header::{time_system_correction, time_system_week},
+    prelude::*,
+    version::Version,
+};
+use std::collections::HashMap;
+
+/// Constellations RINEX2 can describe, one file per constellation
+const V2_CONSTELLATIONS: [Constellation; 2] = [Constellation::GPS, Constellation::Glonass];
+
+/*
+ * SBAS vehicles share the same messages, whatever the augmentation
+ */
+fn system(constellation: Constellation) -> Constellation {
+    match constellation {
+        Constellation::SBAS(_) => Constellation::Geo,
+        c => c,
+    }
+}
+
+/*
+ * Identifies the constellation broadcasting given time system correction,
+ * for example "GAGP" is broadcast by Galileo
+ */
+fn time_system_constellation(system: &str) -> Option<Constellation> {
+    match system.get(..2)? {
+        "GP" => Some(Constellation::GPS),
+        "GA" => Some(Constellation::Galileo),
+        "GL" => Some(Constellation::Glonass),
+        "QZ" => Some(Constellation::QZSS),
+        "BD" => Some(Constellation::BeiDou),
+        "IR" => Some(Constellation::IRNSS),
+        "SB" => Some(Constellation::Geo),
+        _ => None,
+    }
+}
+
+/*
+ * NAV V4 message type of given ephemeris
+ */
+fn ephemeris_msg_type(sv: &Sv, ephemeris: &Ephemeris) -> NavMsgType {
+    match system(sv.constellation) {
+        Constellation::Glonass => NavMsgType::FDMA,
+        Constellation::Geo => NavMsgType::SBAS,
+        Constellation::Galileo => {
+            // data source bit 1: F/NAV E5a-I
+            let source = ephemeris.get_orbit_f64("dataSrc").unwrap_or(0.0) as u32;
+            if source & 0x02 > 0 {
+                NavMsgType::FNAV
+            } else {
+                NavMsgType::INAV
+            }
+        },
+        Constellation::BeiDou => {
+            // GEO vehicles broadcast D2 messages
+            if sv.prn < 6 || sv.prn > 58 {
+                NavMsgType::D2
+            } else {
+                NavMsgType::D1
+            }
+        },
+        _ => NavMsgType::LNAV,
+    }
+}
+
+/*
+ * Drops the orbits fields given revision cannot describe,
+ * for example the Glonass V4 status flags when converting to V3
+ */
+fn retain_revision_orbits(sv: &Sv, ephemeris: &mut Ephemeris, version: Version) {
+    let revision = match closest_revision(sv.constellation, version) {
+        Some(revision) => revision,
+        None => return,
+    };
+    let keys: Vec<&str> = NAV_ORBITS
+        .iter()
+        .filter(|r| r.constellation == sv.constellation.to_3_letter_code())
+        .flat_map(|r| {
+            r.revisions
+                .iter()
+                .filter(|r| {
+                    r.major.parse::<u8>() == Ok(revision.major)
+                        && r.minor.parse::<u8>() == Ok(revision.minor)
+                })
+                .flat_map(|r| r.items.iter().map(|(key, _)| *key))
+        })
+        .collect();
+    ephemeris
+        .orbits
+        .retain(|key, _| keys.contains(&key.as_str()));
+}
+
+/*
+ * NAV V4 message type of ION and STO frames, broadcast by given constellation
+ */
+fn frame_msg_type(constellation: Constellation) -> NavMsgType {
+    match constellation {
+        Constellation::Glonass => NavMsgType::FDMA,
+        Constellation::Geo => NavMsgType::SBAS,
+        Constellation::Galileo => NavMsgType::IFNV,
+        Constellation::BeiDou => NavMsgType::D1D2,
+        _ => NavMsgType::LNAV,
+    }
+}
+
+/*
+ * Converts a NAV V2/V3 RINEX (header and record) to NAV V4.
+ * Header ionospheric and time system corrections become ION and STO frames,
+ * tagged to the first vehicle of the broadcasting constellation:
+ * corrections with no such vehicle are dropped.
+ */
+pub(crate) fn to_v4(header: &mut Header, record: &mut Record) {
+    let mut first: HashMap<Constellation, (Epoch, Sv)> = HashMap::new();
+    for (epoch, frames) in record.iter_mut() {
+        for frame in frames.iter_mut() {
+            if let NavFrame::Eph(msg, sv, ephemeris) = frame {
+                *msg = ephemeris_msg_type(sv, ephemeris);
+                first
+                    .entry(system(sv.constellation))
+                    .or_insert((*epoch, *sv));
+            }
+        }
+    }
+    let mut ionod_corrections: Vec<_> = header.ionod_corrections.drain().collect();
+    ionod_corrections.sort_by_key(|(constellation, _)| *constellation);
+    for (constellation, model) in ionod_corrections {
+        if let Some((epoch, sv)) = first.get(&system(constellation)) {
+            let msg = frame_msg_type(system(constellation));
+            record
+                .entry(*epoch)
+                .or_default()
+                .push(NavFrame::Ion(msg, *sv, model));
+        }
+    }
+    let mut time_corrections: Vec<_> = header.time_corrections.drain().collect();
+    time_corrections.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (system, (epoch, sto)) in time_corrections {
+        let constellation = match time_system_constellation(&system) {
+            Some(c) => c,
+            None => continue,
+        };
+        if let Some((_, sv)) = first.get(&constellation) {
+            let msg = frame_msg_type(constellation);
+            record
+                .entry(epoch)
+                .or_default()
+                .push(NavFrame::Sto(msg, *sv, sto));
+        }
+    }
+    header.version = Version::new(4, 0);
+}
+
+/*
+ * Converts a NAV RINEX (header and record) to NAV V3.
+ * The first ION and STO frames of each constellation and time system
+ * become header corrections, other ION, STO and EOP frames are dropped.
+ */
+pub(crate) fn to_v3(header: &mut Header, record: &mut Record) {
+    for (epoch, frames) in record.iter_mut() {
+        frames.retain(|frame| match frame {
+            NavFrame::Eph(_, _, _) => true,
+            NavFrame::Ion(_, sv, model) => {
+                // V3 headers describe Klobuchar and NeQuick-G models
+                if !matches!(model, IonMessage::BdgimModel(_)) {
+                    header
+                        .ionod_corrections
+                        .entry(system(sv.constellation))
+                        .or_insert(model.clone());
+                }
+                false
+            },
+            NavFrame::Sto(_, _, sto) => {
+                // V3 headers do not describe the transmission time,
+                // nor the drift rate term
+                let (week, seconds) = time_system_week(&sto.system, *epoch);
+                header.time_corrections.entry(sto.system.clone()).or_insert(
+                    time_system_correction(&sto.system, (sto.a.0, sto.a.1), week, seconds),
+                );
+                false
+            },
+            NavFrame::Eop(_, _, _) => false,
+        });
+        for frame in frames.iter_mut() {
+            if let NavFrame::Eph(msg, sv, ephemeris) = frame {
+                *msg = NavMsgType::LNAV;
+                retain_revision_orbits(sv, ephemeris, Version::new(3, 5));
+            }
+        }
+    }
+    record.retain(|_, frames| !frames.is_empty());
+    header.version = Version::new(3, 5);
+}
+
+/*
+ * Splits a NAV RINEX (header and record) into single constellation
+ * NAV V2 RINEX: GPS (.n) and Glonass (.g). Other constellations are dropped.
+ * V4 ION and STO frames are first migrated to header corrections, like in V3.
+ */
+pub(crate) fn to_v2(header: &Header, record: &Record) -> Vec<(Header, Record)> {
+    let mut ret: Vec<(Header, Record)> = Vec::new();
+    let (mut header, mut record) = (header.clone(), record.clone());
+    if header.version.major > 3 {
+        to_v3(&mut header, &mut record);
+    }
+    for constellation in V2_CONSTELLATIONS {
+        let mut rec = Record::new();
+        for (epoch, frames) in record.iter() {
+            let frames: Vec<NavFrame> = frames
+                .iter()
+                .filter_map(|frame| match frame {
+                    NavFrame::Eph(_, sv, ephemeris) if sv.constellation == constellation => {
+                        let mut ephemeris = ephemeris.clone();
+                        retain_revision_orbits(sv, &mut ephemeris, Version::new(2, 11));
+                        Some(NavFrame::Eph(NavMsgType::LNAV, *sv, ephemeris))
+                    },
+                    _ => None,
+                })
+                .collect();
+            if !frames.is_empty() {
+                rec.insert(*epoch, frames);
+            }
+        }
+        if rec.is_empty() {
+            continue;
+        }
+        let mut hd = header.clone();
+        hd.version = Version::new(2, 11);
+        hd.constellation = Some(constellation);
+        hd.ionod_corrections
+            .retain(|c, model| *c == constellation && model.as_klobuchar().is_some());
+        hd.time_corrections.retain(|system, _| match constellation {
+            Constellation::GPS => system.eq("GPUT"),
+            _ => system.eq("GLUT"),
+        });
+        ret.push((hd, rec));
+    }
+    ret
+}
@@ -1,10 +1,13 @@
 use super::{
     orbits::{closest_revision, NAV_ORBITS},
-    OrbitItem,
+    NavMsgType, OrbitItem,
 };
 use crate::{epoch, prelude::*, sv, version::Version};
 
-use hifitime::GPST_REF_EPOCH;
+#[cfg(feature = "nav")]
+use crate::ground_position::elevation_azimuth;
+
+use hifitime::{BDT_REF_EPOCH, GPST_REF_EPOCH};
 use std::collections::HashMap;
 use std::str::FromStr;
 use thiserror::Error;
@@ -198,7 +201,10 @@ impl Ephemeris {
      * Parses ephemeris from given line iterator
      * RINEX V4 content specific method
      */
-    pub(crate) fn parse_v4(mut lines: std::str::Lines<'_>) -> Result<(Epoch, Sv, Self), Error> {
+    pub(crate) fn parse_v4(
+        msg: NavMsgType,
+        mut lines: std::str::Lines<'_>,
+    ) -> Result<(Epoch, Sv, Self), Error> {
         let line = match lines.next() {
             Some(l) => l,
             _ => return Err(Error::MissingData),
@@ -214,7 +220,11 @@ impl Ephemeris {
         let clock_bias = f64::from_str(clk_bias.replace("D", "E").trim())?;
         let clock_drift = f64::from_str(clk_dr.replace("D", "E").trim())?;
         let clock_drift_rate = f64::from_str(clk_drr.replace("D", "E").trim())?;
-        let orbits = parse_orbits(Version { major: 4, minor: 0 }, sv.constellation, lines)?;
+        let orbits = parse_orbits(
+            v4_orbits_revision(msg, sv.constellation),
+            sv.constellation,
+            lines,
+        )?;
         Ok((
             epoch,
             sv,
@@ -270,14 +280,20 @@ impl Ephemeris {
         let perturbations = self.perturbations()?;
 
         let weeks = self.get_weeks()?;
-        let t0 = GPST_REF_EPOCH + Duration::from_days((weeks * 7).into());
+        // BeiDou weeks are counted in BDT, other weeks are aligned to GPST
+        let t_ref = if self.orbits.contains_key("bdtWeek") {
+            BDT_REF_EPOCH
+        } else {
+            GPST_REF_EPOCH
+        };
+        let t0 = t_ref + Duration::from_days((weeks * 7).into());
         let toe = t0 + Duration::from_seconds(kepler.toe as f64);
         let t_k = (epoch - toe).to_seconds();
 
         let n0 = (Kepler::EARTH_GM_CONSTANT / kepler.a.powf(3.0)).sqrt();
         let n = n0 + perturbations.dn;
         let m_k = kepler.m_0 + n * t_k;
-        let e_k = m_k + kepler.e * m_k.sin();
+        let e_k = Self::eccentric_anomaly(m_k, kepler.e);
         let nu_k = ((1.0 - kepler.e.powf(2.0)).sqrt() * e_k.sin()).atan2(e_k.cos() - kepler.e);
         let phi_k = nu_k + kepler.omega;
 
@@ -306,6 +322,23 @@ impl Ephemeris {
         Some((x_k, y_k, z_k))
     }
 
+    /// Max. number of iterations when solving Kepler's equation
+    const KEPLER_MAX_ITER: usize = 30;
+
+    /*
+     * Solves Kepler's equation E = M + e sin(E) by fixed point iteration
+     */
+    fn eccentric_anomaly(m_k: f64, e: f64) -> f64 {
+        let mut e_k = m_k;
+        for _ in 0..Self::KEPLER_MAX_ITER {
+            let next = m_k + e * e_k.sin();
+            if (next - e_k).abs() < 1.0E-14 {
+                return next;
+            }
+            e_k = next;
+        }
+        e_k
+    }
     pub(crate) fn sv_position(&self, epoch: Epoch) -> Option<(f64, f64, f64)> {
         if let Some(pos_x) = self.get_orbit_f64("satPosX") {
             if let Some(pos_y) = self.get_orbit_f64("satPosY") {
@@ -326,38 +359,19 @@ impl Ephemeris {
         reference: GroundPosition,
         epoch: Epoch,
     ) -> Option<(f64, f64)> {
-        let (sv_x, sv_y, sv_z) = self.sv_position(epoch)?;
-        let (ref_x, ref_y, ref_z) = reference.to_ecef_wgs84();
-        let (sv_lat, sv_lon, _) = map_3d::ecef2geodetic(sv_x, sv_y, sv_z, map_3d::Ellipsoid::WGS84);
-        // pseudo range
-        let a_i = (sv_x - ref_x, sv_y - ref_y, sv_z - ref_z);
-        let norm = (a_i.0.powf(2.0) + a_i.1.powf(2.0) + a_i.2.powf(2.0)).sqrt();
-        let a_i = (a_i.0 / norm, a_i.1 / norm, a_i.2 / norm); // normalized
-                                                              // dot product
-        let ecef2enu = (
-            (-sv_lon.sin(), sv_lon.cos(), 0.0_f64),
-            (
-                -sv_lon.cos() * sv_lat.sin(),
-                -sv_lon.sin() * sv_lat.sin(),
-                sv_lat.cos(),
-            ),
-            (
-                sv_lon.cos() * sv_lat.cos(),
-                sv_lon.sin() * sv_lat.cos(),
-                sv_lat.sin(),
-            ),
-        );
-        let a_enu = (
-            ecef2enu.0 .0 * a_i.0 + ecef2enu.0 .1 * a_i.1 + ecef2enu.0 .2 * a_i.2,
-            ecef2enu.1 .0 * a_i.0 + ecef2enu.1 .1 * a_i.1 + ecef2enu.1 .2 * a_i.2,
-            ecef2enu.2 .0 * a_i.0 + ecef2enu.2 .1 * a_i.1 + ecef2enu.2 .2 * a_i.2,
-        );
-        let elev = a_enu.2.asin();
-        let mut azim = map_3d::rad2deg(a_enu.0.atan2(a_enu.1));
-        if azim < 0.0 {
-            azim += 360.0;
-        }
-        Some((map_3d::rad2deg(elev), azim))
+        let sv = self.sv_position(epoch)?;
+        Some(elevation_azimuth(reference.to_ecef_wgs84(), sv))
+    }
+}
+
+/*
+ * NAV V4 legacy messages share the V3 orbits layout,
+ * only GLONASS FDMA and the modernized messages differ
+ */
+pub(crate) fn v4_orbits_revision(msg: NavMsgType, constellation: Constellation) -> Version {
+    match (msg, constellation) {
+        (NavMsgType::CNVX, _) | (_, Constellation::Glonass) => Version::new(4, 0),
+        _ => Version::new(3, 0),
     }
 }
 
@@ -610,21 +624,27 @@ mod test {
 
         assert!(xyz.is_some());
         let (x, y, z) = xyz.unwrap();
-        assert!((x - -5678509.38584636).abs() < 1E-6);
-        assert!((y - -24923975.356725316).abs() < 1E-6);
-        assert!((z - 7056393.437932).abs() < 1E-6);
+        assert!((x - -5678409.752770652).abs() < 1E-6);
+        assert!((y - -24923962.750211433).abs() < 1E-6);
+        assert!((z - 7056520.560621752).abs() < 1E-6);
+
+        // reference satellite position of this example: a single
+        // iteration of Kepler's equation was 160m away from it
+        let (ref_x, ref_y, ref_z) = ref_pos.to_ecef_wgs84();
+        let err = ((x - ref_x).powi(2) + (y - ref_y).powi(2) + (z - ref_z).powi(2)).sqrt();
+        assert!(err < 5.0, "kepler2ecef: |e| = {}m", err);
 
         assert!(el_azim.is_some());
         let (elev, azim) = el_azim.unwrap();
         assert!(
-            (elev - -0.23579324).abs() < 1E-3,
+            (elev - 1.10138372).abs() < 1E-3,
             "elev° failed with |e| = {}",
-            (elev - -0.23579324).abs()
+            (elev - 1.10138372).abs()
         );
         assert!(
-            (azim - 215.63240776).abs() < 1E-3,
+            (azim - 34.38218826).abs() < 1E-3,
             "azim° failed with |e| = {}",
-            (azim - 215.63240776).abs()
+            (azim - 34.38218826).abs()
         );
 
         let orbits = build_orbits(
@@ -660,8 +680,71 @@ mod test {
 
         assert!(xyz.is_some());
         let (x, y, z) = xyz.unwrap();
-        assert!((x - -11840614.01333711).abs() < 1E-6);
-        assert!((y - 19224209.93574417).abs() < 1E-6);
-        assert!((z - 13435836.30353981).abs() < 1E-6);
+        assert!((x - -11841271.522863723).abs() < 1E-6);
+        assert!((y - 19223672.317115113).abs() < 1E-6);
+        assert!((z - 13436031.22883126).abs() < 1E-6);
+    }
+    #[test]
+    fn eccentric_anomaly() {
+        // up to the eccentricity of E14 and E18
+        for e in [0.0, 0.01, 0.16] {
+            for i in 0..36 {
+                let m_k = (i as f64 * 10.0).to_radians();
+                let e_k = Ephemeris::eccentric_anomaly(m_k, e);
+                assert!(
+                    (e_k - e * e_k.sin() - m_k).abs() < 1.0E-12,
+                    "kepler's equation not solved for e={}, m={}",
+                    e,
+                    m_k
+                );
+            }
+        }
+    }
+    #[cfg(all(feature = "nav", feature = "flate2"))]
+    #[test]
+    fn kepler2ecef_continuity() {
+        /*
+         * Successive broadcast ephemerides describe the same orbit:
+         * both must agree (to the broadcast accuracy) in between.
+         * Counting BeiDou weeks in GPST leads to errors of thousands of km
+         */
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let mut ephemerides: HashMap<Sv, Vec<(Epoch, &Ephemeris)>> = HashMap::new();
+        for (toc, (_, sv, ephemeris)) in nav.ephemeris() {
+            let meo = match sv.constellation {
+                Constellation::GPS => true,
+                // E14 and E18 were injected on eccentric orbits, not usable
+                Constellation::Galileo => sv.prn != 14 && sv.prn != 18,
+                // BeiDou GEO are not described by Kepler's elements
+                Constellation::BeiDou => sv.prn > 5 && sv.prn < 59,
+                _ => false,
+            };
+            if meo && ephemeris.kepler().is_some() {
+                ephemerides.entry(*sv).or_default().push((*toc, ephemeris));
+            }
+        }
+        let mut tested = Vec::<Constellation>::new();
+        for (sv, series) in ephemerides {
+            for pair in series.windows(2) {
+                let ((t0, eph0), (t1, eph1)) = (pair[0], pair[1]);
+                let dt = t1 - t0;
+                if dt > Duration::from_hours(2.0) {
+                    continue;
+                }
+                let t = t0 + dt / 2.0;
+                let (x0, y0, z0) = eph0.kepler2ecef(t).unwrap();
+                let (x1, y1, z1) = eph1.kepler2ecef(t).unwrap();
+                let err = ((x0 - x1).powi(2) + (y0 - y1).powi(2) + (z0 - z1).powi(2)).sqrt();
+                assert!(err < 5.0, "{} @ {}: |e| = {}m", sv, t, err);
+                if !tested.contains(&sv.constellation) {
+                    tested.push(sv.constellation);
+                }
+            }
+        }
+        assert_eq!(tested.len(), 3);
     }
 }
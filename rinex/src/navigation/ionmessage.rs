@@ -1,4 +1,4 @@
-use crate::{epoch, prelude::*};
+use crate::{carrier::Carrier, epoch, ionex, prelude::*, SPEED_OF_LIGHT};
 use bitflags::bitflags;
 use std::str::FromStr;
 use thiserror::Error;
@@ -30,6 +30,17 @@ pub enum Error {
     EpochError(#[from] epoch::Error),
 }
 
+/// Model evaluation error
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+pub enum IonModelError {
+    #[error("NeQuick-G is not supported: CCIR and MODIP maps are not provided")]
+    NequickGNotSupported,
+    #[error("BDGIM is not supported: the non broadcast background is not modeled")]
+    BdgimNotSupported,
+    #[error("no ionosphere model available")]
+    NoModel,
+}
+
 /// Klobuchar Parameters region
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Serialize))]
@@ -139,6 +150,76 @@ bitflags! {
     }
 }
 
+impl KbModel {
+    /// Evaluates the ionospheric delay [s] on the model reference carrier
+    /// (L1 for GPS, QZSS and IRNSS, B1I for BeiDou), as described in IS-GPS-200.
+    ///   - `t`: signal reception [Epoch]
+    ///   - `elev`, `azim`: Sv elevation and azimuth angles [°]
+    ///   - `lat`, `lon`: receiver geodetic latitude and longitude [°]
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::navigation::KbModel;
+    /// let model = KbModel {
+    ///     alpha: (7.4506E-9, -1.4901E-8, -5.9605E-8, 1.1921E-7),
+    ///     beta: (9.0112E4, -6.5536E4, -1.3107E5, 4.5875E5),
+    ///     ..Default::default()
+    /// };
+    /// let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 12, 0, 0);
+    /// let delay = model.delay(t, 30.0, 180.0, 52.0, 4.3);
+    /// assert!(delay > 5.0E-9);
+    /// ```
+    pub fn delay(&self, t: Epoch, elev: f64, azim: f64, lat: f64, lon: f64) -> f64 {
+        const PI: f64 = std::f64::consts::PI;
+        let elev = elev / 180.0; // semicircles
+        let azim = azim.to_radians();
+        // earth centered angle
+        let psi = 0.0137 / (elev + 0.11) - 0.022;
+        // ionospheric pierce point
+        let phi_i = (lat / 180.0 + psi * azim.cos()).clamp(-0.416, 0.416);
+        let lambda_i = lon / 180.0 + psi * azim.sin() / (phi_i * PI).cos();
+        // geomagnetic latitude
+        let phi_m = phi_i + 0.064 * ((lambda_i - 1.617) * PI).cos();
+        // local time
+        let t_gps = t.to_gpst_seconds().rem_euclid(86400.0);
+        let t_local = (43200.0 * lambda_i + t_gps).rem_euclid(86400.0);
+
+        let (a0, a1, a2, a3) = self.alpha;
+        let (b0, b1, b2, b3) = self.beta;
+        let amp = (a0 + phi_m * (a1 + phi_m * (a2 + phi_m * a3))).max(0.0);
+        let per = (b0 + phi_m * (b1 + phi_m * (b2 + phi_m * b3))).max(72000.0);
+        let x = 2.0 * PI * (t_local - 50400.0) / per;
+        // slant factor
+        let f = 1.0 + 16.0 * (0.53 - elev).powi(3);
+        if x.abs() < 1.57 {
+            f * (5.0E-9 + amp * (1.0 - x.powi(2) / 2.0 + x.powi(4) / 24.0))
+        } else {
+            f * 5.0E-9
+        }
+    }
+    /// Evaluates the vertical Total Electron Content [TECu]
+    /// above given location [°], from the zenith delay on the model reference `carrier`.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::carrier::Carrier;
+    /// use rinex::navigation::KbModel;
+    /// let model = KbModel {
+    ///     alpha: (7.4506E-9, -1.4901E-8, -5.9605E-8, 1.1921E-7),
+    ///     beta: (9.0112E4, -6.5536E4, -1.3107E5, 4.5875E5),
+    ///     ..Default::default()
+    /// };
+    /// // night time: 5ns on L1
+    /// let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 0, 0, 0);
+    /// let tec = model.vertical_tec(t, 52.0, 4.3, Carrier::L1);
+    /// assert!((tec - 9.23).abs() < 0.01);
+    /// ```
+    pub fn vertical_tec(&self, t: Epoch, lat: f64, lon: f64, carrier: Carrier) -> f64 {
+        // obliquity factor at zenith
+        let f = 1.0 + 16.0 * 0.03_f64.powi(3);
+        let delay = self.delay(t, 90.0, 0.0, lat, lon) / f * SPEED_OF_LIGHT;
+        delay / ionex::tec_to_delay(1.0, carrier)
+    }
+}
+
 /// Nequick-G Model payload
 #[derive(Debug, Clone, Default, Copy, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(Serialize))]
@@ -228,6 +309,58 @@ impl BdModel {
         );
         Ok((epoch, Self { alpha }))
     }
+    /// Evaluates the broadcast correction [TECu] to the BDGIM background,
+    /// above given location [°], from the 9 broadcast coefficients,
+    /// as described in BDS-SIS-ICD-B1C. This is not the vertical TEC:
+    /// the non broadcast background term (A0) is not modeled.
+    pub fn tec_correction(&self, t: Epoch, lat: f64, lon: f64) -> f64 {
+        const PI: f64 = std::f64::consts::PI;
+        // geomagnetic north pole
+        let (pole_lat, pole_lon) = (80.27_f64.to_radians(), -72.58_f64.to_radians());
+        let (phi, lambda) = (lat.to_radians(), lon.to_radians());
+        // geomagnetic coordinates
+        let sin_phi_m =
+            pole_lat.sin() * phi.sin() + pole_lat.cos() * phi.cos() * (lambda - pole_lon).cos();
+        let lambda_m = (phi.cos() * (lambda - pole_lon).sin() * pole_lat.cos())
+            .atan2(pole_lat.sin() * sin_phi_m - phi.sin());
+        // sun fixed geomagnetic longitude
+        let t_ut = t.to_mjd_utc_days().fract();
+        let s_lon = PI * (1.0 - 2.0 * t_ut);
+        let lambda_s = (s_lon - pole_lon)
+            .sin()
+            .atan2(pole_lat.sin() * (s_lon - pole_lon).cos());
+        let lambda = lambda_m - lambda_s;
+        // normalized associated Legendre functions
+        let (x, y) = (sin_phi_m, (1.0 - sin_phi_m.powi(2)).max(0.0).sqrt());
+        let p10 = 3.0_f64.sqrt() * x;
+        let p11 = 3.0_f64.sqrt() * y;
+        let p20 = 5.0_f64.sqrt() * (3.0 * x.powi(2) - 1.0) / 2.0;
+        let p21 = (5.0_f64 / 3.0).sqrt() * 3.0 * x * y;
+        let p22 = (5.0_f64 / 12.0).sqrt() * 3.0 * y.powi(2);
+        let a = [
+            1.0,
+            p10,
+            p11 * lambda.cos(),
+            p11 * lambda.sin(),
+            p20,
+            p21 * lambda.cos(),
+            p21 * lambda.sin(),
+            p22 * (2.0 * lambda).cos(),
+            p22 * (2.0 * lambda).sin(),
+        ];
+        let alpha = [
+            self.alpha.0,
+            self.alpha.1,
+            self.alpha.2,
+            self.alpha.3,
+            self.alpha.4,
+            self.alpha.5,
+            self.alpha.6,
+            self.alpha.7,
+            self.alpha.8,
+        ];
+        alpha.iter().zip(a).map(|(alpha, a)| alpha * a).sum()
+    }
 }
 
 /// IonMessage: wraps several ionospheric models
@@ -270,6 +403,31 @@ impl IonMessage {
             _ => None,
         }
     }
+    /// Evaluates the vertical Total Electron Content [TECu] predicted by this model
+    /// above given location [°]. `constellation` is the broadcasting constellation,
+    /// which defines the Klobuchar reference carrier.
+    /// Only the Klobuchar model is supported: NeQuick-G requires the CCIR and MODIP maps,
+    /// and BDGIM its non broadcast background, which we do not provide
+    /// (see [BdModel::tec_correction]).
+    pub fn vertical_tec(
+        &self,
+        t: Epoch,
+        lat: f64,
+        lon: f64,
+        constellation: Constellation,
+    ) -> Result<f64, IonModelError> {
+        match self {
+            Self::KlobucharModel(model) => {
+                let carrier = match constellation {
+                    Constellation::BeiDou => Carrier::B1I,
+                    _ => Carrier::L1,
+                };
+                Ok(model.vertical_tec(t, lat, lon, carrier))
+            },
+            Self::BdgimModel(_) => Err(IonModelError::BdgimNotSupported),
+            Self::NequickGModel(_) => Err(IonModelError::NequickGNotSupported),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -310,6 +468,46 @@ mod test {
         );
     }
     #[test]
+    fn kb_delay() {
+        let model = KbModel {
+            alpha: (7.4506E-9, -1.4901E-8, -5.9605E-8, 1.1921E-7),
+            beta: (9.0112E4, -6.5536E4, -1.3107E5, 4.5875E5),
+            region: KbRegionCode::WideArea,
+        };
+        // night time: constant 5ns, scaled by obliquity factor
+        let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 0, 0, 0);
+        let delay = model.delay(t, 90.0, 0.0, 0.0, 0.0);
+        assert!((delay - 5.0E-9 * (1.0 + 16.0 * 0.03_f64.powi(3))).abs() < 1.0E-15);
+        // day time: amplified, and increases with lower elevations
+        let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 14, 0, 0);
+        let zenith = model.delay(t, 90.0, 0.0, 0.0, 0.0);
+        assert!(zenith > 5.0E-9);
+        let slant = model.delay(t, 10.0, 0.0, 0.0, 0.0);
+        assert!(slant > 2.0 * zenith);
+    }
+    #[test]
+    fn bdgim_correction() {
+        // only the constant term
+        let mut model = BdModel::default();
+        model.alpha.0 = 10.0;
+        let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 0, 0, 0);
+        assert!((model.tec_correction(t, 52.0, 4.3) - 10.0).abs() < 1.0E-9);
+        // zonal term: depends on geomagnetic latitude only
+        let mut model = BdModel::default();
+        model.alpha.1 = 1.0;
+        let north = model.tec_correction(t, 60.0, 0.0);
+        let south = model.tec_correction(t, -60.0, 0.0);
+        assert!(north > 0.0 && south < 0.0);
+        let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 12, 0, 0);
+        assert!((model.tec_correction(t, 60.0, 0.0) - north).abs() < 1.0E-9);
+        // sectorial terms rotate with the Sun
+        let mut model = BdModel::default();
+        model.alpha.2 = 1.0;
+        let noon = model.tec_correction(t, 0.0, 0.0);
+        let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 0, 0, 0);
+        assert!((model.tec_correction(t, 0.0, 180.0) - noon).abs() < 0.2);
+    }
+    #[test]
     fn test_ng() {
         let content =
             "    2022 06 08 09 59 57 7.850000000000E+01 5.390625000000E-01 2.713012695312E-02
@@ -336,5 +534,21 @@ mod test {
         assert!(msg.as_klobuchar().is_some());
         assert!(msg.as_nequick_g().is_none());
         assert!(msg.as_bdgim().is_none());
+        let t = Epoch::from_gregorian_utc_hms(2021, 1, 1, 0, 0, 0);
+        let gps = msg.vertical_tec(t, 0.0, 0.0, Constellation::GPS).unwrap();
+        let bds = msg
+            .vertical_tec(t, 0.0, 0.0, Constellation::BeiDou)
+            .unwrap();
+        assert!(bds < gps, "B1I has a lower frequency");
+        let msg = IonMessage::NequickGModel(NgModel::default());
+        assert_eq!(
+            msg.vertical_tec(t, 0.0, 0.0, Constellation::Galileo),
+            Err(IonModelError::NequickGNotSupported)
+        );
+        let msg = IonMessage::BdgimModel(BdModel::default());
+        assert_eq!(
+            msg.vertical_tec(t, 0.0, 0.0, Constellation::BeiDou),
+            Err(IonModelError::BdgimNotSupported)
+        );
     }
 }
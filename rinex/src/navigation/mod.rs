@@ -1,4 +1,5 @@
 //! NAV RINEX module
+pub(crate) mod conversion;
 mod eopmessage;
 mod ephemeris;
 mod health;
@@ -8,14 +9,22 @@ mod stomessage;
 pub mod orbits;
 pub mod record;
 
+#[cfg(feature = "nav")]
+pub(crate) mod sisre;
+
 pub use eopmessage::EopMessage;
 pub use ephemeris::Ephemeris;
 pub use health::{GeoHealth, GloHealth, Health, IrnssHealth};
-pub use ionmessage::{BdModel, IonMessage, KbModel, KbRegionCode, NgModel, NgRegionFlags};
+pub use ionmessage::{
+    BdModel, IonMessage, IonModelError, KbModel, KbRegionCode, NgModel, NgRegionFlags,
+};
 pub use orbits::OrbitItem;
 pub use record::{NavFrame, NavMsgType, Record};
 pub use stomessage::StoMessage;
 
+#[cfg(feature = "nav")]
+pub use sisre::{PreciseOrbits, SisreStats, SvError};
+
 use crate::{epoch, sv};
 use thiserror::Error;
 
@@ -3,6 +3,7 @@
 use crate::version;
 //use std::fmt::Display;
 use super::health;
+use super::record::fmt_nav_float;
 use crate::constellation::Constellation;
 use bitflags::bitflags;
 use itertools::Itertools;
@@ -133,21 +134,22 @@ impl OrbitItem {
             _ => Err(OrbitItemError::UnknownTypeDescriptor(type_desc.to_string())),
         }
     }
-    /// Formats self following RINEX standards,
+    /// Formats self following RINEX standards (D19.12),
     /// mainly used when producing a file
     pub fn to_string(&self) -> String {
-        match self {
-            OrbitItem::U8(n) => format!("{:14.11E}", *n as f64),
-            OrbitItem::I8(n) => format!("{:14.11E}", *n as f64),
-            OrbitItem::F32(f) => format!("{:14.11E}", f),
-            OrbitItem::F64(f) => format!("{:14.11E}", f),
-            OrbitItem::Health(h) => format!("{:14.11E}", h),
-            OrbitItem::GloHealth(h) => format!("{:14.11E}", h),
-            OrbitItem::GeoHealth(h) => format!("{:14.11E}", h),
-            OrbitItem::IrnssHealth(h) => format!("{:14.11E}", h),
-            OrbitItem::GalHealth(h) => format!("{:14.11E}", h.bits() as f64),
-            OrbitItem::GloStatus(h) => format!("{:14.11E}", h.bits() as f64),
-        }
+        let value = match self {
+            OrbitItem::U8(n) => *n as f64,
+            OrbitItem::I8(n) => *n as f64,
+            OrbitItem::F32(f) => *f as f64,
+            OrbitItem::F64(f) => *f,
+            OrbitItem::Health(h) => h.clone() as u32 as f64,
+            OrbitItem::GloHealth(h) => h.clone() as u32 as f64,
+            OrbitItem::GeoHealth(h) => h.clone() as u32 as f64,
+            OrbitItem::IrnssHealth(h) => h.clone() as u32 as f64,
+            OrbitItem::GalHealth(h) => h.bits() as f64,
+            OrbitItem::GloStatus(h) => h.bits() as f64,
+        };
+        fmt_nav_float(value, 19, 12)
     }
     /// Unwraps OrbitItem as f32
     pub fn as_f32(&self) -> Option<f32> {
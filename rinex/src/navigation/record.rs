@@ -36,6 +36,7 @@ use crate::{
 };
 
 use super::{
+    ephemeris::v4_orbits_revision,
     orbits::{closest_revision, NAV_ORBITS},
     BdModel, EopMessage, Ephemeris, IonMessage, KbModel, NgModel, StoMessage,
 };
@@ -268,7 +269,7 @@ fn parse_v4_record_entry(content: &str) -> Result<(Epoch, NavFrame), Error> {
 
     let (epoch, fr): (Epoch, NavFrame) = match frame_class {
         FrameClass::Ephemeris => {
-            let (epoch, _, ephemeris) = Ephemeris::parse_v4(lines)?;
+            let (epoch, _, ephemeris) = Ephemeris::parse_v4(msg_type, lines)?;
             (epoch, NavFrame::Eph(msg_type, sv, ephemeris))
         },
         FrameClass::SystemTimeOffset => {
@@ -358,146 +359,113 @@ fn fmt_epoch_v2v3(epoch: &Epoch, data: &Vec<NavFrame>, header: &Header) -> Resul
     for fr in data.iter() {
         if let Some(fr) = fr.as_eph() {
             let (_, sv, ephemeris) = fr;
-            match &header.constellation {
-                Some(Constellation::Mixed) => {
-                    // Mixed constellation context
-                    // we need to fully describe the vehicle
-                    lines.push_str(&format!("{} ", sv));
-                },
-                Some(_) => {
-                    // Unique constellation context:
-                    // in V2 format, only PRN is shown
-                    lines.push_str(&format!("{:2} ", sv.prn));
-                },
-                None => {
-                    panic!("can't generate data without predefined constellations");
-                },
-            }
-            lines.push_str(&format!(
-                "{} ",
-                epoch::format(*epoch, None, Type::NavigationData, header.version.major)
-            ));
-            lines.push_str(&format!(
-                "{:14.11E} {:14.11E} {:14.11E}\n   ",
-                ephemeris.clock_bias, ephemeris.clock_drift, ephemeris.clock_drift_rate
-            ));
-            if header.version.major == 3 {
-                lines.push_str("  ");
-            }
             // locate closest revision in db
             let orbits_revision = match closest_revision(sv.constellation, header.version) {
                 Some(v) => v,
                 _ => return Err(Error::OrbitRevision),
             };
-            // retrieve db items / fields to generate,
-            // for this revision
-            let orbits_standards: Vec<_> = NAV_ORBITS
-                .iter()
-                .filter(|r| r.constellation == sv.constellation.to_3_letter_code())
-                .map(|r| {
-                    r.revisions
-                            .iter()
-                            .filter(|r| // identified db revision
-                                u8::from_str_radix(r.major, 10).unwrap() == orbits_revision.major
-                                && u8::from_str_radix(r.minor, 10).unwrap() == orbits_revision.minor
-                            )
-                            .map(|r| &r.items)
-                            .flatten()
-                })
-                .flatten()
-                .collect();
-            let nb_items_per_line = 4;
-            let mut chunks = orbits_standards.chunks_exact(nb_items_per_line).peekable();
-            while let Some(chunk) = chunks.next() {
-                if chunks.peek().is_some() {
-                    for (key, _) in chunk {
-                        if let Some(data) = ephemeris.orbits.get(*key) {
-                            lines.push_str(&format!("{} ", data.to_string()));
-                        } else {
-                            lines.push_str(&format!("                   "));
-                        }
-                    }
-                    lines.push_str(&format!("\n     "));
-                } else {
-                    // last row
-                    for (key, _) in chunk {
-                        if let Some(data) = ephemeris.orbits.get(*key) {
-                            lines.push_str(&format!("{}", data.to_string()));
-                        } else {
-                            lines.push_str(&format!("                   "));
-                        }
-                    }
-                    lines.push_str("\n");
-                }
-            }
+            lines.push_str(&fmt_ephemeris(
+                epoch,
+                sv,
+                ephemeris,
+                header,
+                orbits_revision,
+            ));
         }
     }
     lines = fmt_rework(header.version.major, &lines);
     Ok(lines)
 }
 
+/*
+ * Formats a floating point number following the Fortran Dw.p descriptor,
+ * with a two digit exponent.
+ */
+pub(crate) fn fmt_nav_float(value: f64, width: usize, precision: usize) -> String {
+    let formatted = format!("{:.*E}", precision, value);
+    let (mantissa, exponent) = formatted.split_at(formatted.find('E').unwrap_or(0));
+    let exponent = i32::from_str(&exponent[1..]).unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!(
+        "{:>width$}",
+        format!("{}E{}{:02}", mantissa, sign, exponent.abs()),
+        width = width
+    )
+}
+
+/*
+ * Formats given ephemeris: SV/EPOCH/SV CLK line, followed by the
+ * broadcast orbits of given db revision, 4 D19.12 fields per line
+ */
+fn fmt_ephemeris(
+    epoch: &Epoch,
+    sv: &Sv,
+    ephemeris: &Ephemeris,
+    header: &Header,
+    orbits_revision: Version,
+) -> String {
+    let major = header.version.major;
+    let mut lines = String::with_capacity(128);
+    let epoch = epoch::format(*epoch, None, Type::NavigationData, major);
+    if major < 3 {
+        match &header.constellation {
+            Some(Constellation::Mixed) => lines.push_str(&format!("{}{}", sv, epoch)),
+            _ => lines.push_str(&format!("{:2} {}", sv.prn, epoch)),
+        }
+    } else {
+        lines.push_str(&format!("{} {}", sv, epoch));
+    }
+    lines.push_str(&fmt_nav_floats(&[
+        ephemeris.clock_bias,
+        ephemeris.clock_drift,
+        ephemeris.clock_drift_rate,
+    ]));
+    lines.push('\n');
+    // retrieve db items / fields to generate,
+    // for this revision
+    let orbits_standards: Vec<_> = NAV_ORBITS
+        .iter()
+        .filter(|r| r.constellation == sv.constellation.to_3_letter_code())
+        .flat_map(|r| {
+            r.revisions
+                .iter()
+                .filter(|r| // identified db revision
+                    r.major.parse::<u8>() == Ok(orbits_revision.major)
+                    && r.minor.parse::<u8>() == Ok(orbits_revision.minor))
+                .flat_map(|r| &r.items)
+        })
+        .collect();
+    let indent = if major < 3 { "   " } else { "    " };
+    for chunk in orbits_standards.chunks(4) {
+        let mut line = indent.to_string();
+        for (key, _) in chunk {
+            if let Some(data) = ephemeris.orbits.get(*key) {
+                line.push_str(&data.to_string());
+            } else {
+                // data is missing: either not parsed or not provided
+                line.push_str(&" ".repeat(19));
+            }
+        }
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    lines
+}
+
 fn fmt_epoch_v4(epoch: &Epoch, data: &Vec<NavFrame>, header: &Header) -> Result<String, Error> {
     let mut lines = String::with_capacity(128);
+    let t = epoch::format(*epoch, None, Type::NavigationData, header.version.major);
     for fr in data.iter() {
         if let Some(fr) = fr.as_eph() {
             let (msgtype, sv, ephemeris) = fr;
             lines.push_str(&format!("> {} {} {}\n", FrameClass::Ephemeris, sv, msgtype));
-            match &header.constellation {
-                Some(Constellation::Mixed) => {
-                    // Mixed constellation context
-                    // we need to fully describe the vehicle
-                    lines.push_str(&sv.to_string());
-                    lines.push_str(" ");
-                },
-                Some(_) => {
-                    // Unique constellation context:
-                    // in V2 format, only PRN is shown
-                    lines.push_str(&format!("{:02} ", sv.prn));
-                },
-                None => panic!("producing data with no constellation previously defined"),
-            }
-            lines.push_str(&format!(
-                "{} ",
-                epoch::format(*epoch, None, Type::NavigationData, header.version.major)
-            ));
-            lines.push_str(&format!(
-                "{:14.13E} {:14.13E} {:14.13E}\n",
-                ephemeris.clock_bias, ephemeris.clock_drift, ephemeris.clock_drift_rate
+            lines.push_str(&fmt_ephemeris(
+                epoch,
+                sv,
+                ephemeris,
+                header,
+                v4_orbits_revision(msgtype, sv.constellation),
             ));
-            // locate closest revision in db
-            let orbits_revision = match closest_revision(sv.constellation, header.version) {
-                Some(v) => v,
-                _ => return Err(Error::OrbitRevision),
-            };
-            // retrieve db items / fields to generate,
-            // for this revision
-            let orbits_standards: Vec<_> = NAV_ORBITS
-                .iter()
-                .filter(|r| r.constellation == sv.constellation.to_3_letter_code())
-                .map(|r| {
-                    r.revisions
-                        .iter()
-                        .filter(|r| // identified db revision
-                            u8::from_str_radix(r.major, 10).unwrap() == orbits_revision.major
-                            && u8::from_str_radix(r.minor, 10).unwrap() == orbits_revision.minor)
-                        .map(|r| &r.items)
-                        .flatten()
-                })
-                .flatten()
-                .collect();
-            let mut index = 0;
-            for (key, _) in orbits_standards.iter() {
-                index += 1;
-                if let Some(data) = ephemeris.orbits.get(*key) {
-                    lines.push_str(&format!(" {}", data.to_string()));
-                } else {
-                    // data is missing: either not parsed or not provided
-                    lines.push_str("              ");
-                }
-                if (index % 4) == 0 {
-                    lines.push_str("\n   "); //TODO: do not TAB when writing last line of grouping
-                }
-            }
         } else if let Some(fr) = fr.as_sto() {
             let (msg, sv, sto) = fr;
             lines.push_str(&format!(
@@ -506,40 +474,92 @@ fn fmt_epoch_v4(epoch: &Epoch, data: &Vec<NavFrame>, header: &Header) -> Result<
                 sv,
                 msg
             ));
+            let line = format!("    {} {:<18} {:<18} {}", t, sto.system, "", sto.utc);
+            lines.push_str(line.trim_end());
+            lines.push('\n');
             lines.push_str(&format!(
-                "    {} {}    {}\n",
-                epoch::format(*epoch, None, Type::NavigationData, header.version.major),
-                sto.system,
-                sto.utc
+                "    {}\n",
+                fmt_nav_floats(&[sto.t_tm as f64, sto.a.0, sto.a.1, sto.a.2])
             ));
+        } else if let Some(fr) = fr.as_eop() {
+            let (msg, sv, eop) = fr;
             lines.push_str(&format!(
-                "   {:14.13E} {:14.13E} {:14.13E} {:14.13E}\n",
-                sto.t_tm as f64, sto.a.0, sto.a.1, sto.a.2
+                "> {} {} {}\n",
+                FrameClass::EarthOrientation,
+                sv,
+                msg
             ));
-        } else if let Some(_fr) = fr.as_eop() {
-            todo!("NAV V4: EOP: we have no example as of today");
-            //(x, xr, xrr), (y, yr, yrr), t_tm, (dut, dutr, dutrr)) = frame.as_eop()
-        }
-        // EOP
-        else if let Some(fr) = fr.as_ion() {
+            lines.push_str(&format!(
+                "    {}{}\n",
+                t,
+                fmt_nav_floats(&[eop.x.0, eop.x.1, eop.x.2])
+            ));
+            lines.push_str(&format!(
+                "    {:19}{}\n",
+                "",
+                fmt_nav_floats(&[eop.y.0, eop.y.1, eop.y.2])
+            ));
+            lines.push_str(&format!(
+                "    {}\n",
+                fmt_nav_floats(&[
+                    eop.t_tm as f64,
+                    eop.delta_ut1.0,
+                    eop.delta_ut1.1,
+                    eop.delta_ut1.2,
+                ])
+            ));
+        } else if let Some(fr) = fr.as_ion() {
             let (msg, sv, ion) = fr;
             lines.push_str(&format!(
                 "> {} {} {}\n",
-                FrameClass::EarthOrientation,
+                FrameClass::IonosphericModel,
                 sv,
                 msg
             ));
-            match ion {
-                IonMessage::KlobucharModel(_model) => todo!("ION:Kb"),
-                IonMessage::NequickGModel(_model) => todo!("ION:Ng"),
-                IonMessage::BdgimModel(_model) => todo!("ION:Bd"),
+            let coefs: Vec<f64> = match ion {
+                IonMessage::KlobucharModel(model) => vec![
+                    model.alpha.0,
+                    model.alpha.1,
+                    model.alpha.2,
+                    model.alpha.3,
+                    model.beta.0,
+                    model.beta.1,
+                    model.beta.2,
+                    model.beta.3,
+                    model.region as u8 as f64,
+                ],
+                IonMessage::NequickGModel(model) => {
+                    vec![model.a.0, model.a.1, model.a.2, model.region.bits() as f64]
+                },
+                IonMessage::BdgimModel(model) => vec![
+                    model.alpha.0,
+                    model.alpha.1,
+                    model.alpha.2,
+                    model.alpha.3,
+                    model.alpha.4,
+                    model.alpha.5,
+                    model.alpha.6,
+                    model.alpha.7,
+                    model.alpha.8,
+                ],
+            };
+            // 1st line is the epoch and 3 coefficients, then 4 per line
+            lines.push_str(&format!("    {}{}\n", t, fmt_nav_floats(&coefs[..3])));
+            for chunk in coefs[3..].chunks(4) {
+                lines.push_str(&format!("    {}\n", fmt_nav_floats(chunk)));
             }
-        } // ION
+        }
     }
-    lines = fmt_rework(4, &lines);
     Ok(lines)
 }
 
+/*
+ * Formats consecutive D19.12 fields
+ */
+fn fmt_nav_floats(values: &[f64]) -> String {
+    values.iter().map(|v| fmt_nav_float(*v, 19, 12)).collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,320 @@
+//! Signal In Space Range Error (SISRE) analysis,
+//! comparing broadcast ephemerides to precise products.
+use super::Ephemeris;
+use crate::epoch::gnss_instant;
+use crate::prelude::*;
+use crate::{clocks, Rinex, SPEED_OF_LIGHT};
+use std::collections::{BTreeMap, HashMap};
+
+/// Precise orbits, as provided by an SP3 file for example:
+/// Sv positions, expressed in meters ECEF, sorted by [Epoch].
+pub type PreciseOrbits = BTreeMap<Epoch, HashMap<Sv, (f64, f64, f64)>>;
+
+/// Broadcast ephemeris error, for a given Sv at a given Epoch.
+/// All errors are expressed as broadcast - precise.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SvError {
+    /// Radial, along-track and cross-track orbit errors [m],
+    /// when precise orbits were provided
+    pub orbit: Option<(f64, f64, f64)>,
+    /// Clock error, expressed in [m],
+    /// when precise clocks were provided
+    pub clock: Option<f64>,
+}
+
+/// SISRE statistics, for a given constellation.
+/// All values are expressed in meters. Clock related statistics
+/// are evaluated once the constellation wide clock offset, at every epoch,
+/// has been removed, because it is absorbed by the receiver clock
+/// in the navigation solution.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct SisreStats {
+    /// RMS radial error
+    pub radial_rms: Option<f64>,
+    /// RMS along-track error
+    pub along_rms: Option<f64>,
+    /// RMS cross-track error
+    pub cross_rms: Option<f64>,
+    /// RMS clock error
+    pub clock_rms: Option<f64>,
+    /// Orbit only SISRE (RMS)
+    pub orbit_sisre: Option<f64>,
+    /// Global SISRE (orbit and clock) (RMS)
+    pub sisre: Option<f64>,
+    /// Number of samples that contributed
+    pub samples: usize,
+}
+
+/*
+ * Radial and along/cross-track weight factors, per constellation
+ * (Montenbruck et al., 2015). Second term is already squared.
+ * GEO/IGSO vehicles have different weights due to their altitude.
+ */
+pub(crate) fn sisre_weights(sv: &Sv) -> (f64, f64) {
+    match sv.constellation {
+        Constellation::Glonass => (0.98, 1.0 / 45.0),
+        Constellation::Galileo => (0.98, 1.0 / 61.0),
+        Constellation::BeiDou => {
+            if sv.prn < 6 || sv.prn > 58 || [6, 7, 8, 9, 10, 13, 16, 38, 39, 40].contains(&sv.prn) {
+                (0.99, 1.0 / 126.0)
+            } else {
+                (0.98, 1.0 / 54.0)
+            }
+        },
+        Constellation::QZSS | Constellation::IRNSS => (0.99, 1.0 / 126.0),
+        _ => (0.98, 1.0 / 49.0),
+    }
+}
+
+/*
+ * Broadcast clock offset [s] at desired epoch,
+ * toc being the clock reference epoch (NAV frame epoch)
+ */
+pub(crate) fn broadcast_clock(toc: Epoch, ephemeris: &Ephemeris, t: Epoch) -> f64 {
+    let (a0, a1, a2) = ephemeris.sv_clock();
+    let dt = (t - toc).to_seconds();
+    a0 + a1 * dt + a2 * dt.powi(2)
+}
+
+fn cross(u: (f64, f64, f64), v: (f64, f64, f64)) -> (f64, f64, f64) {
+    (
+        u.1 * v.2 - u.2 * v.1,
+        u.2 * v.0 - u.0 * v.2,
+        u.0 * v.1 - u.1 * v.0,
+    )
+}
+
+fn dot(u: (f64, f64, f64), v: (f64, f64, f64)) -> f64 {
+    u.0 * v.0 + u.1 * v.1 + u.2 * v.2
+}
+
+fn normalize(u: (f64, f64, f64)) -> (f64, f64, f64) {
+    let norm = dot(u, u).sqrt();
+    (u.0 / norm, u.1 / norm, u.2 / norm)
+}
+
+/*
+ * Projects broadcast orbit error into the radial, along-track
+ * and cross-track frame of the precise orbit
+ */
+fn rac_errors(
+    ephemeris: &Ephemeris,
+    precise: (f64, f64, f64),
+    t: Epoch,
+) -> Option<(f64, f64, f64)> {
+    let dt = Duration::from_seconds(0.5);
+    let broadcast = ephemeris.sv_position(t)?;
+    let before = ephemeris.sv_position(t - dt)?;
+    let after = ephemeris.sv_position(t + dt)?;
+    let velocity = (after.0 - before.0, after.1 - before.1, after.2 - before.2);
+
+    let e_r = normalize(precise);
+    let e_c = normalize(cross(precise, velocity));
+    let e_a = cross(e_c, e_r);
+
+    let err = (
+        broadcast.0 - precise.0,
+        broadcast.1 - precise.1,
+        broadcast.2 - precise.2,
+    );
+    Some((dot(err, e_r), dot(err, e_a), dot(err, e_c)))
+}
+
+fn precise_clock(clocks: &clocks::Record, t: &Epoch, sv: &Sv) -> Option<f64> {
+    let dtypes = clocks.get(t)?;
+    let systems = dtypes.get(&clocks::DataType::AS)?;
+    let data = systems.get(&clocks::System::Sv(*sv))?;
+    Some(data.bias)
+}
+
+/*
+ * Evaluates broadcast errors against precise products.
+ * Reference epochs are the precise orbit epochs when provided,
+ * the precise clock epochs otherwise.
+ */
+pub(crate) fn sv_errors(
+    nav: &Rinex,
+    orbits: Option<&PreciseOrbits>,
+    clocks: Option<&clocks::Record>,
+) -> BTreeMap<Sv, BTreeMap<Epoch, SvError>> {
+    let mut ret: BTreeMap<Sv, BTreeMap<Epoch, SvError>> = BTreeMap::new();
+    let mut epochs: Vec<(Epoch, Vec<Sv>)> = Vec::new();
+    if let Some(orbits) = orbits {
+        for (e, svs) in orbits {
+            epochs.push((*e, svs.keys().copied().collect()));
+        }
+    } else if let Some(clocks) = clocks {
+        for (e, dtypes) in clocks {
+            if let Some(systems) = dtypes.get(&clocks::DataType::AS) {
+                epochs.push((*e, systems.keys().filter_map(|s| s.as_sv()).collect()));
+            }
+        }
+    }
+    for (t, svs) in epochs {
+        for sv in svs {
+            let (toc, ephemeris) = match nav.sv_ephemeris(sv, t) {
+                Some(eph) => eph,
+                None => continue,
+            };
+            if ephemeris.kepler().is_none() {
+                // Sv state vectors (Glonass, SBAS) are not propagated
+                continue;
+            }
+            // precise products are expressed in GPST
+            let (t_gnss, toc) = (
+                gnss_instant(t, Constellation::GPS),
+                gnss_instant(toc, sv.constellation),
+            );
+            let orbit = match orbits {
+                Some(orbits) => {
+                    let precise = orbits.get(&t).and_then(|svs| svs.get(&sv));
+                    precise.and_then(|precise| rac_errors(ephemeris, *precise, t_gnss))
+                },
+                None => None,
+            };
+            let clock = match clocks {
+                Some(clocks) => precise_clock(clocks, &t, &sv).map(|precise| {
+                    (broadcast_clock(toc, ephemeris, t_gnss) - precise) * SPEED_OF_LIGHT
+                }),
+                None => None,
+            };
+            if orbit.is_some() || clock.is_some() {
+                ret.entry(sv)
+                    .or_default()
+                    .insert(t, SvError { orbit, clock });
+            }
+        }
+    }
+    ret
+}
+
+fn rms(sum_sq: f64, n: usize) -> Option<f64> {
+    if n > 0 {
+        Some((sum_sq / n as f64).sqrt())
+    } else {
+        None
+    }
+}
+
+/*
+ * Evaluates SISRE statistics per constellation
+ */
+pub(crate) fn sisre_stats(
+    errors: &BTreeMap<Sv, BTreeMap<Epoch, SvError>>,
+) -> HashMap<Constellation, SisreStats> {
+    // constellation wide clock offset, for every epoch
+    let mut offsets: HashMap<(Constellation, Epoch), (f64, usize)> = HashMap::new();
+    for (sv, epochs) in errors {
+        for (t, err) in epochs {
+            if let Some(clock) = err.clock {
+                let (sum, n) = offsets.entry((sv.constellation, *t)).or_insert((0.0, 0));
+                *sum += clock;
+                *n += 1;
+            }
+        }
+    }
+    #[derive(Default)]
+    struct Sums {
+        r: f64,
+        a: f64,
+        c: f64,
+        orbit: f64,
+        n_orbit: usize,
+        clock: f64,
+        n_clock: usize,
+        sisre: f64,
+        n_sisre: usize,
+        samples: usize,
+    }
+    let mut sums: HashMap<Constellation, Sums> = HashMap::new();
+    for (sv, epochs) in errors {
+        let (w_r, w_ac2) = sisre_weights(sv);
+        let sums = sums.entry(sv.constellation).or_default();
+        for (t, err) in epochs {
+            sums.samples += 1;
+            let clock = err.clock.map(|clock| {
+                let (sum, n) = offsets[&(sv.constellation, *t)];
+                clock - sum / n as f64
+            });
+            if let Some(clock) = clock {
+                sums.clock += clock.powi(2);
+                sums.n_clock += 1;
+            }
+            if let Some((r, a, c)) = err.orbit {
+                sums.r += r.powi(2);
+                sums.a += a.powi(2);
+                sums.c += c.powi(2);
+                sums.orbit += (w_r * r).powi(2) + w_ac2 * (a.powi(2) + c.powi(2));
+                sums.n_orbit += 1;
+                if let Some(clock) = clock {
+                    sums.sisre += (w_r * r - clock).powi(2) + w_ac2 * (a.powi(2) + c.powi(2));
+                    sums.n_sisre += 1;
+                }
+            }
+        }
+    }
+    sums.into_iter()
+        .map(|(constellation, s)| {
+            (
+                constellation,
+                SisreStats {
+                    radial_rms: rms(s.r, s.n_orbit),
+                    along_rms: rms(s.a, s.n_orbit),
+                    cross_rms: rms(s.c, s.n_orbit),
+                    clock_rms: rms(s.clock, s.n_clock),
+                    orbit_sisre: rms(s.orbit, s.n_orbit),
+                    sisre: rms(s.sisre, s.n_sisre),
+                    samples: s.samples,
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sv;
+    use std::str::FromStr;
+    #[test]
+    fn sisre_weighting() {
+        let (w_r, w_ac2) = sisre_weights(&sv!("G01"));
+        assert_eq!(w_r, 0.98);
+        assert_eq!(w_ac2, 1.0 / 49.0);
+        let (w_r, _) = sisre_weights(&sv!("C01"));
+        assert_eq!(w_r, 0.99);
+        let (w_r, _) = sisre_weights(&sv!("C20"));
+        assert_eq!(w_r, 0.98);
+    }
+    #[test]
+    fn sisre_from_errors() {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2021, 1, 1);
+        let mut errors: BTreeMap<Sv, BTreeMap<Epoch, SvError>> = BTreeMap::new();
+        for (sv, clock) in [(sv!("G01"), 1.0), (sv!("G02"), -1.0)] {
+            let mut epochs = BTreeMap::new();
+            epochs.insert(
+                t0,
+                SvError {
+                    orbit: Some((1.0, 7.0, 0.0)),
+                    clock: Some(clock + 10.0), // common offset is removed
+                },
+            );
+            errors.insert(sv, epochs);
+        }
+        let stats = sisre_stats(&errors);
+        let gps = stats.get(&Constellation::GPS).unwrap();
+        assert_eq!(gps.samples, 2);
+        assert_eq!(gps.radial_rms, Some(1.0));
+        assert_eq!(gps.along_rms, Some(7.0));
+        assert_eq!(gps.cross_rms, Some(0.0));
+        assert_eq!(gps.clock_rms, Some(1.0));
+        let orbit = gps.orbit_sisre.unwrap();
+        assert!((orbit - (0.98_f64.powi(2) + 1.0).sqrt()).abs() < 1E-9);
+        let sisre = gps.sisre.unwrap();
+        let expected =
+            (((0.98_f64 - 1.0).powi(2) + 1.0 + (0.98_f64 + 1.0).powi(2) + 1.0) / 2.0).sqrt();
+        assert!((sisre - expected).abs() < 1E-9);
+    }
+}
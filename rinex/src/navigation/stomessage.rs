@@ -38,6 +38,8 @@ impl StoMessage {
         let (epoch, rem) = line.split_at(23);
         let (system, _) = rem.split_at(5);
         let (epoch, _) = epoch::parse(epoch.trim())?;
+        // UTC identifier follows the (optional) SBAS identifier
+        let utc = line.get(62..).unwrap_or("").trim();
 
         let line = match lines.next() {
             Some(l) => l,
@@ -46,7 +48,7 @@ impl StoMessage {
         let (time, rem) = line.split_at(23);
         let (a0, rem) = rem.split_at(19);
         let (a1, rem) = rem.split_at(19);
-        let (a2, rem) = rem.split_at(19);
+        let (a2, _) = rem.split_at(19);
 
         let t_tm = f64::from_str(time.trim())?;
         Ok((
@@ -59,7 +61,7 @@ impl StoMessage {
                     f64::from_str(a1.trim()).unwrap_or(0.0_f64),
                     f64::from_str(a2.trim()).unwrap_or(0.0_f64),
                 ),
-                utc: rem.trim().to_string(),
+                utc: utc.to_string(),
             },
         ))
     }
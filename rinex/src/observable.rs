@@ -43,6 +43,12 @@ pub enum Observable {
     RainIncrement,
     /// Hail Indicator
     HailIndicator,
+    /// Dew point, derived from temperature and humidity, in Celcius degrees
+    DewPoint,
+    /// Weighted mean temperature of the atmosphere, in Kelvin
+    WeightedMeanTemperature,
+    /// Precipitable water vapor, derived from the wet zenith delay, in mm
+    PrecipitableWaterVapor,
 }
 
 impl Default for Observable {
@@ -106,6 +112,9 @@ impl std::fmt::Display for Observable {
             Self::WindSpeed => write!(f, "WS"),
             Self::RainIncrement => write!(f, "RI"),
             Self::HailIndicator => write!(f, "HI"),
+            Self::DewPoint => write!(f, "DP"),
+            Self::WeightedMeanTemperature => write!(f, "TM"),
+            Self::PrecipitableWaterVapor => write!(f, "PW"),
             Self::SSI(c) | Self::Phase(c) | Self::Doppler(c) | Self::PseudoRange(c) => {
                 write!(f, "{}", c)
             },
@@ -129,6 +138,9 @@ impl std::str::FromStr for Observable {
             "WS" => Ok(Self::WindSpeed),
             "RI" => Ok(Self::RainIncrement),
             "HI" => Ok(Self::HailIndicator),
+            "DP" => Ok(Self::DewPoint),
+            "TM" => Ok(Self::WeightedMeanTemperature),
+            "PW" => Ok(Self::PrecipitableWaterVapor),
             _ => {
                 let len = content.len();
                 if len > 1 && len < 4 {
@@ -196,6 +208,10 @@ mod test {
         assert_eq!(obs.clone().unwrap().to_string(), "WS");
         assert_eq!(Observable::from_str("ws"), obs.clone());
 
+        let obs = Observable::from_str("PW");
+        assert_eq!(obs, Ok(Observable::PrecipitableWaterVapor));
+        assert_eq!(obs.clone().unwrap().to_string(), "PW");
+
         let obs = Observable::from_str("Wa");
         assert!(obs.is_err());
 
@@ -0,0 +1,381 @@
+//! Observation RINEX revision conversions
+use super::{HeaderFields, Record};
+use crate::{prelude::*, version::Version};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Tracking modes assumptions, used when converting Observation RINEX
+/// from or to RINEX2. RINEX2 observables only describe the frequency band
+/// (and P code tracking), while modern observables also describe
+/// the tracking mode ("C1C", "C2W", "L2L"..). Receivers track different signals:
+/// you should define one set of assumptions per receiver.
+/// Default assumptions match common geodetic receivers:
+/// C/A code on L1, semi-codeless P(Y) code tracking on L2 (C2 is L2C),
+/// GLONASS P code on L2, pilot and data (X) for modern signals.
+/// ```
+/// use rinex::prelude::*;
+/// use rinex::observation::TrackingModes;
+/// use std::str::FromStr;
+/// // this receiver tracks L2C phase
+/// let modes = TrackingModes::default()
+///     .with_mode(Constellation::GPS, "L2", "L2L");
+/// let l2 = Observable::from_str("L2").unwrap();
+/// assert_eq!(
+///     modes.v3_observable(Constellation::GPS, &l2),
+///     Some(Observable::from_str("L2L").unwrap())
+/// );
+/// assert_eq!(
+///     modes.v3_observable(Constellation::Glonass, &l2),
+///     Some(Observable::from_str("L2P").unwrap())
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackingModes {
+    /// RINEX3 observables, per RINEX2 observables, per constellation
+    modes: HashMap<Constellation, HashMap<String, String>>,
+}
+
+/*
+ * Tracking modes of a frequency band:
+ * (constellation, band, C/A code attribute, P code attribute, phase/doppler/SSI attribute)
+ */
+type BandModes = (Constellation, char, Option<char>, Option<char>, char);
+
+const DEFAULT_MODES: [BandModes; 20] = [
+    (Constellation::GPS, '1', Some('C'), Some('W'), 'C'),
+    (Constellation::GPS, '2', Some('X'), Some('W'), 'W'),
+    (Constellation::GPS, '5', Some('X'), None, 'X'),
+    (Constellation::Glonass, '1', Some('C'), Some('P'), 'C'),
+    (Constellation::Glonass, '2', Some('C'), Some('P'), 'P'),
+    (Constellation::Galileo, '1', Some('X'), None, 'X'),
+    (Constellation::Galileo, '5', Some('X'), None, 'X'),
+    (Constellation::Galileo, '6', Some('X'), None, 'X'),
+    (Constellation::Galileo, '7', Some('X'), None, 'X'),
+    (Constellation::Galileo, '8', Some('X'), None, 'X'),
+    (Constellation::Geo, '1', Some('C'), None, 'C'),
+    (Constellation::Geo, '5', Some('I'), None, 'I'),
+    (Constellation::BeiDou, '2', Some('I'), None, 'I'),
+    (Constellation::BeiDou, '6', Some('I'), None, 'I'),
+    (Constellation::BeiDou, '7', Some('I'), None, 'I'),
+    (Constellation::QZSS, '1', Some('C'), None, 'C'),
+    (Constellation::QZSS, '2', Some('X'), None, 'X'),
+    (Constellation::QZSS, '5', Some('X'), None, 'X'),
+    (Constellation::IRNSS, '5', Some('A'), None, 'A'),
+    (Constellation::IRNSS, '9', Some('A'), None, 'A'),
+];
+
+/*
+ * Constellations that RINEX2 observables are attributed to,
+ * in Mixed RINEX2 headers
+ */
+const V2_CONSTELLATIONS: [Constellation; 6] = [
+    Constellation::GPS,
+    Constellation::Glonass,
+    Constellation::Galileo,
+    Constellation::BeiDou,
+    Constellation::QZSS,
+    Constellation::Geo,
+];
+
+/*
+ * SBAS observables are described for all augmentation systems
+ */
+fn system(constellation: Constellation) -> Constellation {
+    match constellation {
+        Constellation::SBAS(_) => Constellation::Geo,
+        c => c,
+    }
+}
+
+impl Default for TrackingModes {
+    fn default() -> Self {
+        let mut modes: HashMap<Constellation, HashMap<String, String>> = HashMap::new();
+        for (constellation, band, code, pcode, carrier) in DEFAULT_MODES {
+            let modes = modes.entry(constellation).or_default();
+            if let Some(code) = code {
+                modes.insert(format!("C{}", band), format!("C{}{}", band, code));
+            }
+            if let Some(pcode) = pcode {
+                modes.insert(format!("P{}", band), format!("C{}{}", band, pcode));
+            }
+            for t in ['L', 'D', 'S'] {
+                modes.insert(
+                    format!("{}{}", t, band),
+                    format!("{}{}{}", t, band, carrier),
+                );
+            }
+        }
+        Self { modes }
+    }
+}
+
+impl TrackingModes {
+    /// Defines the signal tracked by the receiver, for given RINEX2 observable
+    /// ("C1", "P2", "L2"..) and constellation, as a RINEX3 observable ("C1C", "C2W", "L2L"..)
+    pub fn with_mode(&self, constellation: Constellation, v2: &str, v3: &str) -> Self {
+        let mut s = self.clone();
+        s.modes
+            .entry(system(constellation))
+            .or_default()
+            .insert(v2.trim().to_uppercase(), v3.trim().to_uppercase());
+        s
+    }
+    /// Returns the RINEX3 observable we assume for given RINEX2 observable
+    /// and constellation, or None if it is not described.
+    pub fn v3_observable(
+        &self,
+        constellation: Constellation,
+        observable: &Observable,
+    ) -> Option<Observable> {
+        let modes = self.modes.get(&system(constellation))?;
+        let v3 = modes.get(&observable.to_string())?;
+        Observable::from_str(v3).ok()
+    }
+    /// Returns the RINEX2 observable matching given RINEX3 observable
+    /// and constellation, or None if it cannot be described in RINEX2.
+    pub fn v2_observable(
+        &self,
+        constellation: Constellation,
+        observable: &Observable,
+    ) -> Option<Observable> {
+        let code = observable.to_string();
+        if let Some(modes) = self.modes.get(&system(constellation)) {
+            // assumed tracking modes
+            for (v2, v3) in modes {
+                if *v3 == code {
+                    if let Some(pcode) = modes.get(&format!("P{}", &code[1..2])) {
+                        // C/A and P code assumed on the same signal: prefer P code
+                        if *pcode == code && v2.starts_with('C') {
+                            continue;
+                        }
+                    }
+                    return Observable::from_str(v2).ok();
+                }
+            }
+        }
+        let mut chars = code.chars();
+        let (t, band, attribute) = (chars.next()?, chars.next()?, chars.next()?);
+        let t = match (t, system(constellation), attribute) {
+            ('C', Constellation::GPS, 'P' | 'W' | 'Y' | 'M') => 'P',
+            ('C', Constellation::Glonass, 'P') => 'P',
+            (t, _, _) => t,
+        };
+        Observable::from_str(&format!("{}{}", t, band)).ok()
+    }
+}
+
+/*
+ * Converts an Observation RINEX (header and record) to given revision
+ */
+pub(crate) fn convert(
+    header: &mut Header,
+    record: &mut Record,
+    version: Version,
+    modes: &TrackingModes,
+) {
+    let obs = match header.obs.as_mut() {
+        Some(obs) => obs,
+        None => return,
+    };
+    let (from_v2, to_v2) = (header.version.major < 3, version.major < 3);
+    if from_v2 && !to_v2 {
+        upgrade(obs, record, modes);
+    } else if !from_v2 && to_v2 {
+        downgrade(obs, record, modes, header.constellation);
+    }
+    if let Some(crinex) = obs.crinex.as_mut() {
+        // CRINEX1 compresses RINEX2, CRINEX3 compresses modern revisions
+        crinex.version.major = if to_v2 { 1 } else { 3 };
+    }
+    header.version = version;
+}
+
+/*
+ * Renames the observations of every Sv, dropping those that have no equivalent
+ */
+fn rename_observations(
+    record: &mut Record,
+    conversions: &HashMap<Constellation, HashMap<Observable, Observable>>,
+) {
+    for (_, (_, vehicles)) in record.iter_mut() {
+        for (sv, observations) in vehicles.iter_mut() {
+            let conversions = conversions.get(&system(sv.constellation));
+            *observations = observations
+                .drain()
+                .filter_map(|(observable, data)| {
+                    let observable = conversions?.get(&observable)?;
+                    Some((observable.clone(), data))
+                })
+                .collect();
+        }
+        vehicles.retain(|_, observations| !observations.is_empty());
+    }
+}
+
+/*
+ * Renames the data scalings
+ */
+fn rename_scalings(
+    obs: &mut HeaderFields,
+    conversions: &HashMap<Constellation, HashMap<Observable, Observable>>,
+) {
+    for (constellation, scalings) in obs.scalings.iter_mut() {
+        let conversions = conversions.get(&system(*constellation));
+        *scalings = scalings
+            .drain()
+            .filter_map(|(observable, scaling)| {
+                let observable = conversions?.get(&observable)?;
+                Some((observable.clone(), scaling))
+            })
+            .collect();
+    }
+}
+
+/*
+ * RINEX2 to modern revisions: observables are described per constellation
+ */
+fn upgrade(obs: &mut HeaderFields, record: &mut Record, modes: &TrackingModes) {
+    let mut conversions: HashMap<Constellation, HashMap<Observable, Observable>> = HashMap::new();
+    for (constellation, observables) in obs.codes.iter() {
+        let constellation = system(*constellation);
+        let conversions = conversions.entry(constellation).or_default();
+        for observable in observables {
+            if let Some(v3) = modes.v3_observable(constellation, observable) {
+                conversions.insert(observable.clone(), v3);
+            }
+        }
+    }
+    // only describe observables actually encountered
+    let mut encountered: HashMap<Constellation, Vec<Observable>> = HashMap::new();
+    for (_, (_, vehicles)) in record.iter() {
+        for (sv, observations) in vehicles {
+            let encountered = encountered.entry(system(sv.constellation)).or_default();
+            for observable in observations.keys() {
+                if !encountered.contains(observable) {
+                    encountered.push(observable.clone());
+                }
+            }
+        }
+    }
+    let mut codes: HashMap<Constellation, Vec<Observable>> = HashMap::new();
+    for (constellation, observables) in obs.codes.iter() {
+        let constellation = system(*constellation);
+        let encountered = match encountered.get(&constellation) {
+            Some(encountered) => encountered,
+            None => continue,
+        };
+        let v3: Vec<Observable> = observables
+            .iter()
+            .filter(|observable| encountered.contains(observable))
+            .filter_map(|observable| conversions[&constellation].get(observable))
+            .cloned()
+            .collect();
+        if !v3.is_empty() {
+            codes.insert(constellation, v3);
+        }
+    }
+    rename_observations(record, &conversions);
+    rename_scalings(obs, &conversions);
+    obs.codes = codes;
+}
+
+/*
+ * Modern revisions to RINEX2: a single list of observables, that only describe
+ * the frequency band. When several signals share the same band,
+ * we retain the one that matches the tracking mode assumptions.
+ */
+fn downgrade(
+    obs: &mut HeaderFields,
+    record: &mut Record,
+    modes: &TrackingModes,
+    constellation: Option<Constellation>,
+) {
+    let mut constellations: Vec<Constellation> = obs.codes.keys().copied().collect();
+    constellations.sort();
+    let mut v2_codes: Vec<Observable> = Vec::new();
+    let mut conversions: HashMap<Constellation, HashMap<Observable, Observable>> = HashMap::new();
+    for constellation in constellations {
+        let system = system(constellation);
+        let mut selected: HashMap<Observable, Observable> = HashMap::new();
+        for observable in &obs.codes[&constellation] {
+            let v2 = match modes.v2_observable(system, observable) {
+                Some(v2) => v2,
+                None => continue,
+            };
+            let assumed = modes.v3_observable(system, &v2);
+            match selected.get(&v2) {
+                Some(_) if assumed.as_ref() != Some(observable) => continue,
+                _ => {
+                    selected.insert(v2.clone(), observable.clone());
+                },
+            }
+            if !v2_codes.contains(&v2) {
+                v2_codes.push(v2);
+            }
+        }
+        let conversions = conversions.entry(system).or_default();
+        for (v2, v3) in selected {
+            conversions.insert(v3, v2);
+        }
+    }
+    rename_observations(record, &conversions);
+    // RINEX2 cannot describe data scalings, phase shifts, nor Glonass biases
+    obs.scalings.clear();
+    obs.phase_shifts.clear();
+    obs.glo_biases.clear();
+    // RINEX2 declares a single list of observables, for all constellations
+    let mut codes: HashMap<Constellation, Vec<Observable>> = HashMap::new();
+    match constellation {
+        Some(Constellation::Mixed) | None => {
+            for constellation in V2_CONSTELLATIONS {
+                codes.insert(constellation, v2_codes.clone());
+            }
+        },
+        Some(constellation) => {
+            codes.insert(constellation, v2_codes);
+        },
+    }
+    obs.codes = codes;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn test_tracking_modes() {
+        let modes = TrackingModes::default();
+        for (constellation, v2, v3) in [
+            (Constellation::GPS, "C1", "C1C"),
+            (Constellation::GPS, "P1", "C1W"),
+            (Constellation::GPS, "P2", "C2W"),
+            (Constellation::GPS, "C2", "C2X"),
+            (Constellation::GPS, "L2", "L2W"),
+            (Constellation::Glonass, "P2", "C2P"),
+            (Constellation::Galileo, "C5", "C5X"),
+            (Constellation::SBAS(Augmentation::WAAS), "L5", "L5I"),
+        ] {
+            let (v2, v3) = (
+                Observable::from_str(v2).unwrap(),
+                Observable::from_str(v3).unwrap(),
+            );
+            assert_eq!(modes.v3_observable(constellation, &v2), Some(v3.clone()));
+            assert_eq!(modes.v2_observable(constellation, &v3), Some(v2));
+        }
+        // signals we do not assume
+        for (constellation, v3, v2) in [
+            (Constellation::GPS, "C1P", "P1"),
+            (Constellation::GPS, "C2L", "C2"),
+            (Constellation::GPS, "L2L", "L2"),
+            (Constellation::Glonass, "C1C", "C1"),
+            (Constellation::Galileo, "L1C", "L1"),
+        ] {
+            let (v3, v2) = (
+                Observable::from_str(v3).unwrap(),
+                Observable::from_str(v2).unwrap(),
+            );
+            assert_eq!(modes.v2_observable(constellation, &v3), Some(v2));
+        }
+        let l2 = Observable::from_str("L2").unwrap();
+        assert_eq!(modes.v3_observable(Constellation::Galileo, &l2), None);
+    }
+}
@@ -1,10 +1,12 @@
 //! Observation RINEX module
-use super::{epoch, prelude::*, version::Version};
+use super::{epoch, merge, prelude::*, version::Version};
 use std::collections::HashMap;
 
+pub(crate) mod conversion;
 pub mod record;
 mod snr;
 
+pub use conversion::TrackingModes;
 pub use record::{LliFlags, ObservationData, Record};
 pub use snr::Snr;
 
@@ -109,6 +111,13 @@ pub struct HeaderFields {
     pub dcb_compensations: Vec<Constellation>,
     /// Optionnal data scalings
     pub scalings: HashMap<Constellation, HashMap<Observable, f64>>,
+    /// Phase shift corrections [cycles] applied to phase observations,
+    /// per constellation and observable. Corrections that only apply
+    /// to some vehicles are stored per Sv, others with no Sv.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub phase_shifts: HashMap<Constellation, HashMap<Observable, HashMap<Option<Sv>, f64>>>,
+    /// Glonass code-phase alignment biases [m], per observable
+    pub glo_biases: HashMap<Observable, f64>,
 }
 
 impl HeaderFields {
@@ -135,10 +144,84 @@ impl HeaderFields {
         1.0
     }
 
+    /// Add a phase shift correction [cycles], that applies to
+    /// given vehicle, or all vehicles of this constellation when `sv` is None
+    pub fn with_phase_shift(
+        &self,
+        c: Constellation,
+        observable: Observable,
+        sv: Option<Sv>,
+        shift: f64,
+    ) -> Self {
+        let mut s = self.clone();
+        s.phase_shifts
+            .entry(c)
+            .or_default()
+            .entry(observable)
+            .or_default()
+            .insert(sv, shift);
+        s
+    }
+    /// Returns phase shift correction [cycles] applied to given vehicle
+    /// and given phase observable, if declared. Vehicle specific
+    /// corrections prevail over constellation wide corrections
+    pub fn phase_shift(&self, c: &Constellation, sv: &Sv, observable: &Observable) -> Option<f64> {
+        let shifts = self.phase_shifts.get(c)?.get(observable)?;
+        shifts.get(&Some(*sv)).or(shifts.get(&None)).copied()
+    }
+
+    /// Merges the phase shift corrections declared by `rhs`. Observables
+    /// described by both headers must declare the same corrections:
+    /// their phases would not be aligned to one another otherwise.
+    pub(crate) fn merge_phase_shifts_mut(&mut self, rhs: &Self) -> Result<(), merge::Error> {
+        for (constellation, codes) in rhs.codes.iter() {
+            for code in codes {
+                let rhs_shifts = rhs
+                    .phase_shifts
+                    .get(constellation)
+                    .and_then(|s| s.get(code));
+                let lhs_shifts = self
+                    .phase_shifts
+                    .get(constellation)
+                    .and_then(|s| s.get(code));
+                let described = self
+                    .codes
+                    .get(constellation)
+                    .map(|codes| codes.contains(code))
+                    .unwrap_or(false);
+                if described {
+                    if lhs_shifts != rhs_shifts {
+                        return Err(merge::Error::PhaseShiftMismatch);
+                    }
+                } else if let Some(shifts) = rhs_shifts {
+                    self.phase_shifts
+                        .entry(*constellation)
+                        .or_default()
+                        .insert(code.clone(), shifts.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a Glonass code-phase alignment bias [m], for given observable
+    pub fn with_glo_bias(&self, observable: Observable, bias: f64) -> Self {
+        let mut s = self.clone();
+        s.glo_biases.insert(observable, bias);
+        s
+    }
+    /// Returns Glonass code-phase alignment bias [m]
+    /// of given observable, if known
+    pub fn glo_bias(&self, observable: &Observable) -> Option<f64> {
+        self.glo_biases.get(observable).copied()
+    }
+
     /// Emphasize that DCB is compensated for
     pub fn with_dcb_compensation(&self, c: Constellation) -> Self {
         let mut s = self.clone();
-        s.dcb_compensations.push(c);
+        if !s.dcb_compensations.contains(&c) {
+            s.dcb_compensations.push(c);
+        }
         s
     }
     /// Returns true if DCB compensation was applied for given constellation.
@@ -399,6 +482,59 @@ pub trait IonoDelay {
     ) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>>;
 }
 
+/// Rate of TEC Index (ROTI) estimation trait.
+#[cfg(feature = "obs")]
+#[cfg_attr(docrs, doc(cfg(feature = "obs")))]
+pub trait Roti {
+    /// Returns the Rate Of TEC Index [TECu.min⁻¹]: standard deviation of the
+    /// Rate Of TEC (ROT) over consecutive windows of given duration, typically 5 minutes,
+    /// for each individual Sv. ROT is the time derivative of the slant TEC
+    /// obtained from the geometry free phase combination, sorted by reference
+    /// phase observable, as [IonoDelay::iono_delay]. It is not formed across
+    /// missing samples, nor across loss of lock.
+    /// Each index is labelled by the Epoch starting its window,
+    /// and requires at least 3 ROT values. Glonass vehicles are not considered,
+    /// their FDMA frequencies are not known at this level.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use rinex::observation::*; // .roti()
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let roti = rinex.roti(Duration::from_seconds(300.0));
+    /// for (observable, vehicles) in roti {
+    ///     for (sv, indices) in vehicles {
+    ///         for (epoch, roti) in indices {
+    ///             // roti: TECu/min
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    fn roti(&self, window: Duration) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>>;
+}
+
+/// Scintillation indices estimation trait.
+/// These indices are meaningful on high rate (typically 50 Hz) observations only.
+#[cfg(feature = "obs")]
+#[cfg_attr(docrs, doc(cfg(feature = "obs")))]
+pub trait Scintillation {
+    /// Returns the amplitude scintillation index S4: normalized standard deviation
+    /// of the signal intensity, derived from the SSI observations [dB-Hz],
+    /// over consecutive windows of given duration (typically 60 s),
+    /// for each SSI observable and individual Sv.
+    /// Each index is labelled by the Epoch starting its window,
+    /// and requires at least 3 observations.
+    fn s4(&self, window: Duration) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>>;
+    /// Returns the phase scintillation index σφ [rad]: standard deviation of the
+    /// detrended carrier phase, over consecutive windows of given duration
+    /// (typically 60 s), for each phase observable and individual Sv.
+    /// The phase is detrended by a cubic polynomial fit over each window,
+    /// in place of the usual high pass filter. Windows containing a loss
+    /// of lock or a receiver clock jump (step on the phase rate) are discarded,
+    /// others require at least 5 observations.
+    fn sigma_phi(&self, window: Duration)
+        -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>>;
+}
+
 #[cfg(test)]
 mod crinex {
     use super::*;
@@ -5,10 +5,10 @@ use thiserror::Error;
 
 use crate::{
     constellation, epoch, gnss_time::GnssTime, merge, merge::Merge, prelude::*, split,
-    split::Split, sv, types::Type, version::Version, Carrier, Observable,
+    split::Split, sv, types::Type, version::Version, Carrier, Observable, SPEED_OF_LIGHT,
 };
 
-use super::Snr;
+use super::{HeaderFields, Snr};
 use hifitime::Duration;
 
 #[derive(Error, Debug)]
@@ -203,12 +203,12 @@ pub(crate) fn parse_epoch(
     let observables = &obs.codes;
 
     // grab possible clock offset
-    let offs: Option<&str> = match header.version.major < 2 {
+    let offs: Option<&str> = match header.version.major < 3 {
         true => {
             // RINEX 2
-            // clock offsets are last 12 characters
-            if line.len() > 60 - 12 {
-                Some(line.split_at(60 - 12).1.trim())
+            // clock offsets are last 12 characters of the first line
+            if line.len() > 68 {
+                Some(line.split_at(68).1.trim())
             } else {
                 None
             }
@@ -250,7 +250,8 @@ pub(crate) fn parse_epoch(
             // This remains empty on RINEX3, because we have such information
             // on following lines, which is much more convenient
             let mut systems = String::with_capacity(24 * 3); //SVNN
-            systems.push_str(rem.trim());
+                                                             // first line is limited to 12 vehicles, followed by the possible clock offset
+            systems.push_str(rem.get(..36).unwrap_or(rem).trim());
             while systems.len() / 3 < n_sat.into() {
                 if let Some(l) = lines.next() {
                     systems.push_str(l.trim());
@@ -260,11 +261,24 @@ pub(crate) fn parse_epoch(
             }
             parse_v2(&header, &systems, observables, lines)
         },
-        _ => parse_v3(observables, lines),
+        _ => parse_v3(header, observables, lines),
     };
     Ok((epoch, clock_offset, data))
 }
 
+/*
+ * Data scaling declared in the header for given observable, if any.
+ * Scaled observations are divided on parsing, multiplied back on formatting.
+ */
+fn observable_scaling(
+    header: &Header,
+    constellation: &Constellation,
+    observable: &Observable,
+) -> Option<f64> {
+    let obs = header.obs.as_ref()?;
+    obs.scalings.get(constellation)?.get(observable).copied()
+}
+
 /*
  * Parses a V2 epoch from given lines iteratoor
  * Vehicle description is contained in the epoch descriptor
@@ -378,6 +392,11 @@ fn parse_v2(
                         }
                     }
                     //println!("{} {:?} {:?} ==> {}", obs, lli, snr, obscodes[obs_ptr-1]); //DEBUG
+                    let observable = &observables[obs_ptr - 1];
+                    let obs = match observable_scaling(header, &sv.constellation, observable) {
+                        Some(scaling) => obs / scaling,
+                        None => obs,
+                    };
                     inner.insert(
                         observables[obs_ptr - 1].clone(),
                         ObservationData { obs, lli, snr },
@@ -439,6 +458,7 @@ fn parse_v2(
  * Format is much simpler, one vehicle is described in a single line
  */
 fn parse_v3(
+    header: &Header,
     observables: &HashMap<Constellation, Vec<Observable>>,
     lines: std::str::Lines<'_>,
 ) -> BTreeMap<Sv, HashMap<Observable, ObservationData>> {
@@ -488,6 +508,11 @@ fn parse_v3(
                         //println!("LLI {:?}", lli); //DEBUG
                         //println!("SSI {:?}", snr);
                         // build content
+                        let obs = match observable_scaling(header, &sv.constellation, &obscodes[i])
+                        {
+                            Some(scaling) => obs / scaling,
+                            None => obs,
+                        };
                         inner.insert(obscodes[i].clone(), ObservationData { obs, lli, snr });
                     }
                 }
@@ -532,7 +557,7 @@ fn fmt_epoch_v3(
     ));
 
     if let Some(data) = clock_offset {
-        lines.push_str(&format!("{:13.4}", data));
+        lines.push_str(&format!("      {:15.12}", data));
     }
 
     lines.push_str("\n");
@@ -541,7 +566,11 @@ fn fmt_epoch_v3(
         if let Some(observables) = observables.get(&sv.constellation) {
             for observable in observables {
                 if let Some(observation) = data.get(observable) {
-                    lines.push_str(&format!("{:14.3}", observation.obs));
+                    let obs = match observable_scaling(header, &sv.constellation, observable) {
+                        Some(scaling) => observation.obs * scaling,
+                        None => observation.obs,
+                    };
+                    lines.push_str(&format!("{:14.3}", obs));
                     if let Some(flag) = observation.lli {
                         lines.push_str(&format!("{}", flag.bits()));
                     } else {
@@ -578,21 +607,18 @@ fn fmt_epoch_v2(
         data.len()
     ));
 
-    let mut index = 0_u8;
     for (sv_index, (sv, _)) in data.iter().enumerate() {
-        if index == 12 {
-            index = 0;
-            if sv_index == 12 {
-                // first line
-                if let Some(data) = clock_offset {
-                    // push clock offsets
-                    lines.push_str(&format!(" {:9.1}", data));
-                }
-            }
+        if sv_index > 0 && sv_index % 12 == 0 {
             lines.push_str(&format!("\n                                "));
         }
         lines.push_str(&sv.to_string());
-        index += 1;
+        if sv_index == 11 || (sv_index < 11 && sv_index == data.len() - 1) {
+            // first line is terminated by possible clock offset
+            if let Some(data) = clock_offset {
+                let padding = 68 - lines.len();
+                lines.push_str(&format!("{:padding$}{:12.9}", "", data));
+            }
+        }
     }
     let obs_per_line = 5;
     // for each vehicle per epoch
@@ -605,7 +631,11 @@ fn fmt_epoch_v2(
                     lines.push_str("\n");
                 }
                 if let Some(observation) = observations.get(observable) {
-                    let formatted_obs = format!("{:14.3}", observation.obs);
+                    let obs = match observable_scaling(header, &sv.constellation, observable) {
+                        Some(scaling) => observation.obs * scaling,
+                        None => observation.obs,
+                    };
+                    let formatted_obs = format!("{:14.3}", obs);
                     let formatted_flags: String = match observation.lli {
                         Some(lli) => match observation.snr {
                             Some(snr) => format!("{}{:x}", lli.bits(), snr),
@@ -738,6 +768,258 @@ impl GnssTime for Record {
     }
 }
 
+/*
+ * Applies (sign = 1.0) or reverts (sign = -1.0) the receiver clock offsets
+ * that come with the epochs: time tags, pseudo ranges and phases are corrected
+ * by the clock offset, Doppler shifts by the clock drift,
+ * which we derive from the offsets themselves.
+ */
+pub(crate) fn receiver_clock_offset_compensation(record: &mut Record, sign: f64) {
+    let offsets: Vec<(Epoch, f64)> = record
+        .iter()
+        .filter_map(|((t, _), (clk, _))| clk.map(|clk| (*t, clk)))
+        .collect();
+    let mut drifts: HashMap<Epoch, f64> = HashMap::with_capacity(offsets.len());
+    for (index, (t, _)) in offsets.iter().enumerate() {
+        let (t0, clk0) = offsets[index.saturating_sub(1)];
+        let (t1, clk1) = offsets[(index + 1).min(offsets.len() - 1)];
+        let dt = (t1 - t0).to_seconds();
+        if dt > 0.0 {
+            drifts.insert(*t, (clk1 - clk0) / dt);
+        }
+    }
+    let mut compensated = Record::new();
+    for ((t, flag), (clk, mut vehicles)) in std::mem::take(record) {
+        let offset = match clk {
+            Some(clk) => sign * clk,
+            None => {
+                compensated.insert((t, flag), (clk, vehicles));
+                continue;
+            },
+        };
+        let drift = sign * drifts.get(&t).copied().unwrap_or(0.0);
+        for (sv, observations) in vehicles.iter_mut() {
+            for (observable, data) in observations.iter_mut() {
+                if observable.is_pseudorange_observable() {
+                    data.obs -= offset * SPEED_OF_LIGHT;
+                } else if let Ok(carrier) = observable.carrier(sv.constellation) {
+                    if observable.is_phase_observable() {
+                        data.obs -= offset * carrier.frequency();
+                    } else if observable.is_doppler_observable() {
+                        data.obs += drift * carrier.frequency();
+                    }
+                }
+            }
+        }
+        let t = t - Duration::from_seconds(offset);
+        compensated.insert((t, flag), (clk, vehicles));
+    }
+    *record = compensated;
+}
+
+/*
+ * Quarter cycle phase shift correction [cycles] that aligns given phase
+ * observable to the reference signal of its frequency band,
+ * following RINEX3.04 (table A23). None when we have no such correction.
+ */
+pub(crate) fn reference_phase_shift(
+    constellation: Constellation,
+    observable: &Observable,
+) -> Option<f64> {
+    if !observable.is_phase_observable() {
+        return None;
+    }
+    let code = observable.to_string();
+    let (band, attribute) = (code.get(1..2)?, code.get(2..3)?);
+    match constellation {
+        Constellation::GPS => match (band, attribute) {
+            ("1", "C") | ("2", "P" | "W" | "Y" | "M" | "D") | ("5", "I" | "X") => Some(0.0),
+            ("1", "S" | "L" | "X" | "P" | "W" | "Y" | "M") => Some(0.25),
+            ("2", "C" | "S" | "L" | "X") | ("5", "Q") => Some(-0.25),
+            _ => None,
+        },
+        Constellation::Glonass => match (band, attribute) {
+            ("1" | "2", "C") | ("3", "I" | "X") => Some(0.0),
+            ("1" | "2", "P") => Some(0.25),
+            ("3", "Q") => Some(-0.25),
+            _ => None,
+        },
+        Constellation::Galileo => match (band, attribute) {
+            ("1" | "6", "B") | ("5" | "7" | "8", "I" | "X") => Some(0.0),
+            ("1" | "6", "C") => Some(0.5),
+            ("5" | "7" | "8", "Q") => Some(-0.25),
+            _ => None,
+        },
+        Constellation::BeiDou => match (band, attribute) {
+            ("2" | "6" | "7", "I" | "X") => Some(0.0),
+            ("2" | "6" | "7", "Q") => Some(-0.25),
+            _ => None,
+        },
+        Constellation::QZSS => match (band, attribute) {
+            ("1", "C") | ("2", "S" | "L" | "X") | ("5", "I" | "X") => Some(0.0),
+            ("1", "S" | "L" | "X") => Some(0.25),
+            ("5", "Q") => Some(-0.25),
+            _ => None,
+        },
+        Constellation::Geo | Constellation::SBAS(_) => match (band, attribute) {
+            ("1", "C") | ("5", "I" | "X") => Some(0.0),
+            ("5", "Q") => Some(-0.25),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/*
+ * Applies given phase shift corrections [cycles], per constellation and observable,
+ * to vehicles for which the header does not declare a correction already
+ */
+pub(crate) fn phase_shift_compensation(
+    record: &mut Record,
+    shifts: &HashMap<Constellation, HashMap<Observable, f64>>,
+    declared: &HeaderFields,
+) {
+    for (_, (_, vehicles)) in record.iter_mut() {
+        for (sv, observations) in vehicles.iter_mut() {
+            let shifts = match shifts.get(&sv.constellation) {
+                Some(shifts) => shifts,
+                None => continue,
+            };
+            for (observable, data) in observations.iter_mut() {
+                if declared
+                    .phase_shift(&sv.constellation, sv, observable)
+                    .is_some()
+                {
+                    continue;
+                }
+                if let Some(shift) = shifts.get(observable) {
+                    data.obs += shift;
+                }
+            }
+        }
+    }
+}
+
+/*
+ * Converts a pseudo range [m] or phase [cycles] observation to meters
+ */
+fn observation_meters(sv: Sv, observable: &Observable, value: f64) -> Option<f64> {
+    if observable.is_pseudorange_observable() {
+        Some(value)
+    } else if observable.is_phase_observable() {
+        let carrier = observable.carrier(sv.constellation).ok()?;
+        Some(value * carrier.wavelength())
+    } else {
+        None
+    }
+}
+
+/*
+ * Detects the receiver clock jumps, in milliseconds:
+ * a jump is a step of an integer number of ms (in light distance)
+ * common to most vehicles, on a given pseudo range or phase observable.
+ * When `repair` is set, jumps are also removed from the following observations.
+ */
+pub(crate) fn receiver_clock_jumps(
+    record: &mut Record,
+    repair: bool,
+) -> BTreeMap<Epoch, HashMap<Observable, i64>> {
+    const JUMP_TOLERANCE: f64 = 0.01;
+    const MIN_VEHICLES: usize = 2;
+    let ms = SPEED_OF_LIGHT * 1.0E-3;
+    let mut jumps: BTreeMap<Epoch, HashMap<Observable, i64>> = BTreeMap::new();
+    // accumulated jumps [ms]
+    let mut accumulated: HashMap<Observable, i64> = HashMap::new();
+    // last two (repaired) observations [m], per Sv and observable
+    let mut history: HashMap<(Sv, Observable), [(Epoch, f64); 2]> = HashMap::new();
+    let mut previous: HashMap<(Sv, Observable), (Epoch, f64)> = HashMap::new();
+    let mut prev_epoch: Option<Epoch> = None;
+    let mut prev2_epoch: Option<Epoch> = None;
+    for ((t, flag), (_, vehicles)) in record.iter_mut() {
+        if !flag.is_ok() {
+            continue;
+        }
+        // jump candidates [ms], per observable
+        let mut candidates: HashMap<Observable, Vec<f64>> = HashMap::new();
+        let mut repaired: HashMap<(Sv, Observable), f64> = HashMap::new();
+        for (sv, observations) in vehicles.iter() {
+            for (observable, data) in observations {
+                let value = match observation_meters(*sv, observable, data.obs) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let jump = *accumulated.get(observable).unwrap_or(&0) as f64;
+                let value = value - jump * ms;
+                repaired.insert((*sv, observable.clone()), value);
+                let key = (*sv, observable.clone());
+                if let Some([(t0, v0), (t1, v1)]) = history.get(&key) {
+                    // vehicle observed on the previous two epochs
+                    if Some(*t1) != prev_epoch || Some(*t0) != prev2_epoch {
+                        continue;
+                    }
+                    let dt0 = (*t1 - *t0).to_seconds();
+                    let dt1 = (*t - *t1).to_seconds();
+                    let step = (value - v1) - (v1 - v0) * dt1 / dt0;
+                    candidates
+                        .entry(observable.clone())
+                        .or_default()
+                        .push(step / ms);
+                }
+            }
+        }
+        for (observable, steps) in candidates {
+            let mut votes: HashMap<i64, usize> = HashMap::new();
+            for step in steps.iter() {
+                let n = step.round();
+                if n != 0.0 && (step - n).abs() < JUMP_TOLERANCE {
+                    *votes.entry(n as i64).or_insert(0) += 1;
+                }
+            }
+            let elected = votes
+                .into_iter()
+                .filter(|(_, count)| *count >= MIN_VEHICLES && 2 * count > steps.len())
+                .max_by_key(|(_, count)| *count);
+            if let Some((n, _)) = elected {
+                *accumulated.entry(observable.clone()).or_insert(0) += n;
+                jumps.entry(*t).or_default().insert(observable.clone(), n);
+                for ((_, obs), value) in repaired.iter_mut() {
+                    if *obs == observable {
+                        *value -= n as f64 * ms;
+                    }
+                }
+            }
+        }
+        for (key, value) in repaired {
+            let last = previous.insert(key.clone(), (*t, value));
+            if let Some(last) = last {
+                history.insert(key, [last, (*t, value)]);
+            } else {
+                history.remove(&key);
+            }
+        }
+        prev2_epoch = prev_epoch;
+        prev_epoch = Some(*t);
+        if repair {
+            for (sv, observations) in vehicles.iter_mut() {
+                for (observable, data) in observations.iter_mut() {
+                    let jump = match accumulated.get(observable) {
+                        Some(jump) if *jump != 0 => *jump as f64 * 1.0E-3,
+                        _ => continue,
+                    };
+                    if observable.is_pseudorange_observable() {
+                        data.obs -= jump * SPEED_OF_LIGHT;
+                    } else if observable.is_phase_observable() {
+                        if let Ok(carrier) = observable.carrier(sv.constellation) {
+                            data.obs -= jump * carrier.frequency();
+                        }
+                    }
+                }
+            }
+        }
+    }
+    jumps
+}
+
 #[cfg(feature = "processing")]
 use crate::preprocessing::*;
 
@@ -770,11 +1052,15 @@ impl Smooth for Record {
                     // locate associated L code
                     let ph_tolocate = "L".to_owned() + &pr_code;
 
-                    let mut ph_data: Option<f64> = None;
+                    let mut ph_data: Option<(f64, bool)> = None;
                     for (rhs_observable, rhs_observation) in &rhs_observables {
                         let rhs_code = rhs_observable.to_string();
                         if rhs_code == ph_tolocate {
-                            ph_data = Some(rhs_observation.obs);
+                            let lock_loss = rhs_observation
+                                .lli
+                                .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                                .unwrap_or(false);
+                            ph_data = Some((rhs_observation.obs, lock_loss));
                             break;
                         }
                     }
@@ -783,10 +1069,19 @@ impl Smooth for Record {
                         continue; // can't progress at this point
                     }
 
-                    let phase_data = ph_data.unwrap();
+                    let (phase_data, lock_loss) = ph_data.unwrap();
 
                     if let Some(data) = buffer.get_mut(&sv) {
-                        if let Some((n, prev_result, prev_phase)) = data.get_mut(&pr_observable) {
+                        if lock_loss {
+                            // cycle slip: phase is no longer continuous,
+                            // filter is reset and pr observation is untouched
+                            data.insert(
+                                pr_observable.clone(),
+                                (2.0_f64, pr_observation.obs, phase_data),
+                            );
+                        } else if let Some((n, prev_result, prev_phase)) =
+                            data.get_mut(pr_observable)
+                        {
                             let delta_phase = phase_data - *prev_phase;
                             // implement corrector equation
                             pr_observation.obs = 1.0 / *n * pr_observation.obs
@@ -2142,6 +2437,277 @@ impl Mp for Record {
     }
 }
 
+/*
+ * Sorts a time series into consecutive windows of given duration,
+ * labelled by their starting Epoch
+ */
+#[cfg(feature = "obs")]
+fn windows(series: &BTreeMap<Epoch, f64>, window: Duration) -> BTreeMap<Epoch, Vec<(Epoch, f64)>> {
+    let mut ret: BTreeMap<Epoch, Vec<(Epoch, f64)>> = BTreeMap::new();
+    for (t, value) in series {
+        ret.entry(t.floor(window)).or_default().push((*t, *value));
+    }
+    ret
+}
+
+/*
+ * Standard deviation of a population
+ */
+#[cfg(feature = "obs")]
+fn population_std_dev(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt()
+}
+
+/*
+ * Residuals of a cubic polynomial fit of (t [s], y) samples
+ */
+#[cfg(feature = "obs")]
+fn cubic_detrend(samples: &[(f64, f64)]) -> Option<Vec<f64>> {
+    const N: usize = 4;
+    let (t0, y0) = samples.first()?;
+    let span = samples.last()?.0 - t0;
+    if span <= 0.0 {
+        return None;
+    }
+    // normal equations, on normalized time
+    let mut a = [[0.0_f64; N + 1]; N];
+    for (t, y) in samples {
+        let x = (t - t0) / span;
+        let powers = [1.0, x, x * x, x * x * x];
+        for i in 0..N {
+            for j in 0..N {
+                a[i][j] += powers[i] * powers[j];
+            }
+            a[i][N] += powers[i] * (y - y0);
+        }
+    }
+    // Gaussian elimination, with partial pivoting
+    for col in 0..N {
+        let pivot = (col..N).max_by(|i, j| a[*i][col].abs().total_cmp(&a[*j][col].abs()))?;
+        a.swap(col, pivot);
+        if a[col][col].abs() < 1.0E-12 {
+            return None;
+        }
+        let pivot_row = a[col];
+        for row in a.iter_mut().skip(col + 1) {
+            let k = row[col] / pivot_row[col];
+            for (value, pivot) in row.iter_mut().zip(pivot_row.iter()).skip(col) {
+                *value -= k * pivot;
+            }
+        }
+    }
+    let mut coefs = [0.0_f64; N];
+    for row in (0..N).rev() {
+        let sum: f64 = (row + 1..N).map(|c| a[row][c] * coefs[c]).sum();
+        coefs[row] = (a[row][N] - sum) / a[row][row];
+    }
+    Some(
+        samples
+            .iter()
+            .map(|(t, y)| {
+                let x = (t - t0) / span;
+                let fit = coefs[0] + x * (coefs[1] + x * (coefs[2] + x * coefs[3]));
+                y - y0 - fit
+            })
+            .collect(),
+    )
+}
+
+#[cfg(feature = "obs")]
+use crate::observation::{Roti, Scintillation};
+
+#[cfg(feature = "obs")]
+impl Roti for Record {
+    fn roti(&self, window: Duration) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> {
+        let mut ret: HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> = HashMap::new();
+        // slant TEC [TECu] and loss of lock, per reference phase and Sv
+        let mut stec: HashMap<(Observable, Sv), BTreeMap<Epoch, (f64, bool)>> = HashMap::new();
+        for ((epoch, flag), (_, vehicles)) in self {
+            if !flag.is_ok() {
+                continue;
+            }
+            for (sv, observations) in vehicles {
+                if sv.constellation == Constellation::Glonass {
+                    continue; // FDMA: unknown frequencies
+                }
+                let mut phases: Vec<(&Observable, &ObservationData, Carrier)> = observations
+                    .iter()
+                    .filter(|(observable, data)| {
+                        observable.is_phase_observable() && data.obs != 0.0
+                    })
+                    .filter_map(|(observable, data)| {
+                        let carrier = observable.carrier(sv.constellation).ok()?;
+                        Some((observable, data, carrier))
+                    })
+                    .collect();
+                phases.sort_by_key(|(observable, _, _)| observable.to_string());
+                let (ref_observable, ref_data, ref_carrier) = match phases.first() {
+                    Some(reference) => *reference,
+                    None => continue,
+                };
+                let (_, data, carrier) = match phases.iter().find(|(_, _, c)| *c != ref_carrier) {
+                    Some(other) => *other,
+                    None => continue,
+                };
+                // [m] per TECu, on the geometry free combination
+                let alpha = 40.3E16
+                    * (1.0 / carrier.frequency().powi(2) - 1.0 / ref_carrier.frequency().powi(2));
+                let gf = ref_data.obs * ref_carrier.wavelength() - data.obs * carrier.wavelength();
+                let lock_loss = [ref_data, data].iter().any(|data| {
+                    data.lli
+                        .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                        .unwrap_or(false)
+                });
+                stec.entry((ref_observable.clone(), *sv))
+                    .or_default()
+                    .insert(*epoch, (gf / alpha, lock_loss));
+            }
+        }
+        for ((observable, sv), series) in stec {
+            // sampling interval: missing samples may hide a new ambiguity
+            let sampling = series
+                .keys()
+                .zip(series.keys().skip(1))
+                .map(|(prev, t)| *t - *prev)
+                .min();
+            let max_dt = match sampling {
+                Some(dt) => dt * 1.5,
+                None => continue,
+            };
+            // rate of TEC [TECu/min]
+            let mut rot: BTreeMap<Epoch, f64> = BTreeMap::new();
+            let mut prev: Option<(Epoch, f64)> = None;
+            for (t, (tec, lock_loss)) in series {
+                if let Some((prev_t, prev_tec)) = prev {
+                    let dt = t - prev_t;
+                    if !lock_loss && dt <= max_dt && dt < window {
+                        rot.insert(t, (tec - prev_tec) / dt.to_seconds() * 60.0);
+                    }
+                }
+                prev = Some((t, tec));
+            }
+            for (t, samples) in windows(&rot, window) {
+                if samples.len() < 3 {
+                    continue;
+                }
+                let values: Vec<f64> = samples.iter().map(|(_, rot)| *rot).collect();
+                ret.entry(observable.clone())
+                    .or_default()
+                    .entry(sv)
+                    .or_default()
+                    .insert(t, population_std_dev(&values));
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(feature = "obs")]
+impl Scintillation for Record {
+    fn s4(&self, window: Duration) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> {
+        let mut ret: HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> = HashMap::new();
+        // signal intensity, per SSI observable and Sv
+        let mut intensity: HashMap<(Observable, Sv), BTreeMap<Epoch, f64>> = HashMap::new();
+        for ((epoch, flag), (_, vehicles)) in self {
+            if !flag.is_ok() {
+                continue;
+            }
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    if observable.is_ssi_observable() && data.obs > 0.0 {
+                        intensity
+                            .entry((observable.clone(), *sv))
+                            .or_default()
+                            .insert(*epoch, 10.0_f64.powf(data.obs / 10.0));
+                    }
+                }
+            }
+        }
+        for ((observable, sv), series) in intensity {
+            for (t, samples) in windows(&series, window) {
+                if samples.len() < 3 {
+                    continue;
+                }
+                let n = samples.len() as f64;
+                let mean = samples.iter().map(|(_, i)| i).sum::<f64>() / n;
+                let mean2 = samples.iter().map(|(_, i)| i * i).sum::<f64>() / n;
+                let s4 = ((mean2 - mean * mean).max(0.0) / (mean * mean)).sqrt();
+                ret.entry(observable.clone())
+                    .or_default()
+                    .entry(sv)
+                    .or_default()
+                    .insert(t, s4);
+            }
+        }
+        ret
+    }
+    fn sigma_phi(
+        &self,
+        window: Duration,
+    ) -> HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> {
+        let mut ret: HashMap<Observable, HashMap<Sv, BTreeMap<Epoch, f64>>> = HashMap::new();
+        // phase [cycles] and loss of lock, per phase observable and Sv
+        let mut phases: HashMap<(Observable, Sv), BTreeMap<Epoch, (f64, bool)>> = HashMap::new();
+        for ((epoch, flag), (_, vehicles)) in self {
+            if !flag.is_ok() {
+                continue;
+            }
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    if observable.is_phase_observable() && data.obs != 0.0 {
+                        let lock_loss = data
+                            .lli
+                            .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                            .unwrap_or(false);
+                        phases
+                            .entry((observable.clone(), *sv))
+                            .or_default()
+                            .insert(*epoch, (data.obs, lock_loss));
+                    }
+                }
+            }
+        }
+        for ((observable, sv), series) in phases {
+            let mut windowed: BTreeMap<Epoch, (Vec<(f64, f64)>, bool)> = BTreeMap::new();
+            let mut prev: Option<(f64, Option<f64>)> = None;
+            let mut recovering = false;
+            for (t, (phase, lock_loss)) in series {
+                // receiver clock jumps are not flagged: look for steps on the phase rate,
+                // the following sample being the step back to nominal rate
+                let rate = prev.map(|(prev_phase, _)| phase - prev_phase);
+                let step = match (prev, rate) {
+                    (Some((_, Some(prev_rate))), Some(rate)) => (rate - prev_rate).abs() > 1.0E4,
+                    _ => false,
+                };
+                let clock_jump = step && !recovering;
+                recovering = clock_jump;
+                prev = Some((phase, rate));
+                let (samples, slipped) = windowed.entry(t.floor(window)).or_default();
+                samples.push((
+                    (t - Epoch::default()).to_seconds(),
+                    phase * 2.0 * std::f64::consts::PI,
+                ));
+                *slipped |= lock_loss || clock_jump;
+            }
+            for (t, (samples, slipped)) in windowed {
+                if slipped || samples.len() < 5 {
+                    continue;
+                }
+                if let Some(residuals) = cubic_detrend(&samples) {
+                    ret.entry(observable.clone())
+                        .or_default()
+                        .entry(sv)
+                        .or_default()
+                        .insert(t, population_std_dev(&residuals));
+                }
+            }
+        }
+        ret
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -2197,4 +2763,23 @@ mod test {
             false
         );
     }
+    #[test]
+    fn detrending() {
+        // cubic trends are removed
+        let samples: Vec<(f64, f64)> = (0..60)
+            .map(|i| {
+                let t = 1.0E6 + i as f64;
+                (t, 1.0E8 + 3.0 * i as f64 - 0.01 * (i as f64).powi(3))
+            })
+            .collect();
+        let residuals = cubic_detrend(&samples).unwrap();
+        assert!(residuals.iter().all(|r| r.abs() < 1.0E-5));
+        // noise remains
+        let samples: Vec<(f64, f64)> = (0..60)
+            .map(|i| (i as f64, if i % 2 == 0 { 0.1 } else { -0.1 }))
+            .collect();
+        let residuals = cubic_detrend(&samples).unwrap();
+        assert!((population_std_dev(&residuals) - 0.1).abs() < 1.0E-2);
+        assert!(cubic_detrend(&[(0.0, 1.0)]).is_none());
+    }
 }
@@ -0,0 +1,123 @@
+//! Differential Code Biases estimation, from a single station
+use super::geometry::sv_elevation_azimuth;
+use super::spp::index_ephemerides;
+use super::tec::station_id;
+use super::SPEED_OF_LIGHT;
+use crate::ionex::BiasSource;
+use crate::prelude::*;
+use std::collections::HashMap;
+
+/// Elevation mask [°]: low elevation code observations are dominated by multipath
+const ELEVATION_MASK: f64 = 10.0;
+
+/// Minimal number of observations, per Sv
+const MIN_SAMPLES: usize = 10;
+
+/*
+ * Elevation weighted mean, standard deviation and number of samples
+ */
+#[derive(Default)]
+struct Accumulator {
+    weights: f64,
+    sum: f64,
+    sum2: f64,
+    n: usize,
+}
+
+impl Accumulator {
+    fn push(&mut self, value: f64, weight: f64) {
+        self.weights += weight;
+        self.sum += weight * value;
+        self.sum2 += weight * value * value;
+        self.n += 1;
+    }
+    fn mean(&self) -> f64 {
+        self.sum / self.weights
+    }
+    fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        (self.sum2 / self.weights - mean * mean).max(0.0).sqrt()
+    }
+}
+
+/*
+ * Estimates the `lhs` - `rhs` code biases [ns] of every Sv of given constellation,
+ * and of the receiver, from the code differences of the `obs` station
+ * corrected for the slant ionospheric delays of the `ionex` maps.
+ * Satellite biases are constrained to a zero mean, as in IGS products.
+ */
+pub(crate) fn estimate(
+    obs: &Rinex,
+    nav: &Rinex,
+    ionex: &Rinex,
+    constellation: Constellation,
+    lhs: &Observable,
+    rhs: &Observable,
+) -> HashMap<BiasSource, (f64, f64)> {
+    let mut ret: HashMap<BiasSource, (f64, f64)> = HashMap::new();
+    let rcvr = match obs.header.ground_position {
+        Some(position) => position.to_ecef_wgs84(),
+        None => return ret,
+    };
+    let record = match obs.record.as_obs() {
+        Some(record) => record,
+        None => return ret,
+    };
+    let (lhs_carrier, rhs_carrier) = match (lhs.carrier(constellation), rhs.carrier(constellation))
+    {
+        (Ok(lhs), Ok(rhs)) => (lhs, rhs),
+        _ => return ret,
+    };
+    let (lat, lon, _) = map_3d::ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, map_3d::Ellipsoid::WGS84);
+    let (lat, lon) = (lat.to_degrees(), lon.to_degrees());
+    let ephemerides = index_ephemerides(nav);
+    let mut accumulators: HashMap<Sv, Accumulator> = HashMap::new();
+    for ((t, flag), (_, svs)) in record {
+        if !flag.is_ok() {
+            continue;
+        }
+        for (sv, observations) in svs {
+            if sv.constellation != constellation {
+                continue;
+            }
+            let (c1, c2) = match (observations.get(lhs), observations.get(rhs)) {
+                (Some(c1), Some(c2)) if c1.obs != 0.0 && c2.obs != 0.0 => (c1.obs, c2.obs),
+                _ => continue,
+            };
+            let (elev, azim) = match sv_elevation_azimuth(&ephemerides, rcvr, *t, *sv) {
+                Some((elev, azim)) => (elev, azim),
+                None => continue,
+            };
+            if elev < ELEVATION_MASK {
+                continue;
+            }
+            let (i1, i2) = match (
+                ionex.ionex_slant_delay(*t, lat, lon, elev, azim, lhs_carrier),
+                ionex.ionex_slant_delay(*t, lat, lon, elev, azim, rhs_carrier),
+            ) {
+                (Some(i1), Some(i2)) => (i1, i2),
+                _ => continue,
+            };
+            let bias = ((c1 - c2) - (i1 - i2)) / SPEED_OF_LIGHT * 1.0E9;
+            accumulators
+                .entry(*sv)
+                .or_default()
+                .push(bias, elev.to_radians().sin().powi(2));
+        }
+    }
+    accumulators.retain(|_, acc| acc.n >= MIN_SAMPLES);
+    if accumulators.is_empty() {
+        return ret;
+    }
+    // zero mean satellite biases: the receiver bias is the mean of all Sv biases
+    let n = accumulators.len() as f64;
+    let rcvr_bias = accumulators.values().map(|acc| acc.mean()).sum::<f64>() / n;
+    let mut rcvr_var = 0.0;
+    for (sv, acc) in accumulators {
+        let rms = acc.std_dev() / (acc.n as f64).sqrt();
+        rcvr_var += rms.powi(2);
+        ret.insert(BiasSource::SpaceVehicle(sv), (acc.mean() - rcvr_bias, rms));
+    }
+    ret.insert(station_id(obs), (rcvr_bias, rcvr_var.sqrt() / n));
+    ret
+}
@@ -0,0 +1,23 @@
+//! Sv geometry, seen from the receiver
+use super::spp::{select_ephemeris, sv_state, Ephemerides};
+use super::{elevation_azimuth, SPEED_OF_LIGHT};
+use crate::epoch::gnss_instant;
+use crate::prelude::*;
+
+/*
+ * Sv elevation and azimuth angles [°] at Epoch `t`,
+ * seen from given receiver position (ECEF), from the broadcast ephemerides
+ */
+pub(crate) fn sv_elevation_azimuth(
+    ephemerides: &Ephemerides,
+    rcvr: (f64, f64, f64),
+    t: Epoch,
+    sv: Sv,
+) -> Option<(f64, f64)> {
+    let (toc, ephemeris) = select_ephemeris(ephemerides.get(&sv)?, t)?;
+    // nominal propagation time is sufficient, at this precision
+    let toc = gnss_instant(toc, sv.constellation);
+    let t_rx = gnss_instant(t, Constellation::GPS);
+    let state = sv_state(toc, ephemeris, t_rx, 0.075 * SPEED_OF_LIGHT)?;
+    Some(elevation_azimuth(rcvr, state.position))
+}
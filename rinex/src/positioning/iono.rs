@@ -0,0 +1,56 @@
+//! Ionospheric delays from IONEX maps
+use super::geometry::sv_elevation_azimuth;
+use super::spp::index_ephemerides;
+use crate::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+/*
+ * Slant ionospheric delays [m], per Epoch, Sv and pseudo range
+ * or phase observable, seen from the ground position of the `obs` RINEX
+ */
+pub(crate) fn slant_delays(
+    obs: &Rinex,
+    nav: &Rinex,
+    ionex: &Rinex,
+) -> BTreeMap<Epoch, BTreeMap<Sv, HashMap<Observable, f64>>> {
+    let mut ret: BTreeMap<Epoch, BTreeMap<Sv, HashMap<Observable, f64>>> = BTreeMap::new();
+    let rcvr = match obs.header.ground_position {
+        Some(position) => position.to_ecef_wgs84(),
+        None => return ret,
+    };
+    let record = match obs.record.as_obs() {
+        Some(record) => record,
+        None => return ret,
+    };
+    let (lat, lon, _) = map_3d::ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, map_3d::Ellipsoid::WGS84);
+    let (lat, lon) = (lat.to_degrees(), lon.to_degrees());
+    let ephemerides = index_ephemerides(nav);
+    for ((t, _), (_, svs)) in record {
+        for (sv, observations) in svs {
+            let (elev, azim) = match sv_elevation_azimuth(&ephemerides, rcvr, *t, *sv) {
+                Some((elev, azim)) => (elev, azim),
+                None => continue,
+            };
+            if elev <= 0.0 {
+                continue;
+            }
+            for observable in observations.keys() {
+                if !observable.is_pseudorange_observable() && !observable.is_phase_observable() {
+                    continue;
+                }
+                let carrier = match observable.carrier(sv.constellation) {
+                    Ok(carrier) => carrier,
+                    Err(_) => continue,
+                };
+                if let Some(delay) = ionex.ionex_slant_delay(*t, lat, lon, elev, azim, carrier) {
+                    ret.entry(*t)
+                        .or_default()
+                        .entry(*sv)
+                        .or_default()
+                        .insert(observable.clone(), delay);
+                }
+            }
+        }
+    }
+    ret
+}
@@ -0,0 +1,260 @@
+//! LAMBDA integer ambiguity resolution (Teunissen, 1995),
+//! with the search strategy of the modified LAMBDA method (Chang et al., 2005)
+use nalgebra::{DMatrix, DVector};
+
+/// Maximal number of search iterations
+const MAX_LOOPS: usize = 100_000;
+
+/*
+ * Q = Lt D L factorization: L unit lower triangular, D diagonal
+ */
+fn ld(q: &DMatrix<f64>) -> Option<(DMatrix<f64>, DVector<f64>)> {
+    let n = q.nrows();
+    let mut a = q.clone();
+    let mut l = DMatrix::<f64>::zeros(n, n);
+    let mut d = DVector::<f64>::zeros(n);
+    for i in (0..n).rev() {
+        d[i] = a[(i, i)];
+        if d[i] <= 0.0 {
+            return None;
+        }
+        let sqrt = d[i].sqrt();
+        for j in 0..=i {
+            l[(i, j)] = a[(i, j)] / sqrt;
+        }
+        for j in 0..i {
+            for k in 0..=j {
+                a[(j, k)] -= l[(i, k)] * l[(i, j)];
+            }
+        }
+        let lii = l[(i, i)];
+        for j in 0..=i {
+            l[(i, j)] /= lii;
+        }
+    }
+    Some((l, d))
+}
+
+/*
+ * Integer Gauss transformation
+ */
+fn gauss(l: &mut DMatrix<f64>, z: &mut DMatrix<f64>, i: usize, j: usize) {
+    let n = l.nrows();
+    let mu = l[(i, j)].round();
+    if mu != 0.0 {
+        for k in i..n {
+            l[(k, j)] -= mu * l[(k, i)];
+        }
+        for k in 0..n {
+            z[(k, j)] -= mu * z[(k, i)];
+        }
+    }
+}
+
+/*
+ * Permutation of columns j and j+1
+ */
+fn permute(l: &mut DMatrix<f64>, d: &mut DVector<f64>, j: usize, del: f64, z: &mut DMatrix<f64>) {
+    let n = l.nrows();
+    let eta = d[j] / del;
+    let lambda = d[j + 1] * l[(j + 1, j)] / del;
+    d[j] = eta * d[j + 1];
+    d[j + 1] = del;
+    for k in 0..j {
+        let (a0, a1) = (l[(j, k)], l[(j + 1, k)]);
+        l[(j, k)] = -l[(j + 1, j)] * a0 + a1;
+        l[(j + 1, k)] = eta * a0 + lambda * a1;
+    }
+    l[(j + 1, j)] = lambda;
+    for k in j + 2..n {
+        l.swap((k, j), (k, j + 1));
+    }
+    z.swap_columns(j, j + 1);
+}
+
+/*
+ * Decorrelation: returns the Z transformation
+ */
+fn reduction(l: &mut DMatrix<f64>, d: &mut DVector<f64>) -> DMatrix<f64> {
+    let n = l.nrows();
+    let mut z = DMatrix::<f64>::identity(n, n);
+    if n < 2 {
+        return z;
+    }
+    let mut j = n as isize - 2;
+    let mut k = n as isize - 2;
+    while j >= 0 {
+        let ju = j as usize;
+        if j <= k {
+            for i in ju + 1..n {
+                gauss(l, &mut z, i, ju);
+            }
+        }
+        let del = d[ju] + l[(ju + 1, ju)].powi(2) * d[ju + 1];
+        if del + 1.0E-6 < d[ju + 1] {
+            permute(l, d, ju, del, &mut z);
+            k = j;
+            j = n as isize - 2;
+        } else {
+            j -= 1;
+        }
+    }
+    z
+}
+
+fn sgn(x: f64) -> f64 {
+    if x <= 0.0 {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/*
+ * Depth first search of the `m` best integer candidates,
+ * returns the candidates and their squared distances, sorted.
+ */
+fn search(
+    l: &DMatrix<f64>,
+    d: &DVector<f64>,
+    zs: &DVector<f64>,
+    m: usize,
+) -> Option<(Vec<DVector<f64>>, Vec<f64>)> {
+    let n = l.nrows();
+    let mut s = DMatrix::<f64>::zeros(n, n);
+    let mut dist = vec![0.0_f64; n];
+    let mut zb = vec![0.0_f64; n];
+    let mut z = vec![0.0_f64; n];
+    let mut step = vec![0.0_f64; n];
+    let mut candidates: Vec<(Vec<f64>, f64)> = Vec::with_capacity(m);
+    let mut max_dist = f64::MAX;
+    let mut imax = 0;
+
+    let mut k = n - 1;
+    zb[k] = zs[k];
+    z[k] = zb[k].round();
+    let mut y = zb[k] - z[k];
+    step[k] = sgn(y);
+    let mut converged = false;
+    for _ in 0..MAX_LOOPS {
+        let new_dist = dist[k] + y * y / d[k];
+        if new_dist < max_dist {
+            if k != 0 {
+                k -= 1;
+                dist[k] = new_dist;
+                for i in 0..=k {
+                    s[(k, i)] = s[(k + 1, i)] + (z[k + 1] - zb[k + 1]) * l[(k + 1, i)];
+                }
+                zb[k] = zs[k] + s[(k, k)];
+                z[k] = zb[k].round();
+                y = zb[k] - z[k];
+                step[k] = sgn(y);
+            } else {
+                if candidates.len() < m {
+                    if candidates.is_empty() || new_dist > candidates[imax].1 {
+                        imax = candidates.len();
+                    }
+                    candidates.push((z.clone(), new_dist));
+                } else {
+                    if new_dist < candidates[imax].1 {
+                        candidates[imax] = (z.clone(), new_dist);
+                        imax = (0..m)
+                            .max_by(|a, b| candidates[*a].1.total_cmp(&candidates[*b].1))
+                            .unwrap();
+                    }
+                    max_dist = candidates[imax].1;
+                }
+                z[0] += step[0];
+                y = zb[0] - z[0];
+                step[0] = -step[0] - sgn(step[0]);
+            }
+        } else if k == n - 1 {
+            converged = true;
+            break;
+        } else {
+            k += 1;
+            z[k] += step[k];
+            y = zb[k] - z[k];
+            step[k] = -step[k] - sgn(step[k]);
+        }
+    }
+    if !converged {
+        return None;
+    }
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+    Some(
+        candidates
+            .into_iter()
+            .map(|(z, s)| (DVector::from_vec(z), s))
+            .unzip(),
+    )
+}
+
+/// Resolves the `m` best integer vectors for float ambiguities `a`,
+/// of covariance `q`, in the sense of the squared Mahalanobis distance.
+/// Returns the integer candidates and their squared distances, best first.
+pub(crate) fn lambda(
+    a: &DVector<f64>,
+    q: &DMatrix<f64>,
+    m: usize,
+) -> Option<(Vec<DVector<f64>>, Vec<f64>)> {
+    let n = a.len();
+    if n == 0 || q.nrows() != n || q.ncols() != n || m == 0 {
+        return None;
+    }
+    let (mut l, mut d) = ld(q)?;
+    let z = reduction(&mut l, &mut d);
+    let zs = z.transpose() * a;
+    let (candidates, distances) = search(&l, &d, &zs, m)?;
+    // back to the original space: F = Zt⁻¹ E
+    let zt_inv = z.transpose().try_inverse()?;
+    let candidates = candidates
+        .iter()
+        .map(|e| (&zt_inv * e).map(|x| x.round()))
+        .collect();
+    Some((candidates, distances))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn uncorrelated_rounding() {
+        let a = DVector::from_vec(vec![1.1, -2.05, 3.4]);
+        let q = DMatrix::from_diagonal(&DVector::from_vec(vec![0.01, 0.01, 0.01]));
+        let (candidates, s) = lambda(&a, &q, 2).unwrap();
+        assert_eq!(candidates[0], DVector::from_vec(vec![1.0, -2.0, 3.0]));
+        // second best: the most uncertain rounding is flipped
+        assert_eq!(candidates[1], DVector::from_vec(vec![1.0, -2.0, 4.0]));
+        assert!(s[0] < s[1]);
+        assert!((s[0] - (0.01 + 0.0025 + 0.16) / 0.01).abs() < 1.0E-9);
+    }
+    #[test]
+    fn correlated_search() {
+        // Teunissen's example, in "the least squares ambiguity decorrelation adjustment"
+        let q = DMatrix::from_row_slice(
+            3,
+            3,
+            &[
+                6.290, 5.978, 0.544, 5.978, 6.292, 2.340, 0.544, 2.340, 6.288,
+            ],
+        );
+        let a = DVector::from_vec(vec![5.45, 3.10, 2.97]);
+        let (candidates, s) = lambda(&a, &q, 2).unwrap();
+        // exhaustive check of the best candidate
+        let q_inv = q.clone().try_inverse().unwrap();
+        let dist =
+            |z: &DVector<f64>| ((a.clone() - z).transpose() * &q_inv * (a.clone() - z))[(0, 0)];
+        let best = dist(&candidates[0]);
+        assert!((best - s[0]).abs() < 1.0E-6);
+        assert!((dist(&candidates[1]) - s[1]).abs() < 1.0E-6);
+        for i in -5..15 {
+            for j in -5..15 {
+                for k in -5..15 {
+                    let z = DVector::from_vec(vec![i as f64, j as f64, k as f64]);
+                    assert!(dist(&z) >= best - 1.0E-9);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Navigation solutions, from Observation and Navigation RINEX
+use crate::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+pub(crate) mod dcb;
+pub(crate) mod geometry;
+pub(crate) mod iono;
+pub(crate) mod lambda;
+pub(crate) mod ppp;
+pub(crate) mod rtk;
+pub(crate) mod spp;
+pub(crate) mod tec;
+pub(crate) mod tropo;
+pub(crate) mod velocity;
+
+pub use ppp::{PppOpts, PppSolution};
+pub use rtk::{FixStatus, RtkOpts, RtkSolution};
+pub use spp::SppOpts;
+pub use tec::{IonexOpts, TecFit};
+pub use tropo::{MappingFunction, MeteoConditions, TropoModel, ZenithModel};
+pub use velocity::Velocity;
+
+pub(crate) use crate::ground_position::{ecef2enu, elevation_azimuth};
+pub(crate) use crate::SPEED_OF_LIGHT;
+
+/// Dilution of Precision
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Dop {
+    /// Geometric DOP
+    pub gdop: f64,
+    /// Position DOP
+    pub pdop: f64,
+    /// Horizontal DOP
+    pub hdop: f64,
+    /// Vertical DOP
+    pub vdop: f64,
+    /// Time DOP, on the reference constellation clock
+    pub tdop: f64,
+}
+
+/// Navigation solution, at a given Epoch
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Solution {
+    /// Receiver position, ECEF WGS84 [m]
+    pub position: (f64, f64, f64),
+    /// Receiver clock offset [s], per constellation.
+    /// The lowest constellation is the reference clock,
+    /// the others also absorb the Inter System Bias (ISB)
+    pub clock: BTreeMap<Constellation, f64>,
+    /// Dilution of Precision
+    pub dop: Dop,
+    /// Post fit residuals [m], for every Sv that contributed
+    pub residuals: HashMap<Sv, f64>,
+}
+
+impl Solution {
+    /// Returns receiver position as [GroundPosition]
+    pub fn ground_position(&self) -> GroundPosition {
+        GroundPosition::from_ecef_wgs84(self.position)
+    }
+    /// Returns the reference constellation and receiver clock offset [s]
+    pub fn reference_clock(&self) -> Option<(Constellation, f64)> {
+        self.clock
+            .iter()
+            .next()
+            .map(|(constellation, offset)| (*constellation, *offset))
+    }
+    /// Returns the Inter System Bias [s] of given constellation,
+    /// with respect to the reference clock
+    pub fn isb(&self, constellation: Constellation) -> Option<f64> {
+        let (_, reference) = self.reference_clock()?;
+        let offset = self.clock.get(&constellation)?;
+        Some(offset - reference)
+    }
+}
+
+/*
+ * Rotates given ECEF covariance matrix to the local ENU frame,
+ * at given receiver position (ECEF)
+ */
+pub(crate) fn enu_covariance(position: (f64, f64, f64), q: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let (lat, lon, _) =
+        map_3d::ecef2geodetic(position.0, position.1, position.2, map_3d::Ellipsoid::WGS84);
+    let r = ecef2enu(lat, lon);
+    // Q_enu = R Q_xyz Rt
+    let mut q_enu = [[0.0_f64; 3]; 3];
+    for (i, q_i) in q_enu.iter_mut().enumerate() {
+        for (j, q_ij) in q_i.iter_mut().enumerate() {
+            for k in 0..3 {
+                for l in 0..3 {
+                    *q_ij += r[i][k] * q[k][l] * r[j][l];
+                }
+            }
+        }
+    }
+    q_enu
+}
+
+/*
+ * Low precision (0.01°) Sun position, ECEF [m]
+ */
+pub(crate) fn sun_position(t: Epoch) -> (f64, f64, f64) {
+    // days since J2000
+    let d = t.to_jde_utc_days() - 2_451_545.0;
+    let l = (280.460 + 0.985_647_4 * d).to_radians();
+    let g = (357.528 + 0.985_600_3 * d).to_radians();
+    let lambda = l + (1.915 * g.sin() + 0.020 * (2.0 * g).sin()).to_radians();
+    let epsilon = (23.439 - 4.0E-7 * d).to_radians();
+    let r = (1.000_14 - 0.016_71 * g.cos() - 0.000_14 * (2.0 * g).cos()) * 149_597_870_700.0;
+    let eci = (
+        r * lambda.cos(),
+        r * epsilon.cos() * lambda.sin(),
+        r * epsilon.sin() * lambda.sin(),
+    );
+    // Greenwich mean sidereal time
+    let gmst = (280.460_618_37 + 360.985_647_366_29 * d).to_radians();
+    (
+        gmst.cos() * eci.0 + gmst.sin() * eci.1,
+        -gmst.sin() * eci.0 + gmst.cos() * eci.1,
+        eci.2,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn elev_azim() {
+        let rcvr = GroundPosition::from_geodetic((52.0, 4.3, 0.0)).to_ecef_wgs84();
+        let zenith = GroundPosition::from_geodetic((52.0, 4.3, 20.0E6)).to_ecef_wgs84();
+        let (elev, _) = elevation_azimuth(rcvr, zenith);
+        assert!((elev - 90.0).abs() < 1.0E-6);
+        // north-bound, on the horizon
+        let north = GroundPosition::from_geodetic((53.0, 4.3, 0.0)).to_ecef_wgs84();
+        let (elev, azim) = elevation_azimuth(rcvr, north);
+        assert!(elev < 0.0 && elev > -1.0);
+        assert!(azim < 1.0E-3 || azim > 360.0 - 1.0E-3);
+        let east = GroundPosition::from_geodetic((52.0, 5.3, 0.0)).to_ecef_wgs84();
+        let (_, azim) = elevation_azimuth(rcvr, east);
+        assert!((azim - 90.0).abs() < 1.0);
+    }
+    #[test]
+    fn sun() {
+        // summer solstice: sun at ~23.44° North
+        let t = Epoch::from_gregorian_utc(2020, 6, 20, 21, 44, 0, 0);
+        let (x, y, z) = sun_position(t);
+        let r = (x * x + y * y + z * z).sqrt();
+        assert!((r / 149_597_870_700.0 - 1.016).abs() < 1.0E-3);
+        assert!(((z / r).asin().to_degrees() - 23.44).abs() < 0.05);
+        // local noon, over Greenwich
+        let t = Epoch::from_gregorian_utc(2020, 6, 20, 12, 0, 0, 0);
+        let (x, y, _) = sun_position(t);
+        let lon = y.atan2(x).to_degrees();
+        assert!(lon.abs() < 1.0, "{}", lon);
+    }
+}
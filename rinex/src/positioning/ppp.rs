@@ -0,0 +1,1210 @@
+//! Precise Point Positioning (PPP), with float ambiguities
+use super::spp::{self, earth_rotation, index_ephemerides, select_ephemeris, Ephemerides, SvState};
+use super::{
+    ecef2enu, elevation_azimuth, enu_covariance, sun_position, MeteoConditions, TropoModel,
+    SPEED_OF_LIGHT,
+};
+use crate::antex::{Antenna, Frequency, Pattern};
+use crate::carrier::Carrier;
+use crate::clocks;
+use crate::epoch::gnss_instant;
+use crate::navigation::{sisre::broadcast_clock, PreciseOrbits};
+use crate::observation::{LliFlags, ObservationData};
+use crate::prelude::*;
+use nalgebra::{DMatrix, DVector};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// Precise Point Positioning options
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PppOpts<'a> {
+    /// Elevation mask [°]
+    pub elev_mask: f64,
+    /// Zenithal code noise [m], on a single frequency
+    pub code_sigma: f64,
+    /// Zenithal phase noise [m], on a single frequency
+    pub phase_sigma: f64,
+    /// Zenith wet delay random walk [m.s^-1/2]
+    pub zwd_noise: f64,
+    /// Kinematic receiver: the position is then modeled as a random walk
+    pub kinematic: bool,
+    /// Position random walk [m.s^-1/2], in kinematic mode
+    pub position_noise: f64,
+    /// Geometry free phase jump [m] above which a cycle slip is declared
+    pub slip_threshold: f64,
+    /// Observation gap [s] above which a new ambiguity arc starts
+    pub max_gap: f64,
+    /// Innovations above this many sigmas are rejected
+    pub outlier: f64,
+    /// Tropospheric delay model: a priori zenith delays and mapping functions
+    pub tropo_model: TropoModel,
+    /// Meteo RINEX, from which the surface conditions at the receiver
+    /// are interpolated at each epoch, see [MeteoConditions::from_meteo].
+    /// Standard atmosphere otherwise
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub meteo: Option<&'a Rinex>,
+}
+
+impl<'a> Default for PppOpts<'a> {
+    fn default() -> Self {
+        Self {
+            elev_mask: 10.0,
+            code_sigma: 0.3,
+            phase_sigma: 0.003,
+            zwd_noise: 1.0E-4,
+            kinematic: false,
+            position_noise: 10.0,
+            slip_threshold: 0.05,
+            max_gap: 300.0,
+            outlier: 5.0,
+            tropo_model: TropoModel::default(),
+            meteo: None,
+        }
+    }
+}
+
+impl<'a> PppOpts<'a> {
+    /// Returns options with given elevation mask [°]
+    pub fn with_elevation_mask(&self, mask: f64) -> Self {
+        let mut s = *self;
+        s.elev_mask = mask;
+        s
+    }
+    /// Returns options with given zenithal code noise [m]
+    pub fn with_code_sigma(&self, sigma: f64) -> Self {
+        let mut s = *self;
+        s.code_sigma = sigma;
+        s
+    }
+    /// Returns options with given zenithal phase noise [m]
+    pub fn with_phase_sigma(&self, sigma: f64) -> Self {
+        let mut s = *self;
+        s.phase_sigma = sigma;
+        s
+    }
+    /// Returns options with given zenith wet delay random walk [m.s^-1/2]
+    pub fn with_zwd_noise(&self, noise: f64) -> Self {
+        let mut s = *self;
+        s.zwd_noise = noise;
+        s
+    }
+    /// Selects static or kinematic positioning
+    pub fn with_kinematic(&self, kinematic: bool) -> Self {
+        let mut s = *self;
+        s.kinematic = kinematic;
+        s
+    }
+    /// Returns options with given position random walk [m.s^-1/2]
+    pub fn with_position_noise(&self, noise: f64) -> Self {
+        let mut s = *self;
+        s.position_noise = noise;
+        s
+    }
+    /// Returns options with given cycle slip detection threshold [m]
+    pub fn with_slip_threshold(&self, threshold: f64) -> Self {
+        let mut s = *self;
+        s.slip_threshold = threshold;
+        s
+    }
+    /// Returns options with given maximal observation gap [s]
+    pub fn with_max_gap(&self, gap: f64) -> Self {
+        let mut s = *self;
+        s.max_gap = gap;
+        s
+    }
+    /// Returns options with given tropospheric delay model
+    pub fn with_tropo_model(&self, model: TropoModel) -> Self {
+        let mut s = *self;
+        s.tropo_model = model;
+        s
+    }
+    /// Returns options with given Meteo RINEX, from which the
+    /// surface conditions are interpolated, see [MeteoConditions::from_meteo]
+    pub fn with_meteo(&self, meteo: &'a Rinex) -> Self {
+        let mut s = *self;
+        s.meteo = Some(meteo);
+        s
+    }
+}
+
+/// Precise Point Positioning solution, at a given Epoch
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PppSolution {
+    /// Marker position, ECEF WGS84 [m]
+    pub position: (f64, f64, f64),
+    /// Position standard deviation [m], in the local East, North, Up frame
+    pub std_enu: (f64, f64, f64),
+    /// Receiver clock offset [s], per constellation
+    pub clock: BTreeMap<Constellation, f64>,
+    /// A priori zenith hydrostatic delay [m]
+    pub zhd: f64,
+    /// Estimated zenith wet delay [m]
+    pub zwd: f64,
+    /// Float ionosphere free ambiguities [m], per Sv
+    pub ambiguities: HashMap<Sv, f64>,
+    /// Post fit code and phase residuals [m], per Sv
+    pub residuals: HashMap<Sv, (f64, f64)>,
+}
+
+impl PppSolution {
+    /// Returns the zenith total delay [m]
+    pub fn ztd(&self) -> f64 {
+        self.zhd + self.zwd
+    }
+}
+
+/*
+ * Dual frequency pair, per constellation: the ionosphere free combination
+ * the broadcast and precise clocks are referenced to.
+ */
+pub(super) fn carrier_pair(constellation: Constellation) -> Option<(Carrier, Carrier)> {
+    match constellation {
+        Constellation::GPS | Constellation::QZSS => Some((Carrier::L1, Carrier::L2)),
+        Constellation::Galileo => Some((Carrier::E1, Carrier::E5a)),
+        _ => None,
+    }
+}
+
+/*
+ * Ionosphere free combination coefficients
+ */
+fn if_coefficients(pair: (Carrier, Carrier)) -> (f64, f64) {
+    let (f1, f2) = (pair.0.frequency(), pair.1.frequency());
+    let gamma = f1.powi(2) - f2.powi(2);
+    (f1.powi(2) / gamma, f2.powi(2) / gamma)
+}
+
+/*
+ * Ionosphere free observations, for one Sv
+ */
+struct IfObservation {
+    /// Ionosphere free pseudo range [m]
+    code: f64,
+    /// Ionosphere free phase [m]
+    phase: f64,
+    /// Geometry free phase [m]
+    gf: f64,
+    /// Lock loss flagged on either phase
+    lock_loss: bool,
+}
+
+pub(super) fn select(
+    observations: &HashMap<Observable, ObservationData>,
+    constellation: Constellation,
+    carrier: Carrier,
+    phase: bool,
+) -> Option<&ObservationData> {
+    observations
+        .iter()
+        .filter(|(observable, data)| {
+            let kind = if phase {
+                observable.is_phase_observable()
+            } else {
+                observable.is_pseudorange_observable()
+            };
+            kind && data.obs != 0.0 && observable.carrier(constellation) == Ok(carrier)
+        })
+        .min_by_key(|(observable, _)| observable.to_string())
+        .map(|(_, data)| data)
+}
+
+fn if_observation(
+    constellation: Constellation,
+    observations: &HashMap<Observable, ObservationData>,
+) -> Option<IfObservation> {
+    let pair = carrier_pair(constellation)?;
+    let c1 = select(observations, constellation, pair.0, false)?;
+    let c2 = select(observations, constellation, pair.1, false)?;
+    let l1 = select(observations, constellation, pair.0, true)?;
+    let l2 = select(observations, constellation, pair.1, true)?;
+    let (a, b) = if_coefficients(pair);
+    let (l1_m, l2_m) = (l1.obs * pair.0.wavelength(), l2.obs * pair.1.wavelength());
+    let lock_loss = [l1, l2].iter().any(|data| {
+        data.lli
+            .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+            .unwrap_or(false)
+    });
+    Some(IfObservation {
+        code: a * c1.obs - b * c2.obs,
+        phase: a * l1_m - b * l2_m,
+        gf: l1_m - l2_m,
+        lock_loss,
+    })
+}
+
+/*
+ * Lagrange interpolation of precise orbits, over 10 samples
+ */
+fn interpolate_orbit(orbits: &PreciseOrbits, sv: Sv, t: Epoch) -> Option<(f64, f64, f64)> {
+    const HALF: usize = 5;
+    if let Some(position) = orbits.get(&t).and_then(|svs| svs.get(&sv)) {
+        return Some(*position);
+    }
+    let mut samples: Vec<(f64, (f64, f64, f64))> = orbits
+        .range(..t)
+        .rev()
+        .filter_map(|(e, svs)| svs.get(&sv).map(|p| ((*e - t).to_seconds(), *p)))
+        .take(HALF)
+        .collect();
+    if samples.len() < HALF {
+        return None;
+    }
+    samples.extend(
+        orbits
+            .range((Excluded(t), Unbounded))
+            .filter_map(|(e, svs)| svs.get(&sv).map(|p| ((*e - t).to_seconds(), *p)))
+            .take(HALF),
+    );
+    if samples.len() < 2 * HALF {
+        return None;
+    }
+    let mut position = (0.0_f64, 0.0_f64, 0.0_f64);
+    for (i, (t_i, p_i)) in samples.iter().enumerate() {
+        let mut l = 1.0_f64;
+        for (j, (t_j, _)) in samples.iter().enumerate() {
+            if i != j {
+                l *= -t_j / (t_i - t_j);
+            }
+        }
+        position.0 += l * p_i.0;
+        position.1 += l * p_i.1;
+        position.2 += l * p_i.2;
+    }
+    Some(position)
+}
+
+/*
+ * Antenna model, on the ionosphere free combination
+ */
+#[derive(Debug, Clone, Default)]
+struct AntennaModel {
+    /// Phase center offset [m]: (N, E, U) for receivers, (X, Y, Z) for Sv
+    pco: (f64, f64, f64),
+    /// Phase center variations: (zen1 [°], dzen [°], values [m])
+    pcv: Option<(f64, f64, Vec<f64>)>,
+}
+
+impl AntennaModel {
+    fn new(antenna: &Antenna, frequencies: &[Frequency], pair: (Carrier, Carrier)) -> Option<Self> {
+        let lookup = |carrier: Carrier| {
+            frequencies
+                .iter()
+                .find(|f| f.carrier == carrier)
+                .or(frequencies
+                    .iter()
+                    .find(|f| f.carrier.frequency_mhz() == carrier.frequency_mhz()))
+        };
+        let (f1, f2) = (lookup(pair.0)?, lookup(pair.1)?);
+        let (a, b) = if_coefficients(pair);
+        let pco = (
+            (a * f1.north - b * f2.north) * 1.0E-3,
+            (a * f1.east - b * f2.east) * 1.0E-3,
+            (a * f1.up - b * f2.up) * 1.0E-3,
+        );
+        let pattern = |frequency: &Frequency| {
+            frequency.patterns.iter().find_map(|p| match p {
+                Pattern::NonAzimuthDependent(values) => Some(values.clone()),
+                _ => None,
+            })
+        };
+        let pcv = match (pattern(f1), pattern(f2)) {
+            (Some(p1), Some(p2)) if p1.len() == p2.len() && antenna.dzen > 0.0 => Some((
+                antenna.zen.0,
+                antenna.dzen,
+                p1.iter()
+                    .zip(p2.iter())
+                    .map(|(v1, v2)| (a * v1 - b * v2) * 1.0E-3)
+                    .collect(),
+            )),
+            _ => None,
+        };
+        Some(Self { pco, pcv })
+    }
+    /*
+     * Phase center variation [m], at given zenith (or nadir) angle [°]
+     */
+    fn pcv(&self, zenith: f64) -> f64 {
+        match &self.pcv {
+            Some((zen1, dzen, values)) if !values.is_empty() => {
+                let x = ((zenith - zen1) / dzen).max(0.0);
+                let i = (x.floor() as usize).min(values.len() - 1);
+                if i + 1 >= values.len() {
+                    values[i]
+                } else {
+                    values[i] + (x - i as f64) * (values[i + 1] - values[i])
+                }
+            },
+            _ => 0.0,
+        }
+    }
+}
+
+/*
+ * Searches the antenna model matching given description, in ANTEX record
+ */
+fn antenna_model<F: Fn(&Antenna) -> bool>(
+    antex: &Rinex,
+    pair: (Carrier, Carrier),
+    t: Epoch,
+    matches: F,
+) -> Option<AntennaModel> {
+    let record = antex.record.as_antex()?;
+    record
+        .iter()
+        .filter(|(antenna, _)| {
+            matches(antenna)
+                && antenna.valid_from.map(|from| t >= from).unwrap_or(true)
+                && antenna.valid_until.map(|until| t < until).unwrap_or(true)
+        })
+        .find_map(|(antenna, frequencies)| AntennaModel::new(antenna, frequencies, pair))
+}
+
+fn normalize(v: (f64, f64, f64)) -> (f64, f64, f64) {
+    let norm = (v.0.powi(2) + v.1.powi(2) + v.2.powi(2)).sqrt();
+    (v.0 / norm, v.1 / norm, v.2 / norm)
+}
+
+pub(super) fn dot(u: (f64, f64, f64), v: (f64, f64, f64)) -> f64 {
+    u.0 * v.0 + u.1 * v.1 + u.2 * v.2
+}
+
+fn cross(u: (f64, f64, f64), v: (f64, f64, f64)) -> (f64, f64, f64) {
+    (
+        u.1 * v.2 - u.2 * v.1,
+        u.2 * v.0 - u.0 * v.2,
+        u.0 * v.1 - u.1 * v.0,
+    )
+}
+
+/// (X, Y, Z) unit vectors
+type Frame = ((f64, f64, f64), (f64, f64, f64), (f64, f64, f64));
+
+/*
+ * Sv body frame unit vectors, in nominal yaw attitude
+ */
+fn sv_body_frame(sv: (f64, f64, f64), sun: (f64, f64, f64)) -> Frame {
+    let ez = normalize((-sv.0, -sv.1, -sv.2));
+    let es = normalize((sun.0 - sv.0, sun.1 - sv.1, sun.2 - sv.2));
+    let ey = normalize(cross(ez, es));
+    let ex = cross(ey, ez);
+    (ex, ey, ez)
+}
+
+/*
+ * Phase wind up [cycles], unwrapped with respect to the previous value
+ */
+fn windup(
+    sv: (f64, f64, f64),
+    rcvr: (f64, f64, f64),
+    sun: (f64, f64, f64),
+    previous: Option<f64>,
+) -> f64 {
+    let k = normalize((rcvr.0 - sv.0, rcvr.1 - sv.1, rcvr.2 - sv.2));
+    let (exs, eys, _) = sv_body_frame(sv, sun);
+    let (lat, lon, _) = map_3d::ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, map_3d::Ellipsoid::WGS84);
+    let r = ecef2enu(lat, lon);
+    let exr = (r[1][0], r[1][1], r[1][2]); // north
+    let eyr = (-r[0][0], -r[0][1], -r[0][2]); // west
+    let (ks, kr) = (dot(k, exs), dot(k, exr));
+    let (cs, cr) = (cross(k, eys), cross(k, eyr));
+    let ds = (
+        exs.0 - k.0 * ks - cs.0,
+        exs.1 - k.1 * ks - cs.1,
+        exs.2 - k.2 * ks - cs.2,
+    );
+    let dr = (
+        exr.0 - k.0 * kr + cr.0,
+        exr.1 - k.1 * kr + cr.1,
+        exr.2 - k.2 * kr + cr.2,
+    );
+    let cos = dot(ds, dr) / (dot(ds, ds) * dot(dr, dr)).sqrt();
+    let mut phi = cos.clamp(-1.0, 1.0).acos() / (2.0 * std::f64::consts::PI);
+    if dot(k, cross(ds, dr)) < 0.0 {
+        phi = -phi;
+    }
+    match previous {
+        Some(previous) => phi + (previous - phi + 0.5).floor(),
+        None => phi,
+    }
+}
+
+/*
+ * Orbit and clock products
+ */
+struct Products<'a> {
+    ephemerides: Ephemerides<'a>,
+    orbits: Option<&'a PreciseOrbits>,
+    clocks: Option<&'a Rinex>,
+}
+
+impl<'a> Products<'a> {
+    /*
+     * Sv clock offset [s] at given transmission epoch (RINEX epoch)
+     */
+    fn clock(&self, sv: Sv, t: Epoch) -> Option<f64> {
+        self.clocks
+            .and_then(|clocks| clocks.clock_at(&clocks::System::Sv(sv), t))
+            .map(|data| data.bias)
+            .or_else(|| {
+                // not described by the clock products: broadcast clock
+                let (toc, ephemeris) = select_ephemeris(self.ephemerides.get(&sv)?, t)?;
+                Some(broadcast_clock(
+                    gnss_instant(toc, sv.constellation),
+                    ephemeris,
+                    gnss_instant(t, Constellation::GPS),
+                ))
+            })
+    }
+    /*
+     * True if Sv position at given epoch is interpolated from precise orbits
+     */
+    fn precise_orbit(&self, sv: Sv, t: Epoch) -> bool {
+        self.orbits
+            .and_then(|orbits| interpolate_orbit(orbits, sv, t))
+            .is_some()
+    }
+    /*
+     * Sv position [m] at given transmission epoch (RINEX epoch)
+     */
+    fn position(&self, sv: Sv, t: Epoch) -> Option<(f64, f64, f64)> {
+        self.orbits
+            .and_then(|orbits| interpolate_orbit(orbits, sv, t))
+            .or_else(|| {
+                // not described by the orbit products: broadcast orbit
+                let (_, ephemeris) = select_ephemeris(self.ephemerides.get(&sv)?, t)?;
+                ephemeris.sv_position(gnss_instant(t, Constellation::GPS))
+            })
+    }
+    /*
+     * Sv state at signal transmission, for a signal received at `t`
+     */
+    fn sv_state(&self, sv: Sv, t: Epoch, pr: f64) -> Option<SvState> {
+        let t_tx = t - Duration::from_seconds(pr / SPEED_OF_LIGHT);
+        let t_tx = t_tx - Duration::from_seconds(self.clock(sv, t_tx)?);
+        let position = self.position(sv, t_tx)?;
+        let dt = Duration::from_seconds(0.5);
+        let before = self.position(sv, t_tx - dt)?;
+        let after = self.position(sv, t_tx + dt)?;
+        let velocity = (after.0 - before.0, after.1 - before.1, after.2 - before.2);
+        let relativistic = -2.0 * dot(position, velocity) / SPEED_OF_LIGHT.powi(2);
+        Some(SvState {
+            position,
+            velocity,
+            clock: self.clock(sv, t_tx)? + relativistic,
+            clock_drift: 0.0,
+        })
+    }
+}
+
+/// (Sv, phase, design row, innovation [m], variance [m²])
+type Row = (Sv, bool, DVector<f64>, f64, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Param {
+    Clock(Constellation),
+    Ambiguity(Sv),
+}
+
+/*
+ * Kalman filter state: position, zenith wet delay, then
+ * one clock per constellation and one ambiguity per arc
+ */
+struct Filter {
+    x: DVector<f64>,
+    p: DMatrix<f64>,
+    params: Vec<Param>,
+}
+
+impl Filter {
+    fn new(position: (f64, f64, f64), zwd: f64) -> Self {
+        let x = DVector::from_vec(vec![position.0, position.1, position.2, zwd]);
+        let p = DMatrix::from_diagonal(&DVector::from_vec(vec![
+            30.0_f64.powi(2),
+            30.0_f64.powi(2),
+            30.0_f64.powi(2),
+            0.3_f64.powi(2),
+        ]));
+        Self {
+            x,
+            p,
+            params: Vec::new(),
+        }
+    }
+    fn position(&self) -> (f64, f64, f64) {
+        (self.x[0], self.x[1], self.x[2])
+    }
+    fn index(&self, param: Param) -> Option<usize> {
+        self.params.iter().position(|p| *p == param).map(|i| 4 + i)
+    }
+    /*
+     * Resets (or introduces) a parameter, uncorrelated to the others
+     */
+    fn reset(&mut self, param: Param, value: f64, variance: f64) {
+        let i = match self.index(param) {
+            Some(i) => i,
+            None => {
+                let n = self.x.len();
+                self.x = self.x.clone().insert_row(n, 0.0);
+                self.p = self.p.clone().insert_row(n, 0.0).insert_column(n, 0.0);
+                self.params.push(param);
+                n
+            },
+        };
+        self.x[i] = value;
+        self.p.row_mut(i).fill(0.0);
+        self.p.column_mut(i).fill(0.0);
+        self.p[(i, i)] = variance;
+    }
+    fn remove(&mut self, param: Param) {
+        if let Some(i) = self.index(param) {
+            self.x = self.x.clone().remove_row(i);
+            self.p = self.p.clone().remove_row(i).remove_column(i);
+            self.params.remove(i - 4);
+        }
+    }
+}
+
+/*
+ * Measurement, for one Sv
+ */
+struct Measurement {
+    sv: Sv,
+    obs: IfObservation,
+    state: SvState,
+    /// Ionosphere free noise amplification
+    noise: f64,
+    /// Ionosphere free wind up wavelength [m]
+    windup_wavelength: f64,
+    /// Receiver antenna model
+    antenna: AntennaModel,
+}
+
+/*
+ * Resolves the PPP solution, at every epoch of the `obs` RINEX
+ */
+pub(crate) fn solve(
+    obs: &Rinex,
+    nav: &Rinex,
+    orbits: Option<&PreciseOrbits>,
+    clocks: Option<&Rinex>,
+    antex: Option<&Rinex>,
+    opts: &PppOpts,
+) -> BTreeMap<Epoch, PppSolution> {
+    let mut ret: BTreeMap<Epoch, PppSolution> = BTreeMap::new();
+    let record = match obs.record.as_obs() {
+        Some(record) => record,
+        None => return ret,
+    };
+    let products = Products {
+        ephemerides: index_ephemerides(nav),
+        orbits,
+        clocks,
+    };
+    // a priori solutions
+    let mut spp_opts = spp::SppOpts::default()
+        .with_elevation_mask(opts.elev_mask)
+        .with_tropo_model(opts.tropo_model);
+    spp_opts.meteo = opts.meteo;
+    let apriori = spp::solve(obs, nav, &spp_opts);
+
+    // marker to antenna reference point (E, N, U) [m]
+    let (arp, rcvr_antenna) = match &obs.header.rcvr_antenna {
+        Some(antenna) => (
+            (
+                antenna.eastern.unwrap_or(0.0),
+                antenna.northern.unwrap_or(0.0),
+                antenna.height.unwrap_or(0.0),
+            ),
+            antenna
+                .model
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        None => ((0.0, 0.0, 0.0), String::new()),
+    };
+    let mut rcvr_models: HashMap<(Carrier, Carrier), AntennaModel> = HashMap::new();
+
+    let mut filter: Option<Filter> = None;
+    let mut arcs: HashMap<Sv, (Epoch, f64, f64)> = HashMap::new(); // (last, gf, wind up)
+    let mut previous: Option<Epoch> = None;
+
+    for ((t, flag), (_, svs)) in record {
+        if !flag.is_ok() {
+            continue;
+        }
+        let spp = apriori.get(t);
+        let filter = match (filter.as_mut(), spp) {
+            (Some(filter), _) => filter,
+            (None, Some(spp)) => {
+                let (lat, _, alt) = GroundPosition::from_ecef_wgs84(spp.position).to_geodetic();
+                let meteo = opts
+                    .meteo
+                    .and_then(|meteo| MeteoConditions::from_meteo(meteo, *t, alt));
+                let (_, zwd) = opts.tropo_model.zenith_delays(*t, lat, alt, meteo);
+                filter.insert(Filter::new(spp.position, zwd))
+            },
+            (None, None) => continue,
+        };
+        let position = filter.position();
+        let (lat, lon, alt) =
+            map_3d::ecef2geodetic(position.0, position.1, position.2, map_3d::Ellipsoid::WGS84);
+        let r = ecef2enu(lat, lon);
+        let (lat, lon) = (lat.to_degrees(), lon.to_degrees());
+        let meteo = opts
+            .meteo
+            .and_then(|meteo| MeteoConditions::from_meteo(meteo, *t, alt));
+        let (zhd, _) = opts.tropo_model.zenith_delays(*t, lat, alt, meteo);
+        let enu2ecef = |v: (f64, f64, f64)| {
+            (
+                r[0][0] * v.0 + r[1][0] * v.1 + r[2][0] * v.2,
+                r[0][1] * v.0 + r[1][1] * v.1 + r[2][1] * v.2,
+                r[0][2] * v.0 + r[1][2] * v.1 + r[2][2] * v.2,
+            )
+        };
+        let sun = sun_position(gnss_instant(*t, Constellation::GPS));
+
+        /*
+         * Measurements and ambiguity arcs
+         */
+        let mut measurements: Vec<Measurement> = Vec::new();
+        let mut slips: Vec<Sv> = Vec::new();
+        for (sv, observations) in svs {
+            let pair = match carrier_pair(sv.constellation) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let ifo = match if_observation(sv.constellation, observations) {
+                Some(ifo) => ifo,
+                None => continue,
+            };
+            let slip = match arcs.get(sv) {
+                Some((last, gf, _)) => {
+                    ifo.lock_loss
+                        || (*t - *last).to_seconds() > opts.max_gap
+                        || (ifo.gf - gf).abs() > opts.slip_threshold
+                },
+                None => true,
+            };
+            if slip {
+                slips.push(*sv);
+                arcs.remove(sv);
+            }
+            let mut state = match products.sv_state(*sv, *t, ifo.code) {
+                Some(state) => state,
+                None => continue,
+            };
+            if products.precise_orbit(*sv, *t) {
+                // precise orbits refer to the center of mass
+                if let Some(antex) = antex {
+                    let sv_name = sv.to_string();
+                    let model =
+                        antenna_model(antex, pair, *t, |antenna| antenna.sn.trim() == sv_name);
+                    match model {
+                        Some(model) => {
+                            let (ex, ey, ez) = sv_body_frame(state.position, sun);
+                            let (x, y, z) = model.pco;
+                            state.position = (
+                                state.position.0 + x * ex.0 + y * ey.0 + z * ez.0,
+                                state.position.1 + x * ex.1 + y * ey.1 + z * ez.1,
+                                state.position.2 + x * ex.2 + y * ey.2 + z * ez.2,
+                            );
+                        },
+                        None => continue,
+                    }
+                }
+            }
+            let antenna = rcvr_models
+                .entry(pair)
+                .or_insert_with(|| {
+                    antex
+                        .and_then(|antex| {
+                            antenna_model(antex, pair, *t, |antenna| {
+                                !rcvr_antenna.is_empty()
+                                    && antenna
+                                        .ant_type
+                                        .split_whitespace()
+                                        .collect::<Vec<_>>()
+                                        .join(" ")
+                                        == rcvr_antenna
+                            })
+                        })
+                        .unwrap_or_default()
+                })
+                .clone();
+            let (a, b) = if_coefficients(pair);
+            let (f1, f2) = (pair.0.frequency(), pair.1.frequency());
+            let entry = arcs.entry(*sv).or_insert((*t, ifo.gf, 0.0));
+            entry.0 = *t;
+            entry.1 = ifo.gf;
+            measurements.push(Measurement {
+                sv: *sv,
+                obs: ifo,
+                state,
+                noise: (a.powi(2) + b.powi(2)).sqrt(),
+                windup_wavelength: SPEED_OF_LIGHT / (f1 + f2),
+                antenna,
+            });
+        }
+
+        /*
+         * Time update
+         */
+        let dt = previous.map(|p| (*t - p).to_seconds()).unwrap_or(0.0);
+        previous = Some(*t);
+        if opts.kinematic {
+            for i in 0..3 {
+                filter.p[(i, i)] += opts.position_noise.powi(2) * dt;
+            }
+        }
+        filter.p[(3, 3)] += opts.zwd_noise.powi(2) * dt;
+
+        let mut constellations: Vec<Constellation> =
+            measurements.iter().map(|m| m.sv.constellation).collect();
+        constellations.sort();
+        constellations.dedup();
+        for param in filter.params.clone() {
+            let retained = match param {
+                Param::Clock(c) => constellations.contains(&c),
+                Param::Ambiguity(sv) => {
+                    !slips.contains(&sv) && measurements.iter().any(|m| m.sv == sv)
+                },
+            };
+            if !retained {
+                filter.remove(param);
+            }
+        }
+        for constellation in &constellations {
+            // white noise clocks
+            let clock = spp
+                .and_then(|spp| spp.clock.get(constellation))
+                .map(|dt| dt * SPEED_OF_LIGHT)
+                .or(filter
+                    .index(Param::Clock(*constellation))
+                    .map(|i| filter.x[i]))
+                .or(spp
+                    .and_then(|spp| spp.clock.values().next())
+                    .map(|dt| dt * SPEED_OF_LIGHT))
+                .unwrap_or(0.0);
+            filter.reset(Param::Clock(*constellation), clock, 100.0_f64.powi(2));
+        }
+
+        /*
+         * Measurement update
+         */
+        let rcvr = filter.position();
+        // masked vehicles are dropped, prior to indexing the state
+        measurements.retain(|m| {
+            let (elev, _) = elevation_azimuth(rcvr, m.state.position);
+            if elev < opts.elev_mask {
+                filter.remove(Param::Ambiguity(m.sv));
+                arcs.remove(&m.sv);
+            }
+            elev >= opts.elev_mask
+        });
+        let mut rows: Vec<Row> = Vec::new();
+        for m in measurements.iter_mut() {
+            // antenna phase center
+            let ecc = (
+                arp.0 + m.antenna.pco.1,
+                arp.1 + m.antenna.pco.0,
+                arp.2 + m.antenna.pco.2,
+            );
+            let ecc = enu2ecef(ecc);
+            let apc = (rcvr.0 + ecc.0, rcvr.1 + ecc.1, rcvr.2 + ecc.2);
+            let dx = (
+                m.state.position.0 - apc.0,
+                m.state.position.1 - apc.1,
+                m.state.position.2 - apc.2,
+            );
+            let tau = dot(dx, dx).sqrt() / SPEED_OF_LIGHT;
+            let sv_pos = earth_rotation(m.state.position, tau);
+            let (elev, _) = elevation_azimuth(rcvr, sv_pos);
+            let los = (sv_pos.0 - apc.0, sv_pos.1 - apc.1, sv_pos.2 - apc.2);
+            let rho = dot(los, los).sqrt();
+            let e = (los.0 / rho, los.1 / rho, los.2 / rho);
+            let (mh, mw) = opts.tropo_model.mapping_factors(*t, lat, lon, alt, elev);
+            let clk_index = match filter.index(Param::Clock(m.sv.constellation)) {
+                Some(i) => i,
+                None => continue,
+            };
+            let modeled = rho + filter.x[clk_index] - SPEED_OF_LIGHT * m.state.clock
+                + zhd * mh
+                + filter.x[3] * mw
+                + m.antenna.pcv(90.0 - elev);
+            // wind up
+            let entry = arcs.entry(m.sv).or_insert((*t, m.obs.gf, 0.0));
+            let previous_windup = if slips.contains(&m.sv) {
+                None
+            } else {
+                Some(entry.2)
+            };
+            entry.2 = windup(sv_pos, rcvr, sun, previous_windup);
+            let windup = entry.2 * m.windup_wavelength;
+
+            let amb_index = match filter.index(Param::Ambiguity(m.sv)) {
+                Some(i) => i,
+                None => {
+                    let n0 = m.obs.phase - m.obs.code - windup;
+                    filter.reset(Param::Ambiguity(m.sv), n0, 30.0_f64.powi(2));
+                    filter.index(Param::Ambiguity(m.sv)).unwrap()
+                },
+            };
+            let sin2 = elev.to_radians().sin().powi(2);
+            let n = filter.x.len();
+            let mut h = DVector::<f64>::zeros(n);
+            h[0] = -e.0;
+            h[1] = -e.1;
+            h[2] = -e.2;
+            h[3] = mw;
+            h[clk_index] = 1.0;
+            rows.push((
+                m.sv,
+                false,
+                h.clone(),
+                m.obs.code - modeled,
+                (m.noise * opts.code_sigma).powi(2) / sin2,
+            ));
+            h[amb_index] = 1.0;
+            rows.push((
+                m.sv,
+                true,
+                h,
+                m.obs.phase - modeled - filter.x[amb_index] - windup,
+                (m.noise * opts.phase_sigma).powi(2) / sin2,
+            ));
+        }
+        // states may have been introduced after some rows were formed
+        let n = filter.x.len();
+        for (_, _, h, _, _) in rows.iter_mut() {
+            if h.len() < n {
+                *h = h.clone().resize_vertically(n, 0.0);
+            }
+        }
+        // innovation screening
+        let mut rejected: Vec<Sv> = Vec::new();
+        rows.retain(|(sv, phase, h, v, var)| {
+            let s = (h.transpose() * &filter.p * h)[(0, 0)] + var;
+            if v.abs() > opts.outlier * s.sqrt() {
+                if *phase {
+                    rejected.push(*sv);
+                }
+                false
+            } else {
+                true
+            }
+        });
+        if rows.len() < 4 + constellations.len() {
+            continue;
+        }
+        let mut hm = DMatrix::<f64>::zeros(rows.len(), n);
+        let mut rm = DMatrix::<f64>::zeros(rows.len(), rows.len());
+        let mut v = DVector::<f64>::zeros(rows.len());
+        for (i, (_, _, h, innovation, var)) in rows.iter().enumerate() {
+            hm.row_mut(i).copy_from(&h.transpose());
+            rm[(i, i)] = *var;
+            v[i] = *innovation;
+        }
+        let pht = &filter.p * hm.transpose();
+        let s = &hm * &pht + &rm;
+        let s_inv = match s.cholesky() {
+            Some(cholesky) => cholesky.inverse(),
+            None => continue,
+        };
+        let k = &pht * s_inv;
+        let dx = &k * &v;
+        filter.x += &dx;
+        // Joseph form
+        let ikh = DMatrix::<f64>::identity(n, n) - &k * &hm;
+        filter.p = &ikh * &filter.p * ikh.transpose() + &k * &rm * k.transpose();
+
+        let postfit = &v - &hm * &dx;
+        let mut residuals: HashMap<Sv, (f64, f64)> = HashMap::new();
+        for (i, (sv, phase, _, _, _)) in rows.iter().enumerate() {
+            let entry = residuals.entry(*sv).or_insert((0.0, 0.0));
+            if *phase {
+                entry.1 = postfit[i];
+            } else {
+                entry.0 = postfit[i];
+            }
+        }
+        // rejected phases start a new arc
+        for sv in rejected {
+            filter.remove(Param::Ambiguity(sv));
+            arcs.remove(&sv);
+        }
+
+        let mut q = [[0.0_f64; 3]; 3];
+        for (i, q_i) in q.iter_mut().enumerate() {
+            for (j, q_ij) in q_i.iter_mut().enumerate() {
+                *q_ij = filter.p[(i, j)];
+            }
+        }
+        let q_enu = enu_covariance(filter.position(), q);
+        ret.insert(
+            *t,
+            PppSolution {
+                position: filter.position(),
+                std_enu: (q_enu[0][0].sqrt(), q_enu[1][1].sqrt(), q_enu[2][2].sqrt()),
+                clock: filter
+                    .params
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, param)| match param {
+                        Param::Clock(c) => Some((*c, filter.x[4 + i] / SPEED_OF_LIGHT)),
+                        _ => None,
+                    })
+                    .collect(),
+                zhd,
+                zwd: filter.x[3],
+                ambiguities: filter
+                    .params
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, param)| match param {
+                        Param::Ambiguity(sv) => Some((*sv, filter.x[4 + i])),
+                        _ => None,
+                    })
+                    .collect(),
+                residuals,
+            },
+        );
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn ionosphere_free() {
+        for constellation in [Constellation::GPS, Constellation::Galileo] {
+            let pair = carrier_pair(constellation).unwrap();
+            let (a, b) = if_coefficients(pair);
+            // geometry is preserved, first order ionosphere is cancelled
+            assert!((a - b - 1.0).abs() < 1.0E-12);
+            let (f1, f2) = (pair.0.frequency(), pair.1.frequency());
+            assert!((a / f1.powi(2) - b / f2.powi(2)).abs() < 1.0E-30);
+        }
+        assert!(carrier_pair(Constellation::Glonass).is_none());
+        let opts = PppOpts::default()
+            .with_elevation_mask(15.0)
+            .with_kinematic(true)
+            .with_max_gap(60.0);
+        assert_eq!(opts.elev_mask, 15.0);
+        assert!(opts.kinematic);
+        assert_eq!(opts.max_gap, 60.0);
+        assert_eq!(opts.code_sigma, PppOpts::default().code_sigma);
+    }
+    #[test]
+    fn orbit_interpolation() {
+        let sv = Sv::new(Constellation::GPS, 1);
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        let omega = 2.0 * std::f64::consts::PI / 43082.0;
+        let r = 26_560_000.0_f64;
+        let orbit = |dt: f64| {
+            let phi = omega * dt;
+            (r * phi.cos(), r * phi.sin() * 0.5, r * phi.sin() * 0.8)
+        };
+        let mut orbits = PreciseOrbits::new();
+        for i in 0..24 {
+            let dt = 900.0 * i as f64;
+            orbits.insert(
+                t0 + Duration::from_seconds(dt),
+                vec![(sv, orbit(dt))].into_iter().collect(),
+            );
+        }
+        let dt = 900.0 * 10.0 + 123.4;
+        let p = interpolate_orbit(&orbits, sv, t0 + Duration::from_seconds(dt)).unwrap();
+        let expected = orbit(dt);
+        let err =
+            ((p.0 - expected.0).powi(2) + (p.1 - expected.1).powi(2) + (p.2 - expected.2).powi(2))
+                .sqrt();
+        assert!(err < 1.0E-3, "interpolation error {} m", err);
+        // not enough samples on either side
+        assert!(interpolate_orbit(&orbits, sv, t0 + Duration::from_seconds(1000.0)).is_none());
+        let other = Sv::new(Constellation::GPS, 2);
+        assert!(interpolate_orbit(&orbits, other, t0 + Duration::from_seconds(dt)).is_none());
+    }
+    #[test]
+    fn phase_windup() {
+        let rcvr = GroundPosition::from_geodetic((55.0, 8.0, 0.0)).to_ecef_wgs84();
+        let sun = (1.5E11, 0.0, 0.0);
+        let omega = 2.0 * std::f64::consts::PI / 43082.0;
+        let mut previous: Option<f64> = None;
+        for i in 0..200 {
+            let phi = omega * 60.0 * i as f64;
+            let sv = (26.56E6 * phi.cos(), 26.56E6 * phi.sin(), 5.0E6);
+            let w = windup(sv, rcvr, sun, previous);
+            if let Some(previous) = previous {
+                // unwrapped: continuous
+                assert!(
+                    (w - previous).abs() < 0.5,
+                    "wind up jump {} -> {}",
+                    previous,
+                    w
+                );
+            }
+            previous = Some(w);
+        }
+    }
+    #[test]
+    fn antenna_pcv() {
+        let model = AntennaModel {
+            pco: (0.0, 0.0, 0.1),
+            pcv: Some((0.0, 5.0, vec![0.0, 0.001, 0.003])),
+        };
+        assert_eq!(model.pcv(0.0), 0.0);
+        assert!((model.pcv(2.5) - 0.0005).abs() < 1.0E-12);
+        assert!((model.pcv(7.5) - 0.002).abs() < 1.0E-12);
+        // beyond last zenith angle
+        assert_eq!(model.pcv(80.0), 0.003);
+        assert_eq!(AntennaModel::default().pcv(30.0), 0.0);
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_precise_products() {
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let reference = obs.header.ground_position.unwrap().to_ecef_wgs84();
+        // first two hours
+        let mut record = obs.record.as_obs().unwrap().clone();
+        let t0 = record.keys().next().unwrap().0;
+        let t1 = t0 + Duration::from_hours(2.0);
+        record.retain(|(t, _), _| *t < t1);
+        let obs = obs.with_record(crate::record::Record::ObsRecord(record));
+
+        /*
+         * Precise products, derived from the broadcast ephemerides:
+         * center of mass orbits (5'), 30" clocks and satellite antenna offsets
+         */
+        let pco = (1.0, 0.0, 1.5); // [m], body frame
+        let ephemerides = index_ephemerides(&nav);
+        let mut orbits = PreciseOrbits::new();
+        let mut clocks = clocks::Record::new();
+        let mut antennas = crate::antex::Record::new();
+        for (sv, series) in &ephemerides {
+            let pair = match carrier_pair(sv.constellation) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            for i in -120..=360 {
+                let t = t0 + Duration::from_seconds(30.0 * i as f64);
+                let (toc, ephemeris) = match select_ephemeris(series, t) {
+                    Some(selected) => selected,
+                    None => continue,
+                };
+                let gpst = gnss_instant(t, Constellation::GPS);
+                if i % 10 == 0 {
+                    let apc = ephemeris.sv_position(gpst).unwrap();
+                    let (ex, ey, ez) = sv_body_frame(apc, sun_position(gpst));
+                    let com = (
+                        apc.0 - pco.0 * ex.0 - pco.1 * ey.0 - pco.2 * ez.0,
+                        apc.1 - pco.0 * ex.1 - pco.1 * ey.1 - pco.2 * ez.1,
+                        apc.2 - pco.0 * ex.2 - pco.1 * ey.2 - pco.2 * ez.2,
+                    );
+                    orbits.entry(t).or_default().insert(*sv, com);
+                }
+                let bias = broadcast_clock(gnss_instant(toc, sv.constellation), ephemeris, gpst);
+                clocks
+                    .entry(t)
+                    .or_default()
+                    .entry(clocks::DataType::AS)
+                    .or_default()
+                    .insert(
+                        clocks::System::Sv(*sv),
+                        clocks::Data {
+                            bias,
+                            ..Default::default()
+                        },
+                    );
+            }
+            let frequencies = [pair.0, pair.1]
+                .into_iter()
+                .map(|carrier| Frequency {
+                    carrier,
+                    north: pco.0 * 1.0E3,
+                    east: pco.1 * 1.0E3,
+                    up: pco.2 * 1.0E3,
+                    ..Default::default()
+                })
+                .collect();
+            antennas.push((
+                Antenna::default().with_serial_num(&sv.to_string()),
+                frequencies,
+            ));
+        }
+        let clocks = Rinex::new(
+            Header::default(),
+            crate::record::Record::ClockRecord(clocks),
+        );
+        let antex = Rinex::new(
+            Header::default(),
+            crate::record::Record::AntexRecord(antennas),
+        );
+
+        let distance = |p1: (f64, f64, f64), p2: (f64, f64, f64)| {
+            ((p1.0 - p2.0).powi(2) + (p1.1 - p2.1).powi(2) + (p1.2 - p2.2).powi(2)).sqrt()
+        };
+
+        let opts = PppOpts::default();
+        let solutions = solve(
+            &obs,
+            &nav,
+            Some(&orbits),
+            Some(&clocks),
+            Some(&antex),
+            &opts,
+        );
+        assert_eq!(solutions.len(), 240, "one solution per epoch is expected");
+        for (t, solution) in &solutions {
+            assert!(solution.clock.contains_key(&Constellation::GPS));
+            assert!(solution.clock.contains_key(&Constellation::Galileo));
+            // after convergence
+            if (*t - t0).to_seconds() > 3600.0 {
+                let err = distance(solution.position, reference);
+                assert!(
+                    err < 1.5,
+                    "{}: {:.2} m away from reference position",
+                    t,
+                    err
+                );
+            }
+        }
+        /*
+         * Antenna phase centers being restored, the precise products
+         * are equivalent to the broadcast products they were derived from,
+         * which is not the case if satellite antenna offsets are ignored
+         */
+        let broadcast = solve(&obs, &nav, None, None, None, &opts);
+        let biased = solve(&obs, &nav, Some(&orbits), Some(&clocks), None, &opts);
+        for (t, solution) in &solutions {
+            if (*t - t0).to_seconds() > 3600.0 {
+                let reference = broadcast[t].position;
+                let err = distance(solution.position, reference);
+                assert!(
+                    err < 0.15,
+                    "{}: {:.2} m away from broadcast solution",
+                    t,
+                    err
+                );
+                let err = distance(biased[t].position, reference);
+                assert!(
+                    err > 0.25,
+                    "{}: satellite antenna offsets should not be neglected",
+                    t
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,882 @@
+//! Relative positioning (RTK) between a base station and a rover,
+//! with integer ambiguity resolution
+use super::lambda::lambda;
+use super::ppp::{carrier_pair, dot};
+use super::spp::{self, earth_rotation, index_ephemerides, select_ephemeris, sv_state};
+use super::{
+    ecef2enu, elevation_azimuth, enu_covariance, MeteoConditions, TropoModel, SPEED_OF_LIGHT,
+};
+use crate::carrier::Carrier;
+use crate::differential::DiffContext;
+use crate::epoch::gnss_instant;
+use crate::observation::{LliFlags, ObservationData};
+use crate::prelude::*;
+use nalgebra::{DMatrix, DVector};
+use std::collections::{BTreeMap, HashMap};
+
+/// Relative positioning options
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RtkOpts<'a> {
+    /// Elevation mask [°]
+    pub elev_mask: f64,
+    /// Zenithal code noise [m], on one receiver
+    pub code_sigma: f64,
+    /// Zenithal phase noise [m], on one receiver
+    pub phase_sigma: f64,
+    /// Kinematic rover: the position is then modeled as a random walk
+    pub kinematic: bool,
+    /// Position random walk [m.s^-1/2], in kinematic mode
+    pub position_noise: f64,
+    /// Single difference geometry free phase jump [m]
+    /// above which a cycle slip is declared
+    pub slip_threshold: f64,
+    /// Observation gap [s] above which a new ambiguity arc starts
+    pub max_gap: f64,
+    /// Innovations above this many sigmas are rejected
+    pub outlier: f64,
+    /// Minimal ratio between the second best and the best
+    /// integer candidates, to validate the ambiguity resolution
+    pub ratio: f64,
+    /// Minimal number of double difference ambiguities
+    /// to attempt the ambiguity resolution
+    pub min_ambiguities: usize,
+    /// Tropospheric delay model
+    pub tropo_model: TropoModel,
+    /// Meteo RINEX, from which the surface conditions at the receivers
+    /// are interpolated at each epoch, see [MeteoConditions::from_meteo].
+    /// Standard atmosphere otherwise
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub meteo: Option<&'a Rinex>,
+}
+
+impl<'a> Default for RtkOpts<'a> {
+    fn default() -> Self {
+        Self {
+            elev_mask: 15.0,
+            code_sigma: 0.3,
+            phase_sigma: 0.003,
+            kinematic: false,
+            position_noise: 10.0,
+            slip_threshold: 0.05,
+            max_gap: 300.0,
+            outlier: 5.0,
+            ratio: 3.0,
+            min_ambiguities: 4,
+            tropo_model: TropoModel::default(),
+            meteo: None,
+        }
+    }
+}
+
+impl<'a> RtkOpts<'a> {
+    /// Returns options with given elevation mask [°]
+    pub fn with_elevation_mask(&self, mask: f64) -> Self {
+        let mut s = *self;
+        s.elev_mask = mask;
+        s
+    }
+    /// Returns options with given zenithal code noise [m]
+    pub fn with_code_sigma(&self, sigma: f64) -> Self {
+        let mut s = *self;
+        s.code_sigma = sigma;
+        s
+    }
+    /// Returns options with given zenithal phase noise [m]
+    pub fn with_phase_sigma(&self, sigma: f64) -> Self {
+        let mut s = *self;
+        s.phase_sigma = sigma;
+        s
+    }
+    /// Selects static or kinematic positioning
+    pub fn with_kinematic(&self, kinematic: bool) -> Self {
+        let mut s = *self;
+        s.kinematic = kinematic;
+        s
+    }
+    /// Returns options with given position random walk [m.s^-1/2]
+    pub fn with_position_noise(&self, noise: f64) -> Self {
+        let mut s = *self;
+        s.position_noise = noise;
+        s
+    }
+    /// Returns options with given cycle slip detection threshold [m]
+    pub fn with_slip_threshold(&self, threshold: f64) -> Self {
+        let mut s = *self;
+        s.slip_threshold = threshold;
+        s
+    }
+    /// Returns options with given maximal observation gap [s]
+    pub fn with_max_gap(&self, gap: f64) -> Self {
+        let mut s = *self;
+        s.max_gap = gap;
+        s
+    }
+    /// Returns options with given ambiguity validation ratio
+    pub fn with_ratio(&self, ratio: f64) -> Self {
+        let mut s = *self;
+        s.ratio = ratio;
+        s
+    }
+    /// Returns options with given minimal number of ambiguities
+    pub fn with_min_ambiguities(&self, min: usize) -> Self {
+        let mut s = *self;
+        s.min_ambiguities = min;
+        s
+    }
+    /// Returns options with given tropospheric delay model
+    pub fn with_tropo_model(&self, model: TropoModel) -> Self {
+        let mut s = *self;
+        s.tropo_model = model;
+        s
+    }
+    /// Returns options with given Meteo RINEX, from which the
+    /// surface conditions are interpolated, see [MeteoConditions::from_meteo]
+    pub fn with_meteo(&self, meteo: &'a Rinex) -> Self {
+        let mut s = *self;
+        s.meteo = Some(meteo);
+        s
+    }
+}
+
+/// Ambiguity resolution status
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum FixStatus {
+    /// Float ambiguities
+    #[default]
+    Float,
+    /// Integer ambiguities, validated by the ratio test
+    Fixed,
+}
+
+/// Relative positioning solution, at a given Epoch
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct RtkSolution {
+    /// Rover position, ECEF WGS84 [m]
+    pub position: (f64, f64, f64),
+    /// Baseline from the base station to the rover [m],
+    /// in the local East, North, Up frame of the base station
+    pub baseline: (f64, f64, f64),
+    /// Position standard deviation [m], in the local East, North, Up frame
+    pub std_enu: (f64, f64, f64),
+    /// Ambiguity resolution status
+    pub status: FixStatus,
+    /// Ratio between the second best and the best integer candidates,
+    /// 0 when the ambiguity resolution was not attempted
+    pub ratio: f64,
+    /// Number of double difference phase ambiguities
+    pub ambiguities: usize,
+    /// Reference vehicle of each constellation
+    pub references: HashMap<Constellation, Sv>,
+}
+
+impl RtkSolution {
+    /// Returns the baseline length [m]
+    pub fn baseline_length(&self) -> f64 {
+        let (e, n, u) = self.baseline;
+        (e.powi(2) + n.powi(2) + u.powi(2)).sqrt()
+    }
+    /// Returns true if ambiguities are resolved to integer values
+    pub fn is_fixed(&self) -> bool {
+        self.status == FixStatus::Fixed
+    }
+}
+
+/*
+ * Signal observed by both receivers, on one carrier
+ */
+struct Signal {
+    carrier: Carrier,
+    /// Phase single difference [cycles]
+    phase: f64,
+    /// Pseudo range single difference [m]
+    code: f64,
+    /// Rover pseudo range [m]
+    rover_pr: f64,
+    /// Base pseudo range [m]
+    base_pr: f64,
+    /// Lock loss flagged by either receiver
+    lock_loss: bool,
+}
+
+/*
+ * Selects the phase and pseudo range of given carrier,
+ * among observables that both receivers share
+ */
+fn signal(
+    constellation: Constellation,
+    carrier: Carrier,
+    rover: &HashMap<Observable, ObservationData>,
+    base: &HashMap<Observable, ObservationData>,
+) -> Option<Signal> {
+    let pick = |phase: bool| -> Option<(&ObservationData, &ObservationData)> {
+        rover
+            .iter()
+            .filter(|(observable, data)| {
+                let kind = if phase {
+                    observable.is_phase_observable()
+                } else {
+                    observable.is_pseudorange_observable()
+                };
+                kind && data.obs != 0.0 && observable.carrier(constellation) == Ok(carrier)
+            })
+            .filter_map(|(observable, data)| {
+                base.get(observable)
+                    .filter(|base| base.obs != 0.0)
+                    .map(|base| (observable, data, base))
+            })
+            .min_by_key(|(observable, _, _)| observable.to_string())
+            .map(|(_, rover, base)| (rover, base))
+    };
+    let (rover_l, base_l) = pick(true)?;
+    let (rover_c, base_c) = pick(false)?;
+    let lock_loss = [rover_l, base_l].iter().any(|data| {
+        data.lli
+            .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+            .unwrap_or(false)
+    });
+    Some(Signal {
+        carrier,
+        phase: rover_l.obs - base_l.obs,
+        code: rover_c.obs - base_c.obs,
+        rover_pr: rover_c.obs,
+        base_pr: base_c.obs,
+        lock_loss,
+    })
+}
+
+/*
+ * Measurement, for one Sv
+ */
+struct Measurement {
+    sv: Sv,
+    signals: Vec<Signal>,
+    /// Modeled range single difference [m], at the a priori rover position
+    modeled: f64,
+    /// Rover to Sv unit vector
+    los: (f64, f64, f64),
+    /// Elevation [°], seen from the rover
+    elev: f64,
+}
+
+/*
+ * Modeled range [m] (geometry, Sv clock, troposphere), line of sight and elevation,
+ * from given receiver position, at Epoch `t`
+ */
+fn range(
+    t: Epoch,
+    rcvr: (f64, f64, f64),
+    sv_position: (f64, f64, f64),
+    sv_clock: f64,
+    opts: &RtkOpts,
+) -> (f64, (f64, f64, f64), f64) {
+    let dx = (
+        sv_position.0 - rcvr.0,
+        sv_position.1 - rcvr.1,
+        sv_position.2 - rcvr.2,
+    );
+    let tau = dot(dx, dx).sqrt() / SPEED_OF_LIGHT;
+    let sv = earth_rotation(sv_position, tau);
+    let los = (sv.0 - rcvr.0, sv.1 - rcvr.1, sv.2 - rcvr.2);
+    let rho = dot(los, los).sqrt();
+    let (elev, _) = elevation_azimuth(rcvr, sv);
+    let (lat, lon, alt) = map_3d::ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, map_3d::Ellipsoid::WGS84);
+    let meteo = opts
+        .meteo
+        .and_then(|meteo| MeteoConditions::from_meteo(meteo, t, alt));
+    let tropo =
+        opts.tropo_model
+            .slant_delay(t, lat.to_degrees(), lon.to_degrees(), alt, elev, meteo);
+    (
+        rho - SPEED_OF_LIGHT * sv_clock + tropo,
+        (los.0 / rho, los.1 / rho, los.2 / rho),
+        elev,
+    )
+}
+
+/*
+ * Kalman filter state: rover position,
+ * then one single difference ambiguity [cycles] per Sv and carrier
+ */
+struct Filter {
+    x: DVector<f64>,
+    p: DMatrix<f64>,
+    params: Vec<(Sv, Carrier)>,
+}
+
+impl Filter {
+    fn new(position: (f64, f64, f64)) -> Self {
+        Self {
+            x: DVector::from_vec(vec![position.0, position.1, position.2]),
+            p: DMatrix::from_diagonal_element(3, 3, 30.0_f64.powi(2)),
+            params: Vec::new(),
+        }
+    }
+    fn position(&self) -> (f64, f64, f64) {
+        (self.x[0], self.x[1], self.x[2])
+    }
+    fn index(&self, param: (Sv, Carrier)) -> Option<usize> {
+        self.params.iter().position(|p| *p == param).map(|i| 3 + i)
+    }
+    /*
+     * Resets (or introduces) an ambiguity, uncorrelated to the other states
+     */
+    fn reset(&mut self, param: (Sv, Carrier), value: f64, variance: f64) -> usize {
+        let i = match self.index(param) {
+            Some(i) => i,
+            None => {
+                let n = self.x.len();
+                self.x = self.x.clone().insert_row(n, 0.0);
+                self.p = self.p.clone().insert_row(n, 0.0).insert_column(n, 0.0);
+                self.params.push(param);
+                n
+            },
+        };
+        self.x[i] = value;
+        self.p.row_mut(i).fill(0.0);
+        self.p.column_mut(i).fill(0.0);
+        self.p[(i, i)] = variance;
+        i
+    }
+    fn remove(&mut self, param: (Sv, Carrier)) {
+        if let Some(i) = self.index(param) {
+            self.x = self.x.clone().remove_row(i);
+            self.p = self.p.clone().remove_row(i).remove_column(i);
+            self.params.remove(i - 3);
+        }
+    }
+}
+
+/*
+ * Double difference row: (group, Sv, design row, innovation [m],
+ * Sv single difference variance [m²], reference single difference variance [m²])
+ * Rows of the same group share the reference vehicle.
+ */
+type Row = (
+    (Constellation, Carrier, bool),
+    Sv,
+    DVector<f64>,
+    f64,
+    f64,
+    f64,
+);
+
+/*
+ * Resolves the rover position at every epoch both receivers observed
+ */
+pub(crate) fn solve(
+    rover: &Rinex,
+    base: &Rinex,
+    nav: &Rinex,
+    opts: &RtkOpts,
+) -> BTreeMap<Epoch, RtkSolution> {
+    let mut ret: BTreeMap<Epoch, RtkSolution> = BTreeMap::new();
+    let base_position = match base.header.ground_position {
+        Some(position) => position.to_ecef_wgs84(),
+        None => return ret,
+    };
+    let ctx = match DiffContext::new(base, rover) {
+        Ok(ctx) => ctx,
+        Err(_) => return ret,
+    };
+    let (rover_rec, base_rec) = match (ctx.rover.record.as_obs(), ctx.base.record.as_obs()) {
+        (Some(rover), Some(base)) => (rover, base),
+        _ => return ret,
+    };
+    let ephemerides = index_ephemerides(nav);
+    // a priori rover positions
+    let mut spp_opts = spp::SppOpts::default()
+        .with_elevation_mask(opts.elev_mask)
+        .with_tropo_model(opts.tropo_model);
+    spp_opts.meteo = opts.meteo;
+    let apriori = spp::solve(rover, nav, &spp_opts);
+
+    let (base_lat, base_lon, _) = map_3d::ecef2geodetic(
+        base_position.0,
+        base_position.1,
+        base_position.2,
+        map_3d::Ellipsoid::WGS84,
+    );
+    let base_enu = ecef2enu(base_lat, base_lon);
+
+    let mut filter: Option<Filter> = None;
+    // (last epoch, single difference geometry free [m])
+    let mut arcs: HashMap<Sv, (Epoch, Option<f64>)> = HashMap::new();
+    let mut previous: Option<Epoch> = None;
+
+    for (k, (_, rover_svs)) in rover_rec {
+        let t = k.0;
+        let (_, base_svs) = match base_rec.get(k) {
+            Some(base) => base,
+            None => continue,
+        };
+        let filter = match (filter.as_mut(), apriori.get(&t)) {
+            (Some(filter), _) => filter,
+            (None, Some(spp)) => filter.insert(Filter::new(spp.position)),
+            (None, None) => continue,
+        };
+        let rcvr = filter.position();
+        let t_rx = gnss_instant(t, Constellation::GPS);
+
+        /*
+         * Measurements and ambiguity arcs
+         */
+        let mut measurements: Vec<Measurement> = Vec::new();
+        let mut slips: Vec<Sv> = Vec::new();
+        for (sv, rover_obs) in rover_svs {
+            let pair = match carrier_pair(sv.constellation) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let base_obs = match base_svs.get(sv) {
+                Some(base_obs) => base_obs,
+                None => continue,
+            };
+            let signals: Vec<Signal> = [pair.0, pair.1]
+                .iter()
+                .filter_map(|carrier| signal(sv.constellation, *carrier, rover_obs, base_obs))
+                .collect();
+            if signals.is_empty() {
+                continue;
+            }
+            let ephemeris = ephemerides
+                .get(sv)
+                .and_then(|ephemerides| select_ephemeris(ephemerides, t));
+            let (toc, ephemeris) = match ephemeris {
+                Some((toc, ephemeris)) => (gnss_instant(toc, sv.constellation), ephemeris),
+                None => continue,
+            };
+            let (rover_state, base_state) = match (
+                sv_state(toc, ephemeris, t_rx, signals[0].rover_pr),
+                sv_state(toc, ephemeris, t_rx, signals[0].base_pr),
+            ) {
+                (Some(rover), Some(base)) => (rover, base),
+                _ => continue,
+            };
+            let (rover_range, los, elev) =
+                range(t, rcvr, rover_state.position, rover_state.clock, opts);
+            let (base_range, _, _) = range(
+                t,
+                base_position,
+                base_state.position,
+                base_state.clock,
+                opts,
+            );
+
+            let gf = if signals.len() > 1 {
+                Some(
+                    signals[0].phase * signals[0].carrier.wavelength()
+                        - signals[1].phase * signals[1].carrier.wavelength(),
+                )
+            } else {
+                None
+            };
+            let slip = match arcs.get(sv) {
+                Some((last, prev_gf)) => {
+                    signals.iter().any(|s| s.lock_loss)
+                        || (t - *last).to_seconds() > opts.max_gap
+                        || match (gf, prev_gf) {
+                            (Some(gf), Some(prev_gf)) => (gf - prev_gf).abs() > opts.slip_threshold,
+                            _ => false,
+                        }
+                },
+                None => true,
+            };
+            if slip {
+                slips.push(*sv);
+            }
+            arcs.insert(*sv, (t, gf));
+            if elev < opts.elev_mask {
+                arcs.remove(sv);
+                continue;
+            }
+            measurements.push(Measurement {
+                sv: *sv,
+                signals,
+                modeled: rover_range - base_range,
+                los,
+                elev,
+            });
+        }
+
+        /*
+         * Time update
+         */
+        let dt = previous.map(|p| (t - p).to_seconds()).unwrap_or(0.0);
+        previous = Some(t);
+        if opts.kinematic {
+            for i in 0..3 {
+                filter.p[(i, i)] += opts.position_noise.powi(2) * dt;
+            }
+        }
+        for param in filter.params.clone() {
+            let retained = !slips.contains(&param.0)
+                && measurements
+                    .iter()
+                    .any(|m| m.sv == param.0 && m.signals.iter().any(|s| s.carrier == param.1));
+            if !retained {
+                filter.remove(param);
+            }
+        }
+        for m in &measurements {
+            for s in &m.signals {
+                if filter.index((m.sv, s.carrier)).is_none() {
+                    let lambda = s.carrier.wavelength();
+                    filter.reset(
+                        (m.sv, s.carrier),
+                        s.phase - s.code / lambda,
+                        (30.0 / lambda).powi(2),
+                    );
+                }
+            }
+        }
+
+        /*
+         * Reference vehicles: highest elevation, per constellation and carrier
+         */
+        let mut references: HashMap<(Constellation, Carrier), usize> = HashMap::new();
+        for (i, m) in measurements.iter().enumerate() {
+            for s in &m.signals {
+                let key = (m.sv.constellation, s.carrier);
+                let higher = references
+                    .get(&key)
+                    .map(|j| m.elev > measurements[*j].elev)
+                    .unwrap_or(true);
+                if higher {
+                    references.insert(key, i);
+                }
+            }
+        }
+
+        /*
+         * Double differences
+         */
+        let n = filter.x.len();
+        let variance =
+            |sigma: f64, elev: f64| 2.0 * sigma.powi(2) / elev.to_radians().sin().powi(2);
+        let mut rows: Vec<Row> = Vec::new();
+        for ((constellation, carrier), r) in &references {
+            let reference = &measurements[*r];
+            let ref_signal = reference
+                .signals
+                .iter()
+                .find(|s| s.carrier == *carrier)
+                .unwrap();
+            let lambda = carrier.wavelength();
+            for (i, m) in measurements.iter().enumerate() {
+                if i == *r || m.sv.constellation != *constellation {
+                    continue;
+                }
+                let signal = match m.signals.iter().find(|s| s.carrier == *carrier) {
+                    Some(signal) => signal,
+                    None => continue,
+                };
+                let mut h = DVector::<f64>::zeros(n);
+                h[0] = -m.los.0 + reference.los.0;
+                h[1] = -m.los.1 + reference.los.1;
+                h[2] = -m.los.2 + reference.los.2;
+                let modeled = m.modeled - reference.modeled;
+                rows.push((
+                    (*constellation, *carrier, false),
+                    m.sv,
+                    h.clone(),
+                    signal.code - ref_signal.code - modeled,
+                    variance(opts.code_sigma, m.elev),
+                    variance(opts.code_sigma, reference.elev),
+                ));
+                let (i_sv, i_ref) = match (
+                    filter.index((m.sv, *carrier)),
+                    filter.index((reference.sv, *carrier)),
+                ) {
+                    (Some(i_sv), Some(i_ref)) => (i_sv, i_ref),
+                    _ => continue,
+                };
+                h[i_sv] = lambda;
+                h[i_ref] = -lambda;
+                rows.push((
+                    (*constellation, *carrier, true),
+                    m.sv,
+                    h,
+                    lambda * (signal.phase - ref_signal.phase - filter.x[i_sv] + filter.x[i_ref])
+                        - modeled,
+                    variance(opts.phase_sigma, m.elev),
+                    variance(opts.phase_sigma, reference.elev),
+                ));
+            }
+        }
+        // innovation screening
+        let mut rejected: Vec<(Sv, Carrier)> = Vec::new();
+        rows.retain(|((_, carrier, phase), sv, h, v, var, ref_var)| {
+            let s = (h.transpose() * &filter.p * h)[(0, 0)] + var + ref_var;
+            if v.abs() > opts.outlier * s.sqrt() {
+                if *phase {
+                    rejected.push((*sv, *carrier));
+                }
+                false
+            } else {
+                true
+            }
+        });
+        if rows.len() < 3 {
+            continue;
+        }
+
+        /*
+         * Measurement update
+         */
+        let mut hm = DMatrix::<f64>::zeros(rows.len(), n);
+        let mut rm = DMatrix::<f64>::zeros(rows.len(), rows.len());
+        let mut v = DVector::<f64>::zeros(rows.len());
+        for (i, (group, _, h, innovation, var, ref_var)) in rows.iter().enumerate() {
+            hm.row_mut(i).copy_from(&h.transpose());
+            v[i] = *innovation;
+            // the reference vehicle correlates the double differences of a group
+            for (j, (rhs, _, _, _, _, _)) in rows.iter().enumerate() {
+                if rhs == group {
+                    rm[(i, j)] = *ref_var;
+                }
+            }
+            rm[(i, i)] += var;
+        }
+        let pht = &filter.p * hm.transpose();
+        let s = &hm * &pht + &rm;
+        let s_inv = match s.cholesky() {
+            Some(cholesky) => cholesky.inverse(),
+            None => continue,
+        };
+        let gain = &pht * s_inv;
+        filter.x += &gain * &v;
+        // Joseph form
+        let ikh = DMatrix::<f64>::identity(n, n) - &gain * &hm;
+        filter.p = &ikh * &filter.p * ikh.transpose() + &gain * &rm * gain.transpose();
+
+        /*
+         * Ambiguity resolution, on the double difference ambiguities
+         */
+        let mut dd: Vec<(usize, usize)> = Vec::new();
+        for ((constellation, carrier, phase), sv, _, _, _, _) in &rows {
+            if !*phase {
+                continue;
+            }
+            let reference = measurements[references[&(*constellation, *carrier)]].sv;
+            if let (Some(i_sv), Some(i_ref)) = (
+                filter.index((*sv, *carrier)),
+                filter.index((reference, *carrier)),
+            ) {
+                dd.push((i_sv, i_ref));
+            }
+        }
+        let mut position = filter.position();
+        let mut q_pos = filter.p.slice((0, 0), (3, 3)).clone_owned();
+        let mut status = FixStatus::Float;
+        let mut ratio = 0.0;
+        if dd.len() >= opts.min_ambiguities.max(1) {
+            let mut tm = DMatrix::<f64>::zeros(dd.len(), n);
+            for (i, (i_sv, i_ref)) in dd.iter().enumerate() {
+                tm[(i, *i_sv)] = 1.0;
+                tm[(i, *i_ref)] = -1.0;
+            }
+            let a = &tm * &filter.x;
+            let q_a = &tm * &filter.p * tm.transpose();
+            let q_xa = filter.p.rows(0, 3) * tm.transpose();
+            if let Some((candidates, distances)) = lambda(&a, &q_a, 2) {
+                if distances.len() > 1 {
+                    ratio = if distances[0] > 0.0 {
+                        distances[1] / distances[0]
+                    } else {
+                        f64::INFINITY
+                    };
+                }
+                if ratio >= opts.ratio {
+                    if let Some(q_a_inv) = q_a.clone().cholesky().map(|c| c.inverse()) {
+                        let gain = &q_xa * q_a_inv;
+                        let dx = &gain * (&a - &candidates[0]);
+                        position = (position.0 - dx[0], position.1 - dx[1], position.2 - dx[2]);
+                        q_pos -= &gain * q_xa.transpose();
+                        status = FixStatus::Fixed;
+                    }
+                }
+            }
+        }
+        // rejected phases start a new arc
+        for param in rejected {
+            filter.remove(param);
+            arcs.remove(&param.0);
+        }
+
+        let mut q = [[0.0_f64; 3]; 3];
+        for (i, q_i) in q.iter_mut().enumerate() {
+            for (j, q_ij) in q_i.iter_mut().enumerate() {
+                *q_ij = q_pos[(i, j)];
+            }
+        }
+        let q_enu = enu_covariance(position, q);
+        let b = (
+            position.0 - base_position.0,
+            position.1 - base_position.1,
+            position.2 - base_position.2,
+        );
+        let baseline = (
+            base_enu[0][0] * b.0 + base_enu[0][1] * b.1 + base_enu[0][2] * b.2,
+            base_enu[1][0] * b.0 + base_enu[1][1] * b.1 + base_enu[1][2] * b.2,
+            base_enu[2][0] * b.0 + base_enu[2][1] * b.1 + base_enu[2][2] * b.2,
+        );
+        let mut refs: HashMap<Constellation, Sv> = HashMap::new();
+        for ((constellation, _), i) in &references {
+            // first carrier reference
+            let sv = measurements[*i].sv;
+            let entry = refs.entry(*constellation).or_insert(sv);
+            if measurements[*i].elev > measurements.iter().find(|m| m.sv == *entry).unwrap().elev {
+                *entry = sv;
+            }
+        }
+        ret.insert(
+            t,
+            RtkSolution {
+                position,
+                baseline,
+                std_enu: (
+                    q_enu[0][0].max(0.0).sqrt(),
+                    q_enu[1][1].max(0.0).sqrt(),
+                    q_enu[2][2].max(0.0).sqrt(),
+                ),
+                status,
+                ratio,
+                ambiguities: dd.len(),
+                references: refs,
+            },
+        );
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn synthetic_short_baseline() {
+        let base = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let base_position = base.header.ground_position.unwrap().to_ecef_wgs84();
+        let (lat, lon, _) = map_3d::ecef2geodetic(
+            base_position.0,
+            base_position.1,
+            base_position.2,
+            map_3d::Ellipsoid::WGS84,
+        );
+        // rover: 80 m East, 60 m North, 1.5 m Up
+        let enu = ecef2enu(lat, lon);
+        let offset = (80.0, 60.0, 1.5);
+        let rover_position = (
+            base_position.0 + enu[0][0] * offset.0 + enu[1][0] * offset.1 + enu[2][0] * offset.2,
+            base_position.1 + enu[0][1] * offset.0 + enu[1][1] * offset.1 + enu[2][1] * offset.2,
+            base_position.2 + enu[0][2] * offset.0 + enu[1][2] * offset.1 + enu[2][2] * offset.2,
+        );
+        /*
+         * Rover observations: base observations shifted by the modeled range difference,
+         * with arbitrary integer ambiguities and white noise
+         */
+        let ephemerides = index_ephemerides(&nav);
+        let mut seed = 0x2545_F491_4F6C_DD1D_u64;
+        let mut noise = |sigma: f64| -> f64 {
+            // sum of uniform draws, approximately gaussian
+            (0..12)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    (seed >> 11) as f64 / (1_u64 << 53) as f64
+                })
+                .sum::<f64>()
+                * sigma
+                - 6.0 * sigma
+        };
+        let mut rover = base.clone();
+        let record = rover.record.as_mut_obs().unwrap();
+        let t0 = record.keys().next().unwrap().0;
+        record.retain(|(t, _), _| (*t - t0).to_seconds() < 3600.0);
+        for ((t, _), (_, svs)) in record.iter_mut() {
+            let t_rx = gnss_instant(*t, Constellation::GPS);
+            svs.retain(|sv, _| carrier_pair(sv.constellation).is_some());
+            for (sv, observations) in svs.iter_mut() {
+                let pr = observations
+                    .iter()
+                    .find(|(observable, _)| observable.is_pseudorange_observable())
+                    .map(|(_, data)| data.obs);
+                let ephemeris = ephemerides
+                    .get(sv)
+                    .and_then(|ephemerides| select_ephemeris(ephemerides, *t));
+                let state = match (pr, ephemeris) {
+                    (Some(pr), Some((toc, ephemeris))) => {
+                        sv_state(gnss_instant(toc, sv.constellation), ephemeris, t_rx, pr)
+                    },
+                    _ => None,
+                };
+                let state = match state {
+                    Some(state) => state,
+                    None => {
+                        observations.clear();
+                        continue;
+                    },
+                };
+                let opts = RtkOpts::default();
+                let delta = range(*t, rover_position, state.position, state.clock, &opts).0
+                    - range(*t, base_position, state.position, state.clock, &opts).0;
+                let ambiguity = (sv.prn * 7 % 13) as f64 - 6.0;
+                for (observable, data) in observations.iter_mut() {
+                    if observable.is_pseudorange_observable() {
+                        data.obs += delta + noise(0.3);
+                    } else if observable.is_phase_observable() {
+                        if let Ok(carrier) = observable.carrier(sv.constellation) {
+                            let lambda = carrier.wavelength();
+                            data.obs += (delta + noise(0.002)) / lambda + ambiguity;
+                        }
+                    }
+                }
+            }
+        }
+
+        let solutions = solve(&rover, &base, &nav, &RtkOpts::default());
+        assert!(solutions.len() > 100);
+        let mut fixed = 0;
+        for (t, solution) in &solutions {
+            assert!(solution.references.contains_key(&Constellation::GPS));
+            if solution.is_fixed() {
+                fixed += 1;
+                assert!(solution.ratio >= 3.0);
+                assert!(solution.ambiguities >= 4);
+                let (e, n, u) = solution.baseline;
+                assert!(
+                    (e - offset.0).abs() < 0.01
+                        && (n - offset.1).abs() < 0.01
+                        && (u - offset.2).abs() < 0.02,
+                    "{}: fixed baseline ({:.3}, {:.3}, {:.3})",
+                    t,
+                    e,
+                    n,
+                    u
+                );
+            }
+        }
+        // after a few epochs of convergence, ambiguities are resolved
+        assert!(
+            fixed > solutions.len() * 9 / 10,
+            "{} fixed solutions",
+            fixed
+        );
+        let (_, last) = solutions.iter().last().unwrap();
+        assert!(last.is_fixed());
+        assert!((last.baseline_length() - 100.011).abs() < 0.01);
+    }
+}
@@ -0,0 +1,604 @@
+//! Single Point Positioning (SPP)
+use super::{
+    elevation_azimuth, enu_covariance, Dop, MeteoConditions, Solution, TropoModel, SPEED_OF_LIGHT,
+};
+use crate::carrier::Carrier;
+use crate::epoch::gnss_instant;
+use crate::navigation::{sisre::broadcast_clock, Ephemeris, KbModel};
+use crate::observation::ObservationData;
+use crate::prelude::*;
+use nalgebra::{DMatrix, DVector};
+use std::collections::{BTreeMap, HashMap};
+
+/// (Sv, line of sight, prefit residual [m], weight)
+type Row = (Sv, (f64, f64, f64), f64, f64);
+
+/// Earth rotation rate in WGS84 frame [rad.s⁻¹]
+const EARTH_OMEGA_E_WGS84: f64 = 7.2921151467E-5;
+
+/// Single Point Positioning options
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SppOpts<'a> {
+    /// Elevation mask [°]
+    pub elev_mask: f64,
+    /// Maximal number of iterations, per epoch
+    pub max_iter: usize,
+    /// Iterations stop once the position correction is below this threshold [m]
+    pub convergence: f64,
+    /// Compensate the ionospheric delay, using broadcast models
+    pub iono: bool,
+    /// Compensate the tropospheric delay
+    pub tropo: bool,
+    /// Tropospheric delay model
+    pub tropo_model: TropoModel,
+    /// Meteo RINEX, from which the surface conditions at the receiver
+    /// are interpolated at each epoch, see [MeteoConditions::from_meteo].
+    /// Standard atmosphere otherwise
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub meteo: Option<&'a Rinex>,
+}
+
+impl<'a> Default for SppOpts<'a> {
+    fn default() -> Self {
+        Self {
+            elev_mask: 10.0,
+            max_iter: 20,
+            convergence: 1.0E-4,
+            iono: true,
+            tropo: true,
+            tropo_model: TropoModel::default(),
+            meteo: None,
+        }
+    }
+}
+
+impl<'a> SppOpts<'a> {
+    /// Returns options with given elevation mask [°]
+    pub fn with_elevation_mask(&self, mask: f64) -> Self {
+        let mut s = *self;
+        s.elev_mask = mask;
+        s
+    }
+    /// Returns options with given maximal number of iterations
+    pub fn with_max_iter(&self, max_iter: usize) -> Self {
+        let mut s = *self;
+        s.max_iter = max_iter;
+        s
+    }
+    /// Returns options with given convergence threshold [m]
+    pub fn with_convergence(&self, threshold: f64) -> Self {
+        let mut s = *self;
+        s.convergence = threshold;
+        s
+    }
+    /// Enables or disables the ionospheric compensation
+    pub fn with_iono(&self, iono: bool) -> Self {
+        let mut s = *self;
+        s.iono = iono;
+        s
+    }
+    /// Enables or disables the tropospheric compensation
+    pub fn with_tropo(&self, tropo: bool) -> Self {
+        let mut s = *self;
+        s.tropo = tropo;
+        s
+    }
+    /// Returns options with given tropospheric delay model
+    pub fn with_tropo_model(&self, model: TropoModel) -> Self {
+        let mut s = *self;
+        s.tropo_model = model;
+        s
+    }
+    /// Returns options with given Meteo RINEX, from which the
+    /// surface conditions are interpolated, see [MeteoConditions::from_meteo]
+    pub fn with_meteo(&self, meteo: &'a Rinex) -> Self {
+        let mut s = *self;
+        s.meteo = Some(meteo);
+        s
+    }
+}
+
+/*
+ * Pseudo range reference carrier, per constellation:
+ * the signal the broadcast clock (group delay) refers to.
+ * Glonass and SBAS are not supported.
+ */
+fn reference_carrier(constellation: Constellation) -> Option<Carrier> {
+    match constellation {
+        Constellation::GPS | Constellation::QZSS => Some(Carrier::L1),
+        Constellation::Galileo => Some(Carrier::E1),
+        Constellation::BeiDou => Some(Carrier::B1I),
+        Constellation::IRNSS => Some(Carrier::L5),
+        _ => None,
+    }
+}
+
+/*
+ * Broadcast group delay [s] on the reference carrier
+ */
+fn group_delay(constellation: Constellation, ephemeris: &Ephemeris) -> f64 {
+    let field = match constellation {
+        Constellation::Galileo => "bgdE5bE1",
+        Constellation::BeiDou => "tgd1b1b3",
+        _ => "tgd",
+    };
+    ephemeris.get_orbit_f64(field).unwrap_or(0.0)
+}
+
+/*
+ * Selects the pseudo range on the reference carrier,
+ * prefering codes in alphabetical order (C1C prior C1W for example)
+ */
+fn pseudo_range(
+    constellation: Constellation,
+    observations: &HashMap<Observable, ObservationData>,
+) -> Option<(Carrier, f64)> {
+    let reference = reference_carrier(constellation)?;
+    observations
+        .iter()
+        .filter(|(observable, data)| {
+            observable.is_pseudorange_observable()
+                && data.obs > 0.0
+                && observable.carrier(constellation) == Ok(reference)
+        })
+        .min_by_key(|(observable, _)| observable.to_string())
+        .map(|(_, data)| (reference, data.obs))
+}
+
+/*
+ * Selects the doppler observation [Hz] on the reference carrier
+ */
+fn doppler(
+    constellation: Constellation,
+    observations: &HashMap<Observable, ObservationData>,
+) -> Option<f64> {
+    let reference = reference_carrier(constellation)?;
+    observations
+        .iter()
+        .filter(|(observable, data)| {
+            observable.is_doppler_observable()
+                && data.obs != 0.0
+                && observable.carrier(constellation) == Ok(reference)
+        })
+        .min_by_key(|(observable, _)| observable.to_string())
+        .map(|(_, data)| data.obs)
+}
+
+/*
+ * Sv state at signal transmission
+ */
+pub(crate) struct SvState {
+    /// Position at transmission time, ECEF [m]
+    pub position: (f64, f64, f64),
+    /// Velocity at transmission time, ECEF [m.s⁻¹]
+    pub velocity: (f64, f64, f64),
+    /// Clock offset [s], including relativistic effect
+    pub clock: f64,
+    /// Clock drift [s.s⁻¹]
+    pub clock_drift: f64,
+}
+
+/*
+ * Sv state at signal transmission, from broadcast ephemeris.
+ * The clock offset does not include the group delay,
+ * it is referenced to the dual frequency ionosphere free combination.
+ */
+pub(crate) fn sv_state(toc: Epoch, ephemeris: &Ephemeris, t_rx: Epoch, pr: f64) -> Option<SvState> {
+    let mut t_tx = t_rx - Duration::from_seconds(pr / SPEED_OF_LIGHT);
+    t_tx -= Duration::from_seconds(broadcast_clock(toc, ephemeris, t_tx));
+    let position = ephemeris.sv_position(t_tx)?;
+    let dt = Duration::from_seconds(0.5);
+    let before = ephemeris.sv_position(t_tx - dt)?;
+    let after = ephemeris.sv_position(t_tx + dt)?;
+    let velocity = (after.0 - before.0, after.1 - before.1, after.2 - before.2);
+    let relativistic = -2.0
+        * (position.0 * velocity.0 + position.1 * velocity.1 + position.2 * velocity.2)
+        / SPEED_OF_LIGHT.powi(2);
+    let clock = broadcast_clock(toc, ephemeris, t_tx) + relativistic;
+    let (_, a1, a2) = ephemeris.sv_clock();
+    let clock_drift = a1 + 2.0 * a2 * (t_tx - toc).to_seconds();
+    Some(SvState {
+        position,
+        velocity,
+        clock,
+        clock_drift,
+    })
+}
+
+/*
+ * Broadcast Klobuchar models, per constellation, with their reference carrier
+ */
+fn klobuchar_models(nav: &Rinex) -> HashMap<Constellation, (KbModel, Carrier)> {
+    let mut models: HashMap<Constellation, KbModel> = HashMap::new();
+    for (constellation, model) in &nav.header.ionod_corrections {
+        if let Some(kb) = model.as_klobuchar() {
+            models.insert(*constellation, *kb);
+        }
+    }
+    for (_, (_, sv, model)) in nav.ionosphere_models() {
+        if let Some(kb) = model.as_klobuchar() {
+            models.insert(sv.constellation, *kb);
+        }
+    }
+    models
+        .into_iter()
+        .map(|(constellation, model)| {
+            let carrier = match constellation {
+                Constellation::BeiDou => Carrier::B1I,
+                _ => Carrier::L1,
+            };
+            (constellation, (model, carrier))
+        })
+        .collect()
+}
+
+pub(crate) struct Candidate {
+    pub sv: Sv,
+    pub carrier: Carrier,
+    /// Pseudo range [m]
+    pub pr: f64,
+    /// Doppler [Hz], if observed
+    pub doppler: Option<f64>,
+    pub state: SvState,
+}
+
+/*
+ * Rotates an ECEF vector by the Earth rotation during `tau` [s]:
+ * expresses a vector of the transmission frame in the reception frame.
+ */
+pub(crate) fn earth_rotation(v: (f64, f64, f64), tau: f64) -> (f64, f64, f64) {
+    let theta = EARTH_OMEGA_E_WGS84 * tau;
+    (
+        v.0 * theta.cos() + v.1 * theta.sin(),
+        -v.0 * theta.sin() + v.1 * theta.cos(),
+        v.2,
+    )
+}
+
+/*
+ * Single epoch iterative weighted least squares.
+ * `apriori` is the initial position: once it is known (not the Earth center),
+ * elevation mask, elevation weighting and atmospheric delays are applied.
+ */
+fn solve_epoch(
+    t: Epoch,
+    candidates: &[Candidate],
+    kb_models: &HashMap<Constellation, (KbModel, Carrier)>,
+    apriori: (f64, f64, f64),
+    opts: &SppOpts,
+) -> Option<Solution> {
+    let mut position = apriori;
+    let mut clocks: BTreeMap<Constellation, f64> = BTreeMap::new(); // [m]
+    for iter in 0..opts.max_iter {
+        let known = (position.0.powi(2) + position.1.powi(2) + position.2.powi(2)).sqrt() > 1.0E6;
+        let (lat, lon, alt) =
+            map_3d::ecef2geodetic(position.0, position.1, position.2, map_3d::Ellipsoid::WGS84);
+        let (lat, lon) = (lat.to_degrees(), lon.to_degrees());
+        let meteo = opts
+            .meteo
+            .and_then(|meteo| MeteoConditions::from_meteo(meteo, t, alt));
+
+        let mut rows: Vec<Row> = Vec::new();
+        for candidate in candidates {
+            let (x, y, z) = candidate.state.position;
+            // earth rotation during signal propagation
+            let dx = (x - position.0, y - position.1, z - position.2);
+            let tau = (dx.0.powi(2) + dx.1.powi(2) + dx.2.powi(2)).sqrt() / SPEED_OF_LIGHT;
+            let sv_pos = earth_rotation(candidate.state.position, tau);
+            let los = (
+                sv_pos.0 - position.0,
+                sv_pos.1 - position.1,
+                sv_pos.2 - position.2,
+            );
+            let rho = (los.0.powi(2) + los.1.powi(2) + los.2.powi(2)).sqrt();
+            let mut weight = 1.0_f64;
+            let mut delays = 0.0_f64;
+            if known {
+                let (elev, azim) = elevation_azimuth(position, sv_pos);
+                if elev < opts.elev_mask {
+                    continue;
+                }
+                weight = elev.to_radians().sin().powi(2);
+                if opts.iono {
+                    let model = kb_models
+                        .get(&candidate.sv.constellation)
+                        .or(kb_models.get(&Constellation::GPS));
+                    if let Some((model, reference)) = model {
+                        let scaling =
+                            (reference.frequency() / candidate.carrier.frequency()).powi(2);
+                        delays += model.delay(t, elev, azim, lat, lon) * SPEED_OF_LIGHT * scaling;
+                    }
+                }
+                if opts.tropo {
+                    delays += opts.tropo_model.slant_delay(t, lat, lon, alt, elev, meteo);
+                }
+            }
+            let clock = clocks.get(&candidate.sv.constellation).unwrap_or(&0.0);
+            let predicted = rho + clock - SPEED_OF_LIGHT * candidate.state.clock + delays;
+            rows.push((
+                candidate.sv,
+                (los.0 / rho, los.1 / rho, los.2 / rho),
+                candidate.pr - predicted,
+                weight,
+            ));
+        }
+        // one clock per constellation
+        let mut constellations: Vec<Constellation> =
+            rows.iter().map(|(sv, _, _, _)| sv.constellation).collect();
+        constellations.sort();
+        constellations.dedup();
+        let n_unknowns = 3 + constellations.len();
+        if rows.len() < n_unknowns {
+            return None;
+        }
+        let mut h = DMatrix::<f64>::zeros(rows.len(), n_unknowns);
+        let mut w = DMatrix::<f64>::zeros(rows.len(), rows.len());
+        let mut y = DVector::<f64>::zeros(rows.len());
+        for (i, (sv, los, residual, weight)) in rows.iter().enumerate() {
+            h[(i, 0)] = -los.0;
+            h[(i, 1)] = -los.1;
+            h[(i, 2)] = -los.2;
+            let clk_index = constellations
+                .iter()
+                .position(|c| *c == sv.constellation)
+                .unwrap_or(0);
+            h[(i, 3 + clk_index)] = 1.0;
+            w[(i, i)] = *weight;
+            y[i] = *residual;
+        }
+        let ht = h.transpose();
+        let q = (&ht * &w * &h).try_inverse()?;
+        let dx = &q * &ht * &w * &y;
+
+        position.0 += dx[0];
+        position.1 += dx[1];
+        position.2 += dx[2];
+        for (i, constellation) in constellations.iter().enumerate() {
+            *clocks.entry(*constellation).or_insert(0.0) += dx[3 + i];
+        }
+        clocks.retain(|c, _| constellations.contains(c));
+
+        let correction = (dx[0].powi(2) + dx[1].powi(2) + dx[2].powi(2)).sqrt();
+        if known && correction < opts.convergence {
+            let postfit = &y - &h * &dx;
+            let residuals: HashMap<Sv, f64> = rows
+                .iter()
+                .enumerate()
+                .map(|(i, (sv, _, _, _))| (*sv, postfit[i]))
+                .collect();
+            let dop = dop(&h, position)?;
+            return Some(Solution {
+                position,
+                clock: clocks
+                    .iter()
+                    .map(|(c, offset)| (*c, offset / SPEED_OF_LIGHT))
+                    .collect(),
+                dop,
+                residuals,
+            });
+        }
+        if iter == opts.max_iter - 1 {
+            break;
+        }
+    }
+    None
+}
+
+/*
+ * Dilution of precision, from the (unweighted) design matrix
+ */
+fn dop(h: &DMatrix<f64>, position: (f64, f64, f64)) -> Option<Dop> {
+    let q = (h.transpose() * h).try_inverse()?;
+    let mut q_xyz = [[0.0_f64; 3]; 3];
+    for (i, q_i) in q_xyz.iter_mut().enumerate() {
+        for (j, q_ij) in q_i.iter_mut().enumerate() {
+            *q_ij = q[(i, j)];
+        }
+    }
+    let q_enu = enu_covariance(position, q_xyz);
+    let hdop = (q_enu[0][0] + q_enu[1][1]).sqrt();
+    let vdop = q_enu[2][2].sqrt();
+    let pdop = (q[(0, 0)] + q[(1, 1)] + q[(2, 2)]).sqrt();
+    let tdop = q[(3, 3)].sqrt();
+    Some(Dop {
+        gdop: (pdop.powi(2) + tdop.powi(2)).sqrt(),
+        pdop,
+        hdop,
+        vdop,
+        tdop,
+    })
+}
+
+/*
+ * Keplerian ephemerides, per Sv, sorted by time of clock
+ */
+pub(crate) type Ephemerides<'a> = HashMap<Sv, BTreeMap<Epoch, &'a Ephemeris>>;
+
+pub(crate) fn index_ephemerides(nav: &Rinex) -> Ephemerides<'_> {
+    let mut ephemerides: Ephemerides = HashMap::new();
+    for (toc, (_, sv, ephemeris)) in nav.ephemeris() {
+        if ephemeris.kepler().is_some() {
+            ephemerides.entry(*sv).or_default().insert(*toc, ephemeris);
+        }
+    }
+    ephemerides
+}
+
+/*
+ * Selects the ephemeris closest to `t`, within 4 hours
+ */
+pub(crate) fn select_ephemeris<'a>(
+    ephemerides: &BTreeMap<Epoch, &'a Ephemeris>,
+    t: Epoch,
+) -> Option<(Epoch, &'a Ephemeris)> {
+    let validity = Duration::from_hours(4.0);
+    let before = ephemerides.range(..=t).next_back();
+    let after = ephemerides.range(t..).next();
+    [before, after]
+        .into_iter()
+        .flatten()
+        .filter(|(toc, _)| (t - **toc).abs() <= validity)
+        .min_by_key(|(toc, _)| (t - **toc).abs())
+        .map(|(toc, ephemeris)| (*toc, *ephemeris))
+}
+
+/*
+ * Resolves the navigation solution, at every epoch of the `obs` RINEX
+ */
+pub(crate) fn solve(obs: &Rinex, nav: &Rinex, opts: &SppOpts) -> BTreeMap<Epoch, Solution> {
+    let mut ret: BTreeMap<Epoch, Solution> = BTreeMap::new();
+    resolve(obs, nav, opts, |t, _, solution| {
+        ret.insert(t, solution);
+    });
+    ret
+}
+
+/*
+ * Resolves the navigation solution at every epoch of the `obs` RINEX,
+ * and hands it over with the candidates it was resolved from.
+ */
+pub(crate) fn resolve<F: FnMut(Epoch, Vec<Candidate>, Solution)>(
+    obs: &Rinex,
+    nav: &Rinex,
+    opts: &SppOpts,
+    mut f: F,
+) {
+    let record = match obs.record.as_obs() {
+        Some(record) => record,
+        None => return,
+    };
+    let ephemerides = index_ephemerides(nav);
+    let kb_models = klobuchar_models(nav);
+
+    let mut apriori = match obs.header.ground_position {
+        Some(position) => position.to_ecef_wgs84(),
+        None => (0.0, 0.0, 0.0),
+    };
+    for ((t, flag), (_, svs)) in record {
+        if !flag.is_ok() {
+            continue;
+        }
+        let t_rx = gnss_instant(*t, Constellation::GPS);
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for (sv, observations) in svs {
+            if sv.constellation == Constellation::BeiDou && (sv.prn < 6 || sv.prn > 58) {
+                continue; // GEO vehicles are not supported
+            }
+            let (carrier, pr) = match pseudo_range(sv.constellation, observations) {
+                Some(pr) => pr,
+                None => continue,
+            };
+            let ephemeris = ephemerides
+                .get(sv)
+                .and_then(|ephemerides| select_ephemeris(ephemerides, *t));
+            if let Some((toc, ephemeris)) = ephemeris {
+                let toc = gnss_instant(toc, sv.constellation);
+                if let Some(mut state) = sv_state(toc, ephemeris, t_rx, pr) {
+                    state.clock -= group_delay(sv.constellation, ephemeris);
+                    candidates.push(Candidate {
+                        sv: *sv,
+                        carrier,
+                        pr,
+                        doppler: doppler(sv.constellation, observations),
+                        state,
+                    });
+                }
+            }
+        }
+        if let Some(solution) = solve_epoch(t_rx, &candidates, &kb_models, apriori, opts) {
+            apriori = solution.position;
+            f(*t, candidates, solution);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::positioning::ecef2enu;
+    use crate::sv;
+    use std::str::FromStr;
+    #[test]
+    fn synthetic_solution() {
+        // noise free pseudo ranges, two constellations
+        let rcvr = GroundPosition::from_geodetic((52.0, 4.3, 50.0)).to_ecef_wgs84();
+        let t = Epoch::from_gregorian_utc_at_midnight(2021, 1, 1);
+        let (clk_gps, clk_gal) = (1.0E-4, 1.0E-4 + 20.0E-9);
+        let mut candidates = Vec::new();
+        let mut prn = 1;
+        for (elev, azim) in [
+            (80.0_f64, 0.0_f64),
+            (45.0, 30.0),
+            (30.0, 120.0),
+            (20.0, 200.0),
+            (35.0, 280.0),
+            (60.0, 330.0),
+            (25.0, 70.0),
+        ] {
+            let (lat, lon) = (52.0_f64.to_radians(), 4.3_f64.to_radians());
+            let r = ecef2enu(lat, lon);
+            let (elev, azim) = (elev.to_radians(), azim.to_radians());
+            let enu = (elev.cos() * azim.sin(), elev.cos() * azim.cos(), elev.sin());
+            let range = 22.0E6;
+            let ecef = (
+                r[0][0] * enu.0 + r[1][0] * enu.1 + r[2][0] * enu.2,
+                r[0][1] * enu.0 + r[1][1] * enu.1 + r[2][1] * enu.2,
+                r[0][2] * enu.0 + r[1][2] * enu.1 + r[2][2] * enu.2,
+            );
+            // position at reception, moved back to its transmission frame
+            let sv_rx = (
+                rcvr.0 + range * ecef.0,
+                rcvr.1 + range * ecef.1,
+                rcvr.2 + range * ecef.2,
+            );
+            let theta = EARTH_OMEGA_E_WGS84 * range / SPEED_OF_LIGHT;
+            let sv_tx = (
+                sv_rx.0 * theta.cos() - sv_rx.1 * theta.sin(),
+                sv_rx.0 * theta.sin() + sv_rx.1 * theta.cos(),
+                sv_rx.2,
+            );
+            let (sv, clk) = if prn % 2 == 0 {
+                (Sv::new(Constellation::Galileo, prn), clk_gal)
+            } else {
+                (Sv::new(Constellation::GPS, prn), clk_gps)
+            };
+            candidates.push(Candidate {
+                sv,
+                carrier: Carrier::L1,
+                pr: range + SPEED_OF_LIGHT * (clk - 1.0E-5),
+                doppler: None,
+                state: SvState {
+                    position: sv_tx,
+                    velocity: (0.0, 0.0, 0.0),
+                    clock: 1.0E-5,
+                    clock_drift: 0.0,
+                },
+            });
+            prn += 1;
+        }
+        let opts = SppOpts::default().with_iono(false).with_tropo(false);
+        let solution = solve_epoch(t, &candidates, &HashMap::new(), (0.0, 0.0, 0.0), &opts)
+            .expect("failed to resolve synthetic solution");
+        assert!((solution.position.0 - rcvr.0).abs() < 1.0E-3);
+        assert!((solution.position.1 - rcvr.1).abs() < 1.0E-3);
+        assert!((solution.position.2 - rcvr.2).abs() < 1.0E-3);
+        assert_eq!(solution.reference_clock().unwrap().0, Constellation::GPS);
+        assert!((solution.clock[&Constellation::GPS] - clk_gps).abs() < 1.0E-11);
+        let isb = solution.isb(Constellation::Galileo).unwrap();
+        assert!((isb - 20.0E-9).abs() < 1.0E-11);
+        assert_eq!(solution.residuals.len(), 7);
+        for (_, residual) in solution.residuals {
+            assert!(residual.abs() < 1.0E-3);
+        }
+        assert!(solution.dop.pdop > solution.dop.hdop);
+        assert!(solution.dop.gdop > solution.dop.pdop);
+        // elevation mask
+        let opts = opts.with_elevation_mask(50.0);
+        assert!(solve_epoch(t, &candidates, &HashMap::new(), rcvr, &opts).is_none());
+        // not enough vehicles
+        let opts = opts.with_elevation_mask(0.0);
+        assert!(solve_epoch(t, &candidates[..4], &HashMap::new(), rcvr, &opts).is_none());
+        assert_eq!(reference_carrier(sv!("R01").constellation), None);
+    }
+}
@@ -0,0 +1,779 @@
+//! IONEX maps generation, from a network of ground stations
+use super::geometry::sv_elevation_azimuth;
+use super::ppp::{carrier_pair, select};
+use super::spp::{index_ephemerides, Ephemerides};
+use super::SPEED_OF_LIGHT;
+use crate::ionex::{self, record::MapPoint, BiasSource, GridLinspace, RefSystem};
+use crate::observation::LliFlags;
+use crate::prelude::*;
+use nalgebra::{DMatrix, DVector};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Earth rotation rate [°.s⁻¹], to express pierce points in a Sun fixed frame
+const EARTH_ROTATION_DEG_S: f64 = 360.0 / 86400.0;
+
+/// TEC map fitting technique
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TecFit {
+    /// Spherical harmonics expansion, up to given degree and order,
+    /// of the vertical TEC in a Sun fixed frame
+    SphericalHarmonics {
+        /// Maximal degree and order
+        degree: usize,
+    },
+    /// Ordinary kriging, using an exponential variogram
+    /// of given practical range [km]
+    Kriging {
+        /// Practical range [km]
+        range: f64,
+    },
+}
+
+impl Default for TecFit {
+    fn default() -> Self {
+        Self::SphericalHarmonics { degree: 4 }
+    }
+}
+
+/// IONEX maps generation options
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IonexOpts {
+    /// Latitude grid [°]
+    pub latitude: GridLinspace,
+    /// Longitude grid [°]
+    pub longitude: GridLinspace,
+    /// Single layer (shell) height [km]
+    pub shell_height: f64,
+    /// Time interval between two maps
+    pub interval: Duration,
+    /// Elevation mask [°]
+    pub elev_mask: f64,
+    /// Geometry free phase jump [m] above which a cycle slip is declared
+    pub slip_threshold: f64,
+    /// Observation gap [s] above which a new leveling arc starts
+    pub max_gap: f64,
+    /// Arcs shorter than this many samples are not leveled, but dropped
+    pub min_arc: usize,
+    /// Pierce points are averaged into normal points of this duration,
+    /// per station and Sv, prior fitting
+    pub normal_point: Duration,
+    /// Grid nodes further than this distance [km] from any
+    /// normal point are not estimated (9999 in the IONEX file)
+    pub coverage: f64,
+    /// Fitting technique
+    pub fit: TecFit,
+}
+
+impl Default for IonexOpts {
+    fn default() -> Self {
+        Self {
+            latitude: GridLinspace::from((87.5, -87.5, -2.5)),
+            longitude: GridLinspace::from((-180.0, 180.0, 5.0)),
+            shell_height: 450.0,
+            interval: Duration::from_hours(1.0),
+            elev_mask: 10.0,
+            slip_threshold: 0.05,
+            max_gap: 300.0,
+            min_arc: 10,
+            normal_point: Duration::from_seconds(300.0),
+            coverage: 1500.0,
+            fit: TecFit::default(),
+        }
+    }
+}
+
+impl IonexOpts {
+    /// Returns options with given latitude and longitude grids [°]
+    pub fn with_grid(&self, latitude: GridLinspace, longitude: GridLinspace) -> Self {
+        let mut s = self.clone();
+        s.latitude = latitude;
+        s.longitude = longitude;
+        s
+    }
+    /// Returns options with given shell height [km]
+    pub fn with_shell_height(&self, height: f64) -> Self {
+        let mut s = self.clone();
+        s.shell_height = height;
+        s
+    }
+    /// Returns options with given interval between two maps
+    pub fn with_interval(&self, interval: Duration) -> Self {
+        let mut s = self.clone();
+        s.interval = interval;
+        s
+    }
+    /// Returns options with given elevation mask [°]
+    pub fn with_elevation_mask(&self, mask: f64) -> Self {
+        let mut s = self.clone();
+        s.elev_mask = mask;
+        s
+    }
+    /// Returns options with given coverage distance [km]
+    pub fn with_coverage(&self, coverage: f64) -> Self {
+        let mut s = self.clone();
+        s.coverage = coverage;
+        s
+    }
+    /// Returns options with given fitting technique
+    pub fn with_fit(&self, fit: TecFit) -> Self {
+        let mut s = self.clone();
+        s.fit = fit;
+        s
+    }
+}
+
+/*
+ * Vertical TEC [TECu] at a pierce point [°]
+ */
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    t: Epoch,
+    sv: Sv,
+    lat: f64,
+    lon: f64,
+    vtec: f64,
+}
+
+/*
+ * Geometry free combinations [m], for one Sv
+ */
+struct ArcPoint {
+    t: Epoch,
+    elev: f64,
+    azim: f64,
+    code: f64,
+    phase: f64,
+}
+
+/*
+ * Returns the DCB [ns] of given source, if known
+ */
+fn dcb(dcbs: &HashMap<BiasSource, (f64, f64)>, src: &BiasSource) -> Option<f64> {
+    dcbs.get(src).map(|(bias, _)| *bias)
+}
+
+/*
+ * Station identifier, as described in IONEX DCBs
+ */
+pub(super) fn station_id(obs: &Rinex) -> BiasSource {
+    let name: String = obs.header.station.chars().take(4).collect();
+    BiasSource::Station(name.to_uppercase())
+}
+
+/*
+ * Calibrated vertical TEC samples, at the pierce points of one station:
+ * carrier phase geometry free combinations, leveled to the code per arc.
+ * Vehicles with unknown DCB are skipped, the station DCB must be known.
+ */
+fn station_samples(
+    obs: &Rinex,
+    ephemerides: &Ephemerides,
+    dcbs: &HashMap<BiasSource, (f64, f64)>,
+    header: &ionex::HeaderFields,
+    opts: &IonexOpts,
+) -> Vec<Sample> {
+    let mut samples: Vec<Sample> = Vec::new();
+    let rcvr = match obs.header.ground_position {
+        Some(position) => position.to_ecef_wgs84(),
+        None => return samples,
+    };
+    let record = match obs.record.as_obs() {
+        Some(record) => record,
+        None => return samples,
+    };
+    let (lat, lon, _) = map_3d::ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, map_3d::Ellipsoid::WGS84);
+    let (lat, lon) = (lat.to_degrees(), lon.to_degrees());
+    let mut arcs: HashMap<Sv, Vec<Vec<ArcPoint>>> = HashMap::new();
+    for ((t, flag), (_, svs)) in record {
+        if !flag.is_ok() {
+            continue;
+        }
+        for (sv, observations) in svs {
+            if dcb(dcbs, &BiasSource::SpaceVehicle(*sv)).is_none() {
+                continue;
+            }
+            let pair = match carrier_pair(sv.constellation) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let (c1, c2, l1, l2) = match (
+                select(observations, sv.constellation, pair.0, false),
+                select(observations, sv.constellation, pair.1, false),
+                select(observations, sv.constellation, pair.0, true),
+                select(observations, sv.constellation, pair.1, true),
+            ) {
+                (Some(c1), Some(c2), Some(l1), Some(l2)) => (c1, c2, l1, l2),
+                _ => continue,
+            };
+            let (elev, azim) = match sv_elevation_azimuth(ephemerides, rcvr, *t, *sv) {
+                Some((elev, azim)) => (elev, azim),
+                None => continue,
+            };
+            if elev < opts.elev_mask {
+                continue;
+            }
+            let lock_loss = [l1, l2].iter().any(|data| {
+                data.lli
+                    .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                    .unwrap_or(false)
+            });
+            let point = ArcPoint {
+                t: *t,
+                elev,
+                azim,
+                code: c2.obs - c1.obs,
+                phase: l1.obs * pair.0.wavelength() - l2.obs * pair.1.wavelength(),
+            };
+            let sv_arcs = arcs.entry(*sv).or_default();
+            let new_arc = match sv_arcs.last().and_then(|arc| arc.last()) {
+                Some(last) => {
+                    lock_loss
+                        || (point.t - last.t).to_seconds() > opts.max_gap
+                        || (point.phase - last.phase).abs() > opts.slip_threshold
+                },
+                None => true,
+            };
+            if new_arc {
+                sv_arcs.push(Vec::new());
+            }
+            if let Some(arc) = sv_arcs.last_mut() {
+                arc.push(point);
+            }
+        }
+    }
+    let rcvr_dcb = match dcb(dcbs, &station_id(obs)) {
+        Some(bias) => bias,
+        None => return Vec::new(),
+    };
+    for (sv, sv_arcs) in arcs {
+        let pair = match carrier_pair(sv.constellation) {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let sv_dcb = match dcb(dcbs, &BiasSource::SpaceVehicle(sv)) {
+            Some(bias) => bias,
+            None => continue,
+        };
+        // [m] per TECu, on the geometry free combination
+        let alpha = 40.3E16 * (1.0 / pair.1.frequency().powi(2) - 1.0 / pair.0.frequency().powi(2));
+        let bias = SPEED_OF_LIGHT * 1.0E-9 * (sv_dcb + rcvr_dcb);
+        for arc in sv_arcs {
+            if arc.len() < opts.min_arc {
+                continue;
+            }
+            // leveling: elevation weighted code minus phase mean
+            let (mut sum, mut weights) = (0.0, 0.0);
+            for point in &arc {
+                let w = point.elev.to_radians().sin().powi(2);
+                sum += w * (point.code - point.phase);
+                weights += w;
+            }
+            let offset = sum / weights;
+            for point in &arc {
+                let stec = (point.phase + offset + bias) / alpha;
+                let (lat_p, lon_p) = header.pierce_point(lat, lon, point.elev, point.azim);
+                samples.push(Sample {
+                    t: point.t,
+                    sv,
+                    lat: lat_p,
+                    lon: lon_p,
+                    vtec: stec / header.mapping_factor(point.elev),
+                });
+            }
+        }
+    }
+    samples
+}
+
+/*
+ * Wraps given longitude [°] to [-180, 180[
+ */
+fn wrap(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/*
+ * Great circle distance [km] between two points [°], on a sphere of given radius [km]
+ */
+fn distance(p0: (f64, f64), p1: (f64, f64), radius: f64) -> f64 {
+    let (lat0, lon0) = (p0.0.to_radians(), p0.1.to_radians());
+    let (lat1, lon1) = (p1.0.to_radians(), p1.1.to_radians());
+    let cos = lat0.sin() * lat1.sin() + lat0.cos() * lat1.cos() * (lon1 - lon0).cos();
+    radius * cos.clamp(-1.0, 1.0).acos()
+}
+
+/*
+ * Vertical TEC normal point [TECu], at a pierce point [°]
+ * expressed in the Sun fixed frame of the map
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NormalPoint {
+    lat: f64,
+    lon: f64,
+    vtec: f64,
+}
+
+/*
+ * Averages the samples of the map described at `t`, into normal points
+ */
+fn normal_points(samples: &[(usize, Sample)], t: Epoch, opts: &IonexOpts) -> Vec<NormalPoint> {
+    let start = t - opts.interval * 0.5;
+    let end = t + opts.interval * 0.5;
+    let mut bins: BTreeMap<(usize, Sv, i64), Vec<NormalPoint>> = BTreeMap::new();
+    for (station, sample) in samples {
+        if sample.t < start || sample.t >= end {
+            continue;
+        }
+        let bin = ((sample.t - start).to_seconds() / opts.normal_point.to_seconds()).floor();
+        // the ionosphere is rather fixed with respect to the Sun
+        let lon = sample.lon + (sample.t - t).to_seconds() * EARTH_ROTATION_DEG_S;
+        bins.entry((*station, sample.sv, bin as i64))
+            .or_default()
+            .push(NormalPoint {
+                lat: sample.lat,
+                lon,
+                vtec: sample.vtec,
+            });
+    }
+    bins.values()
+        .map(|points| {
+            let n = points.len() as f64;
+            let lon0 = points[0].lon;
+            let (mut lat, mut lon, mut vtec) = (0.0, 0.0, 0.0);
+            for p in points {
+                lat += p.lat;
+                lon += lon0 + wrap(p.lon - lon0);
+                vtec += p.vtec;
+            }
+            NormalPoint {
+                lat: lat / n,
+                lon: wrap(lon / n),
+                vtec: vtec / n,
+            }
+        })
+        .collect()
+}
+
+/*
+ * Fitted vertical TEC surface
+ */
+trait Surface {
+    /*
+     * Returns the vertical TEC and its RMS [TECu], at given coordinates [°]
+     */
+    fn eval(&self, lat: f64, lon: f64) -> Option<(f64, f64)>;
+}
+
+/*
+ * Fully normalized associated Legendre functions P̄nm(sin(lat)),
+ * up to given degree and order
+ */
+fn legendre(degree: usize, lat: f64) -> Vec<Vec<f64>> {
+    let (x, u) = (lat.to_radians().sin(), lat.to_radians().cos());
+    let mut p = vec![vec![0.0_f64; degree + 1]; degree + 1];
+    p[0][0] = 1.0;
+    for m in 0..=degree {
+        if m > 0 {
+            let k = if m == 1 {
+                3.0_f64
+            } else {
+                (2 * m + 1) as f64 / (2 * m) as f64
+            };
+            p[m][m] = k.sqrt() * u * p[m - 1][m - 1];
+        }
+        if m < degree {
+            p[m + 1][m] = ((2 * m + 3) as f64).sqrt() * x * p[m][m];
+        }
+        for n in m + 2..=degree {
+            let (nf, mf) = (n as f64, m as f64);
+            let a = ((2.0 * nf - 1.0) * (2.0 * nf + 1.0) / ((nf - mf) * (nf + mf))).sqrt();
+            let b = ((2.0 * nf + 1.0) * (nf + mf - 1.0) * (nf - mf - 1.0)
+                / ((nf - mf) * (nf + mf) * (2.0 * nf - 3.0)))
+                .sqrt();
+            p[n][m] = a * x * p[n - 1][m] - b * p[n - 2][m];
+        }
+    }
+    p
+}
+
+/*
+ * Spherical harmonics expansion
+ */
+struct Harmonics {
+    degree: usize,
+    coefs: DVector<f64>,
+    /// Coefficients covariance
+    cov: DMatrix<f64>,
+}
+
+impl Harmonics {
+    fn basis(degree: usize, lat: f64, lon: f64) -> DVector<f64> {
+        let p = legendre(degree, lat);
+        let lon = lon.to_radians();
+        let mut b = Vec::with_capacity((degree + 1).pow(2));
+        for (n, p_n) in p.iter().enumerate() {
+            for (m, p_nm) in p_n.iter().enumerate().take(n + 1) {
+                b.push(p_nm * (m as f64 * lon).cos());
+                if m > 0 {
+                    b.push(p_nm * (m as f64 * lon).sin());
+                }
+            }
+        }
+        DVector::from_vec(b)
+    }
+    /*
+     * Least squares fit, slightly regularized: regional networks
+     * only constrain part of the expansion
+     */
+    fn fit(points: &[NormalPoint], degree: usize) -> Option<Self> {
+        let k = (degree + 1).pow(2);
+        let mut n = DMatrix::<f64>::zeros(k, k);
+        let mut y = DVector::<f64>::zeros(k);
+        for p in points {
+            let b = Self::basis(degree, p.lat, p.lon);
+            n += &b * b.transpose();
+            y += &b * p.vtec;
+        }
+        let ridge = 1.0E-6 * n.trace() / k as f64;
+        for i in 0..k {
+            n[(i, i)] += ridge;
+        }
+        let n_inv = n.try_inverse()?;
+        let coefs = &n_inv * y;
+        let mut residuals = 0.0;
+        for p in points {
+            let b = Self::basis(degree, p.lat, p.lon);
+            residuals += (p.vtec - b.dot(&coefs)).powi(2);
+        }
+        let dof = points.len().saturating_sub(k).max(1);
+        let cov = n_inv * (residuals / dof as f64);
+        Some(Self { degree, coefs, cov })
+    }
+}
+
+impl Surface for Harmonics {
+    fn eval(&self, lat: f64, lon: f64) -> Option<(f64, f64)> {
+        let b = Self::basis(self.degree, lat, lon);
+        let variance = (b.transpose() * &self.cov * &b)[(0, 0)];
+        Some((b.dot(&self.coefs), variance.max(0.0).sqrt()))
+    }
+}
+
+/*
+ * Ordinary kriging
+ */
+struct Kriging {
+    points: Vec<NormalPoint>,
+    /// Kriging system, LU decomposed
+    lu: nalgebra::LU<f64, nalgebra::Dynamic, nalgebra::Dynamic>,
+    /// Sill and nugget [TECu²]
+    sill: f64,
+    nugget: f64,
+    /// Practical range [km]
+    range: f64,
+    /// Shell radius [km]
+    radius: f64,
+}
+
+impl Kriging {
+    fn variogram(&self, h: f64) -> f64 {
+        if h <= 0.0 {
+            0.0
+        } else {
+            self.nugget + (self.sill - self.nugget) * (1.0 - (-3.0 * h / self.range).exp())
+        }
+    }
+    fn fit(points: &[NormalPoint], range: f64, radius: f64) -> Option<Self> {
+        let m = points.len();
+        let mean = points.iter().map(|p| p.vtec).sum::<f64>() / m as f64;
+        let variance = points.iter().map(|p| (p.vtec - mean).powi(2)).sum::<f64>() / m as f64;
+        let sill = variance.max(1.0E-3);
+        let mut kriging = Self {
+            points: points.to_vec(),
+            lu: DMatrix::<f64>::identity(1, 1).lu(),
+            sill,
+            nugget: 0.05 * sill,
+            range,
+            radius,
+        };
+        let mut k = DMatrix::<f64>::zeros(m + 1, m + 1);
+        for i in 0..m {
+            for j in 0..m {
+                let h = distance(
+                    (points[i].lat, points[i].lon),
+                    (points[j].lat, points[j].lon),
+                    radius,
+                );
+                k[(i, j)] = kriging.variogram(h);
+            }
+            k[(i, m)] = 1.0;
+            k[(m, i)] = 1.0;
+        }
+        kriging.lu = k.lu();
+        if !kriging.lu.is_invertible() {
+            return None;
+        }
+        Some(kriging)
+    }
+}
+
+impl Surface for Kriging {
+    fn eval(&self, lat: f64, lon: f64) -> Option<(f64, f64)> {
+        let m = self.points.len();
+        let mut rhs = DVector::<f64>::zeros(m + 1);
+        for (i, p) in self.points.iter().enumerate() {
+            rhs[i] = self.variogram(distance((p.lat, p.lon), (lat, lon), self.radius));
+        }
+        rhs[m] = 1.0;
+        let weights = self.lu.solve(&rhs)?;
+        let mut value = 0.0;
+        let mut variance = weights[m];
+        for (i, p) in self.points.iter().enumerate() {
+            value += weights[i] * p.vtec;
+            variance += weights[i] * rhs[i];
+        }
+        Some((value, variance.max(0.0).sqrt()))
+    }
+}
+
+/*
+ * Fits the TEC and RMS maps described at `t`
+ */
+fn fit_map(
+    points: &[NormalPoint],
+    header: &ionex::HeaderFields,
+    opts: &IonexOpts,
+) -> Option<(ionex::Map, ionex::Map)> {
+    if points.len() < 3 {
+        return None;
+    }
+    let radius = header.base_radius as f64 + opts.shell_height;
+    let surface: Box<dyn Surface> = match opts.fit {
+        TecFit::SphericalHarmonics { degree } => Box::new(Harmonics::fit(points, degree)?),
+        TecFit::Kriging { range } => Box::new(Kriging::fit(points, range, radius)?),
+    };
+    let (mut tec, mut rms) = (ionex::Map::new(), ionex::Map::new());
+    for i in 0..opts.latitude.length() {
+        let lat = opts.latitude.start + opts.latitude.spacing * i as f64;
+        for j in 0..opts.longitude.length() {
+            let lon = opts.longitude.start + opts.longitude.spacing * j as f64;
+            let covered = points
+                .iter()
+                .any(|p| distance((p.lat, p.lon), (lat, lon), radius) <= opts.coverage);
+            if !covered {
+                continue;
+            }
+            if let Some((value, sigma)) = surface.eval(lat, lon) {
+                for (map, value) in [(&mut tec, value), (&mut rms, sigma)] {
+                    map.push(MapPoint {
+                        latitude: lat,
+                        longitude: lon,
+                        altitude: opts.shell_height,
+                        value,
+                    });
+                }
+            }
+        }
+    }
+    Some((tec, rms))
+}
+
+/*
+ * Generates IONEX maps from a network of Observation RINEX
+ */
+pub(crate) fn generate(
+    network: &[Rinex],
+    nav: &Rinex,
+    dcbs: &HashMap<BiasSource, (f64, f64)>,
+    opts: &IonexOpts,
+) -> Option<Rinex> {
+    let mut fields = ionex::HeaderFields::default()
+        .with_base_radius(6371.0)
+        .with_map_dimension(2)
+        .with_mapping_function(ionex::MappingFunction::CosZ)
+        .with_elevation_cutoff(opts.elev_mask as f32)
+        .with_observables("Carrier phase leveled to code")
+        .with_latitude_grid(opts.latitude.clone())
+        .with_longitude_grid(opts.longitude.clone())
+        .with_altitude_grid(GridLinspace::from((
+            opts.shell_height,
+            opts.shell_height,
+            0.0,
+        )))
+        .with_exponent(-1);
+    let ephemerides = index_ephemerides(nav);
+    let mut samples: Vec<(usize, Sample)> = Vec::new();
+    let mut stations: Vec<BiasSource> = Vec::new();
+    // sources skipped for lack of DCB, reported in the header comments
+    let mut skipped: BTreeSet<String> = BTreeSet::new();
+    for obs in network {
+        let id = station_id(obs);
+        if dcb(dcbs, &id).is_none() {
+            if let BiasSource::Station(name) = id {
+                skipped.insert(format!("station {}", name));
+            }
+            continue;
+        }
+        for sv in obs.sv() {
+            if carrier_pair(sv.constellation).is_some()
+                && dcb(dcbs, &BiasSource::SpaceVehicle(sv)).is_none()
+            {
+                skipped.insert(format!("vehicle {}", sv));
+            }
+        }
+        let station = station_samples(obs, &ephemerides, dcbs, &fields, opts);
+        if station.is_empty() {
+            continue;
+        }
+        let index = stations.len();
+        stations.push(id);
+        samples.extend(station.into_iter().map(|sample| (index, sample)));
+    }
+    let first = samples.iter().map(|(_, sample)| sample.t).min()?;
+    let last = samples.iter().map(|(_, sample)| sample.t).max()?;
+    let svs: HashSet<Sv> = samples.iter().map(|(_, sample)| sample.sv).collect();
+    let constellations: HashSet<Constellation> = svs.iter().map(|sv| sv.constellation).collect();
+    let reference = match constellations.len() {
+        1 => *constellations.iter().next()?,
+        _ => Constellation::Mixed,
+    };
+    let fit = match opts.fit {
+        TecFit::SphericalHarmonics { degree } => {
+            format!("spherical harmonics expansion of degree {}", degree)
+        },
+        TecFit::Kriging { range } => {
+            format!("ordinary kriging with a {} km variogram range", range)
+        },
+    };
+    fields = fields
+        .with_reference_system(RefSystem::GnssConstellation(reference))
+        .with_description(&format!(
+            "Vertical TEC from {} station(s), in a single layer model, by {}.",
+            stations.len(),
+            fit
+        ))
+        .with_nb_stations(stations.len() as u32)
+        .with_nb_satellites(svs.len() as u32);
+    for src in svs
+        .iter()
+        .map(|sv| BiasSource::SpaceVehicle(*sv))
+        .chain(stations)
+    {
+        if let Some(dcb) = dcbs.get(&src) {
+            fields = fields.with_dcb(src, *dcb);
+        }
+    }
+    let mut record = ionex::Record::new();
+    let mut t = first.round(opts.interval);
+    while t <= last.round(opts.interval) {
+        let points = normal_points(&samples, t, opts);
+        if let Some((tec, rms)) = fit_map(&points, &fields, opts) {
+            record.insert(t, (tec, Some(rms), None));
+        }
+        t += opts.interval;
+    }
+    let (first, last) = (*record.keys().next()?, *record.keys().next_back()?);
+    fields = fields
+        .with_map_epochs(first, last)
+        .with_nb_maps(record.len() as u32);
+    let mut comments = vec!["TEC/RMS values in 0.1 TECU; 9999, if no value available".to_string()];
+    comments.extend(
+        skipped
+            .iter()
+            .map(|source| format!("{}: unknown DCB, skipped", source)),
+    );
+    let mut header = Header::basic_ionex()
+        .with_ionex_fields(fields)
+        .with_comments(comments);
+    header.sampling_interval = Some(opts.interval);
+    Some(Rinex::new(
+        header,
+        crate::record::Record::IonexRecord(record),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    /*
+     * Synthetic vertical TEC, smooth over Europe
+     */
+    fn vtec(lat: f64, lon: f64) -> f64 {
+        10.0 + 0.2 * (lat - 50.0) + 0.1 * lon
+    }
+    fn points() -> Vec<NormalPoint> {
+        let mut points = Vec::new();
+        for i in 0..20 {
+            for j in 0..20 {
+                let (lat, lon) = (40.0 + i as f64, -5.0 + j as f64);
+                points.push(NormalPoint {
+                    lat,
+                    lon,
+                    vtec: vtec(lat, lon),
+                });
+            }
+        }
+        points
+    }
+    #[test]
+    fn legendre_normalization() {
+        // ∫ P̄nm² cos(lat) dlat = 2 (4π normalization, over the order's longitude factor)
+        let degree = 4;
+        let steps = 20000;
+        let mut integrals = vec![vec![0.0; degree + 1]; degree + 1];
+        for k in 0..steps {
+            let lat = -90.0 + 180.0 * (k as f64 + 0.5) / steps as f64;
+            let p = legendre(degree, lat);
+            let dlat = std::f64::consts::PI / steps as f64;
+            for n in 0..=degree {
+                for m in 0..=n {
+                    integrals[n][m] += p[n][m].powi(2) * lat.to_radians().cos() * dlat;
+                }
+            }
+        }
+        for n in 0..=degree {
+            for m in 0..=n {
+                let expected = if m == 0 { 2.0 } else { 4.0 };
+                assert!(
+                    (integrals[n][m] - expected).abs() < 1.0E-3,
+                    "P{}{}: {}",
+                    n,
+                    m,
+                    integrals[n][m]
+                );
+            }
+        }
+    }
+    #[test]
+    fn spherical_harmonics() {
+        let points = points();
+        let surface = Harmonics::fit(&points, 4).unwrap();
+        for (lat, lon) in [(45.5, 2.5), (52.0, 4.4), (55.0, 10.0)] {
+            let (value, rms) = surface.eval(lat, lon).unwrap();
+            assert!(
+                (value - vtec(lat, lon)).abs() < 0.1,
+                "{} {}",
+                value,
+                vtec(lat, lon)
+            );
+            assert!(rms < 0.1);
+        }
+    }
+    #[test]
+    fn kriging() {
+        let points = points();
+        let surface = Kriging::fit(&points, 1000.0, 6371.0 + 450.0).unwrap();
+        // exact interpolator
+        let (value, rms) = surface.eval(45.0, 5.0).unwrap();
+        assert!((value - vtec(45.0, 5.0)).abs() < 1.0E-6);
+        assert!(rms < 1.0E-3);
+        let (value, rms) = surface.eval(47.5, 2.5).unwrap();
+        assert!((value - vtec(47.5, 2.5)).abs() < 0.1);
+        assert!(rms > 0.0);
+        // further away: larger uncertainty
+        let (_, far) = surface.eval(70.0, 30.0).unwrap();
+        assert!(far > rms);
+    }
+}
@@ -0,0 +1,681 @@
+//! Tropospheric delay models
+use super::geometry::sv_elevation_azimuth;
+use super::spp::index_ephemerides;
+use crate::meteo::InterpolationOpts;
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+
+/// Zenith delay models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ZenithModel {
+    /// Saastamoinen (1972) hydrostatic and wet delays
+    #[default]
+    Saastamoinen,
+    /// UNB3m (Leandro et al., 2006): Saastamoinen like delays
+    /// with temperature lapse rate and water vapor decrease factors,
+    /// on top of a seasonal and latitudinal atmosphere
+    Unb3m,
+}
+
+/// Tropospheric mapping functions, from zenith to slant delays
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MappingFunction {
+    /// Niell (1996) mapping functions
+    #[default]
+    Niell,
+    /// Vienna mapping function (Boehm et al., 2006), from the hydrostatic
+    /// and wet `a` coefficients published for this site and epoch
+    Vmf1 {
+        /// Hydrostatic coefficient
+        ah: f64,
+        /// Wet coefficient
+        aw: f64,
+    },
+    /// Global Mapping Function (Boehm et al., 2006),
+    /// an empirical model of the VMF1 coefficients
+    Gmf,
+}
+
+/// Surface meteorological conditions
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MeteoConditions {
+    /// Pressure [hPa]
+    pub pressure: f64,
+    /// Temperature [°C]
+    pub temperature: f64,
+    /// Relative humidity [%]
+    pub humidity: f64,
+}
+
+impl MeteoConditions {
+    /// Standard atmosphere at given altitude [m]
+    pub fn standard(alt: f64) -> Self {
+        let alt = alt.max(0.0);
+        Self {
+            pressure: 1013.25 * (1.0 - 2.2557E-5 * alt).powf(5.2568),
+            temperature: 15.0 - 6.5E-3 * alt,
+            humidity: 50.0 * (-6.396E-4 * alt).exp(),
+        }
+    }
+    /// Interpolates the conditions at given Epoch, from a Meteo RINEX,
+    /// see [Rinex::meteo_at]. Measurements of sensors with known height
+    /// are reduced to given receiver altitude [m].
+    /// Pressure and temperature are both required,
+    /// humidity defaults to the standard atmosphere at sea level.
+    pub fn from_meteo(meteo: &Rinex, t: Epoch, alt: f64) -> Option<Self> {
+        let opts = InterpolationOpts::default();
+        let interpolate = |observable: Observable| -> Option<f64> {
+            let value = meteo.meteo_at(t, &observable, opts)?;
+            let sensor = meteo
+                .header
+                .meteo
+                .as_ref()
+                .and_then(|header| header.sensors.iter().find(|s| s.observable == observable));
+            match sensor {
+                Some(sensor) => Some(sensor.height_correction(value, alt)),
+                None => Some(value),
+            }
+        };
+        Some(Self {
+            pressure: interpolate(Observable::Pressure)?,
+            temperature: interpolate(Observable::Temperature)?,
+            humidity: interpolate(Observable::HumidityRate).unwrap_or(50.0),
+        })
+    }
+    /// Water vapor partial pressure [hPa]
+    pub fn water_vapor_pressure(&self) -> f64 {
+        let t = self.temperature + 273.15;
+        6.108 * self.humidity / 100.0 * ((17.15 * t - 4684.0) / (t - 38.45)).exp()
+    }
+}
+
+/// Tropospheric delay model: a zenith delay model and a mapping function.
+/// When no meteorological conditions are provided,
+/// the zenith delays are evaluated for a standard atmosphere
+/// (seasonal and latitudinal with [ZenithModel::Unb3m]).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TropoModel {
+    /// Zenith delay model
+    pub zenith: ZenithModel,
+    /// Mapping function
+    pub mapping: MappingFunction,
+}
+
+impl TropoModel {
+    /// Returns model with given zenith delay model
+    pub fn with_zenith_model(&self, zenith: ZenithModel) -> Self {
+        let mut s = *self;
+        s.zenith = zenith;
+        s
+    }
+    /// Returns model with given mapping function
+    pub fn with_mapping_function(&self, mapping: MappingFunction) -> Self {
+        let mut s = *self;
+        s.mapping = mapping;
+        s
+    }
+    /// Zenith hydrostatic and wet delays [m], at given Epoch.
+    ///  - lat: receiver latitude [°]
+    ///  - alt: receiver altitude [m]
+    ///  - meteo: surface conditions, at the receiver
+    pub fn zenith_delays(
+        &self,
+        t: Epoch,
+        lat: f64,
+        alt: f64,
+        meteo: Option<MeteoConditions>,
+    ) -> (f64, f64) {
+        if !(-500.0..=1.0E4).contains(&alt) {
+            return (0.0, 0.0);
+        }
+        match self.zenith {
+            ZenithModel::Saastamoinen => {
+                let meteo = meteo.unwrap_or(MeteoConditions::standard(alt));
+                saastamoinen_zenith(lat, alt, &meteo)
+            },
+            ZenithModel::Unb3m => unb3m(t, lat, alt, meteo),
+        }
+    }
+    /// Hydrostatic and wet mapping factors, at given Epoch.
+    ///  - lat, lon: receiver coordinates [°]
+    ///  - alt: receiver altitude [m]
+    ///  - elev: Sv elevation [°]
+    pub fn mapping_factors(&self, t: Epoch, lat: f64, lon: f64, alt: f64, elev: f64) -> (f64, f64) {
+        let elev = elev.max(1.0);
+        match self.mapping {
+            MappingFunction::Niell => niell(t, lat, alt, elev),
+            MappingFunction::Vmf1 { ah, aw } => vmf1(t, lat, alt, elev, ah, aw),
+            MappingFunction::Gmf => gmf(t, lat, lon, alt, elev),
+        }
+    }
+    /// Slant tropospheric delay [m], at given Epoch.
+    ///  - lat, lon: receiver coordinates [°]
+    ///  - alt: receiver altitude [m]
+    ///  - elev: Sv elevation [°]
+    ///  - meteo: surface conditions, at the receiver
+    pub fn slant_delay(
+        &self,
+        t: Epoch,
+        lat: f64,
+        lon: f64,
+        alt: f64,
+        elev: f64,
+        meteo: Option<MeteoConditions>,
+    ) -> f64 {
+        if elev <= 0.0 {
+            return 0.0;
+        }
+        let (zhd, zwd) = self.zenith_delays(t, lat, alt, meteo);
+        let (mh, mw) = self.mapping_factors(t, lat, lon, alt, elev);
+        zhd * mh + zwd * mw
+    }
+}
+
+/*
+ * Saastamoinen zenith delays [m], for given surface conditions
+ */
+fn saastamoinen_zenith(lat: f64, alt: f64, meteo: &MeteoConditions) -> (f64, f64) {
+    let zhd = 0.0022768 * meteo.pressure
+        / (1.0 - 0.00266 * (2.0 * lat.to_radians()).cos() - 0.00028 * alt / 1.0E3);
+    let temperature = meteo.temperature + 273.15;
+    let zwd = 0.002277 * (1255.0 / temperature + 0.05) * meteo.water_vapor_pressure();
+    (zhd, zwd)
+}
+
+/*
+ * Day of year, counted from January 28th (coldest day in the northern hemisphere),
+ * shifted by half a year in the southern hemisphere
+ */
+fn seasonal_phase(t: Epoch, lat: f64) -> f64 {
+    let doy = t.day_of_year() + if lat < 0.0 { 182.625 } else { 0.0 };
+    2.0 * PI * (doy - 28.0) / 365.25
+}
+
+/*
+ * Interpolates a 15° to 75° latitude table, at given latitude [°]
+ */
+fn interpolate_latitude<const N: usize>(table: &[[f64; N]; 5], lat: f64) -> [f64; N] {
+    let lat = lat.abs();
+    let mut ret = [0.0; N];
+    if lat <= 15.0 {
+        return table[0];
+    }
+    if lat >= 75.0 {
+        return table[4];
+    }
+    let i = ((lat - 15.0) / 15.0) as usize;
+    let f = (lat - 15.0 - i as f64 * 15.0) / 15.0;
+    for (k, value) in ret.iter_mut().enumerate() {
+        *value = table[i][k] + (table[i + 1][k] - table[i][k]) * f;
+    }
+    ret
+}
+
+/*
+ * UNB3m atmosphere: pressure [hPa], temperature [K], relative humidity [%],
+ * temperature lapse rate [K/m] and water vapor decrease factor
+ */
+const UNB3M_AVERAGE: [[f64; 5]; 5] = [
+    [1013.25, 299.65, 75.0, 6.30E-3, 2.77],
+    [1017.25, 294.15, 80.0, 6.05E-3, 3.15],
+    [1015.75, 283.15, 76.0, 5.58E-3, 2.57],
+    [1011.75, 272.15, 77.5, 5.39E-3, 1.81],
+    [1013.00, 263.65, 82.5, 4.53E-3, 1.55],
+];
+
+const UNB3M_AMPLITUDE: [[f64; 5]; 5] = [
+    [0.00, 0.00, 0.0, 0.00E-3, 0.00],
+    [-3.75, 7.00, 0.0, 0.25E-3, 0.33],
+    [-2.25, 11.00, -1.0, 0.32E-3, 0.46],
+    [-1.75, 15.00, -2.5, 0.81E-3, 0.74],
+    [-0.50, 14.50, 2.5, 0.62E-3, 0.30],
+];
+
+/*
+ * UNB3m zenith delays [m]. Surface conditions, when provided,
+ * replace the tabulated atmosphere reduced to the receiver altitude.
+ */
+fn unb3m(t: Epoch, lat: f64, alt: f64, meteo: Option<MeteoConditions>) -> (f64, f64) {
+    const K1: f64 = 77.604; // K/hPa
+    const K2: f64 = 16.6; // K/hPa
+    const K3: f64 = 377_600.0; // K²/hPa
+    const RD: f64 = 287.054; // J/kg/K
+    const G: f64 = 9.80665; // m/s²
+
+    let avg = interpolate_latitude(&UNB3M_AVERAGE, lat);
+    let amp = interpolate_latitude(&UNB3M_AMPLITUDE, lat);
+    let cos = seasonal_phase(t, lat).cos();
+    let params: Vec<f64> = avg
+        .iter()
+        .zip(amp.iter())
+        .map(|(a, b)| a - b * cos)
+        .collect();
+    let (beta, lambda) = (params[3], params[4]);
+
+    // water vapor pressure [hPa], with the enhancement factor
+    let vapor = |pressure: f64, temperature: f64, humidity: f64| -> f64 {
+        let es = 0.01
+            * (1.2378847E-5 * temperature.powi(2) - 1.9121316E-2 * temperature + 33.93711047
+                - 6.3431645E3 / temperature)
+                .exp();
+        let fw = 1.00062 + 3.14E-6 * pressure + 5.6E-7 * (temperature - 273.15).powi(2);
+        humidity / 100.0 * es * fw
+    };
+    let (pressure, temperature, e) = match meteo {
+        Some(meteo) => {
+            let temperature = meteo.temperature + 273.15;
+            let e = vapor(meteo.pressure, temperature, meteo.humidity);
+            (meteo.pressure, temperature, e)
+        },
+        None => {
+            // sea level values, reduced to the receiver altitude
+            let (p0, t0) = (params[0], params[1]);
+            let e0 = vapor(p0, t0, params[2]);
+            let temperature = t0 - beta * alt;
+            let ep = G / RD / beta;
+            let pressure = p0 * (temperature / t0).powf(ep);
+            let e = e0 * (temperature / t0).powf(ep * (lambda + 1.0));
+            (pressure, temperature, e)
+        },
+    };
+
+    let gm = 9.784 * (1.0 - 2.66E-3 * (2.0 * lat.to_radians()).cos() - 2.8E-7 * alt);
+    let zhd = 1.0E-6 * K1 * RD / gm * pressure;
+    let tm = temperature * (1.0 - beta * RD / gm / (lambda + 1.0));
+    let zwd = 1.0E-6 * (tm * K2 + K3) * RD / (gm * (lambda + 1.0) - beta * RD) * e / temperature;
+    (zhd, zwd)
+}
+
+/*
+ * Marini continued fraction, normalized to unity at zenith
+ */
+fn marini(elev: f64, a: f64, b: f64, c: f64) -> f64 {
+    let sin = elev.to_radians().sin();
+    (1.0 + a / (1.0 + b / (1.0 + c))) / (sin + a / (sin + b / (sin + c)))
+}
+
+/*
+ * Niell (1996) height correction to the hydrostatic mapping
+ */
+fn height_correction(elev: f64, alt: f64) -> f64 {
+    let sin = elev.to_radians().sin();
+    (1.0 / sin - marini(elev, 2.53E-5, 5.49E-3, 1.14E-3)) * alt / 1.0E3
+}
+
+const NIELL_HYDRO_AVERAGE: [[f64; 3]; 5] = [
+    [1.2769934E-3, 2.9153695E-3, 62.610505E-3],
+    [1.2683230E-3, 2.9152299E-3, 62.837393E-3],
+    [1.2465397E-3, 2.9288445E-3, 63.721774E-3],
+    [1.2196049E-3, 2.9022565E-3, 63.824265E-3],
+    [1.2045996E-3, 2.9024912E-3, 64.258455E-3],
+];
+
+const NIELL_HYDRO_AMPLITUDE: [[f64; 3]; 5] = [
+    [0.0, 0.0, 0.0],
+    [1.2709626E-5, 2.1414979E-5, 9.0128400E-5],
+    [2.6523662E-5, 3.0160779E-5, 4.3497037E-5],
+    [3.4000452E-5, 7.2562722E-5, 84.795348E-5],
+    [4.1202191E-5, 11.723375E-5, 170.37206E-5],
+];
+
+const NIELL_WET: [[f64; 3]; 5] = [
+    [5.8021897E-4, 1.4275268E-3, 4.3472961E-2],
+    [5.6794847E-4, 1.5138625E-3, 4.6729510E-2],
+    [5.8118019E-4, 1.4572752E-3, 4.3908931E-2],
+    [5.9727542E-4, 1.5007428E-3, 4.4626982E-2],
+    [6.1641693E-4, 1.7599082E-3, 5.4736038E-2],
+];
+
+/*
+ * Niell hydrostatic and wet mapping functions
+ */
+fn niell(t: Epoch, lat: f64, alt: f64, elev: f64) -> (f64, f64) {
+    let avg = interpolate_latitude(&NIELL_HYDRO_AVERAGE, lat);
+    let amp = interpolate_latitude(&NIELL_HYDRO_AMPLITUDE, lat);
+    let cos = seasonal_phase(t, lat).cos();
+    let (a, b, c) = (
+        avg[0] - amp[0] * cos,
+        avg[1] - amp[1] * cos,
+        avg[2] - amp[2] * cos,
+    );
+    let mh = marini(elev, a, b, c) + height_correction(elev, alt);
+    let wet = interpolate_latitude(&NIELL_WET, lat);
+    let mw = marini(elev, wet[0], wet[1], wet[2]);
+    (mh, mw)
+}
+
+/*
+ * VMF1 / GMF hydrostatic `c` coefficient
+ */
+fn vmf1_ch(t: Epoch, lat: f64) -> f64 {
+    // day of year, counted from January 28th
+    let doy = t.to_mjd_utc_days() - 44239.0 + 1.0 - 28.0;
+    let (phh, c11h, c10h) = if lat < 0.0 {
+        (PI, 0.007, 0.002)
+    } else {
+        (0.0, 0.005, 0.001)
+    };
+    0.062
+        + (((doy / 365.25 * 2.0 * PI + phh).cos() + 1.0) * c11h / 2.0 + c10h)
+            * (1.0 - lat.to_radians().cos())
+}
+
+/*
+ * VMF1 hydrostatic and wet mapping functions, for given `a` coefficients
+ */
+fn vmf1(t: Epoch, lat: f64, alt: f64, elev: f64, ah: f64, aw: f64) -> (f64, f64) {
+    let mh = marini(elev, ah, 0.0029, vmf1_ch(t, lat)) + height_correction(elev, alt);
+    let mw = marini(elev, aw, 0.00146, 0.04391);
+    (mh, mw)
+}
+
+/*
+ * GMF: spherical harmonics expansion (degree and order 9)
+ * of the mean and annual amplitude of the VMF1 `a` coefficients
+ */
+const GMF_AH_MEAN: [f64; 55] = [
+    1.2517E+02, 8.503E-01, 6.936E-02, -6.760E+00, 1.771E-01, 1.130E-02, 5.963E-01, 1.808E-02,
+    2.801E-03, -1.414E-03, -1.212E+00, 9.300E-02, 3.683E-03, 1.095E-03, 4.671E-05, 3.959E-01,
+    -3.867E-02, 5.413E-03, -5.289E-04, 3.229E-04, 2.067E-05, 3.000E-01, 2.031E-02, 5.900E-03,
+    4.573E-04, -7.619E-05, 2.327E-06, 3.845E-06, 1.182E-01, 1.158E-02, 5.445E-03, 6.219E-05,
+    4.204E-06, -2.093E-06, 1.540E-07, -4.280E-08, -4.751E-01, -3.490E-02, 1.758E-03, 4.019E-04,
+    -2.799E-06, -1.287E-06, 5.468E-07, 7.580E-08, -6.300E-09, -1.160E-01, 8.301E-03, 8.771E-04,
+    9.955E-05, -1.718E-06, -2.012E-06, 1.170E-08, 1.790E-08, -1.300E-09, 1.000E-10,
+];
+
+const GMF_BH_MEAN: [f64; 55] = [
+    0.000E+00, 0.000E+00, 3.249E-02, 0.000E+00, 3.324E-02, 1.850E-02, 0.000E+00, -1.115E-01,
+    2.519E-02, 4.923E-03, 0.000E+00, 2.737E-02, 1.595E-02, -7.332E-04, 1.933E-04, 0.000E+00,
+    -4.796E-02, 6.381E-03, -1.599E-04, -3.685E-04, 1.815E-05, 0.000E+00, 7.033E-02, 2.426E-03,
+    -1.111E-03, -1.357E-04, -7.828E-06, 2.547E-06, 0.000E+00, 5.779E-03, 3.133E-03, -5.312E-04,
+    -2.028E-05, 2.323E-07, -9.100E-08, -1.650E-08, 0.000E+00, 3.688E-02, -8.638E-04, -8.514E-05,
+    -2.828E-05, 5.403E-07, 4.390E-07, 1.350E-08, 1.800E-09, 0.000E+00, -2.736E-02, -2.977E-04,
+    8.113E-05, 2.329E-07, 8.451E-07, 4.490E-08, -8.100E-09, -1.500E-09, 2.000E-10,
+];
+
+const GMF_AH_AMP: [f64; 55] = [
+    -2.738E-01, -2.837E+00, 1.298E-02, -3.588E-01, 2.413E-02, 3.427E-02, -7.624E-01, 7.272E-02,
+    2.160E-02, -3.385E-03, 4.424E-01, 3.722E-02, 2.195E-02, -1.503E-03, 2.426E-04, 3.013E-01,
+    5.762E-02, 1.019E-02, -4.476E-04, 6.790E-05, 3.227E-05, 3.123E-01, -3.535E-02, 4.840E-03,
+    3.025E-06, -4.363E-05, 2.854E-07, -1.286E-06, -6.725E-01, -3.730E-02, 8.964E-04, 1.399E-04,
+    -3.990E-06, 7.431E-06, -2.796E-07, -1.601E-07, 4.068E-02, -1.352E-02, 7.282E-04, 9.594E-05,
+    2.070E-06, -9.620E-08, -2.742E-07, -6.370E-08, -6.300E-09, 8.625E-02, -5.971E-03, 4.705E-04,
+    2.335E-05, 4.226E-06, 2.475E-07, -8.850E-08, -3.600E-08, -2.900E-09, 0.000E+00,
+];
+
+const GMF_BH_AMP: [f64; 55] = [
+    0.000E+00, 0.000E+00, -1.136E-01, 0.000E+00, -1.868E-01, -1.399E-02, 0.000E+00, -1.043E-01,
+    1.175E-02, -2.240E-03, 0.000E+00, -3.222E-02, 1.333E-02, -2.647E-03, -2.316E-05, 0.000E+00,
+    5.339E-02, 1.107E-02, -3.116E-03, -1.079E-04, -1.299E-05, 0.000E+00, 4.861E-03, 8.891E-03,
+    -6.448E-04, -1.279E-05, 6.358E-06, -1.417E-07, 0.000E+00, 3.041E-02, 1.150E-03, -8.743E-04,
+    -2.781E-05, 6.367E-07, -1.140E-08, -4.200E-08, 0.000E+00, -2.982E-02, -3.000E-03, 1.394E-05,
+    -3.290E-05, -1.705E-07, 7.440E-08, 2.720E-08, -6.600E-09, 0.000E+00, 1.236E-02, -9.981E-04,
+    -3.792E-05, -1.355E-05, 1.162E-06, -1.789E-07, 1.470E-08, -2.400E-09, -4.000E-10,
+];
+
+const GMF_AW_MEAN: [f64; 55] = [
+    5.640E+01, 1.555E+00, -1.011E+00, -3.975E+00, 3.171E-02, 1.065E-01, 6.175E-01, 1.376E-01,
+    4.229E-02, 3.028E-03, 1.688E+00, -1.692E-01, 5.478E-02, 2.473E-02, 6.059E-04, 2.278E+00,
+    6.614E-03, -3.505E-04, -6.697E-03, 8.402E-04, 7.033E-04, -3.236E+00, 2.184E-01, -4.611E-02,
+    -1.613E-02, -1.604E-03, 5.420E-05, 7.922E-05, -2.711E-01, -4.406E-01, -3.376E-02, -1.801E-03,
+    6.502E-04, 1.627E-04, 1.040E-05, -6.000E-06, -1.211E+00, 8.081E-02, -1.067E-02, 3.390E-04,
+    2.590E-04, -1.067E-06, -2.011E-05, 1.104E-06, 7.000E-07, 1.052E+00, -1.181E-01, 2.174E-02,
+    3.099E-03, -6.226E-04, -5.570E-05, 6.740E-06, -1.040E-06, -6.300E-08, 0.000E+00,
+];
+
+const GMF_BW_MEAN: [f64; 55] = [
+    0.000E+00, 0.000E+00, 2.586E-01, 0.000E+00, -1.415E-01, 1.062E-01, 0.000E+00, 1.033E-01,
+    1.091E-01, -4.221E-03, 0.000E+00, 5.146E-01, 8.640E-02, 1.233E-03, -2.120E-03, 0.000E+00,
+    3.152E-01, -6.181E-02, 3.119E-03, 2.212E-03, -1.216E-04, 0.000E+00, -2.025E-02, -3.046E-02,
+    -8.436E-03, 2.073E-04, -2.069E-06, -5.025E-05, 0.000E+00, 1.162E-01, 2.221E-03, 1.219E-03,
+    3.098E-04, 6.022E-05, 1.117E-05, -4.000E-07, 0.000E+00, 3.058E-01, -2.032E-02, -2.094E-03,
+    -1.393E-04, -2.113E-05, -4.079E-06, 6.200E-09, -2.400E-07, 0.000E+00, -1.148E-01, 1.053E-02,
+    1.201E-03, 3.450E-04, -7.920E-06, 2.040E-06, 9.040E-07, -6.500E-08, -1.400E-08,
+];
+
+const GMF_AW_AMP: [f64; 55] = [
+    1.023E-01, -2.695E+00, 3.417E-01, -1.405E-01, 3.175E-01, 2.116E-01, 3.536E+00, -1.505E-01,
+    -1.660E-02, 2.967E-02, 3.819E-01, -1.695E-01, -7.444E-02, 7.409E-03, -6.262E-03, -1.836E+00,
+    -1.759E-02, -6.256E-02, -2.371E-03, 7.947E-04, 1.501E-04, -8.603E-01, -1.360E-01, -3.629E-02,
+    -3.706E-03, -2.976E-04, 1.857E-05, 3.021E-05, 2.248E+00, -1.178E-01, 1.255E-02, 1.134E-03,
+    -2.161E-04, -5.817E-06, 8.836E-07, -1.769E-07, 7.313E-01, -1.188E-01, 1.145E-02, 1.011E-03,
+    1.083E-04, 2.570E-06, -2.140E-06, -5.710E-08, 2.000E-08, -1.632E+00, -6.948E-03, -3.893E-03,
+    8.592E-04, 7.577E-05, 4.539E-06, -3.852E-07, -2.213E-07, -1.370E-08, 5.800E-09,
+];
+
+const GMF_BW_AMP: [f64; 55] = [
+    0.000E+00, 0.000E+00, -8.865E-02, 0.000E+00, -4.309E-01, 6.340E-02, 0.000E+00, 1.162E-01,
+    6.176E-02, -4.234E-03, 0.000E+00, 2.530E-01, 4.017E-02, -6.204E-03, 4.977E-03, 0.000E+00,
+    -1.737E-01, -5.638E-03, 1.488E-04, 4.857E-04, -1.809E-04, 0.000E+00, -1.514E-01, -1.685E-02,
+    5.333E-03, -7.611E-05, 2.394E-05, 8.195E-06, 0.000E+00, 9.326E-02, -1.275E-02, -3.071E-04,
+    5.374E-05, -3.391E-05, -7.436E-06, 6.747E-07, 0.000E+00, -8.637E-02, -3.807E-03, -6.833E-04,
+    -3.861E-05, -2.268E-05, 1.454E-06, 3.860E-07, -1.068E-07, 0.000E+00, -2.658E-02, -1.947E-03,
+    7.131E-04, -3.506E-05, 1.885E-07, 5.792E-07, 3.990E-08, 2.000E-08, -5.700E-09,
+];
+
+/*
+ * Legendre functions (unnormalized) P(n, m) for n, m <= 9, at sin(lat)
+ * expanded as cos(m.lon) and sin(m.lon) terms
+ */
+fn gmf_harmonics(lat: f64, lon: f64) -> ([f64; 55], [f64; 55]) {
+    let mut fac = [1.0_f64; 20];
+    for i in 1..20 {
+        fac[i] = fac[i - 1] * i as f64;
+    }
+    let t = lat.to_radians().sin();
+    let mut p = [[0.0_f64; 10]; 10];
+    for (n, p_n) in p.iter_mut().enumerate() {
+        for (m, p_nm) in p_n.iter_mut().enumerate().take(n + 1) {
+            let mut sum = 0.0;
+            for k in 0..=(n - m) / 2 {
+                sum += (-1.0_f64).powi(k as i32) * fac[2 * n - 2 * k]
+                    / fac[k]
+                    / fac[n - k]
+                    / fac[n - m - 2 * k]
+                    * t.powi((n - m - 2 * k) as i32);
+            }
+            *p_nm = sum / 2.0_f64.powi(n as i32) * (1.0 - t * t).powf(m as f64 / 2.0);
+        }
+    }
+    let lon = lon.to_radians();
+    let (mut a, mut b) = ([0.0_f64; 55], [0.0_f64; 55]);
+    let mut i = 0;
+    for (n, p_n) in p.iter().enumerate() {
+        for (m, p_nm) in p_n.iter().enumerate().take(n + 1) {
+            a[i] = p_nm * (m as f64 * lon).cos();
+            b[i] = p_nm * (m as f64 * lon).sin();
+            i += 1;
+        }
+    }
+    (a, b)
+}
+
+/*
+ * GMF hydrostatic and wet mapping functions
+ */
+fn gmf(t: Epoch, lat: f64, lon: f64, alt: f64, elev: f64) -> (f64, f64) {
+    let doy = t.to_mjd_utc_days() - 44239.0 + 1.0 - 28.0;
+    let annual = (doy / 365.25 * 2.0 * PI).cos();
+    let (a, b) = gmf_harmonics(lat, lon);
+    let expand = |am: &[f64; 55], bm: &[f64; 55], aa: &[f64; 55], ba: &[f64; 55]| -> f64 {
+        let (mut mean, mut amp) = (0.0, 0.0);
+        for i in 0..55 {
+            mean += am[i] * a[i] + bm[i] * b[i];
+            amp += aa[i] * a[i] + ba[i] * b[i];
+        }
+        (mean + amp * annual) * 1.0E-5
+    };
+    let ah = expand(&GMF_AH_MEAN, &GMF_BH_MEAN, &GMF_AH_AMP, &GMF_BH_AMP);
+    let aw = expand(&GMF_AW_MEAN, &GMF_BW_MEAN, &GMF_AW_AMP, &GMF_BW_AMP);
+    vmf1(t, lat, alt, elev, ah, aw)
+}
+
+/*
+ * Slant tropospheric delays [m], per Epoch and Sv,
+ * seen from the ground position of the `obs` RINEX
+ */
+pub(crate) fn slant_delays(
+    obs: &Rinex,
+    nav: &Rinex,
+    meteo: Option<&Rinex>,
+    model: &TropoModel,
+) -> BTreeMap<Epoch, BTreeMap<Sv, f64>> {
+    let mut ret: BTreeMap<Epoch, BTreeMap<Sv, f64>> = BTreeMap::new();
+    let rcvr = match obs.header.ground_position {
+        Some(position) => position.to_ecef_wgs84(),
+        None => return ret,
+    };
+    let record = match obs.record.as_obs() {
+        Some(record) => record,
+        None => return ret,
+    };
+    let (lat, lon, alt) = map_3d::ecef2geodetic(rcvr.0, rcvr.1, rcvr.2, map_3d::Ellipsoid::WGS84);
+    let (lat, lon) = (lat.to_degrees(), lon.to_degrees());
+    let ephemerides = index_ephemerides(nav);
+    for ((t, _), (_, svs)) in record {
+        let conditions = meteo.and_then(|meteo| MeteoConditions::from_meteo(meteo, *t, alt));
+        let (zhd, zwd) = model.zenith_delays(*t, lat, alt, conditions);
+        for sv in svs.keys() {
+            let (elev, _) = match sv_elevation_azimuth(&ephemerides, rcvr, *t, *sv) {
+                Some(angles) => angles,
+                None => continue,
+            };
+            if elev <= 0.0 {
+                continue;
+            }
+            let (mh, mw) = model.mapping_factors(*t, lat, lon, alt, elev);
+            ret.entry(*t).or_default().insert(*sv, zhd * mh + zwd * mw);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn saastamoinen_delays() {
+        let t = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        let model = TropoModel::default();
+        // ~2.4 m at zenith, at sea level
+        let zenith = model.slant_delay(t, 45.0, 5.0, 0.0, 90.0, None);
+        assert!(zenith > 2.3 && zenith < 2.5, "{}", zenith);
+        // decreases with altitude
+        assert!(model.slant_delay(t, 45.0, 5.0, 2000.0, 90.0, None) < zenith);
+        // increases at low elevations
+        let slant = model.slant_delay(t, 45.0, 5.0, 0.0, 10.0, None);
+        assert!(slant > 5.0 * zenith);
+        assert_eq!(model.slant_delay(t, 45.0, 5.0, 0.0, -5.0, None), 0.0);
+        let (zhd, zwd) = model.zenith_delays(t, 45.0, 0.0, None);
+        assert!((zhd + zwd - zenith).abs() < 1.0E-3);
+        assert!(zhd > 2.2 && zwd < 0.2);
+        // altitude validity range
+        assert!(model.zenith_delays(t, 31.5, -400.0, None).0 > zhd);
+        assert_eq!(model.zenith_delays(t, 31.5, -600.0, None), (0.0, 0.0));
+        assert_eq!(model.zenith_delays(t, 45.0, 1.2E4, None), (0.0, 0.0));
+    }
+    #[test]
+    fn zenith_models() {
+        let t = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        let saastamoinen = TropoModel::default();
+        let unb3m = TropoModel::default().with_zenith_model(ZenithModel::Unb3m);
+        for lat in [-60.0, -30.0, 10.0, 45.0, 70.0] {
+            let (zhd, zwd) = unb3m.zenith_delays(t, lat, 0.0, None);
+            assert!(zhd > 2.2 && zhd < 2.4, "{}: {}", lat, zhd);
+            assert!(zwd > 0.02 && zwd < 0.4, "{}: {}", lat, zwd);
+            let (zhd_h, zwd_h) = unb3m.zenith_delays(t, lat, 1500.0, None);
+            assert!(zhd_h < zhd && zwd_h < zwd);
+        }
+        // northern summer is wetter
+        let winter = Epoch::from_gregorian_utc_at_midnight(2020, 1, 28);
+        assert!(
+            unb3m.zenith_delays(t, 45.0, 0.0, None).1
+                > unb3m.zenith_delays(winter, 45.0, 0.0, None).1
+        );
+        // measured conditions drive both models the same way
+        let meteo = MeteoConditions {
+            pressure: 980.0,
+            temperature: 20.0,
+            humidity: 70.0,
+        };
+        let (zhd, zwd) = saastamoinen.zenith_delays(t, 45.0, 0.0, Some(meteo));
+        let (unb3m_zhd, unb3m_zwd) = unb3m.zenith_delays(t, 45.0, 0.0, Some(meteo));
+        assert!(
+            (zhd - 0.0022768 * 980.0 / (1.0 - 0.00266 * 90.0_f64.to_radians().cos())).abs()
+                < 1.0E-9
+        );
+        assert!((zhd - unb3m_zhd).abs() < 2.0E-3);
+        assert!((zwd - unb3m_zwd).abs() < 0.03, "{} {}", zwd, unb3m_zwd);
+        let standard = MeteoConditions::standard(0.0);
+        assert_eq!(standard.pressure, 1013.25);
+        assert_eq!(standard.temperature, 15.0);
+        assert!((standard.water_vapor_pressure() - 8.5).abs() < 0.5);
+    }
+    #[test]
+    fn mapping_functions() {
+        let niell = TropoModel::default();
+        let gmf = niell.with_mapping_function(MappingFunction::Gmf);
+        let vmf1 = niell.with_mapping_function(MappingFunction::Vmf1 {
+            ah: 1.2465397E-3,
+            aw: 5.8118019E-4,
+        });
+        for (y, m) in [(2020, 1), (2020, 4), (2020, 7), (2020, 10)] {
+            let t = Epoch::from_gregorian_utc_at_midnight(y, m, 15);
+            for (lat, lon) in [
+                (-70.0, 20.0),
+                (-35.0, 150.0),
+                (0.0, -60.0),
+                (45.0, 5.0),
+                (65.0, -100.0),
+            ] {
+                let (mh, mw) = niell.mapping_factors(t, lat, lon, 0.0, 90.0);
+                assert!((mh - 1.0).abs() < 1.0E-6 && (mw - 1.0).abs() < 1.0E-6);
+                for elev in [5.0, 10.0, 30.0, 60.0] {
+                    let (nh, nw) = niell.mapping_factors(t, lat, lon, 0.0, elev);
+                    let (gh, gw) = gmf.mapping_factors(t, lat, lon, 0.0, elev);
+                    let cosecant = 1.0 / elev.to_radians().sin();
+                    assert!(nh > 0.85 * cosecant && nh < cosecant);
+                    // empirical models agree at the percent level
+                    assert!(
+                        (gh - nh).abs() / nh < 0.01,
+                        "{}: {} {} {} {}",
+                        t,
+                        lat,
+                        elev,
+                        gh,
+                        nh
+                    );
+                    assert!(
+                        (gw - nw).abs() / nw < 0.02,
+                        "{}: {} {} {} {}",
+                        t,
+                        lat,
+                        elev,
+                        gw,
+                        nw
+                    );
+                }
+            }
+            let (vh, vw) = vmf1.mapping_factors(t, 45.0, 5.0, 0.0, 5.0);
+            let (nh, nw) = niell.mapping_factors(t, 45.0, 5.0, 0.0, 5.0);
+            assert!((vh - nh).abs() / nh < 0.01 && (vw - nw).abs() / nw < 0.01);
+        }
+        // hydrostatic mapping increases with altitude
+        let t = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        assert!(
+            niell.mapping_factors(t, 45.0, 5.0, 2000.0, 10.0).0
+                > niell.mapping_factors(t, 45.0, 5.0, 0.0, 10.0).0
+        );
+        // slant delay
+        let zenith = niell.slant_delay(t, 45.0, 5.0, 0.0, 90.0, None);
+        let (zhd, zwd) = niell.zenith_delays(t, 45.0, 0.0, None);
+        assert!((zenith - zhd - zwd).abs() < 1.0E-3);
+        assert!(niell.slant_delay(t, 45.0, 5.0, 0.0, 10.0, None) > 5.0 * zenith);
+        assert_eq!(niell.slant_delay(t, 45.0, 5.0, 0.0, -1.0, None), 0.0);
+    }
+}
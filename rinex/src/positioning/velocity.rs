@@ -0,0 +1,237 @@
+//! Receiver velocity from Doppler observations
+use super::spp::{earth_rotation, resolve, Candidate, SppOpts};
+use super::{elevation_azimuth, enu_covariance, SPEED_OF_LIGHT};
+use crate::prelude::*;
+use nalgebra::{DMatrix, DVector};
+use std::collections::{BTreeMap, HashMap};
+
+/// (Sv, line of sight, observed - modeled range rate [m.s⁻¹], weight)
+type Row = (Sv, (f64, f64, f64), f64, f64);
+
+/// Receiver velocity, at a given Epoch
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Velocity {
+    /// Velocity, ECEF WGS84 [m.s⁻¹]
+    pub ecef: (f64, f64, f64),
+    /// Velocity in the local East, North, Up frame [m.s⁻¹]
+    pub enu: (f64, f64, f64),
+    /// Covariance of the ENU velocity [m².s⁻²]
+    pub covariance: [[f64; 3]; 3],
+    /// Receiver clock drift [s.s⁻¹]
+    pub clock_drift: f64,
+    /// Post fit range rate residuals [m.s⁻¹], for every Sv that contributed
+    pub residuals: HashMap<Sv, f64>,
+}
+
+impl Velocity {
+    /// Returns the 3D speed [m.s⁻¹]
+    pub fn speed(&self) -> f64 {
+        (self.enu.0.powi(2) + self.enu.1.powi(2) + self.enu.2.powi(2)).sqrt()
+    }
+    /// Returns the horizontal speed [m.s⁻¹]
+    pub fn horizontal_speed(&self) -> f64 {
+        (self.enu.0.powi(2) + self.enu.1.powi(2)).sqrt()
+    }
+    /// Returns the East, North and Up standard deviations [m.s⁻¹]
+    pub fn std_enu(&self) -> (f64, f64, f64) {
+        (
+            self.covariance[0][0].sqrt(),
+            self.covariance[1][1].sqrt(),
+            self.covariance[2][2].sqrt(),
+        )
+    }
+}
+
+/*
+ * Single epoch weighted least squares on the range rates,
+ * at given receiver position. The problem is linear: no iteration.
+ */
+fn solve_epoch(
+    candidates: &[Candidate],
+    position: (f64, f64, f64),
+    opts: &SppOpts,
+) -> Option<Velocity> {
+    let mut rows: Vec<Row> = Vec::new();
+    for candidate in candidates {
+        let doppler = match candidate.doppler {
+            Some(doppler) => doppler,
+            None => continue,
+        };
+        let (x, y, z) = candidate.state.position;
+        let dx = (x - position.0, y - position.1, z - position.2);
+        let tau = (dx.0.powi(2) + dx.1.powi(2) + dx.2.powi(2)).sqrt() / SPEED_OF_LIGHT;
+        let sv_pos = earth_rotation(candidate.state.position, tau);
+        let sv_vel = earth_rotation(candidate.state.velocity, tau);
+        let (elev, _) = elevation_azimuth(position, sv_pos);
+        if elev < opts.elev_mask {
+            continue;
+        }
+        let los = (
+            sv_pos.0 - position.0,
+            sv_pos.1 - position.1,
+            sv_pos.2 - position.2,
+        );
+        let rho = (los.0.powi(2) + los.1.powi(2) + los.2.powi(2)).sqrt();
+        let los = (los.0 / rho, los.1 / rho, los.2 / rho);
+        // positive doppler: approaching vehicle
+        let range_rate = -doppler * candidate.carrier.wavelength();
+        let sv_rate = sv_vel.0 * los.0 + sv_vel.1 * los.1 + sv_vel.2 * los.2;
+        rows.push((
+            candidate.sv,
+            los,
+            range_rate - sv_rate + SPEED_OF_LIGHT * candidate.state.clock_drift,
+            elev.to_radians().sin().powi(2),
+        ));
+    }
+    // 3 velocity components + clock drift, with redundancy
+    if rows.len() < 5 {
+        return None;
+    }
+    let mut h = DMatrix::<f64>::zeros(rows.len(), 4);
+    let mut w = DMatrix::<f64>::zeros(rows.len(), rows.len());
+    let mut y = DVector::<f64>::zeros(rows.len());
+    for (i, (_, los, rate, weight)) in rows.iter().enumerate() {
+        h[(i, 0)] = -los.0;
+        h[(i, 1)] = -los.1;
+        h[(i, 2)] = -los.2;
+        h[(i, 3)] = 1.0;
+        w[(i, i)] = *weight;
+        y[i] = *rate;
+    }
+    let ht = h.transpose();
+    let q = (&ht * &w * &h).try_inverse()?;
+    let x = &q * &ht * &w * &y;
+    let postfit = &y - &h * &x;
+    // a posteriori variance factor
+    let sigma0 = (postfit.transpose() * &w * &postfit)[(0, 0)] / (rows.len() - 4) as f64;
+
+    let mut q_xyz = [[0.0_f64; 3]; 3];
+    for (i, q_i) in q_xyz.iter_mut().enumerate() {
+        for (j, q_ij) in q_i.iter_mut().enumerate() {
+            *q_ij = sigma0 * q[(i, j)];
+        }
+    }
+    let ecef = (x[0], x[1], x[2]);
+    let (lat, lon, _) =
+        map_3d::ecef2geodetic(position.0, position.1, position.2, map_3d::Ellipsoid::WGS84);
+    let r = super::ecef2enu(lat, lon);
+    let enu = (
+        r[0][0] * ecef.0 + r[0][1] * ecef.1 + r[0][2] * ecef.2,
+        r[1][0] * ecef.0 + r[1][1] * ecef.1 + r[1][2] * ecef.2,
+        r[2][0] * ecef.0 + r[2][1] * ecef.1 + r[2][2] * ecef.2,
+    );
+    Some(Velocity {
+        ecef,
+        enu,
+        covariance: enu_covariance(position, q_xyz),
+        clock_drift: x[3] / SPEED_OF_LIGHT,
+        residuals: rows
+            .iter()
+            .enumerate()
+            .map(|(i, (sv, _, _, _))| (*sv, postfit[i]))
+            .collect(),
+    })
+}
+
+/*
+ * Resolves the receiver velocity, at every epoch of the `obs` RINEX
+ * where a navigation solution was obtained.
+ */
+pub(crate) fn solve(obs: &Rinex, nav: &Rinex, opts: &SppOpts) -> BTreeMap<Epoch, Velocity> {
+    let mut ret: BTreeMap<Epoch, Velocity> = BTreeMap::new();
+    resolve(obs, nav, opts, |t, candidates, solution| {
+        if let Some(velocity) = solve_epoch(&candidates, solution.position, opts) {
+            ret.insert(t, velocity);
+        }
+    });
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::carrier::Carrier;
+    use crate::positioning::ecef2enu;
+    use crate::positioning::spp::SvState;
+    #[test]
+    fn synthetic_velocity() {
+        let (lat, lon) = (52.0_f64, 4.3_f64);
+        let rcvr = GroundPosition::from_geodetic((lat, lon, 50.0)).to_ecef_wgs84();
+        let r = ecef2enu(lat.to_radians(), lon.to_radians());
+        // 10 m/s East, 5 m/s North, 0.1 m/s Up
+        let v_enu = (10.0, 5.0, 0.1);
+        let v_rx = (
+            r[0][0] * v_enu.0 + r[1][0] * v_enu.1 + r[2][0] * v_enu.2,
+            r[0][1] * v_enu.0 + r[1][1] * v_enu.1 + r[2][1] * v_enu.2,
+            r[0][2] * v_enu.0 + r[1][2] * v_enu.1 + r[2][2] * v_enu.2,
+        );
+        let drift = 1.0E-9;
+        let mut candidates = Vec::new();
+        for (prn, (elev, azim)) in [
+            (80.0_f64, 0.0_f64),
+            (45.0, 30.0),
+            (30.0, 120.0),
+            (20.0, 200.0),
+            (35.0, 280.0),
+            (60.0, 330.0),
+            (5.0, 70.0),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let (elev, azim) = (elev.to_radians(), azim.to_radians());
+            let enu = (elev.cos() * azim.sin(), elev.cos() * azim.cos(), elev.sin());
+            let los = (
+                r[0][0] * enu.0 + r[1][0] * enu.1 + r[2][0] * enu.2,
+                r[0][1] * enu.0 + r[1][1] * enu.1 + r[2][1] * enu.2,
+                r[0][2] * enu.0 + r[1][2] * enu.1 + r[2][2] * enu.2,
+            );
+            let range = 22.0E6;
+            let tau = range / SPEED_OF_LIGHT;
+            let sv_rx = (
+                rcvr.0 + range * los.0,
+                rcvr.1 + range * los.1,
+                rcvr.2 + range * los.2,
+            );
+            // sv velocity, expressed in the reception frame
+            let v_sv = (1000.0 * prn as f64, -2000.0, 500.0);
+            let sv_drift = 1.0E-11;
+            let rate = (v_sv.0 - v_rx.0) * los.0
+                + (v_sv.1 - v_rx.1) * los.1
+                + (v_sv.2 - v_rx.2) * los.2
+                + SPEED_OF_LIGHT * (drift - sv_drift);
+            let carrier = Carrier::L1;
+            candidates.push(Candidate {
+                sv: Sv::new(Constellation::GPS, prn as u8 + 1),
+                carrier,
+                pr: range,
+                doppler: Some(-rate / carrier.wavelength()),
+                state: SvState {
+                    position: earth_rotation(sv_rx, -tau),
+                    velocity: earth_rotation(v_sv, -tau),
+                    clock: 0.0,
+                    clock_drift: sv_drift,
+                },
+            });
+        }
+        let opts = SppOpts::default();
+        let velocity =
+            solve_epoch(&candidates, rcvr, &opts).expect("failed to resolve synthetic velocity");
+        // 5° vehicle is masked out
+        assert_eq!(velocity.residuals.len(), 6);
+        assert!((velocity.enu.0 - v_enu.0).abs() < 1.0E-6);
+        assert!((velocity.enu.1 - v_enu.1).abs() < 1.0E-6);
+        assert!((velocity.enu.2 - v_enu.2).abs() < 1.0E-6);
+        assert!((velocity.clock_drift - drift).abs() < 1.0E-15);
+        assert!((velocity.speed() - 125.01_f64.sqrt()).abs() < 1.0E-6);
+        assert!((velocity.horizontal_speed() - 125.0_f64.sqrt()).abs() < 1.0E-6);
+        let (std_e, std_n, std_u) = velocity.std_enu();
+        assert!(std_e < 1.0E-6 && std_n < 1.0E-6 && std_u < 1.0E-6);
+        // missing doppler observations
+        for candidate in candidates.iter_mut().skip(2) {
+            candidate.doppler = None;
+        }
+        assert!(solve_epoch(&candidates, rcvr, &opts).is_none());
+    }
+}
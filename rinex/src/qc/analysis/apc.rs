@@ -0,0 +1,211 @@
+use crate::positioning::{ecef2enu, Solution, SppOpts};
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub struct QcApcAnalysis {
+    /// Number of navigation solutions
+    pub solutions: usize,
+    /// Mean antenna position
+    pub mean: GroundPosition,
+    /// Standard deviation of the solutions [m], in the local East, North, Up frame
+    pub std_enu: (f64, f64, f64),
+    /// Mean position offset [m] (East, North, Up) from the reference position
+    pub offset_enu: Option<(f64, f64, f64)>,
+    /// Mean Position Dilution of Precision
+    pub mean_pdop: f64,
+}
+
+/*
+ * Projects an ECEF vector to the local ENU frame, at given position
+ */
+fn to_enu(position: (f64, f64, f64), vector: (f64, f64, f64)) -> (f64, f64, f64) {
+    let (lat, lon, _) =
+        map_3d::ecef2geodetic(position.0, position.1, position.2, map_3d::Ellipsoid::WGS84);
+    let r = ecef2enu(lat, lon);
+    let v = [vector.0, vector.1, vector.2];
+    let dot = |row: [f64; 3]| row[0] * v[0] + row[1] * v[1] + row[2] * v[2];
+    (dot(r[0]), dot(r[1]), dot(r[2]))
+}
+
+impl QcApcAnalysis {
+    /// Estimates the antenna position by Single Point Positioning
+    /// (`obs` and `nav` context), and compares it to the
+    /// `reference` position, if provided.
+    /// Returns None if no solution could be resolved.
+    pub fn new(
+        obs: &Rinex,
+        nav: &Rinex,
+        reference: Option<GroundPosition>,
+        opts: SppOpts,
+    ) -> Option<Self> {
+        let solutions = obs.spp(nav, opts);
+        Self::from_solutions(&solutions, reference)
+    }
+    fn from_solutions(
+        solutions: &BTreeMap<Epoch, Solution>,
+        reference: Option<GroundPosition>,
+    ) -> Option<Self> {
+        let n = solutions.len();
+        if n == 0 {
+            return None;
+        }
+        let (mut mean, mut mean_pdop) = ((0.0_f64, 0.0_f64, 0.0_f64), 0.0_f64);
+        for solution in solutions.values() {
+            mean.0 += solution.position.0 / n as f64;
+            mean.1 += solution.position.1 / n as f64;
+            mean.2 += solution.position.2 / n as f64;
+            mean_pdop += solution.dop.pdop / n as f64;
+        }
+        let mut var = (0.0_f64, 0.0_f64, 0.0_f64);
+        for solution in solutions.values() {
+            let p = solution.position;
+            let (e, n_, u) = to_enu(mean, (p.0 - mean.0, p.1 - mean.1, p.2 - mean.2));
+            var.0 += e.powi(2) / n as f64;
+            var.1 += n_.powi(2) / n as f64;
+            var.2 += u.powi(2) / n as f64;
+        }
+        let offset_enu = reference.map(|reference| {
+            let r = reference.to_ecef_wgs84();
+            to_enu(r, (mean.0 - r.0, mean.1 - r.1, mean.2 - r.2))
+        });
+        Some(Self {
+            solutions: n,
+            mean: GroundPosition::from_ecef_wgs84(mean),
+            std_enu: (var.0.sqrt(), var.1.sqrt(), var.2.sqrt()),
+            offset_enu,
+            mean_pdop,
+        })
+    }
+}
+
+use crate::qc::HtmlReport;
+use horrorshow::{helper::doctype, RenderBox};
+
+impl HtmlReport for QcApcAnalysis {
+    fn to_html(&self) -> String {
+        format!(
+            "{}",
+            html! {
+                : doctype::HTML;
+                html {
+                    head {
+                        meta(content="text/html", charset="utf-8");
+                        meta(name="viewport", content="width=device-width, initial-scale=1");
+                        link(rel="stylesheet", href="https:////cdn.jsdelivr.net/npm/bulma@0.9.4/css/bulma.min.css");
+                        title {
+                            : "Antenna Phase Center"
+                        }
+                    }
+                    body {
+                        table(class="table is-bordered") {
+                            tbody {
+                                : self.to_inline_html()
+                            }
+                        }
+                    }
+                }
+            }
+        )
+    }
+    fn to_inline_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {
+            tr {
+                th {
+                    : "Solutions"
+                }
+                td {
+                    : self.solutions.to_string()
+                }
+            }
+            tr {
+                th {
+                    : "Mean position"
+                }
+            }
+            : self.mean.to_inline_html();
+            tr {
+                th {
+                    : "Standard deviation"
+                }
+                td {
+                    : format!("East: {:.3} m", self.std_enu.0)
+                }
+                td {
+                    : format!("North: {:.3} m", self.std_enu.1)
+                }
+                td {
+                    : format!("Up: {:.3} m", self.std_enu.2)
+                }
+            }
+            tr {
+                th {
+                    : "|QC - reference|"
+                }
+                @ if let Some((e, n, u)) = self.offset_enu {
+                    td {
+                        : format!("East: {:.3} m", e)
+                    }
+                    td {
+                        : format!("North: {:.3} m", n)
+                    }
+                    td {
+                        : format!("Up: {:.3} m", u)
+                    }
+                } else {
+                    td {
+                        : "Unknown reference position"
+                    }
+                }
+            }
+            tr {
+                th {
+                    : "Mean PDOP"
+                }
+                td {
+                    : format!("{:.3}", self.mean_pdop)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::positioning::Dop;
+    #[test]
+    fn apc_statistics() {
+        let reference = GroundPosition::from_geodetic((55.0, 8.0, 0.0));
+        let r = reference.to_ecef_wgs84();
+        let mut solutions = BTreeMap::new();
+        for (i, dz) in [-1.0, 1.0, -1.0, 1.0].iter().enumerate() {
+            solutions.insert(
+                Epoch::from_gregorian_utc(2020, 6, 25, 0, 0, i as u8, 0),
+                Solution {
+                    position: (r.0, r.1, r.2 + 10.0 + dz),
+                    dop: Dop {
+                        pdop: 2.0,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            );
+        }
+        let apc = QcApcAnalysis::from_solutions(&solutions, Some(reference)).unwrap();
+        assert_eq!(apc.solutions, 4);
+        assert!((apc.mean_pdop - 2.0).abs() < 1.0E-9);
+        // 10m along Z: mostly North and Up, at 55°N
+        let (e, n, u) = apc.offset_enu.unwrap();
+        let lat = 55.0_f64.to_radians();
+        assert!(e.abs() < 1.0E-6);
+        assert!((n - 10.0 * lat.cos()).abs() < 1.0E-3);
+        assert!((u - 10.0 * lat.sin()).abs() < 1.0E-3);
+        let std = (apc.std_enu.0.powi(2) + apc.std_enu.1.powi(2) + apc.std_enu.2.powi(2)).sqrt();
+        assert!((std - 1.0).abs() < 1.0E-6);
+        let html = apc.to_html();
+        assert!(html.contains("Antenna Phase Center"));
+        assert!(html.contains("Mean PDOP"));
+        assert!(QcApcAnalysis::from_solutions(&BTreeMap::new(), None).is_none());
+    }
+}
@@ -0,0 +1,112 @@
+use super::QcOpts;
+use crate::clocks::Deviation;
+use crate::prelude::*;
+
+/// Clock name, estimator, (τ, deviation) series
+type Stability = (String, Deviation, Vec<(Duration, f64)>);
+
+#[derive(Debug, Clone)]
+pub struct QcClockAnalysis {
+    /// Frequency stability, per clock and estimator
+    pub stability: Vec<Stability>,
+}
+
+impl QcClockAnalysis {
+    pub fn new(rnx: &Rinex, _opts: &QcOpts) -> Self {
+        let mut stability = Vec::new();
+        if rnx.is_clocks_rinex() {
+            for system in rnx.clock_ref_systems() {
+                for dev in Deviation::ALL {
+                    let values = rnx.clock_stability(&system, dev);
+                    if !values.is_empty() {
+                        stability.push((system.to_string(), dev, values));
+                    }
+                }
+            }
+        }
+        #[cfg(feature = "obs")]
+        if rnx.is_observation_rinex() {
+            for dev in Deviation::ALL {
+                let values = rnx.recvr_clock_stability(dev);
+                if !values.is_empty() {
+                    stability.push(("Receiver".to_string(), dev, values));
+                }
+            }
+        }
+        Self { stability }
+    }
+}
+
+use crate::qc::HtmlReport;
+use horrorshow::{helper::doctype, RenderBox};
+
+impl HtmlReport for QcClockAnalysis {
+    fn to_html(&self) -> String {
+        format!(
+            "{}",
+            html! {
+                : doctype::HTML;
+                html {
+                    head {
+                        meta(content="text/html", charset="utf-8");
+                        meta(name="viewport", content="width=device-width, initial-scale=1");
+                        link(rel="stylesheet", href="https:////cdn.jsdelivr.net/npm/bulma@0.9.4/css/bulma.min.css");
+                        title {
+                            : "Clock stability"
+                        }
+                    }
+                    body {
+                        table(class="table is-bordered") {
+                            tbody {
+                                : self.to_inline_html()
+                            }
+                        }
+                    }
+                }
+            }
+        )
+    }
+    fn to_inline_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {
+            @ if self.stability.is_empty() {
+                tr {
+                    th {
+                        : "Stability"
+                    }
+                    td {
+                        : "Unfeasible"
+                    }
+                }
+            } else {
+                tr {
+                    th {
+                        : "Clock"
+                    }
+                    th {
+                        : "Estimator"
+                    }
+                    th {
+                        : "τ"
+                    }
+                }
+                @ for (clock, dev, values) in &self.stability {
+                    tr {
+                        td {
+                            : clock.to_string()
+                        }
+                        td {
+                            : dev.to_string()
+                        }
+                        td {
+                            @ for (tau, value) in values {
+                                p {
+                                    : format!("{}: {:.3E}", tau, value)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
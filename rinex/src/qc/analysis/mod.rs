@@ -9,6 +9,24 @@ use obs::QcObsAnalysis;
 
 mod sampling;
 
+mod clock;
+use clock::QcClockAnalysis;
+
+#[cfg(feature = "nav")]
+mod sisre;
+#[cfg(feature = "nav")]
+pub use sisre::QcSisreAnalysis;
+
+#[cfg(feature = "positioning")]
+mod apc;
+#[cfg(feature = "positioning")]
+pub use apc::QcApcAnalysis;
+
+#[cfg(feature = "positioning")]
+mod velocity;
+#[cfg(feature = "positioning")]
+pub use velocity::QcVelocityAnalysis;
+
 use sampling::QcSamplingAnalysis;
 use sv::QcSvAnalysis;
 
@@ -27,6 +45,9 @@ pub struct QcAnalysis {
     /// is truly complete when both "obs" and "processing"
     /// features are enabled
     observ: QcObsAnalysis,
+    /// Clock stability analysis, either on
+    /// Clock RINEX or receiver clock offsets
+    clock: QcClockAnalysis,
 }
 
 impl QcAnalysis {
@@ -39,6 +60,7 @@ impl QcAnalysis {
             sampling: QcSamplingAnalysis::new(rnx, opts),
             #[cfg(feature = "obs")]
             observ: QcObsAnalysis::new(rnx, nav, opts),
+            clock: QcClockAnalysis::new(rnx, opts),
         }
     }
 }
@@ -104,6 +126,18 @@ impl HtmlReport for QcAnalysis {
                         }
                     }
                 }
+                div(id="clocks") {
+                    table(class="table is-bordered") {
+                        thead {
+                            th {
+                                : "Clock stability"
+                            }
+                        }
+                        tbody {
+                            : self.clock.to_inline_html()
+                        }
+                    }
+                }
             }
         }
     }
@@ -1,4 +1,9 @@
-use crate::{carrier, observation::Snr, prelude::*, Carrier};
+use crate::{
+    carrier,
+    observation::{LliFlags, Snr},
+    prelude::*,
+    Carrier,
+};
 
 use super::{pretty_array, QcOpts};
 use std::collections::HashMap;
@@ -10,6 +15,15 @@ use crate::preprocessing::*; // include preprocessing toolkit when feasible,
 #[cfg(feature = "obs")]
 use crate::observation::Observation; // having this feature unlocks full OBS RINEX analysis
 
+#[cfg(feature = "obs")]
+use crate::observation::{Roti, Scintillation};
+
+/*
+ * Worst ROTI, S4 and Sigma Phi indices of a vehicle
+ */
+#[cfg(feature = "obs")]
+type IonoIndices = (Sv, Option<f64>, Option<f64>, Option<f64>);
+
 /*
  * GNSS signal special formatting
  */
@@ -249,6 +263,73 @@ fn report_ssi_statistics(
     }
 }
 
+/*
+ * Reports worst ionospheric activity indices, per vehicle
+ */
+#[cfg(feature = "obs")]
+fn report_iono_indices(indices: &Vec<IonoIndices>) -> Box<dyn RenderBox + '_> {
+    let pretty = |value: &Option<f64>| match value {
+        Some(value) => format!("{:.3}", value),
+        None => "/".to_string(),
+    };
+    box_html! {
+        table(class="table is-bordered") {
+            thead {
+                tr {
+                    th {
+                        : "Sv"
+                    }
+                    th {
+                        : "Max. ROTI (5') [TECu/min]"
+                    }
+                    th {
+                        : "Max. S4 (60s)"
+                    }
+                    th {
+                        : "Max. Sigma Phi (60s) [rad]"
+                    }
+                }
+            }
+            tbody {
+                @ for (sv, roti, s4, sigma_phi) in indices {
+                    tr {
+                        th {
+                            : sv.to_string()
+                        }
+                        td {
+                            : pretty(roti)
+                        }
+                        td {
+                            : pretty(s4)
+                        }
+                        td {
+                            : pretty(sigma_phi)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/*
+ * Worst index value, per vehicle, over all signals
+ */
+#[cfg(feature = "obs")]
+fn max_index(
+    indices: &HashMap<Observable, HashMap<Sv, std::collections::BTreeMap<Epoch, f64>>>,
+    sv: &Sv,
+) -> Option<f64> {
+    indices
+        .values()
+        .filter_map(|vehicles| vehicles.get(sv))
+        .flat_map(|values| values.values())
+        .copied()
+        .fold(None, |max, value| {
+            Some(max.map_or(value, |max: f64| max.max(value)))
+        })
+}
+
 #[derive(Debug, Clone)]
 /// OBS RINEX specific QC analysis.  
 /// Full OBS RINEX analysis requires both the "obs" and "processing" features.
@@ -263,6 +344,8 @@ pub struct QcObsAnalysis {
     has_doppler: bool,
     /// Abnormal events, by chronological epochs
     anomalies: Vec<(Epoch, EpochFlag)>,
+    /// Phase observations flagged with a loss of lock
+    cycle_slips: usize,
     /// Total number of epochs identified
     total_epochs: usize,
     /// Epochs with at least 1 observation
@@ -275,12 +358,26 @@ pub struct QcObsAnalysis {
     #[cfg(feature = "obs")]
     /// SSi statistical analysis (mean, stddev, skew)
     ssi_stats: HashMap<Observable, (f64, f64, f64)>,
+    #[cfg(feature = "obs")]
+    /// Worst ROTI, S4 and Sigma Phi indices, per Sv
+    iono_indices: Vec<IonoIndices>,
     #[cfg(feature = "processing")]
     /// Receiver clock drift analysis
     clock_drift: Option<f64>,
 }
 
 impl QcObsAnalysis {
+    /*
+     * Worst ionospheric indices, only available with "obs" feature
+     */
+    #[cfg(feature = "obs")]
+    fn iono_indices_report(&self) -> Box<dyn RenderBox + '_> {
+        report_iono_indices(&self.iono_indices)
+    }
+    #[cfg(not(feature = "obs"))]
+    fn iono_indices_report(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {}
+    }
     pub fn new(rnx: &Rinex, _nav: &Option<Rinex>, opts: &QcOpts) -> Self {
         let sv: Vec<_> = rnx.sv().collect();
         let obs = rnx.header.obs.as_ref().unwrap();
@@ -290,6 +387,7 @@ impl QcObsAnalysis {
         let mut signals: Vec<Carrier> = Vec::new();
         let mut codes: Vec<String> = Vec::new();
         let mut anomalies: Vec<(Epoch, EpochFlag)> = Vec::new();
+        let mut cycle_slips: usize = 0;
         let mut total_epochs: usize = 0;
         let mut epoch_with_obs: Vec<Epoch> = Vec::new();
         let mut complete_epochs: HashMap<Carrier, usize> = HashMap::new();
@@ -298,6 +396,8 @@ impl QcObsAnalysis {
             (Sv::default(), Epoch::default(), Snr::DbHz0),
         );
         let mut ssi_stats: HashMap<Observable, (f64, f64, f64)> = HashMap::new();
+        #[cfg(feature = "obs")]
+        let mut iono_indices: Vec<IonoIndices> = Vec::new();
 
         let mut clock_drift: Option<f64> = None;
 
@@ -334,6 +434,15 @@ impl QcObsAnalysis {
                         if !codes.contains(&code) {
                             codes.push(code);
                         }
+                        if observable.is_phase_observable() {
+                            let lock_loss = observation
+                                .lli
+                                .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                                .unwrap_or(false);
+                            if lock_loss {
+                                cycle_slips += 1;
+                            }
+                        }
 
                         if let Some(snr) = observation.snr {
                             if snr < min_max_snr.0 .2 {
@@ -448,6 +557,27 @@ impl QcObsAnalysis {
                     *sk = skew;
                 }
             }
+
+            /*
+             * Ionospheric activity
+             */
+            #[cfg(feature = "obs")]
+            {
+                let roti = r.roti(Duration::from_seconds(300.0));
+                let s4 = r.s4(Duration::from_seconds(60.0));
+                let sigma_phi = r.sigma_phi(Duration::from_seconds(60.0));
+                for sv in sv.iter() {
+                    let indices = (
+                        max_index(&roti, sv),
+                        max_index(&s4, sv),
+                        max_index(&sigma_phi, sv),
+                    );
+                    if indices != (None, None, None) {
+                        iono_indices.push((*sv, indices.0, indices.1, indices.2));
+                    }
+                }
+                iono_indices.sort_by_key(|(sv, _, _, _)| *sv);
+            }
         }
 
         codes.sort();
@@ -469,6 +599,7 @@ impl QcObsAnalysis {
             codes,
             signals,
             anomalies,
+            cycle_slips,
             total_epochs,
             total_with_obs: epoch_with_obs.len(),
             complete_epochs: {
@@ -479,6 +610,8 @@ impl QcObsAnalysis {
             },
             min_max_snr,
             ssi_stats,
+            #[cfg(feature = "obs")]
+            iono_indices,
             clock_drift,
         }
     }
@@ -531,6 +664,14 @@ impl HtmlReport for QcObsAnalysis {
                     }
                 }
             }
+            tr {
+                th {
+                    : "Cycle slips"
+                }
+                td {
+                    : self.cycle_slips.to_string()
+                }
+            }
             div(class="table-container") {
                 : report_anomalies(&self.anomalies)
             }
@@ -578,6 +719,9 @@ impl HtmlReport for QcObsAnalysis {
             div(class="epoch-completion") {
                 : report_ssi_statistics(&self.ssi_stats)
             }
+            div(class="table-container") {
+                : self.iono_indices_report()
+            }
         }
     }
 }
@@ -0,0 +1,91 @@
+use crate::navigation::{PreciseOrbits, SisreStats};
+use crate::prelude::*;
+
+#[derive(Debug, Clone)]
+pub struct QcSisreAnalysis {
+    /// SISRE statistics, per constellation
+    pub stats: Vec<(Constellation, SisreStats)>,
+}
+
+impl QcSisreAnalysis {
+    /// Compares broadcast ephemerides (`nav`) to precise products
+    pub fn new(nav: &Rinex, orbits: Option<&PreciseOrbits>, clocks: Option<&Rinex>) -> Self {
+        let mut stats: Vec<_> = nav.sisre(orbits, clocks).into_iter().collect();
+        stats.sort_by_key(|(constellation, _)| *constellation);
+        Self { stats }
+    }
+}
+
+use crate::qc::HtmlReport;
+use horrorshow::RenderBox;
+
+fn meters(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.3E} m", value),
+        None => "N/A".to_string(),
+    }
+}
+
+impl HtmlReport for QcSisreAnalysis {
+    fn to_html(&self) -> String {
+        todo!()
+    }
+    fn to_inline_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {
+            tr {
+                th {
+                    : "Constellation"
+                }
+                th {
+                    : "Radial (RMS)"
+                }
+                th {
+                    : "Along track (RMS)"
+                }
+                th {
+                    : "Cross track (RMS)"
+                }
+                th {
+                    : "Clock (RMS)"
+                }
+                th {
+                    : "Orbit SISRE"
+                }
+                th {
+                    : "SISRE"
+                }
+                th {
+                    : "Samples"
+                }
+            }
+            @ for (constellation, stats) in &self.stats {
+                tr {
+                    td {
+                        : constellation.to_string()
+                    }
+                    td {
+                        : meters(stats.radial_rms)
+                    }
+                    td {
+                        : meters(stats.along_rms)
+                    }
+                    td {
+                        : meters(stats.cross_rms)
+                    }
+                    td {
+                        : meters(stats.clock_rms)
+                    }
+                    td {
+                        : meters(stats.orbit_sisre)
+                    }
+                    td {
+                        : meters(stats.sisre)
+                    }
+                    td {
+                        : stats.samples.to_string()
+                    }
+                }
+            }
+        }
+    }
+}
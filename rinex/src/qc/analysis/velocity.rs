@@ -0,0 +1,226 @@
+use crate::positioning::{SppOpts, Velocity};
+use crate::prelude::*;
+use std::collections::BTreeMap;
+
+/// RMS speed [m.s⁻¹] above which a segment is reported as kinematic
+const MOTION_THRESHOLD: f64 = 0.1;
+
+/// Velocity statistics, between two "antenna being moved" events
+#[derive(Debug, Clone, PartialEq)]
+pub struct QcVelocitySegment {
+    /// First epoch of this segment
+    pub start: Epoch,
+    /// Last epoch of this segment
+    pub end: Epoch,
+    /// Number of velocity solutions
+    pub solutions: usize,
+    /// Mean East, North, Up velocity [m.s⁻¹]
+    pub mean_enu: (f64, f64, f64),
+    /// RMS speed [m.s⁻¹]
+    pub rms_speed: f64,
+    /// Maximal speed [m.s⁻¹]
+    pub max_speed: f64,
+    /// Mean receiver clock drift [s.s⁻¹]
+    pub mean_clock_drift: f64,
+}
+
+impl QcVelocitySegment {
+    /// Returns true if the antenna was moving during this segment
+    pub fn kinematic(&self) -> bool {
+        self.rms_speed > MOTION_THRESHOLD
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QcVelocityAnalysis {
+    /// Epochs of "antenna being moved" events
+    pub events: Vec<Epoch>,
+    /// Velocity statistics, per segment delimited by these events
+    pub segments: Vec<QcVelocitySegment>,
+}
+
+impl QcVelocityAnalysis {
+    /// Estimates the receiver velocity from Doppler observations
+    /// (`obs` and `nav` context) and analyzes it
+    /// in between the "antenna being moved" events of `obs`.
+    /// Returns None if no velocity could be resolved.
+    pub fn new(obs: &Rinex, nav: &Rinex, opts: SppOpts) -> Option<Self> {
+        let events: Vec<Epoch> = match obs.record.as_obs() {
+            Some(record) => record
+                .keys()
+                .filter_map(|(t, flag)| {
+                    if *flag == EpochFlag::AntennaBeingMoved {
+                        Some(*t)
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let velocities = obs.doppler_velocity(nav, opts);
+        Self::from_velocities(&velocities, events)
+    }
+    fn from_velocities(velocities: &BTreeMap<Epoch, Velocity>, events: Vec<Epoch>) -> Option<Self> {
+        if velocities.is_empty() {
+            return None;
+        }
+        let mut segments: Vec<QcVelocitySegment> = Vec::new();
+        let mut bounds: Vec<Option<Epoch>> = vec![None];
+        bounds.extend(events.iter().map(|t| Some(*t)));
+        bounds.push(None);
+        for window in bounds.windows(2) {
+            let segment: Vec<(&Epoch, &Velocity)> = velocities
+                .iter()
+                .filter(|(t, _)| {
+                    window[0].map(|start| **t >= start).unwrap_or(true)
+                        && window[1].map(|end| **t < end).unwrap_or(true)
+                })
+                .collect();
+            if segment.is_empty() {
+                continue;
+            }
+            let n = segment.len() as f64;
+            let (mut mean_enu, mut sum_sq, mut max_speed, mut drift) =
+                ((0.0_f64, 0.0_f64, 0.0_f64), 0.0_f64, 0.0_f64, 0.0_f64);
+            for (_, velocity) in &segment {
+                mean_enu.0 += velocity.enu.0 / n;
+                mean_enu.1 += velocity.enu.1 / n;
+                mean_enu.2 += velocity.enu.2 / n;
+                let speed = velocity.speed();
+                sum_sq += speed.powi(2);
+                max_speed = max_speed.max(speed);
+                drift += velocity.clock_drift / n;
+            }
+            segments.push(QcVelocitySegment {
+                start: *segment[0].0,
+                end: *segment[segment.len() - 1].0,
+                solutions: segment.len(),
+                mean_enu,
+                rms_speed: (sum_sq / n).sqrt(),
+                max_speed,
+                mean_clock_drift: drift,
+            });
+        }
+        Some(Self { events, segments })
+    }
+}
+
+use crate::qc::HtmlReport;
+use horrorshow::RenderBox;
+
+impl HtmlReport for QcVelocityAnalysis {
+    fn to_html(&self) -> String {
+        todo!()
+    }
+    fn to_inline_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {
+            tr {
+                th {
+                    : "Antenna being moved"
+                }
+                td {
+                    : self.events.len().to_string()
+                }
+            }
+            tr {
+                th {
+                    : "Start"
+                }
+                th {
+                    : "End"
+                }
+                th {
+                    : "Solutions"
+                }
+                th {
+                    : "Mean velocity (ENU)"
+                }
+                th {
+                    : "RMS speed"
+                }
+                th {
+                    : "Max. speed"
+                }
+                th {
+                    : "Clock drift"
+                }
+                th {
+                    : "Motion"
+                }
+            }
+            @ for segment in &self.segments {
+                tr {
+                    td {
+                        : segment.start.to_string()
+                    }
+                    td {
+                        : segment.end.to_string()
+                    }
+                    td {
+                        : segment.solutions.to_string()
+                    }
+                    td {
+                        : format!("{:.3} {:.3} {:.3} m/s", segment.mean_enu.0, segment.mean_enu.1, segment.mean_enu.2)
+                    }
+                    td {
+                        : format!("{:.3} m/s", segment.rms_speed)
+                    }
+                    td {
+                        : format!("{:.3} m/s", segment.max_speed)
+                    }
+                    td {
+                        : format!("{:.3E} s/s", segment.mean_clock_drift)
+                    }
+                    td {
+                        @ if segment.kinematic() {
+                            : "Kinematic"
+                        } else {
+                            : "Static"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[test]
+    fn velocity_segments() {
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        let mut velocities = BTreeMap::new();
+        for i in 0..30 {
+            let t = t0 + Duration::from_seconds(30.0 * i as f64);
+            // antenna moved in between events
+            let east = if (10..20).contains(&i) { 1.0 } else { 0.001 };
+            velocities.insert(
+                t,
+                Velocity {
+                    enu: (east, 0.0, 0.0),
+                    clock_drift: 1.0E-9,
+                    ..Default::default()
+                },
+            );
+        }
+        let events = vec![
+            t0 + Duration::from_seconds(300.0),
+            t0 + Duration::from_seconds(600.0),
+        ];
+        let analysis = QcVelocityAnalysis::from_velocities(&velocities, events).unwrap();
+        assert_eq!(analysis.events.len(), 2);
+        assert_eq!(analysis.segments.len(), 3);
+        let kinematic: Vec<bool> = analysis.segments.iter().map(|s| s.kinematic()).collect();
+        assert_eq!(kinematic, vec![false, true, false]);
+        let segment = &analysis.segments[1];
+        assert_eq!(segment.start, t0 + Duration::from_seconds(300.0));
+        assert_eq!(segment.end, t0 + Duration::from_seconds(570.0));
+        assert_eq!(segment.solutions, 10);
+        assert!((segment.mean_enu.0 - 1.0).abs() < 1.0E-9);
+        assert!((segment.max_speed - 1.0).abs() < 1.0E-9);
+        assert!((segment.mean_clock_drift - 1.0E-9).abs() < 1.0E-18);
+        assert!(QcVelocityAnalysis::from_velocities(&BTreeMap::new(), Vec::new()).is_none());
+    }
+}
@@ -8,6 +8,18 @@ pub use opts::{QcClassification, QcOpts};
 mod analysis;
 use analysis::QcAnalysis;
 
+#[cfg(feature = "nav")]
+use analysis::QcSisreAnalysis;
+
+#[cfg(feature = "nav")]
+use crate::navigation::PreciseOrbits;
+
+#[cfg(feature = "positioning")]
+use analysis::{QcApcAnalysis, QcVelocityAnalysis};
+
+#[cfg(feature = "positioning")]
+use crate::positioning::SppOpts;
+
 #[cfg(feature = "processing")]
 use crate::preprocessing::*;
 
@@ -71,6 +83,15 @@ pub struct QcReport<'a> {
     /// All analysis that were performed, sorted by
     /// opts.classification (if possible)
     analysis: Vec<QcAnalysis>,
+    /// Broadcast versus precise products analysis
+    #[cfg(feature = "nav")]
+    sisre: Option<QcSisreAnalysis>,
+    /// Antenna position estimate
+    #[cfg(feature = "positioning")]
+    apc: Option<QcApcAnalysis>,
+    /// Receiver velocity analysis
+    #[cfg(feature = "positioning")]
+    velocity: Option<QcVelocityAnalysis>,
 }
 
 impl<'a> QcReport<'a> {
@@ -97,6 +118,12 @@ impl<'a> QcReport<'a> {
                 nav_filenames,
                 nav_rinex: nav_rinex.clone(),
                 analysis: vec![QcAnalysis::new(rnx, &nav_rinex, &opts)],
+                #[cfg(feature = "nav")]
+                sisre: None,
+                #[cfg(feature = "positioning")]
+                apc: None,
+                #[cfg(feature = "positioning")]
+                velocity: None,
                 opts,
                 constellations: {
                     //TODO: take secondary file into account,
@@ -183,7 +210,64 @@ impl<'a> QcReport<'a> {
             analysis,
             nav_rinex,
             nav_filenames,
+            #[cfg(feature = "nav")]
+            sisre: None,
+            #[cfg(feature = "positioning")]
+            apc: None,
+            #[cfg(feature = "positioning")]
+            velocity: None,
+        }
+    }
+    /// Augments this report with a SISRE analysis, comparing the broadcast
+    /// ephemerides of the Navigation context (or primary file, if it is a NAV RINEX)
+    /// to precise orbits and/or precise clocks (Clock RINEX).
+    #[cfg(feature = "nav")]
+    #[cfg_attr(docrs, doc(cfg(feature = "nav")))]
+    pub fn with_sisre(mut self, orbits: Option<&PreciseOrbits>, clocks: Option<&Rinex>) -> Self {
+        let nav = if self.rinex.is_navigation_rinex() {
+            Some(self.rinex)
+        } else {
+            self.nav_rinex.as_ref()
+        };
+        if let Some(nav) = nav {
+            if orbits.is_some() || clocks.is_some() {
+                self.sisre = Some(QcSisreAnalysis::new(nav, orbits, clocks));
+            }
+        }
+        self
+    }
+    /// Augments this report with an antenna position estimate,
+    /// obtained by Single Point Positioning, when the primary file is an
+    /// Observation RINEX and a Navigation context was provided.
+    /// The estimate is compared to the ground position of the QC options,
+    /// or to the APPROX POSITION of the primary file.
+    #[cfg(feature = "positioning")]
+    #[cfg_attr(docrs, doc(cfg(feature = "positioning")))]
+    pub fn with_apc_estimate(mut self, opts: SppOpts) -> Self {
+        if self.rinex.is_observation_rinex() {
+            if let Some(nav) = &self.nav_rinex {
+                let reference = self
+                    .opts
+                    .ground_position
+                    .or(self.rinex.header.ground_position);
+                self.apc = QcApcAnalysis::new(self.rinex, nav, reference, opts);
+            }
+        }
+        self
+    }
+    /// Augments this report with a receiver velocity analysis, obtained from
+    /// the Doppler observations, when the primary file is an Observation RINEX
+    /// and a Navigation context was provided. The velocity is analyzed
+    /// in between "antenna being moved" events, to detect antenna motion.
+    #[cfg(feature = "positioning")]
+    #[cfg_attr(docrs, doc(cfg(feature = "positioning")))]
+    pub fn with_velocity_estimate(mut self, opts: SppOpts) -> Self {
+        if self.rinex.is_observation_rinex() {
+            if let Some(nav) = &self.nav_rinex {
+                self.velocity = QcVelocityAnalysis::new(self.rinex, nav, opts);
+            }
         }
+        self
     }
 }
 
@@ -400,6 +484,71 @@ impl<'a> HtmlReport for QcReport<'a> {
                     }
                 }
             }
+            : self.sisre_html();
+            : self.positioning_html()
         }
     }
 }
+
+impl<'a> QcReport<'a> {
+    #[cfg(feature = "nav")]
+    fn sisre_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {
+            @ if let Some(sisre) = &self.sisre {
+                div(id="sisre") {
+                    table(class="table is-bordered") {
+                        thead {
+                            th {
+                                : "Broadcast versus Precise (SISRE)"
+                            }
+                        }
+                        tbody {
+                            : sisre.to_inline_html()
+                        }
+                    }
+                }
+            }
+        }
+    }
+    #[cfg(not(feature = "nav"))]
+    fn sisre_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {}
+    }
+    #[cfg(feature = "positioning")]
+    fn positioning_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {
+            @ if let Some(apc) = &self.apc {
+                div(id="apc") {
+                    table(class="table is-bordered") {
+                        thead {
+                            th {
+                                : "Antenna position estimate (SPP)"
+                            }
+                        }
+                        tbody {
+                            : apc.to_inline_html()
+                        }
+                    }
+                }
+            }
+            @ if let Some(velocity) = &self.velocity {
+                div(id="velocity") {
+                    table(class="table is-bordered") {
+                        thead {
+                            th {
+                                : "Receiver velocity (Doppler)"
+                            }
+                        }
+                        tbody {
+                            : velocity.to_inline_html()
+                        }
+                    }
+                }
+            }
+        }
+    }
+    #[cfg(not(feature = "positioning"))]
+    fn positioning_html(&self) -> Box<dyn RenderBox + '_> {
+        box_html! {}
+    }
+}
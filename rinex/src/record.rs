@@ -185,38 +185,27 @@ impl Record {
                 }
             },
             Type::IonosphereMaps => {
-                if let Some(r) = self.as_ionex() {
-                    for (index, (epoch, (_map, _, _))) in r.iter().enumerate() {
-                        let _ = write!(writer, "{:6}                                                      START OF TEC MAP", index);
-                        let _ = write!(
-                            writer,
-                            "{}                        EPOCH OF CURRENT MAP",
-                            epoch::format(*epoch, None, Type::IonosphereMaps, 1)
-                        );
-                        let _ = write!(writer, "{:6}                                                      END OF TEC MAP", index);
+                if let (Some(r), Some(ionex)) = (self.as_ionex(), &header.ionex) {
+                    // RMS and Height maps are passed after TEC maps,
+                    // they are related to the TEC map of same index
+                    for (index, (epoch, (map, _, _))) in r.iter().enumerate() {
+                        let map = ionex::record::fmt_map(index + 1, *epoch, map, "TEC", ionex);
+                        write!(writer, "{}", map)?;
                     }
-                    /*
-                     * not efficient browsing, but matches provided examples and common formatting.
-                     * RMS and Height maps are passed after TEC maps.
-                     */
-                    for (index, (epoch, (_, _map, _))) in r.iter().enumerate() {
-                        let _ = write!(writer, "{:6}                                                      START OF RMS MAP", index);
-                        let _ = write!(
-                            writer,
-                            "{}                        EPOCH OF CURRENT MAP",
-                            epoch::format(*epoch, None, Type::IonosphereMaps, 1)
-                        );
-                        let _ = write!(writer, "{:6}                                                      END OF RMS MAP", index);
+                    for (index, (epoch, (_, map, _))) in r.iter().enumerate() {
+                        if let Some(map) = map {
+                            let map = ionex::record::fmt_map(index + 1, *epoch, map, "RMS", ionex);
+                            write!(writer, "{}", map)?;
+                        }
                     }
-                    for (index, (epoch, (_, _, _map))) in r.iter().enumerate() {
-                        let _ = write!(writer, "{:6}                                                      START OF HEIGHT MAP", index);
-                        let _ = write!(
-                            writer,
-                            "{}                        EPOCH OF CURRENT MAP",
-                            epoch::format(*epoch, None, Type::IonosphereMaps, 1)
-                        );
-                        let _ = write!(writer, "{:6}                                                      END OF HEIGHT MAP", index);
+                    for (index, (epoch, (_, _, map))) in r.iter().enumerate() {
+                        if let Some(map) = map {
+                            let map =
+                                ionex::record::fmt_map(index + 1, *epoch, map, "HEIGHT", ionex);
+                            write!(writer, "{}", map)?;
+                        }
                     }
+                    writeln!(writer, "{:60}END OF FILE", "")?;
                 }
             },
             _ => panic!("record type not supported yet"),
@@ -285,10 +274,7 @@ pub fn parse_record(
     // IONEX case
     //  Default map type is TEC, it will come with identified Epoch
     //  but others may exist:
-    //    in this case we used the previously identified Epoch
-    //    and attach other kinds of maps
-    let mut ionx_rms = false;
-    let mut ionx_height = false;
+    //    in this case we attach them to the related TEC map
     let mut ionx_rec = ionex::Record::new();
     // we need to store encountered epochs, to relate RMS and H maps
     //    that might be provided in a separate sequence
@@ -371,8 +357,6 @@ pub fn parse_record(
             // in case of CRINEX -> RINEX < 3 being recovered,
             // we have more than 1 ligne to process
             let new_epoch = is_new_epoch(line, &header);
-            ionx_rms |= ionex::record::is_new_rms_map(line);
-            ionx_height |= ionex::record::is_new_height_map(line);
 
             if new_epoch && !first_epoch {
                 match &header.rinex_type {
@@ -463,29 +447,14 @@ pub fn parse_record(
                         if let Ok((index, epoch, map)) =
                             ionex::record::parse_map(header, &epoch_content)
                         {
-                            if ionx_rms {
-                                ionx_rms = false;
-                                if let Some(e) = ionx_epochs.get(index) {
-                                    // relate
-                                    if let Some((_, rms, _)) = ionx_rec.get_mut(e) {
-                                        // locate
-                                        *rms = Some(map); // insert
-                                    }
-                                }
-                            } else if ionx_height {
-                                ionx_height = false;
-                                if let Some(e) = ionx_epochs.get(index) {
-                                    // relate
-                                    if let Some((_, _, h)) = ionx_rec.get_mut(e) {
-                                        // locate
-                                        *h = Some(map); // insert
-                                    }
-                                }
-                            } else {
-                                // TEC map => insert epoch
-                                ionx_epochs.push(epoch.clone());
-                                ionx_rec.insert(epoch, (map, None, None));
-                            }
+                            ionex::record::insert_map(
+                                &mut ionx_rec,
+                                &mut ionx_epochs,
+                                &epoch_content,
+                                index,
+                                epoch,
+                                map,
+                            );
                         }
                     },
                 }
@@ -574,26 +543,14 @@ pub fn parse_record(
         },
         Type::IonosphereMaps => {
             if let Ok((index, epoch, map)) = ionex::record::parse_map(header, &epoch_content) {
-                if ionx_rms {
-                    if let Some(e) = ionx_epochs.get(index) {
-                        // relate
-                        if let Some((_, rms, _)) = ionx_rec.get_mut(e) {
-                            // locate
-                            *rms = Some(map); // insert
-                        }
-                    }
-                } else if ionx_height {
-                    if let Some(e) = ionx_epochs.get(index) {
-                        // relate
-                        if let Some((_, _, h)) = ionx_rec.get_mut(e) {
-                            // locate
-                            *h = Some(map); // insert
-                        }
-                    }
-                } else {
-                    // introduce TEC+epoch
-                    ionx_rec.insert(epoch, (map, None, None));
-                }
+                ionex::record::insert_map(
+                    &mut ionx_rec,
+                    &mut ionx_epochs,
+                    &epoch_content,
+                    index,
+                    epoch,
+                    map,
+                );
             }
         },
         Type::AntennaData => {
@@ -651,10 +608,10 @@ impl Merge for Record {
             if let Some(rhs) = rhs.as_meteo() {
                 lhs.merge_mut(&rhs)?;
             }
-        /*} else if let Some(lhs) = self.as_mut_ionex() {
-        if let Some(rhs) = rhs.as_ionex() {
-            lhs.merge_mut(&rhs)?;
-        }*/
+        } else if let Some(lhs) = self.as_mut_ionex() {
+            if let Some(rhs) = rhs.as_ionex() {
+                lhs.merge_mut(rhs)?;
+            }
         } else if let Some(lhs) = self.as_mut_antex() {
             if let Some(rhs) = rhs.as_antex() {
                 lhs.merge_mut(&rhs)?;
@@ -193,6 +193,37 @@ fn meteo_comparison(dut: &Rinex, model: &Rinex, filename: &str) {
     }
 }
 
+fn navigation_comparison(dut: &Rinex, model: &Rinex, filename: &str) {
+    let rec_dut = dut
+        .record
+        .as_nav()
+        .expect("failed to unwrap as navigation rinex record");
+    let rec_model = model
+        .record
+        .as_nav()
+        .expect("failed to unwrap as navigation rinex record");
+    for (e_model, frames_model) in rec_model.iter() {
+        if let Some(frames_dut) = rec_dut.get(e_model) {
+            for frame in frames_model.iter() {
+                assert!(
+                    frames_dut.contains(frame),
+                    "\"{}\" - {:?} - missing or faulty frame {:?}",
+                    filename,
+                    e_model,
+                    frame
+                );
+            }
+        } else {
+            panic!("\"{}\" - missing epoch {:?}", filename, e_model);
+        }
+    }
+    for e_dut in rec_dut.keys() {
+        if !rec_model.contains_key(e_dut) {
+            panic!("\"{}\" - parsed {:?} unexpectedly", filename, e_dut);
+        }
+    }
+}
+
 /*
  * Compares "dut" Device Under Test to given Model,
  * panics on unexpected content with detailed explanations.
@@ -204,5 +235,7 @@ pub fn compare_with_panic(dut: &Rinex, model: &Rinex, filename: &str) {
         meteo_comparison(&dut, &model, filename);
     } else if dut.is_clocks_rinex() {
         clocks_comparison(&dut, &model, filename);
+    } else if dut.is_navigation_rinex() {
+        navigation_comparison(dut, model, filename);
     }
 }
@@ -3,6 +3,7 @@ mod test {
     use rinex::clocks;
     use rinex::clocks::record::{DataType, System};
     use rinex::prelude::*;
+    use std::str::FromStr;
     #[test]
     fn v3_usno_example() {
         let test_resource =
@@ -177,4 +178,47 @@ mod test {
             }
         }*/
     }
+    #[test]
+    fn v2_cod20352_clock_interpolation() {
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/CLK/V2/COD20352.CLK";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        let t0 = rinex.first_epoch().unwrap();
+        let g01 = System::Sv(Sv::from_str("G01").unwrap());
+        let r22 = System::Sv(Sv::from_str("R22").unwrap());
+
+        // exact sample
+        let data = rinex.clock_at(&g01, t0).unwrap();
+        assert_eq!(data.bias, -0.141648778557E-03);
+        assert_eq!(data.bias_sigma, Some(0.305413520003E-11));
+
+        // mid point
+        let data = rinex
+            .clock_at(&g01, t0 + Duration::from_seconds(15.0))
+            .unwrap();
+        let expected = (-0.141648778557E-03 + -0.141648969129E-03) / 2.0;
+        assert!((data.bias - expected).abs() < 1E-15);
+        // 2nd sample has no sigma: 1st one is propagated
+        assert_eq!(data.bias_sigma, Some(0.305413520003E-11));
+
+        // out of data set
+        assert!(rinex
+            .clock_at(&g01, t0 - Duration::from_seconds(1.0))
+            .is_none());
+        assert!(rinex
+            .clock_at(&g01, t0 + Duration::from_seconds(211.0))
+            .is_none());
+
+        // data gap: 00:03:30 to 10:00:00
+        assert!(rinex
+            .clock_at(&r22, t0 + Duration::from_seconds(205.0))
+            .is_some());
+        assert!(rinex
+            .clock_at(&r22, t0 + Duration::from_seconds(3600.0))
+            .is_none());
+        let data = rinex
+            .clock_at(&r22, t0 + Duration::from_hours(10.0))
+            .unwrap();
+        assert_eq!(data.bias, -0.643217640876E-04);
+    }
 }
@@ -0,0 +1,193 @@
+#[cfg(test)]
+#[cfg(feature = "flate2")]
+mod test {
+    use rinex::carrier::Carrier;
+    use rinex::cs::{CsDetector, CsSelectionMethod, CsStrategy};
+    use rinex::observation::LliFlags;
+    use rinex::prelude::*;
+    use std::str::FromStr;
+    /*
+     * Introduces a cycle slip of given amplitude on given phase observable,
+     * from given epoch until the end of the arc
+     */
+    fn inject(rnx: &mut Rinex, sv: Sv, observable: &str, from: Epoch, cycles: f64) {
+        let observable = Observable::from_str(observable).unwrap();
+        let record = rnx.record.as_mut_obs().unwrap();
+        let mut last: Option<Epoch> = None;
+        for ((t, _), (_, svs)) in record.iter_mut() {
+            if *t < from {
+                continue;
+            }
+            if let Some(data) = svs.get_mut(&sv).and_then(|obs| obs.get_mut(&observable)) {
+                if let Some(last) = last {
+                    if *t - last > Duration::from_seconds(300.0) {
+                        return;
+                    }
+                }
+                data.obs += cycles;
+                last = Some(*t);
+            }
+        }
+    }
+    /*
+     * Full day ESBC00DNK observations, reduced to given vehicles
+     */
+    fn fixture(vehicles: &[&str]) -> Rinex {
+        let mut rnx = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let vehicles: Vec<Sv> = vehicles
+            .iter()
+            .map(|sv| Sv::from_str(sv).unwrap())
+            .collect();
+        let record = rnx.record.as_mut_obs().unwrap();
+        record.retain(|_, (_, svs)| {
+            svs.retain(|sv, _| vehicles.contains(sv));
+            !svs.is_empty()
+        });
+        rnx
+    }
+    fn detected(detector: &CsDetector, rnx: &Rinex, carrier: Carrier, sv: Sv, t: Epoch) -> bool {
+        detector
+            .cs_detection(rnx)
+            .get(&carrier)
+            .and_then(|svs| svs.get(&sv))
+            .map(|epochs| epochs.contains(&t))
+            .unwrap_or(false)
+    }
+    #[test]
+    fn v3_esbc00dnk_cs_detection() {
+        let rnx = fixture(&["E01"]);
+        let sv = Sv::from_str("E01").unwrap();
+        // a continuous E01 arc: one epoch in the middle of it
+        let epochs: Vec<Epoch> = rnx
+            .record
+            .as_obs()
+            .unwrap()
+            .iter()
+            .filter_map(|((t, _), (_, svs))| svs.get(&sv).map(|_| *t))
+            .collect();
+        let t = epochs[epochs.len() / 2];
+
+        for strategy in [
+            CsStrategy::GfAdvanced,
+            CsStrategy::GfSimple,
+            CsStrategy::Mw,
+            CsStrategy::PhaseDoppler,
+            CsStrategy::SingleFrequency,
+        ] {
+            let detector = CsDetector::default().with_method(CsSelectionMethod::Manual(strategy));
+            assert!(
+                !detected(&detector, &rnx, Carrier::E1, sv, t),
+                "{:?}: false detection",
+                strategy
+            );
+            // slip on E1
+            let mut slipped = rnx.clone();
+            inject(&mut slipped, sv, "L1C", t, 100.0);
+            assert!(
+                detected(&detector, &slipped, Carrier::E1, sv, t),
+                "{:?}: missed detection",
+                strategy
+            );
+        }
+
+        // geometry free, with several combinations: slip is attributed
+        let detector =
+            CsDetector::default().with_method(CsSelectionMethod::Manual(CsStrategy::GfAdvanced));
+        let mut slipped = rnx.clone();
+        inject(&mut slipped, sv, "L5Q", t, 10.0);
+        assert!(detected(&detector, &slipped, Carrier::E5a, sv, t));
+        assert!(!detected(&detector, &slipped, Carrier::E1, sv, t));
+        let mut slipped = rnx.clone();
+        inject(&mut slipped, sv, "L1C", t, 10.0);
+        assert!(detected(&detector, &slipped, Carrier::E1, sv, t));
+        assert!(!detected(&detector, &slipped, Carrier::E5a, sv, t));
+
+        // detections are written back
+        let cs = detector.cs_detection_mut(&mut slipped);
+        assert!(cs.get(&Carrier::E1).unwrap().get(&sv).unwrap().contains(&t));
+        let record = slipped.record.as_obs().unwrap();
+        let (_, (_, svs)) = record.iter().find(|((e, _), _)| *e == t).unwrap();
+        for (observable, data) in svs.get(&sv).unwrap() {
+            if !observable.is_phase_observable() {
+                continue;
+            }
+            let lock_loss = data
+                .lli
+                .map(|lli| lli.intersects(LliFlags::LOCK_LOSS))
+                .unwrap_or(false);
+            assert_eq!(
+                lock_loss,
+                observable.to_string() == "L1C",
+                "{}: unexpected lli {:?}",
+                observable,
+                data.lli
+            );
+        }
+    }
+    #[test]
+    fn v3_esbc00dnk_cs_repair() {
+        let rnx = fixture(&["E01", "G05"]);
+        let detector = CsDetector::default();
+        let mut reference = rnx.clone();
+        detector.cs_repair_mut(reference.record.as_mut_obs().unwrap());
+
+        // triple frequency Galileo, dual frequency GPS with two L2 signals
+        let mut slipped = rnx.clone();
+        let mut injected: Vec<(Sv, &str, Epoch, i64)> = Vec::new();
+        for (sv, observables) in [
+            ("E01", ["L1C", "L5Q", "L7Q"]),
+            ("G05", ["L1C", "L2W", "L2L"]),
+        ] {
+            let sv = Sv::from_str(sv).unwrap();
+            let epochs: Vec<Epoch> = rnx
+                .record
+                .as_obs()
+                .unwrap()
+                .iter()
+                .filter_map(|((t, _), (_, svs))| svs.get(&sv).map(|_| *t))
+                .collect();
+            let t = epochs[epochs.len() / 2];
+            for (observable, cycles) in observables.iter().zip([7, 2, -3]) {
+                inject(&mut slipped, sv, observable, t, cycles as f64);
+                injected.push((sv, observable, t, cycles));
+            }
+        }
+        let repaired = detector.cs_repair_mut(slipped.record.as_mut_obs().unwrap());
+        for (sv, observable, t, cycles) in &injected {
+            let slips = repaired
+                .get(&Observable::from_str(observable).unwrap())
+                .and_then(|svs| svs.get(sv))
+                .unwrap();
+            assert!(
+                slips.contains(&(*t, *cycles)),
+                "{} {}: {:?}",
+                sv,
+                observable,
+                slips
+            );
+        }
+        // repaired arcs are continuous, loss of lock is cleared
+        let record = slipped.record.as_obs().unwrap();
+        let expected = reference.record.as_obs().unwrap();
+        for (k, (_, svs)) in record {
+            let (_, expected) = expected.get(k).unwrap();
+            for (sv, observations) in svs {
+                for (observable, data) in observations {
+                    let expected = expected.get(sv).unwrap().get(observable).unwrap();
+                    assert!(
+                        (data.obs - expected.obs).abs() < 1.0E-6,
+                        "{:?} {} {}: not repaired",
+                        k,
+                        sv,
+                        observable
+                    );
+                    assert_eq!(data.lli, expected.lli);
+                }
+            }
+        }
+    }
+}
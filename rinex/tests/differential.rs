@@ -0,0 +1,176 @@
+#[cfg(test)]
+#[cfg(all(feature = "obs", feature = "processing"))]
+mod test {
+    use rinex::differential::DiffContext;
+    use rinex::observation::LliFlags;
+    use rinex::prelude::*;
+    use std::str::FromStr;
+    fn value(rnx: &Rinex, t: Epoch, sv: Sv, observable: &str) -> Option<f64> {
+        let observable = Observable::from_str(observable).unwrap();
+        rnx.record
+            .as_obs()
+            .unwrap()
+            .iter()
+            .find(|((e, _), _)| *e == t)
+            .and_then(|(_, (_, svs))| svs.get(&sv))
+            .and_then(|observations| observations.get(&observable))
+            .map(|data| data.obs)
+    }
+    #[test]
+    fn v3_larm0630_noa10630_differences() {
+        let base = Rinex::from_file("../test_resources/OBS/V3/LARM0630.22O").unwrap();
+        let rover = Rinex::from_file("../test_resources/OBS/V3/NOA10630.22O").unwrap();
+        let ctx = DiffContext::new(&base, &rover).unwrap();
+        // epochs are aligned, vehicles are shared
+        let epochs: Vec<Epoch> = ctx.base.epoch().collect();
+        assert_eq!(epochs, ctx.rover.epoch().collect::<Vec<_>>());
+        assert_eq!(epochs.len(), 3);
+        assert_eq!(
+            ctx.base.sv().collect::<Vec<_>>(),
+            ctx.rover.sv().collect::<Vec<_>>()
+        );
+        for observable in ctx.rover.observable() {
+            assert!(observable.is_phase_observable() || observable.is_pseudorange_observable());
+        }
+
+        let t0 = epochs[0];
+        // single differences: rover - base
+        let sd = ctx.single_differences();
+        for sv in ctx.rover.sv() {
+            for observable in ["C1C", "L1C", "C2W", "L2W"] {
+                let expected = match (
+                    value(&rover, t0, sv, observable),
+                    value(&base, t0, sv, observable),
+                ) {
+                    (Some(rover), Some(base)) => rover - base,
+                    _ => continue,
+                };
+                let sd = value(&sd, t0, sv, observable).unwrap();
+                assert!((sd - expected).abs() < 1.0E-6);
+            }
+        }
+
+        // double differences, against the reference vehicle
+        let dd = ctx.double_differences();
+        let references = ctx.reference_vehicles();
+        for ((t, _), refs) in &references {
+            let reference = refs.get(&Constellation::GPS).unwrap();
+            assert!(value(&dd, *t, *reference, "L1C").is_none());
+            // reference is retained
+            assert_eq!(
+                reference,
+                references
+                    .values()
+                    .next()
+                    .unwrap()
+                    .get(&Constellation::GPS)
+                    .unwrap()
+            );
+            for sv in ctx.rover.sv() {
+                if sv == *reference {
+                    continue;
+                }
+                if let (Some(lhs), Some(rhs)) =
+                    (value(&sd, *t, sv, "L1C"), value(&sd, *t, *reference, "L1C"))
+                {
+                    let dd = value(&dd, *t, sv, "L1C").unwrap();
+                    assert!((dd - (lhs - rhs)).abs() < 1.0E-6);
+                }
+            }
+        }
+
+        // user defined reference
+        let g17 = Sv::from_str("G17").unwrap();
+        let references = ctx.with_reference(g17).reference_vehicles();
+        for refs in references.values() {
+            assert_eq!(refs.get(&Constellation::GPS), Some(&g17));
+        }
+    }
+    #[test]
+    fn v2_zegv0010_wsra0010_confirmed_cycle_slips() {
+        let base = Rinex::from_file("../test_resources/OBS/V2/zegv0010.21o").unwrap();
+        let mut rover = Rinex::from_file("../test_resources/OBS/V2/wsra0010.21o").unwrap();
+        let l1 = Observable::from_str("L1").unwrap();
+        // declared slips only
+        for (_, (_, svs)) in rover.record.as_mut_obs().unwrap().iter_mut() {
+            for observations in svs.values_mut() {
+                for data in observations.values_mut() {
+                    data.lli = None;
+                }
+            }
+        }
+        let ctx = DiffContext::new(&base, &rover).unwrap();
+        let epochs: Vec<Epoch> = ctx.rover.epoch().collect();
+        let t = epochs[10];
+        // a GPS vehicle, that is not the reference
+        let refs = ctx.reference_vehicles();
+        let (_, (_, svs)) = ctx
+            .rover
+            .record
+            .as_obs()
+            .unwrap()
+            .iter()
+            .find(|((e, _), _)| *e == t)
+            .unwrap();
+        let sv = *svs
+            .keys()
+            .find(|sv| {
+                sv.constellation == Constellation::GPS
+                    && refs
+                        .iter()
+                        .all(|(_, refs)| refs.get(&Constellation::GPS) != Some(sv))
+            })
+            .unwrap();
+        assert!(ctx.confirmed_cycle_slips().is_empty());
+        // loss of lock, but no phase jump: not confirmed,
+        // even though the geometry varies by tens of cycles between epochs
+        let mut declared = rover.clone();
+        for ((e, _), (_, svs)) in declared.record.as_mut_obs().unwrap().iter_mut() {
+            if *e == t {
+                svs.get_mut(&sv).unwrap().get_mut(&l1).unwrap().lli = Some(LliFlags::LOCK_LOSS);
+            }
+        }
+        let ctx = DiffContext::new(&base, &declared).unwrap();
+        assert!(ctx.confirmed_cycle_slips().is_empty());
+        // actual slip: confirmed
+        for ((e, _), (_, svs)) in declared.record.as_mut_obs().unwrap().iter_mut() {
+            if *e >= t {
+                svs.get_mut(&sv).unwrap().get_mut(&l1).unwrap().obs += 7.0;
+            }
+        }
+        let ctx = DiffContext::new(&base, &declared).unwrap();
+        assert_eq!(ctx.confirmed_cycle_slips(), vec![t]);
+        // slip declared by a cycle slip event
+        let mut slipped = rover.clone();
+        let record = slipped.record.as_mut_obs().unwrap();
+        let mut event = std::collections::BTreeMap::new();
+        for ((e, _), (_, svs)) in record.iter_mut() {
+            if *e >= t {
+                let data = svs.get_mut(&sv).unwrap().get_mut(&l1).unwrap();
+                data.obs += 7.0;
+                if *e == t {
+                    let mut observations = std::collections::HashMap::new();
+                    observations.insert(l1.clone(), *data);
+                    event.insert(sv, observations);
+                }
+            }
+        }
+        record.insert((t, EpochFlag::CycleSlip), (None, event));
+        let ctx = DiffContext::new(&base, &slipped).unwrap();
+        assert_eq!(ctx.confirmed_cycle_slips(), vec![t]);
+        assert_eq!(
+            slipped
+                .observation_confirmed_cycle_slip_epochs(&base)
+                .unwrap(),
+            vec![t]
+        );
+    }
+    #[test]
+    fn differential_context_errors() {
+        let obs = Rinex::from_file("../test_resources/OBS/V3/LARM0630.22O").unwrap();
+        let nav = Rinex::from_file("../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx")
+            .unwrap();
+        assert!(DiffContext::new(&nav, &obs).is_err());
+        assert!(DiffContext::new(&obs, &nav).is_err());
+    }
+}
@@ -28,6 +28,8 @@ mod test {
         assert_eq!(header.base_radius, 6371.0);
         assert_eq!(header.elevation_cutoff, 0.0);
         assert_eq!(header.mapping, None);
+        // CODE Klobuchar-style model
+        assert!(header.is_theoretical_model());
 
         let record = rinex.record.as_ionex();
         assert_eq!(record.is_some(), true);
@@ -106,4 +108,243 @@ mod test {
             }
         }
     }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v1_jplg0010_17i_tec_at() {
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/IONEX/V1/jplg0010.17i.gz";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        assert!(!rinex.header.ionex.as_ref().unwrap().is_theoretical_model());
+        let record = rinex.record.as_ionex().unwrap();
+        // grid nodes are preserved
+        for (epoch, (tec, rms, _)) in record {
+            for (index, point) in tec.iter().enumerate().step_by(97) {
+                let (value, rms_value) = rinex
+                    .tec_at(*epoch, point.latitude, point.longitude)
+                    .unwrap();
+                assert!((value - point.value).abs() < 1.0E-9);
+                let rms = rms.as_ref().unwrap();
+                assert_eq!(rms_value, Some(rms[index].value));
+            }
+        }
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2017, 1, 1);
+        let (tec, rms) = rinex.tec_at(t0, 87.5, -180.0).unwrap();
+        assert!((tec - 3.3).abs() < 1.0E-9);
+        assert!(rms.is_some());
+        // in between maps: bounded by the surrounding values
+        let t = t0 + Duration::from_hours(1.0);
+        for (lat, lon) in [(43.6, 1.44), (-33.9, 151.2), (0.0, -179.0)] {
+            let (tec, rms) = rinex.tec_at(t, lat, lon).unwrap();
+            let (tec0, _) = rinex.tec_at(t0, lat, lon + 15.0).unwrap();
+            let (tec1, _) = rinex
+                .tec_at(t0 + Duration::from_hours(2.0), lat, lon - 15.0)
+                .unwrap();
+            assert!((tec - (tec0 + tec1) / 2.0).abs() < 1.0E-9);
+            assert!(rms.is_some());
+        }
+        // outside of the grid and time frame
+        assert!(rinex.tec_at(t0, 88.0, 0.0).is_none());
+        assert!(rinex
+            .tec_at(t0 - Duration::from_seconds(1.0), 0.0, 0.0)
+            .is_none());
+    }
+    #[test]
+    #[cfg(all(feature = "flate2", feature = "nav"))]
+    fn v1_ckmg0090_21i_slant_delay() {
+        use rinex::carrier::Carrier;
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/IONEX/V1/CKMG0090.21I.gz";
+        let ionex = Rinex::from_file(&test_resource).unwrap();
+        // Klobuchar model, broadcasted a week earlier
+        let test_resource = env!("CARGO_MANIFEST_DIR").to_owned()
+            + "/../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx";
+        let nav = Rinex::from_file(&test_resource).unwrap();
+        let kb = nav.header.ionod_corrections[&Constellation::GPS]
+            .as_klobuchar()
+            .unwrap();
+        let (lat, lon) = (52.0, 4.4);
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2021, 1, 9);
+        let l1 = ionex
+            .ionex_slant_delay(t0, lat, lon, 90.0, 0.0, Carrier::L1)
+            .unwrap();
+        let l2 = ionex
+            .ionex_slant_delay(t0, lat, lon, 90.0, 0.0, Carrier::L2)
+            .unwrap();
+        let ratio = (Carrier::L1.frequency() / Carrier::L2.frequency()).powi(2);
+        assert!((l2 / l1 - ratio).abs() < 1.0E-9);
+        let (mut sum, mut n) = (0.0, 0);
+        for hour in 0..24 {
+            let t = t0 + Duration::from_hours(hour as f64);
+            let mut previous = 0.0;
+            for elev in [90.0, 60.0, 30.0, 15.0] {
+                for azim in [0.0, 90.0, 180.0, 270.0] {
+                    let delay = ionex
+                        .ionex_slant_delay(t, lat, lon, elev, azim, Carrier::L1)
+                        .unwrap();
+                    assert!(delay > 0.0 && delay < 30.0, "{}", delay);
+                    sum += delay / (kb.delay(t, elev, azim, lat, lon) * 299_792_458.0);
+                    n += 1;
+                    if azim == 0.0 {
+                        // lower elevations see more ionosphere
+                        assert!(delay > previous * 0.8);
+                        previous = delay;
+                    }
+                }
+            }
+        }
+        // Klobuchar only corrects about 50% of the delay
+        let ratio = sum / n as f64;
+        assert!(ratio > 0.3 && ratio < 3.0, "{}", ratio);
+    }
+    #[test]
+    #[cfg(all(feature = "nav", feature = "flate2"))]
+    fn v1_ckmg0090_21i_broadcast_model() {
+        use rinex::navigation::IonModelError;
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/IONEX/V1/CKMG0090.21I.gz";
+        let igs = Rinex::from_file(&test_resource).unwrap();
+        let test_resource = env!("CARGO_MANIFEST_DIR").to_owned()
+            + "/../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx";
+        let nav = Rinex::from_file(&test_resource).unwrap();
+        let grid = &igs.header.ionex.as_ref().unwrap().grid;
+        // render the header Klobuchar model at every IGS map
+        let t0 = igs.first_epoch().unwrap();
+        let series =
+            TimeSeries::inclusive(t0, igs.last_epoch().unwrap(), Duration::from_hours(1.0));
+        let model = nav
+            .ionex_from_broadcast(Constellation::GPS, grid, series)
+            .unwrap();
+        assert_eq!(model.epoch().count(), igs.epoch().count());
+        let fields = model.header.ionex.as_ref().unwrap();
+        assert!(fields.is_theoretical_model());
+        assert_eq!(fields.grid.latitude, grid.latitude);
+        assert_eq!(fields.grid.longitude, grid.longitude);
+        assert_eq!(fields.nb_maps, 25);
+        let record = model.record.as_ionex().unwrap();
+        for (_, (tec, rms, _)) in record {
+            assert_eq!(tec.len(), 71 * 73);
+            assert!(rms.is_none());
+            // night time floor (5ns on L1) or above
+            assert!(tec.iter().all(|p| p.value > 9.2));
+        }
+        // same model at all nodes
+        let t = t0 + Duration::from_hours(13.0);
+        let kb = nav.header.ionod_corrections[&Constellation::GPS]
+            .as_klobuchar()
+            .unwrap();
+        let (tec, _) = model.tec_at(t, 52.5, 5.0).unwrap();
+        let expected = kb.vertical_tec(t, 52.5, 5.0, rinex::carrier::Carrier::L1);
+        assert!((tec - expected).abs() < 1.0E-6);
+
+        // production
+        let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/kb0090.21i";
+        model.to_file(&path).unwrap();
+        let parsed = Rinex::from_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(parsed.header.ionex, model.header.ionex);
+        let (tec, _) = parsed.tec_at(t, 52.5, 5.0).unwrap();
+        assert!((tec - expected).abs() < 0.1);
+
+        // model versus IGS
+        let diff = model.ionex_diff(&igs).unwrap();
+        assert_eq!(diff.epoch().count(), 25);
+        for (t, (tec, _, _)) in diff.record.as_ionex().unwrap() {
+            for point in tec.iter().step_by(97) {
+                let (lhs, _) = model.tec_at(*t, point.latitude, point.longitude).unwrap();
+                let (rhs, _) = igs.tec_at(*t, point.latitude, point.longitude).unwrap();
+                assert!((point.value - (lhs - rhs)).abs() < 1.0E-6);
+            }
+        }
+        // NeQuick-G is not supported
+        let series = TimeSeries::inclusive(t0, t, Duration::from_hours(1.0));
+        assert_eq!(
+            nav.ionex_from_broadcast(Constellation::Galileo, grid, series.clone()),
+            Err(IonModelError::NequickGNotSupported)
+        );
+        // no model broadcast by Glonass
+        assert_eq!(
+            nav.ionex_from_broadcast(Constellation::Glonass, grid, series),
+            Err(IonModelError::NoModel)
+        );
+    }
+    #[test]
+    #[cfg(all(feature = "nav", feature = "flate2"))]
+    fn v4_kms300dnk_broadcast_models() {
+        use rinex::ionex::{Grid, GridLinspace};
+        use rinex::navigation::IonModelError;
+        let test_resource = env!("CARGO_MANIFEST_DIR").to_owned()
+            + "/../test_resources/NAV/V4/KMS300DNK_R_20221591000_01H_MN.rnx.gz";
+        let nav = Rinex::from_file(&test_resource).unwrap();
+        let grid = Grid {
+            latitude: GridLinspace::from((70.0, 50.0, -5.0)),
+            longitude: GridLinspace::from((0.0, 20.0, 5.0)),
+            height: GridLinspace::from((350.0, 350.0, 0.0)),
+        };
+        let t0 = Epoch::from_gregorian_utc_hms(2022, 6, 8, 9, 0, 0);
+        let series = TimeSeries::inclusive(
+            t0,
+            t0 + Duration::from_hours(2.0),
+            Duration::from_seconds(1800.0),
+        );
+        // models are broadcast at 09:59: prior maps are not rendered
+        for constellation in [Constellation::GPS, Constellation::BeiDou] {
+            let model = nav
+                .ionex_from_broadcast(constellation, &grid, series.clone())
+                .unwrap();
+            assert_eq!(model.epoch().count(), 3);
+            for (_, (tec, _, _)) in model.record.as_ionex().unwrap() {
+                assert_eq!(tec.len(), 25);
+            }
+        }
+        assert_eq!(
+            nav.ionex_from_broadcast(Constellation::Galileo, &grid, series)
+                .err(),
+            Some(IonModelError::NequickGNotSupported)
+        );
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v1_production() {
+        let folder = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/IONEX/V1/";
+        for file in std::fs::read_dir(folder).unwrap() {
+            let path = file.unwrap().path();
+            let path = path.to_str().unwrap();
+            if !path.ends_with(".gz") {
+                continue;
+            }
+            let rinex = Rinex::from_file(path).unwrap();
+            let copy_path = path.to_owned() + "-copy";
+            assert!(rinex.to_file(&copy_path).is_ok());
+            let copy = Rinex::from_file(&copy_path);
+            let _ = std::fs::remove_file(&copy_path);
+            let copy = copy.unwrap();
+            assert!(copy.is_ionex(), "{}", path);
+            assert_eq!(copy.record, rinex.record, "{}", path);
+            let (header, header_copy) = (
+                rinex.header.ionex.as_ref().unwrap(),
+                copy.header.ionex.as_ref().unwrap(),
+            );
+            assert_eq!(header_copy.reference, header.reference);
+            // description is wrapped on word boundaries
+            let words = |desc: &Option<String>| {
+                desc.as_ref()
+                    .map(|desc| desc.split_ascii_whitespace().collect::<Vec<_>>().join(" "))
+            };
+            assert_eq!(words(&header_copy.description), words(&header.description));
+            assert_eq!(header_copy.grid, header.grid);
+            assert_eq!(header_copy.exponent, header.exponent);
+            assert_eq!(header_copy.mapping, header.mapping);
+            assert_eq!(header_copy.elevation_cutoff, header.elevation_cutoff);
+            assert_eq!(header_copy.nb_stations, header.nb_stations);
+            assert_eq!(header_copy.nb_satellites, header.nb_satellites);
+            assert_eq!(header_copy.dcbs, header.dcbs);
+            let record = rinex.record.as_ionex().unwrap();
+            assert_eq!(header_copy.nb_maps as usize, record.len());
+            assert_eq!(
+                header_copy.epoch_of_first_map,
+                record.keys().next().copied()
+            );
+            assert_eq!(header_copy.epoch_of_last_map, record.keys().last().copied());
+        }
+    }
 }
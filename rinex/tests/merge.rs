@@ -50,4 +50,128 @@ mod merge {
 
         let _ = std::fs::remove_file("merge.txt"); // remove file we just generated
     }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn merge_ionex() {
+        let test_resources = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/";
+        let path1 = test_resources.to_owned() + "IONEX/V1/CKMG0090.21I.gz";
+        let r1 = Rinex::from_file(&path1).unwrap();
+        let path2 = test_resources.to_owned() + "IONEX/V1/CKMG0020.22I.gz";
+        let r2 = Rinex::from_file(&path2).unwrap();
+
+        let merged = r1.merge(&r2);
+        assert!(merged.is_ok(), "failed to merge IONEX maps");
+        let merged = merged.unwrap();
+        assert_eq!(merged.epoch().count(), 50);
+        let record = merged.record.as_ionex().unwrap();
+        for (epoch, maps) in r1.record.as_ionex().unwrap() {
+            assert_eq!(record.get(epoch), Some(maps));
+        }
+        for (epoch, maps) in r2.record.as_ionex().unwrap() {
+            assert_eq!(record.get(epoch), Some(maps));
+        }
+        // maps description is updated
+        let (first, last) = (r1.first_epoch().unwrap(), r2.last_epoch().unwrap());
+        let ionex = r1.header.ionex.as_ref().unwrap();
+        assert_eq!(
+            merged.header.ionex,
+            Some(ionex.with_map_epochs(first, last).with_nb_maps(50))
+        );
+
+        // merging a dataset into itself is idempotent
+        let merged = r1.merge(&r1).unwrap();
+        assert_eq!(merged.record, r1.record);
+
+        // RMS maps are introduced
+        let mut rms = r1.clone();
+        let record = rms.record.as_mut_ionex().unwrap();
+        for (_, (tec, rms, _)) in record.iter_mut() {
+            *rms = Some(tec.clone());
+        }
+        let merged = r1.merge(&rms).unwrap();
+        for (_, (tec, rms, _)) in merged.record.as_ionex().unwrap() {
+            assert_eq!(rms.as_ref(), Some(tec));
+        }
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn merge_ionex_mismatch() {
+        use rinex::ionex::{GridLinspace, MappingFunction};
+        use rinex::merge::Error;
+        let test_resources = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/";
+        let path1 = test_resources.to_owned() + "IONEX/V1/CKMG0090.21I.gz";
+        let r1 = Rinex::from_file(&path1).unwrap();
+        // different reference system
+        let path2 = test_resources.to_owned() + "IONEX/V1/jplg0010.17i.gz";
+        let r2 = Rinex::from_file(&path2).unwrap();
+        assert!(matches!(r1.merge(&r2), Err(Error::IonexReferenceMismatch)));
+
+        let ionex = r1.header.ionex.as_ref().unwrap();
+        let mut r2 = r1.clone();
+        r2.header.ionex =
+            Some(ionex.with_latitude_grid(GridLinspace::new(85.0, -85.0, -5.0).unwrap()));
+        assert!(matches!(r1.merge(&r2), Err(Error::IonexMapGridMismatch)));
+
+        r2.header.ionex = Some(ionex.with_base_radius(6378.0));
+        assert!(matches!(r1.merge(&r2), Err(Error::IonexBaseRadiusMismatch)));
+
+        r2.header.ionex = Some(ionex.with_map_dimension(3));
+        assert!(matches!(
+            r1.merge(&r2),
+            Err(Error::IonexMapDimensionsMismatch)
+        ));
+
+        let mut r1 = r1.clone();
+        r1.header.ionex = Some(ionex.with_mapping_function(MappingFunction::CosZ));
+        r2.header.ionex = Some(ionex.with_mapping_function(MappingFunction::QFac));
+        assert!(matches!(
+            r1.merge(&r2),
+            Err(Error::IonexMappingFunctionMismatch)
+        ));
+
+        // finest scaling is retained
+        r2.header.ionex = Some(ionex.with_exponent(-2));
+        let merged = r1.merge(&r2).unwrap();
+        assert_eq!(merged.header.ionex.unwrap().exponent, -2);
+    }
+    #[test]
+    fn merge_phase_shifts() {
+        use rinex::merge::Error;
+        use std::str::FromStr;
+        let test_resources = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/";
+        let path = test_resources.to_owned() + "OBS/V3/LARM0010.22O";
+        let r1 = Rinex::from_file(&path).unwrap();
+        let l2s = Observable::from_str("L2S").unwrap();
+        let g01 = Sv::new(Constellation::GPS, 1);
+        // identical corrections
+        let merged = r1.merge(&r1).unwrap();
+        let obs = merged.header.obs.as_ref().unwrap();
+        assert_eq!(
+            obs.phase_shift(&Constellation::GPS, &g01, &l2s),
+            Some(-0.25)
+        );
+        // L2S is described by both files, with different corrections
+        let mut r2 = r1.clone();
+        let obs = r2.header.obs.as_mut().unwrap();
+        *obs = obs.with_phase_shift(Constellation::GPS, l2s.clone(), Some(g01), 0.25);
+        assert!(matches!(r1.merge(&r2), Err(Error::PhaseShiftMismatch)));
+        // corrections of observables only described by one file are preserved
+        let mut r2 = r1.clone();
+        let obs = r2.header.obs.as_mut().unwrap();
+        obs.phase_shifts.clear();
+        obs.codes.clear();
+        let l5x = Observable::from_str("L5X").unwrap();
+        obs.codes.insert(Constellation::GPS, vec![l5x.clone()]);
+        *obs = obs.with_phase_shift(Constellation::GPS, l5x.clone(), None, -0.25);
+        let merged = r1.merge(&r2).unwrap();
+        let obs = merged.header.obs.as_ref().unwrap();
+        assert_eq!(
+            obs.phase_shift(&Constellation::GPS, &g01, &l2s),
+            Some(-0.25)
+        );
+        assert_eq!(
+            obs.phase_shift(&Constellation::GPS, &g01, &l5x),
+            Some(-0.25)
+        );
+    }
 }
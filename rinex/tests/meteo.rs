@@ -219,4 +219,153 @@ mod test {
             }
         }
     }
+    #[test]
+    #[cfg(feature = "processing")]
+    fn v2_abvi0010_15m_interpolation() {
+        use rinex::meteo::{InterpolationMethod, InterpolationOpts};
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/MET/V2/abvi0010.15m";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        let linear = InterpolationOpts::default();
+        let spline = linear.with_method(InterpolationMethod::Spline);
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2015, 1, 1);
+        // samples are preserved
+        for opts in [linear, spline] {
+            let value = rinex.meteo_at(t0, &Observable::Temperature, opts);
+            assert_eq!(value, Some(25.6));
+        }
+        // in between samples
+        for opts in [linear, spline] {
+            let t = t0 + Duration::from_seconds(150.0);
+            let value = rinex.meteo_at(t, &Observable::Temperature, opts).unwrap();
+            assert!((value - 25.5).abs() < 0.05, "{}", value);
+        }
+        // no extrapolation
+        let t = t0 - Duration::from_seconds(30.0);
+        assert!(rinex.meteo_at(t, &Observable::Pressure, linear).is_none());
+        // gap limit
+        let opts = linear.with_max_gap(Duration::from_seconds(30.0));
+        let t = t0 + Duration::from_seconds(30.0);
+        assert!(rinex.meteo_at(t, &Observable::Pressure, opts).is_none());
+
+        // alignment to a 30s observation time frame:
+        // samples stop at 00:09:00 and resume at 09:00:00
+        let series = TimeSeries::inclusive(
+            t0,
+            t0 + Duration::from_hours(1.0),
+            Duration::from_seconds(30.0),
+        );
+        let aligned = rinex.meteo_align(series, None, linear);
+        assert_eq!(aligned.epoch().count(), 19);
+        assert_eq!(aligned.observable().count(), 7);
+        let record = aligned.record.as_meteo().unwrap();
+        let values = record.get(&(t0 + Duration::from_seconds(30.0))).unwrap();
+        let pressure = values.get(&Observable::Pressure).unwrap();
+        assert!((pressure - 1018.65).abs() < 1.0E-6);
+        // sensor height is unknown: no correction
+        let antenna = GroundPosition::from_ecef_wgs84((6_378_137.0 + 100.0, 0.0, 0.0));
+        let series = TimeSeries::inclusive(
+            t0,
+            t0 + Duration::from_hours(1.0),
+            Duration::from_seconds(30.0),
+        );
+        let corrected = rinex.meteo_align(series, Some(antenna), linear);
+        assert_eq!(corrected.record, aligned.record);
+    }
+    #[test]
+    #[cfg(feature = "processing")]
+    fn v2_cari0010_07m_height_correction() {
+        use rinex::meteo::InterpolationOpts;
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/MET/V2/cari0010.07m";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        let t0 = Epoch::from_gregorian_utc(1996, 4, 1, 0, 0, 15, 0);
+        let series = TimeSeries::inclusive(
+            t0,
+            t0 + Duration::from_seconds(30.0),
+            Duration::from_seconds(5.0),
+        );
+        // antenna, 100m above the pressure sensor
+        let antenna = GroundPosition::from_ecef_wgs84((6_378_137.0 + 1334.5678, 0.0, 0.0));
+        let aligned = rinex.meteo_align(series, Some(antenna), InterpolationOpts::default());
+        assert_eq!(aligned.epoch().count(), 7);
+        let record = aligned.record.as_meteo().unwrap();
+        let values = record.get(&t0).unwrap();
+        // pressure sensor is positioned: ~12 hPa lower, 100m above
+        let pressure = values.get(&Observable::Pressure).unwrap();
+        assert!((pressure - 987.1 + 11.6).abs() < 0.5, "{}", pressure);
+        // other sensors are not positioned
+        let temperature = values.get(&Observable::Temperature).unwrap();
+        assert_eq!(*temperature, 10.6);
+        // corrected sensor now lies at the antenna
+        let sensors = &aligned.header.meteo.as_ref().unwrap().sensors;
+        let sensor = sensors
+            .iter()
+            .find(|s| s.observable == Observable::Pressure)
+            .unwrap();
+        let height = sensor.height().unwrap();
+        assert!((height - 1334.5678).abs() < 1.0E-3);
+    }
+    #[test]
+    fn v2_abvi0010_15m_products() {
+        let test_resource =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/MET/V2/abvi0010.15m";
+        let mut rinex = Rinex::from_file(&test_resource).unwrap();
+        // ZWD [mm], as estimated by a GNSS solution
+        for values in rinex.record.as_mut_meteo().unwrap().values_mut() {
+            values.insert(Observable::ZenithWetDelay, 200.0);
+        }
+        let meteo = rinex.header.meteo.as_mut().unwrap();
+        meteo.codes.push(Observable::ZenithWetDelay);
+        rinex.dew_point_mut();
+        rinex.weighted_mean_temperature_mut();
+        rinex.zenith_dry_delay_mut(18.43, 0.0);
+        rinex.precipitable_water_vapor_mut();
+
+        let codes = &rinex.header.meteo.as_ref().unwrap().codes;
+        for observable in [
+            Observable::DewPoint,
+            Observable::WeightedMeanTemperature,
+            Observable::ZenithDryDelay,
+            Observable::PrecipitableWaterVapor,
+        ] {
+            assert!(codes.contains(&observable));
+        }
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2015, 1, 1);
+        let values = rinex.record.as_meteo().unwrap().get(&t0).unwrap();
+        // TD=25.6°C HR=78.9%
+        let dew_point = values.get(&Observable::DewPoint).unwrap();
+        assert!((dew_point - 21.6).abs() < 0.1, "{}", dew_point);
+        let tm = values.get(&Observable::WeightedMeanTemperature).unwrap();
+        assert!((tm - 285.3).abs() < 0.1, "{}", tm);
+        // PR=1018.6 hPa
+        let zhd = values.get(&Observable::ZenithDryDelay).unwrap();
+        assert!((zhd - 2325.0).abs() < 1.0, "{}", zhd);
+        let pwv = values.get(&Observable::PrecipitableWaterVapor).unwrap();
+        assert!((pwv - 32.5).abs() < 0.1, "{}", pwv);
+        assert_eq!(rinex.dew_point().count(), rinex.epoch().count());
+        assert_eq!(
+            rinex.precipitable_water_vapor().count(),
+            rinex.epoch().count()
+        );
+
+        // derived observables are exported
+        let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/products.txt";
+        assert!(rinex.to_file(&path).is_ok());
+        let copy = Rinex::from_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let values = copy.record.as_meteo().unwrap().get(&t0).unwrap();
+        let copied = values.get(&Observable::PrecipitableWaterVapor).unwrap();
+        assert!((copied - pwv).abs() < 0.05);
+
+        // dew point is not defined in dry air
+        let mut rinex = Rinex::from_file(&test_resource).unwrap();
+        let record = rinex.record.as_mut_meteo().unwrap();
+        let (t_dry, values) = record.iter_mut().last().unwrap();
+        let t_dry = *t_dry;
+        values.insert(Observable::HumidityRate, 0.0);
+        rinex.dew_point_mut();
+        assert_eq!(rinex.dew_point().count(), rinex.epoch().count() - 1);
+        assert!(rinex.dew_point().all(|(t, _)| t != t_dry));
+    }
 }
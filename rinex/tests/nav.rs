@@ -1,9 +1,11 @@
 #[cfg(test)]
 mod test {
     use itertools::*;
+    use rinex::merge::Merge;
     use rinex::navigation::*;
     use rinex::prelude::*;
     use rinex::sv;
+    use rinex::version::Version;
     use std::str::FromStr;
     #[test]
     fn v2_amel0010_21g() {
@@ -1224,4 +1226,229 @@ mod test {
             }
         }
     }
+    #[test]
+    fn v3_amel00nld_nav_v4_conversion() {
+        let test_resource = env!("CARGO_MANIFEST_DIR").to_owned()
+            + "/../test_resources/NAV/V3/AMEL00NLD_R_20210010000_01D_MN.rnx";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        let v4 = rinex.to_nav_v4();
+        assert_eq!(v4.header.version, Version::new(4, 0));
+        assert!(v4.header.ionod_corrections.is_empty());
+        assert!(v4.header.time_corrections.is_empty());
+        let record = v4.record.as_nav().unwrap();
+        let (mut ion_count, mut sto_count) = (0, 0);
+        for (_, frames) in record {
+            for fr in frames {
+                if let Some((msg, sv, _)) = fr.as_eph() {
+                    let expected = match sv.constellation {
+                        Constellation::Glonass => NavMsgType::FDMA,
+                        Constellation::BeiDou if sv.prn == 5 => NavMsgType::D2,
+                        Constellation::BeiDou => NavMsgType::D1,
+                        // data source 258: F/NAV
+                        Constellation::Galileo => NavMsgType::FNAV,
+                        _ => panic!("unexpected vehicle {}", sv),
+                    };
+                    assert_eq!(msg, expected, "bad message type for {}", sv);
+                } else if let Some((msg, sv, model)) = fr.as_ion() {
+                    // this file has no GPS nor QZSS vehicles to tag their models to
+                    ion_count += 1;
+                    assert_eq!(msg, NavMsgType::IFNV);
+                    assert_eq!(sv.constellation, Constellation::Galileo);
+                    assert_eq!(model.as_nequick_g().unwrap().a.0, 66.25);
+                } else if let Some((msg, sv, sto)) = fr.as_sto() {
+                    sto_count += 1;
+                    assert_eq!(msg, NavMsgType::IFNV);
+                    assert_eq!(sv.constellation, Constellation::Galileo);
+                    assert!(sto.system.eq("GAGP") || sto.system.eq("GAUT"));
+                }
+            }
+        }
+        assert_eq!(ion_count, 1);
+        assert_eq!(sto_count, 2);
+        // RINEX4 production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/amel-v4.rnx";
+        v4.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.version, Version::new(4, 0));
+        assert_eq!(parsed.record, v4.record);
+        // back to RINEX3: Galileo corrections are preserved
+        let v3 = parsed.to_nav_v3();
+        assert_eq!(v3.header.version, Version::new(3, 5));
+        assert_eq!(v3.header.ionod_corrections.len(), 1);
+        assert_eq!(
+            v3.header.ionod_corrections.get(&Constellation::Galileo),
+            rinex.header.ionod_corrections.get(&Constellation::Galileo)
+        );
+        for system in ["GAGP", "GAUT"] {
+            assert_eq!(
+                v3.header.time_corrections.get(system),
+                rinex.header.time_corrections.get(system)
+            );
+        }
+        assert!(v3.header.time_corrections.get("GPUT").is_none());
+        assert_eq!(v3.record, rinex.record);
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v4_kms300dnk_nav_v3_conversion() {
+        let test_resource = env!("CARGO_MANIFEST_DIR").to_owned()
+            + "/../test_resources/NAV/V4/KMS300DNK_R_20221591000_01H_MN.rnx.gz";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        let v3 = rinex.to_nav_v3();
+        let record = v3.record.as_nav().unwrap();
+        for (_, frames) in record {
+            for fr in frames {
+                let (msg, _, _) = fr.as_eph().expect("only ephemeris frames expected");
+                assert_eq!(msg, NavMsgType::LNAV);
+            }
+        }
+        let mut constellations: Vec<_> = v3.header.ionod_corrections.keys().copied().collect();
+        constellations.sort();
+        assert_eq!(
+            constellations,
+            vec![
+                Constellation::GPS,
+                Constellation::BeiDou,
+                Constellation::Galileo
+            ]
+        );
+        let (epoch, gput) = v3.header.time_corrections.get("GPUT").unwrap();
+        assert_eq!(
+            *epoch,
+            Epoch::from_gregorian_utc_hms(2022, 06, 10, 19, 56, 48)
+        );
+        assert_eq!(gput.a.0, 9.313225746155E-10);
+        // reference time: seconds of week
+        assert_eq!(gput.t_tm, 5 * 86400 + 19 * 3600 + 56 * 60 + 48);
+        assert_eq!(v3.header.time_corrections.len(), 3);
+        // V3 headers have fewer digits
+        let v3_header_eq = |lhs: &Header, rhs: &Header| {
+            assert_eq!(lhs.time_corrections.len(), rhs.time_corrections.len());
+            for (system, (epoch, sto)) in &lhs.time_corrections {
+                let (rhs_epoch, rhs_sto) = &rhs.time_corrections[system];
+                assert_eq!(epoch, rhs_epoch);
+                assert_eq!(sto.t_tm, rhs_sto.t_tm);
+                assert!((sto.a.0 - rhs_sto.a.0).abs() < 1.0E-18);
+                assert!((sto.a.1 - rhs_sto.a.1).abs() < 1.0E-23);
+            }
+        };
+        // RINEX3 production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/kms-v3.rnx";
+        v3.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.version, Version::new(3, 5));
+        assert_eq!(
+            parsed.header.ionod_corrections.len(),
+            v3.header.ionod_corrections.len()
+        );
+        v3_header_eq(&parsed.header, &v3.header);
+        assert_eq!(parsed.record, v3.record);
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v4_kms300dnk_nav_v2_conversion() {
+        let test_resource = env!("CARGO_MANIFEST_DIR").to_owned()
+            + "/../test_resources/NAV/V4/KMS300DNK_R_20221591000_01H_MN.rnx.gz";
+        let rinex = Rinex::from_file(&test_resource).unwrap();
+        let split = rinex.to_nav_v2();
+        assert_eq!(split.len(), 2);
+        let gps = &split[&Constellation::GPS];
+        assert_eq!(gps.header.version, Version::new(2, 11));
+        assert_eq!(gps.sv().count(), 21);
+        for (_, frames) in gps.record.as_nav().unwrap() {
+            for fr in frames {
+                assert!(fr.as_eph().is_some(), "only ephemeris frames expected");
+            }
+        }
+        // ION and STO frames are migrated to the header
+        assert_eq!(gps.header.ionod_corrections.len(), 1);
+        let kb = gps.header.ionod_corrections[&Constellation::GPS]
+            .as_klobuchar()
+            .unwrap();
+        assert_eq!(kb.alpha.0, 1.024454832077E-08);
+        assert_eq!(kb.beta.3, -5.89824E5);
+        assert_eq!(gps.header.time_corrections.len(), 1);
+        let (epoch, gput) = &gps.header.time_corrections["GPUT"];
+        assert_eq!(
+            *epoch,
+            Epoch::from_gregorian_utc_hms(2022, 06, 10, 19, 56, 48)
+        );
+        assert_eq!(gput.a.0, 9.313225746155E-10);
+        let glo = &split[&Constellation::Glonass];
+        assert_eq!(glo.sv().count(), 10);
+        assert!(glo.header.ionod_corrections.is_empty());
+        // RINEX2 (.n) production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/kms-v2.22n";
+        gps.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.version, Version::new(2, 11));
+        // V2 headers have fewer digits
+        let parsed_kb = parsed.header.ionod_corrections[&Constellation::GPS]
+            .as_klobuchar()
+            .unwrap();
+        for (lhs, rhs) in [
+            (parsed_kb.alpha.0, kb.alpha.0),
+            (parsed_kb.alpha.3, kb.alpha.3),
+            (parsed_kb.beta.1, kb.beta.1),
+            (parsed_kb.beta.3, kb.beta.3),
+        ] {
+            assert!(((lhs - rhs) / rhs).abs() < 1.0E-4, "{} {}", lhs, rhs);
+        }
+        let (parsed_epoch, parsed_gput) = &parsed.header.time_corrections["GPUT"];
+        assert_eq!(parsed_epoch, epoch);
+        assert!((parsed_gput.a.0 - gput.a.0).abs() < 1.0E-18);
+        assert_eq!(parsed.record, gps.record);
+    }
+    #[test]
+    fn nav_v2_pair_conversion() {
+        let gps = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/CBW100NLD_R_20210010000_01D_MN.rnx"),
+        )
+        .unwrap()
+        .to_nav_v2()
+        .remove(&Constellation::GPS)
+        .unwrap();
+        assert_eq!(gps.header.constellation, Some(Constellation::GPS));
+        assert_eq!(gps.sv().count(), 2);
+        assert!(gps.header.time_corrections.contains_key("GPUT"));
+        assert_eq!(gps.header.time_corrections.len(), 1);
+        assert_eq!(gps.header.ionod_corrections.len(), 1);
+        // RINEX2 (.n) production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/cbw10010.21n";
+        gps.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.version, Version::new(2, 11));
+        assert_eq!(parsed.header.time_corrections, gps.header.time_corrections);
+        assert_eq!(
+            parsed.header.ionod_corrections,
+            gps.header.ionod_corrections
+        );
+        assert_eq!(parsed.record, gps.record);
+
+        let glo = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/NAV/V2/dlf10010.21g"),
+        )
+        .unwrap();
+        // single mixed file
+        let mixed = parsed.merge(&glo).unwrap().to_nav_v3();
+        assert_eq!(mixed.header.constellation, Some(Constellation::Mixed));
+        assert_eq!(mixed.header.version, Version::new(3, 5));
+        assert_eq!(mixed.sv().count(), gps.sv().count() + glo.sv().count());
+        assert!(mixed.header.time_corrections.contains_key("GPUT"));
+        assert!(mixed.header.time_corrections.contains_key("GLUT"));
+        // and back
+        let split = mixed.to_nav_v2();
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[&Constellation::GPS].record, gps.record);
+        assert_eq!(split[&Constellation::Glonass].record, glo.record);
+        assert_eq!(
+            split[&Constellation::Glonass].header.time_corrections,
+            glo.header.time_corrections
+        );
+    }
 }
@@ -1,6 +1,6 @@
 #[cfg(test)]
 mod test {
-    use rinex::{header::*, observation::*, prelude::*};
+    use rinex::{header::*, observation::*, prelude::*, version::Version};
     use std::str::FromStr;
     /*
      * Helper: to create a list of observable
@@ -910,6 +910,590 @@ mod test {
         assert_eq!(s2.obs, 43.650);
     }
     #[test]
+    fn v2_delf0010_21o_ionospheric_indices() {
+        let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o").unwrap();
+        let window = Duration::from_seconds(300.0);
+        let roti = rinex.roti(window);
+        let l1 = Observable::from_str("L1").unwrap();
+        assert_eq!(roti.len(), 1, "L2 is not a reference");
+        let vehicles = &roti[&l1];
+        assert!(vehicles.len() > 5);
+        for (sv, indices) in vehicles {
+            for (t, roti) in indices {
+                assert_eq!(*t, t.floor(window), "windows are aligned");
+                assert!(*roti >= 0.0 && *roti < 1.0, "{} {} {}", sv, t, roti);
+            }
+        }
+        let s4 = rinex.s4(window);
+        for observable in ["S1", "S2"] {
+            let vehicles = &s4[&Observable::from_str(observable).unwrap()];
+            assert!(vehicles.len() > 5);
+            for (sv, indices) in vehicles {
+                for (t, s4) in indices {
+                    assert!(*s4 >= 0.0 && *s4 < 1.0, "{} {} {}", sv, t, s4);
+                }
+            }
+        }
+        let sigma_phi = rinex.sigma_phi(window);
+        for observable in ["L1", "L2"] {
+            let vehicles = &sigma_phi[&Observable::from_str(observable).unwrap()];
+            for (sv, indices) in vehicles {
+                for (t, sigma) in indices {
+                    // periodic receiver clock jumps would exceed 1E6 rad
+                    assert!(*sigma >= 0.0 && *sigma < 10.0, "{} {} {}", sv, t, sigma);
+                }
+            }
+        }
+        // windows containing a clock jump are discarded
+        let g07 = Sv::new(Constellation::GPS, 7);
+        let g07_l1 = &sigma_phi[&l1][&g07];
+        assert!(g07_l1
+            .get(&Epoch::from_gregorian_utc(2021, 1, 1, 0, 0, 0, 0))
+            .is_none());
+        assert!(g07_l1
+            .get(&Epoch::from_gregorian_utc(2021, 1, 1, 0, 5, 0, 0))
+            .is_some());
+        // indices are not formed over shorter windows than the sampling
+        assert!(rinex.s4(Duration::from_seconds(60.0)).is_empty());
+    }
+    #[test]
+    fn v3_noa10630_code_bias_compensation() {
+        let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/OBS/V3/NOA10630.22O";
+        let rinex = Rinex::from_file(&path).unwrap();
+        let c1c = Observable::from_str("C1C").unwrap();
+        let compensated = rinex.observation_code_bias_compensation(|_, _, observable| {
+            if *observable == c1c {
+                Some(1.5)
+            } else {
+                None
+            }
+        });
+        let obs = compensated.header.obs.as_ref().unwrap();
+        assert_eq!(obs.dcb_compensations, vec![Constellation::GPS]);
+        assert!(obs.dcb_compensation(None));
+        let (lhs, rhs) = (
+            rinex.record.as_obs().unwrap(),
+            compensated.record.as_obs().unwrap(),
+        );
+        for ((_, (_, vehicles)), (_, (_, compensated))) in lhs.iter().zip(rhs.iter()) {
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    let value = compensated[sv][observable].obs;
+                    if *observable == c1c {
+                        assert!((data.obs - value - 1.5).abs() < 1.0E-9);
+                    } else {
+                        assert_eq!(data.obs, value);
+                    }
+                }
+            }
+        }
+        // SYS / DCBS APPLIED production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/noa1-dcbs.22o";
+        compensated.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        let obs = parsed.header.obs.as_ref().unwrap();
+        assert_eq!(obs.dcb_compensations, vec![Constellation::GPS]);
+    }
+    #[test]
+    fn v2_delf0010_21o_clock_jumps() {
+        let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o").unwrap();
+        let jumps = rinex.observation_clock_jumps();
+        let expected = vec![
+            Epoch::from_gregorian_utc(2021, 1, 1, 0, 2, 0, 0),
+            Epoch::from_gregorian_utc(2021, 1, 1, 0, 24, 30, 0),
+            Epoch::from_gregorian_utc(2021, 1, 1, 0, 47, 30, 0),
+        ];
+        assert_eq!(jumps.keys().copied().collect::<Vec<_>>(), expected);
+        for (t, jumps) in jumps {
+            // steered receiver: both code and phase jump by 1 ms
+            for observable in ["C1", "P1", "P2", "L1", "L2"] {
+                let observable = Observable::from_str(observable).unwrap();
+                assert_eq!(jumps.get(&observable), Some(&1), "{} {}", t, observable);
+            }
+        }
+        let repaired = rinex.observation_clock_jumps_repair();
+        assert!(repaired.observation_clock_jumps().is_empty());
+        let (c1, l1) = (
+            Observable::from_str("C1").unwrap(),
+            Observable::from_str("L1").unwrap(),
+        );
+        let g07 = Sv::new(Constellation::GPS, 7);
+        let (before, after) = (
+            Epoch::from_gregorian_utc(2021, 1, 1, 0, 1, 30, 0),
+            Epoch::from_gregorian_utc(2021, 1, 1, 0, 2, 0, 0),
+        );
+        let (lhs, rhs) = (
+            rinex.record.as_obs().unwrap(),
+            repaired.record.as_obs().unwrap(),
+        );
+        for record in [lhs, rhs] {
+            assert_eq!(record.len(), lhs.len());
+        }
+        let g07_obs = |record: &rinex::observation::Record, t: Epoch, observable: &Observable| {
+            record[&(t, EpochFlag::Ok)].1[&g07][observable].obs
+        };
+        // 1 ms in light distance
+        let jump = 299_792.458;
+        let raw = g07_obs(lhs, after, &c1) - g07_obs(lhs, before, &c1);
+        let fixed = g07_obs(rhs, after, &c1) - g07_obs(rhs, before, &c1);
+        assert!((raw - fixed - jump).abs() < 1.0E-3);
+        let raw = g07_obs(lhs, after, &l1) - g07_obs(lhs, before, &l1);
+        let fixed = g07_obs(rhs, after, &l1) - g07_obs(rhs, before, &l1);
+        assert!((raw - fixed - 1575.42E6 * 1.0E-3).abs() < 1.0E-3);
+        // first values are preserved
+        let t0 = Epoch::from_gregorian_utc(2021, 1, 1, 0, 0, 0, 0);
+        assert_eq!(g07_obs(lhs, t0, &c1), g07_obs(rhs, t0, &c1));
+        // windows are no longer discarded
+        let window = Duration::from_seconds(300.0);
+        let sigma_phi = repaired.sigma_phi(window);
+        let sigma = sigma_phi[&l1][&g07].get(&t0);
+        assert!(sigma.is_some());
+        assert!(*sigma.unwrap() < 10.0);
+    }
+    #[test]
+    fn v3_vlns0010_receiver_clock_offset() {
+        let rinex = Rinex::from_file("../test_resources/OBS/V3/VLNS0010.22O").unwrap();
+        let obs = rinex.header.obs.as_ref().unwrap();
+        assert!(!obs.clock_offset_applied);
+        // synthetic offsets: 100 us + 10 ns/s
+        let mut rinex = rinex.clone();
+        let t0 = rinex.first_epoch().unwrap();
+        let offset = |t: Epoch| 1.0E-4 + 1.0E-8 * (t - t0).to_seconds();
+        for ((t, _), (clk, _)) in rinex.record.as_mut_obs().unwrap().iter_mut() {
+            *clk = Some(offset(*t));
+        }
+        let applied = rinex.apply_receiver_clock_offset();
+        let obs = applied.header.obs.as_ref().unwrap();
+        assert!(obs.clock_offset_applied);
+        let (lhs, rhs) = (
+            rinex.record.as_obs().unwrap(),
+            applied.record.as_obs().unwrap(),
+        );
+        assert_eq!(lhs.len(), rhs.len());
+        for (((t, _), (_, vehicles)), ((t_rhs, _), (clk, compensated))) in
+            lhs.iter().zip(rhs.iter())
+        {
+            let dt = offset(*t);
+            assert_eq!(*clk, Some(dt), "offsets are preserved");
+            assert!(((*t - *t_rhs).to_seconds() - dt).abs() < 1.0E-9);
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    let value = compensated[sv][observable].obs;
+                    if observable.is_pseudorange_observable() {
+                        assert!((data.obs - value - dt * 299_792_458.0).abs() < 1.0E-6);
+                    } else if observable.is_phase_observable() {
+                        let f = observable.carrier(sv.constellation).unwrap().frequency();
+                        assert!((data.obs - value - dt * f).abs() < 1.0E-6);
+                    } else if observable.is_doppler_observable() {
+                        let f = observable.carrier(sv.constellation).unwrap().frequency();
+                        assert!((value - data.obs - 1.0E-8 * f).abs() < 1.0E-6);
+                    } else {
+                        assert_eq!(data.obs, value);
+                    }
+                }
+            }
+        }
+        // applying twice has no effect
+        assert_eq!(applied.apply_receiver_clock_offset(), applied);
+        // RCV CLOCK OFFS APPL and epoch clock offsets production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/vlns-clk.22o";
+        applied.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert!(parsed.header.obs.as_ref().unwrap().clock_offset_applied);
+        let parsed = parsed.record.as_obs().unwrap();
+        for ((_, (clk, _)), (_, (parsed, _))) in rhs.iter().zip(parsed.iter()) {
+            assert!((clk.unwrap() - parsed.unwrap()).abs() < 1.0E-12);
+        }
+        // reverting restores the original data
+        let reverted = applied.revert_receiver_clock_offset();
+        assert!(!reverted.header.obs.as_ref().unwrap().clock_offset_applied);
+        let reverted = reverted.record.as_obs().unwrap();
+        for (((t, _), (_, vehicles)), ((t_rhs, _), (_, restored))) in
+            lhs.iter().zip(reverted.iter())
+        {
+            assert!((*t - *t_rhs).to_seconds().abs() < 1.0E-9);
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    assert!((data.obs - restored[sv][observable].obs).abs() < 1.0E-6);
+                }
+            }
+        }
+    }
+    #[test]
+    fn v3_vlns0010_data_scaling() {
+        let path = "../test_resources/OBS/V3/VLNS0010.22O";
+        let rinex = Rinex::from_file(path).unwrap();
+        // declare scalings: all Glonass observables, 14 GPS observables
+        let gps = "C1C L1C S1C C2P C2W C2S C2L C2X L2P L2W L2S L2L L2X S2P";
+        let header_lines = format!(
+            "{:<60}SYS / SCALE FACTOR\n{:<60}SYS / SCALE FACTOR\n{:<60}SYS / SCALE FACTOR\n{:<60}END OF HEADER",
+            "R   10",
+            format!("G  100  14 {}", &gps[..47]),
+            format!("{:10} {}", "", &gps[48..]),
+            ""
+        );
+        let content = std::fs::read_to_string(path)
+            .unwrap()
+            .replace(&format!("{:<60}END OF HEADER", ""), &header_lines);
+        let scaled_path =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/vlns-scaled.22o";
+        std::fs::write(&scaled_path, content).unwrap();
+        let scaled = Rinex::from_file(&scaled_path).unwrap();
+        let _ = std::fs::remove_file(&scaled_path);
+        let obs = scaled.header.obs.as_ref().unwrap();
+        assert_eq!(obs.scalings[&Constellation::Glonass].len(), 9);
+        assert_eq!(obs.scalings[&Constellation::GPS].len(), 14);
+        let s2p = Observable::from_str("S2P").unwrap();
+        let s2w = Observable::from_str("S2W").unwrap();
+        assert_eq!(obs.scaling(&Constellation::GPS, s2p.clone()), 100.0);
+        assert_eq!(obs.scaling(&Constellation::GPS, s2w.clone()), 1.0);
+        // observations are divided by the scaling
+        let (lhs, rhs) = (
+            rinex.record.as_obs().unwrap(),
+            scaled.record.as_obs().unwrap(),
+        );
+        assert_eq!(lhs.len(), rhs.len());
+        for ((_, (_, vehicles)), (_, (_, scaled))) in lhs.iter().zip(rhs.iter()) {
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    let scaling = obs.scaling(&sv.constellation, observable.clone());
+                    let value = scaled[sv][observable].obs;
+                    assert!((data.obs / scaling - value).abs() < 1.0E-9);
+                }
+            }
+        }
+        // observations are multiplied back on production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/vlns-copy.22o";
+        scaled.to_file(&copy_path).unwrap();
+        let content = std::fs::read_to_string(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(content.matches("SYS / SCALE FACTOR").count(), 3);
+        assert_eq!(parsed.header.obs, scaled.header.obs);
+        assert_eq!(parsed.record, scaled.record);
+        // CRINEX: scaling applies to the recovered content
+        let content = std::fs::read_to_string("../test_resources/CRNX/V3/VLNS0010.22D")
+            .unwrap()
+            .replace(&format!("{:<60}END OF HEADER", ""), &header_lines);
+        let crinex_path =
+            env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/vlns-scaled.22d";
+        std::fs::write(&crinex_path, content).unwrap();
+        let parsed = Rinex::from_file(&crinex_path).unwrap();
+        let _ = std::fs::remove_file(&crinex_path);
+        assert_eq!(parsed.header.obs.as_ref().unwrap().scalings, obs.scalings);
+        let parsed = parsed.record.as_obs().unwrap();
+        assert_eq!(parsed.len(), rhs.len());
+        for ((_, (_, vehicles)), (_, (_, scaled))) in rhs.iter().zip(parsed.iter()) {
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    assert_eq!(data.obs, scaled[sv][observable].obs);
+                }
+            }
+        }
+    }
+    #[test]
+    fn v3_vlns0010_phase_shift() {
+        let path = "../test_resources/OBS/V3/VLNS0010.22O";
+        let rinex = Rinex::from_file(path).unwrap();
+        // blank SYS / PHASE SHIFT: nothing declared
+        assert!(rinex.header.obs.as_ref().unwrap().phase_shifts.is_empty());
+        let aligned = rinex.apply_phase_shift();
+        let obs = aligned.header.obs.as_ref().unwrap();
+        let expected = [
+            (Constellation::GPS, "L1C", 0.0),
+            (Constellation::GPS, "L2W", 0.0),
+            (Constellation::GPS, "L2L", -0.25),
+            (Constellation::GPS, "L2X", -0.25),
+            (Constellation::Glonass, "L1C", 0.0),
+            (Constellation::Glonass, "L2P", 0.25),
+        ];
+        for (constellation, code, shift) in expected {
+            let observable = Observable::from_str(code).unwrap();
+            let sv = Sv::new(constellation, 1);
+            assert_eq!(
+                obs.phase_shift(&constellation, &sv, &observable),
+                Some(shift)
+            );
+        }
+        let (lhs, rhs) = (
+            rinex.record.as_obs().unwrap(),
+            aligned.record.as_obs().unwrap(),
+        );
+        for ((_, (_, vehicles)), (_, (_, shifted))) in lhs.iter().zip(rhs.iter()) {
+            for (sv, observations) in vehicles {
+                for (observable, data) in observations {
+                    let value = shifted[sv][observable].obs;
+                    match obs.phase_shift(&sv.constellation, sv, observable) {
+                        Some(shift) => assert_eq!(value, data.obs + shift),
+                        None => assert_eq!(value, data.obs),
+                    }
+                }
+            }
+        }
+        // applying twice has no effect
+        assert_eq!(aligned.apply_phase_shift(), aligned);
+        // SYS / PHASE SHIFT production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/vlns-shift.22o";
+        aligned.to_file(&copy_path).unwrap();
+        let content = std::fs::read_to_string(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert!(content.contains(&format!("{:<60}SYS / PHASE SHIFT", "G L2X -0.25000")));
+        assert_eq!(
+            parsed.header.obs.as_ref().unwrap().phase_shifts,
+            obs.phase_shifts
+        );
+        // vehicle specific corrections
+        let content = std::fs::read_to_string(path).unwrap().replace(
+            &format!("{:<60}END OF HEADER", ""),
+            &format!(
+                "{:<60}SYS / PHASE SHIFT\n{:<60}SYS / PHASE SHIFT\n{:<60}END OF HEADER",
+                "G L2X -0.25000  11 G01 G02 G03 G04 G05 G06 G07 G08 G09 G10",
+                format!("{:18} G11", ""),
+                ""
+            ),
+        );
+        std::fs::write(&copy_path, content).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        let obs = parsed.header.obs.as_ref().unwrap();
+        let l2x = Observable::from_str("L2X").unwrap();
+        for (prn, shift) in [(1, Some(-0.25)), (11, Some(-0.25)), (12, None)] {
+            let sv = Sv::new(Constellation::GPS, prn);
+            assert_eq!(obs.phase_shift(&Constellation::GPS, &sv, &l2x), shift);
+        }
+        // partially declared observables: only the other vehicles are aligned
+        let mut parsed = parsed.clone();
+        let l2w = Observable::from_str("L2W").unwrap();
+        for (_, (_, vehicles)) in parsed.record.as_mut_obs().unwrap().iter_mut() {
+            for (sv, observations) in vehicles.iter_mut() {
+                if sv.constellation == Constellation::GPS {
+                    // L2X is not tracked in this file
+                    let data = observations[&l2w].clone();
+                    observations.insert(l2x.clone(), data);
+                }
+            }
+        }
+        let aligned = parsed.apply_phase_shift();
+        let obs = aligned.header.obs.as_ref().unwrap();
+        for prn in [1, 11, 12] {
+            let sv = Sv::new(Constellation::GPS, prn);
+            assert_eq!(obs.phase_shift(&Constellation::GPS, &sv, &l2x), Some(-0.25));
+        }
+        let (lhs, rhs) = (
+            parsed.record.as_obs().unwrap(),
+            aligned.record.as_obs().unwrap(),
+        );
+        let mut tested = (false, false);
+        for ((_, (_, vehicles)), (_, (_, shifted))) in lhs.iter().zip(rhs.iter()) {
+            for (sv, observations) in vehicles {
+                if sv.constellation != Constellation::GPS {
+                    continue;
+                }
+                if let Some(data) = observations.get(&l2x) {
+                    let value = shifted[sv][&l2x].obs;
+                    if sv.prn <= 11 {
+                        assert_eq!(value, data.obs, "{} already aligned", sv);
+                        tested.0 = true;
+                    } else {
+                        assert_eq!(value, data.obs - 0.25, "{} not aligned", sv);
+                        tested.1 = true;
+                    }
+                }
+            }
+        }
+        assert_eq!(tested, (true, true));
+    }
+    #[test]
+    fn v2_delf0010_21o_receiver_clock_offset() {
+        let mut rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o").unwrap();
+        for (index, (_, (clk, _))) in rinex.record.as_mut_obs().unwrap().iter_mut().enumerate() {
+            *clk = Some(-1.0E-3 + index as f64 * 1.0E-9);
+        }
+        // epoch clock offsets production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/delf-clk.21o";
+        rinex.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        let (lhs, rhs) = (
+            rinex.record.as_obs().unwrap(),
+            parsed.record.as_obs().unwrap(),
+        );
+        assert_eq!(lhs.len(), rhs.len());
+        for ((k, (clk, vehicles)), (k_rhs, (parsed, parsed_vehicles))) in lhs.iter().zip(rhs.iter())
+        {
+            assert_eq!(k, k_rhs);
+            assert!((clk.unwrap() - parsed.unwrap()).abs() < 1.0E-9);
+            assert_eq!(vehicles.len(), parsed_vehicles.len());
+        }
+    }
+    #[test]
+    fn v2_delf0010_21o_v3_conversion() {
+        let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o").unwrap();
+        let modes = TrackingModes::default();
+        let v3 = rinex.obs_version_conversion(Version::new(3, 4), &modes);
+        assert_eq!(v3.header.version, Version::new(3, 4));
+        let codes = &v3.header.obs.as_ref().unwrap().codes;
+        assert_eq!(
+            codes.len(),
+            2,
+            "only encountered constellations are described"
+        );
+        for (constellation, expected) in [
+            (
+                Constellation::GPS,
+                vec!["L1C", "L2W", "C1C", "C2W", "C1W", "S1C", "S2W"],
+            ),
+            (
+                Constellation::Glonass,
+                vec!["L1C", "L2P", "C1C", "C2P", "C1P", "S1C", "S2P"],
+            ),
+        ] {
+            let mut codes = codes[&constellation].clone();
+            codes.sort();
+            assert_eq!(codes, create_observ_list(expected));
+        }
+        let (lhs, rhs) = (rinex.record.as_obs().unwrap(), v3.record.as_obs().unwrap());
+        assert_eq!(lhs.len(), rhs.len());
+        let (p1, c1w) = (
+            Observable::from_str("P1").unwrap(),
+            Observable::from_str("C1W").unwrap(),
+        );
+        for ((k, (_, vehicles)), (k_rhs, (_, converted))) in lhs.iter().zip(rhs.iter()) {
+            assert_eq!(k, k_rhs);
+            assert_eq!(vehicles.len(), converted.len());
+            for (sv, observations) in vehicles {
+                assert_eq!(observations.len(), converted[sv].len());
+                if sv.constellation != Constellation::GPS {
+                    continue;
+                }
+                if let Some(p1) = observations.get(&p1) {
+                    assert_eq!(converted[sv].get(&c1w), Some(p1));
+                }
+            }
+        }
+        // RINEX3 production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/delf-v3.rnx";
+        v3.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.version, Version::new(3, 4));
+        assert_eq!(parsed.header.obs, v3.header.obs);
+        assert_eq!(parsed.record, v3.record);
+        // back to RINEX2
+        let v2 = v3.obs_version_conversion(Version::new(2, 11), &modes);
+        assert_eq!(v2.record, rinex.record);
+        assert_eq!(v2.header.obs, rinex.header.obs);
+    }
+    #[test]
+    fn v3_larm0010_v2_conversion() {
+        let rinex = Rinex::from_file("../test_resources/OBS/V3/LARM0010.22O").unwrap();
+        let modes = TrackingModes::default();
+        let v2 = rinex.obs_version_conversion(Version::new(2, 11), &modes);
+        let codes = &v2.header.obs.as_ref().unwrap().codes;
+        let expected: Vec<Observable> = [
+            "C1", "L1", "D1", "S1", "C2", "L2", "D2", "S2", "P2", "C5", "L5", "D5", "S5",
+        ]
+        .iter()
+        .map(|code| Observable::from_str(code).unwrap())
+        .collect();
+        for constellation in [Constellation::GPS, Constellation::Glonass] {
+            assert_eq!(codes[&constellation], expected);
+        }
+        let (lhs, rhs) = (rinex.record.as_obs().unwrap(), v2.record.as_obs().unwrap());
+        let (l2, l2w, l2s, l2p) = (
+            Observable::from_str("L2").unwrap(),
+            Observable::from_str("L2W").unwrap(),
+            Observable::from_str("L2S").unwrap(),
+            Observable::from_str("L2P").unwrap(),
+        );
+        for ((_, (_, vehicles)), (_, (_, converted))) in lhs.iter().zip(rhs.iter()) {
+            for (sv, observations) in vehicles {
+                let reference = match sv.constellation {
+                    Constellation::GPS => &l2w,
+                    _ => &l2p,
+                };
+                assert_eq!(
+                    converted.get(sv).and_then(|obs| obs.get(&l2)),
+                    observations.get(reference),
+                    "{}: L2 is not the assumed signal",
+                    sv
+                );
+            }
+        }
+        // L2C tracking assumption
+        let l2c = modes.with_mode(Constellation::GPS, "L2", "L2S");
+        let v2_l2c = rinex.obs_version_conversion(Version::new(2, 11), &l2c);
+        let converted = v2_l2c.record.as_obs().unwrap();
+        for ((_, (_, vehicles)), (_, (_, converted))) in lhs.iter().zip(converted.iter()) {
+            for (sv, observations) in vehicles {
+                if sv.constellation == Constellation::GPS {
+                    assert_eq!(converted[sv].get(&l2), observations.get(&l2s));
+                }
+            }
+        }
+        // RINEX2 production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/larm-v2.22o";
+        v2.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.version, Version::new(2, 11));
+        assert_eq!(parsed.header.obs, v2.header.obs);
+        assert_eq!(parsed.record, v2.record);
+        // RINEX4: same observables
+        let v4 = rinex.obs_version_conversion(Version::new(4, 0), &modes);
+        assert_eq!(v4.header.obs, rinex.header.obs);
+        assert_eq!(v4.record, rinex.record);
+        // Glonass channels are preserved
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/larm-v4.22o";
+        v4.to_file(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert_eq!(parsed.header.glo_channels.len(), 24);
+        assert_eq!(parsed.header.glo_channels, rinex.header.glo_channels);
+        assert_eq!(parsed.record, v4.record);
+    }
+    #[test]
+    fn v3_larm0010_glonass_biases() {
+        let rinex = Rinex::from_file("../test_resources/OBS/V3/LARM0010.22O").unwrap();
+        let obs = rinex.header.obs.as_ref().unwrap();
+        assert_eq!(obs.glo_biases.len(), 4);
+        for code in ["C1C", "C1P", "C2C", "C2P"] {
+            let observable = Observable::from_str(code).unwrap();
+            assert_eq!(obs.glo_bias(&observable), Some(-71.940));
+        }
+        assert_eq!(obs.glo_bias(&Observable::from_str("L1C").unwrap()), None);
+        // GLONASS COD/PHS/BIS production
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/larm-bis.22o";
+        rinex.to_file(&copy_path).unwrap();
+        let content = std::fs::read_to_string(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert!(content.contains(&format!(
+            "{:<60}GLONASS COD/PHS/BIS",
+            " C1C  -71.940 C1P  -71.940 C2C  -71.940 C2P  -71.940"
+        )));
+        assert_eq!(
+            parsed.header.obs.as_ref().unwrap().glo_biases,
+            obs.glo_biases
+        );
+        // unknown biases: blank fields
+        let mut unknown = rinex.clone();
+        unknown.header.obs.as_mut().unwrap().glo_biases.clear();
+        let copy_path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/larm-nobis.22o";
+        unknown.to_file(&copy_path).unwrap();
+        let content = std::fs::read_to_string(&copy_path).unwrap();
+        let parsed = Rinex::from_file(&copy_path).unwrap();
+        let _ = std::fs::remove_file(&copy_path);
+        assert!(content.contains(&format!(
+            "{:<60}GLONASS COD/PHS/BIS",
+            " C1C          C1P          C2C          C2P"
+        )));
+        assert!(parsed.header.obs.as_ref().unwrap().glo_biases.is_empty());
+    }
+    #[test]
     fn v3_noa10630() {
         let rnx = Rinex::from_file("../test_resources/OBS/V3/NOA10630.22O").unwrap();
         let expected: Vec<Epoch> = vec![
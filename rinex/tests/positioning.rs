@@ -0,0 +1,470 @@
+#[cfg(test)]
+#[cfg(feature = "positioning")]
+mod test {
+    use rinex::positioning::{
+        MappingFunction, MeteoConditions, PppOpts, RtkOpts, SppOpts, TropoModel, ZenithModel,
+    };
+    use rinex::prelude::*;
+    use rinex::record::Record;
+    use rinex::types::Type as RinexType;
+    use std::collections::{BTreeMap, HashMap};
+    fn distance(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_spp() {
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let reference = obs.header.ground_position.unwrap().to_ecef_wgs84();
+        let solutions = obs.spp(&nav, SppOpts::default());
+        assert_eq!(solutions.len(), 2880, "one solution per epoch is expected");
+        for (t, solution) in &solutions {
+            let err = distance(solution.position, reference);
+            assert!(
+                err < 5.0,
+                "{}: {:.2} m away from reference position",
+                t,
+                err
+            );
+            assert!(solution.dop.pdop > 0.0 && solution.dop.pdop < 5.0);
+            for constellation in [
+                Constellation::GPS,
+                Constellation::Galileo,
+                Constellation::BeiDou,
+            ] {
+                assert!(solution.clock.contains_key(&constellation));
+            }
+            // inter system biases remain within a few tens of ns
+            let isb = solution.isb(Constellation::BeiDou).unwrap();
+            assert!(isb.abs() < 100.0E-9, "{}: BeiDou ISB {:e}", t, isb);
+            for residual in solution.residuals.values() {
+                assert!(residual.abs() < 20.0);
+            }
+        }
+        // surface conditions: standard atmosphere by default
+        let (_, _, alt) = obs.header.ground_position.unwrap().to_geodetic();
+        let standard = MeteoConditions::standard(alt);
+        // hourly meteo observations, pressure dropping by `drop` [hPa] over the day
+        let meteo = |drop: f64| -> Rinex {
+            let t0 = obs.first_epoch().unwrap();
+            let mut record: BTreeMap<Epoch, HashMap<Observable, f64>> = BTreeMap::new();
+            for hour in 0..=24 {
+                let mut values = HashMap::new();
+                values.insert(
+                    Observable::Pressure,
+                    standard.pressure - drop * hour as f64 / 24.0,
+                );
+                values.insert(Observable::Temperature, standard.temperature);
+                values.insert(Observable::HumidityRate, standard.humidity);
+                record.insert(t0 + Duration::from_hours(hour as f64), values);
+            }
+            Rinex::new(
+                Header::default().with_type(RinexType::MeteoData),
+                Record::MeteoRecord(record),
+            )
+        };
+        let steady = meteo(0.0);
+        let opts = SppOpts::default().with_meteo(&steady);
+        for (t, solution) in obs.spp(&nav, opts) {
+            assert!(distance(solution.position, solutions[&t].position) < 0.01);
+        }
+        // conditions are interpolated at each epoch
+        let dropping = meteo(100.0);
+        let opts = opts.with_meteo(&dropping);
+        let (first, last) = (obs.first_epoch().unwrap(), obs.last_epoch().unwrap());
+        for (t, solution) in obs.spp(&nav, opts) {
+            let err = distance(solution.position, solutions[&t].position);
+            if t == first {
+                assert!(err < 0.01, "{}: {:.3} m", t, err);
+            } else if t == last {
+                assert!(err > 0.1, "{}: {:.3} m", t, err);
+            }
+        }
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_doppler_velocity() {
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let velocities = obs.doppler_velocity(&nav, SppOpts::default());
+        assert_eq!(velocities.len(), 2880, "one solution per epoch is expected");
+        // static station
+        let n = velocities.len() as f64;
+        let mut mean = (0.0_f64, 0.0_f64, 0.0_f64);
+        for (t, velocity) in &velocities {
+            assert!(velocity.speed() < 0.5, "{}: {:.3} m/s", t, velocity.speed());
+            let (std_e, std_n, std_u) = velocity.std_enu();
+            assert!(std_e < 0.1 && std_n < 0.1 && std_u < 0.1);
+            assert!(velocity.clock_drift.abs() < 1.0E-6);
+            mean.0 += velocity.enu.0 / n;
+            mean.1 += velocity.enu.1 / n;
+            mean.2 += velocity.enu.2 / n;
+        }
+        assert!(mean.0.abs() < 0.01 && mean.1.abs() < 0.01 && mean.2.abs() < 0.01);
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_ppp() {
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let reference = obs.header.ground_position.unwrap().to_ecef_wgs84();
+        // broadcast orbits and clocks only
+        let solutions = obs.ppp(&nav, None, None, None, PppOpts::default());
+        assert_eq!(solutions.len(), 2880, "one solution per epoch is expected");
+        let t0 = *solutions.keys().next().unwrap();
+        for (t, solution) in &solutions {
+            assert!(solution.clock.contains_key(&Constellation::GPS));
+            assert!(solution.clock.contains_key(&Constellation::Galileo));
+            assert!(solution.ambiguities.len() >= 5);
+            // after convergence
+            if (*t - t0).to_seconds() > 3600.0 {
+                let err = distance(solution.position, reference);
+                assert!(
+                    err < 1.5,
+                    "{}: {:.2} m away from reference position",
+                    t,
+                    err
+                );
+                assert!(solution.zwd.abs() < 0.6);
+                assert!(solution.ztd() > 2.0 && solution.ztd() < 3.0);
+                for (code, phase) in solution.residuals.values() {
+                    assert!(code.abs() < 10.0);
+                    assert!(phase.abs() < 2.0);
+                }
+            }
+        }
+        let (_, last) = solutions.iter().last().unwrap();
+        assert!(distance(last.position, reference) < 1.0);
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_zero_baseline_rtk() {
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        // first hour
+        let mut rover = obs.clone();
+        let record = rover.record.as_mut_obs().unwrap();
+        let t0 = record.keys().next().unwrap().0;
+        record.retain(|(t, _), _| (*t - t0).to_seconds() < 3600.0);
+
+        // kinematic rover, sharing the antenna of the base station
+        let opts = RtkOpts::default().with_kinematic(true);
+        let solutions = rover.rtk(&obs, &nav, opts);
+        assert_eq!(solutions.len(), 120, "one solution per epoch is expected");
+        for (t, solution) in &solutions {
+            assert!(solution.is_fixed(), "{}: ambiguities not resolved", t);
+            assert!(
+                solution.baseline_length() < 1.0E-3,
+                "{}: {:.4} m baseline",
+                t,
+                solution.baseline_length()
+            );
+        }
+
+        // base position is required
+        let mut base = obs.clone();
+        base.header.ground_position = None;
+        assert!(rover.rtk(&base, &nav, opts).is_empty());
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_tropo_delays() {
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        let model = TropoModel::default();
+        let delays = obs.tropo_delays(&nav, None, model);
+        assert_eq!(
+            delays.len(),
+            2880,
+            "one set of delays per epoch is expected"
+        );
+        for svs in delays.values() {
+            assert!(svs.len() > 5);
+            for delay in svs.values() {
+                // 2.3 m at zenith, 60 m on the horizon
+                assert!(*delay > 2.3 && *delay < 65.0, "{} m", delay);
+            }
+        }
+        // alternate models
+        let model = model
+            .with_zenith_model(ZenithModel::Unb3m)
+            .with_mapping_function(MappingFunction::Gmf);
+        let alternate = obs.tropo_delays(&nav, None, model);
+        for (t, svs) in &alternate {
+            for (sv, delay) in svs {
+                let reference = delays[t][sv];
+                assert!((delay - reference).abs() < 0.05 * reference);
+            }
+        }
+
+        // meteo observations: low pressure lowers the hydrostatic delay
+        let t0 = obs.first_epoch().unwrap();
+        let mut record: BTreeMap<Epoch, HashMap<Observable, f64>> = BTreeMap::new();
+        for hour in 0..=24 {
+            let mut values = HashMap::new();
+            values.insert(Observable::Pressure, 960.0);
+            values.insert(Observable::Temperature, 15.0);
+            values.insert(Observable::HumidityRate, 50.0);
+            record.insert(t0 + Duration::from_hours(hour as f64), values);
+        }
+        let meteo = Rinex::new(
+            Header::default().with_type(RinexType::MeteoData),
+            Record::MeteoRecord(record),
+        );
+        let model = TropoModel::default();
+        let measured = obs.tropo_delays(&nav, Some(&meteo), model);
+        for (t, svs) in &measured {
+            for (sv, delay) in svs {
+                let ratio = delay / delays[t][sv];
+                assert!(ratio > 0.93 && ratio < 0.97, "{}", ratio);
+            }
+        }
+
+        // ground position is required
+        let mut obs = obs.clone();
+        obs.header.ground_position = None;
+        assert!(obs.tropo_delays(&nav, None, model).is_empty());
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v2_delf0010_21o_ionex_delays() {
+        use rinex::carrier::Carrier;
+        use std::str::FromStr;
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/OBS/V2/delf0010.21o"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/NAV/V2/cbw10010.21n.gz"),
+        )
+        .unwrap();
+        let mut ionex = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/IONEX/V1/CKMG0090.21I.gz"),
+        )
+        .unwrap();
+        // maps do not cover this day
+        assert!(obs.ionex_delays(&nav, &ionex).is_empty());
+        // use them as if they did
+        let record = ionex.record.as_ionex().unwrap();
+        let record = record
+            .iter()
+            .map(|(t, maps)| (*t - Duration::from_days(8.0), maps.clone()))
+            .collect();
+        ionex.record = Record::IonexRecord(record);
+
+        let delays = obs.ionex_delays(&nav, &ionex);
+        assert!(!delays.is_empty());
+        let ratio = (Carrier::L1.frequency() / Carrier::L2.frequency()).powi(2);
+        for svs in delays.values() {
+            for (sv, observables) in svs {
+                assert_eq!(sv.constellation, Constellation::GPS);
+                let l1 = observables[&Observable::from_str("C1").unwrap()];
+                // a few meters on L1
+                assert!(l1 > 0.5 && l1 < 30.0, "{} m", l1);
+                // same delay for code and phase, on a given carrier
+                assert_eq!(observables[&Observable::from_str("L1").unwrap()], l1);
+                if let Some(l2) = observables.get(&Observable::from_str("P2").unwrap()) {
+                    assert!((l2 / l1 - ratio).abs() < 1.0E-9);
+                }
+                assert!(!observables.contains_key(&Observable::from_str("S1").unwrap()));
+            }
+        }
+
+        // ground position is required
+        let mut obs = obs.clone();
+        obs.header.ground_position = None;
+        assert!(obs.ionex_delays(&nav, &ionex).is_empty());
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v2_dutch_network_ionex() {
+        use rinex::ionex::BiasSource;
+        use rinex::positioning::{IonexOpts, TecFit};
+        use std::str::FromStr;
+        let network: Vec<Rinex> = ["delf", "wsra", "zegv", "rovn"]
+            .iter()
+            .map(|station| {
+                Rinex::from_file(
+                    &(env!("CARGO_MANIFEST_DIR").to_owned()
+                        + &format!("/../test_resources/OBS/V2/{}0010.21o", station)),
+                )
+                .unwrap()
+            })
+            .collect();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/NAV/V2/cbw10010.21n.gz"),
+        )
+        .unwrap();
+        // null biases
+        let mut dcbs: HashMap<BiasSource, (f64, f64)> = HashMap::new();
+        for obs in &network {
+            let name: String = obs.header.station.chars().take(4).collect();
+            dcbs.insert(BiasSource::Station(name), (0.0, 0.0));
+            for sv in obs.sv() {
+                dcbs.insert(BiasSource::SpaceVehicle(sv), (0.0, 0.0));
+            }
+        }
+        // unknown biases are not assumed
+        assert!(
+            Rinex::ionex_from_network(&network, &nav, &HashMap::new(), &IonexOpts::default())
+                .is_none()
+        );
+        let mut partial = dcbs.clone();
+        partial.remove(&BiasSource::Station("ZEGV".to_string()));
+        partial.remove(&BiasSource::SpaceVehicle(Sv::from_str("G07").unwrap()));
+        let ionex =
+            Rinex::ionex_from_network(&network, &nav, &partial, &IonexOpts::default()).unwrap();
+        let fields = ionex.header.ionex.as_ref().unwrap();
+        assert_eq!(fields.nb_stations, 2);
+        assert!(!fields
+            .dcbs
+            .contains_key(&BiasSource::SpaceVehicle(Sv::from_str("G07").unwrap())));
+        for skipped in ["station ZEGV", "vehicle G07"] {
+            let comment = format!("{}: unknown DCB, skipped", skipped);
+            assert!(ionex.header.comments.contains(&comment), "{}", comment);
+        }
+        for fit in [
+            TecFit::SphericalHarmonics { degree: 2 },
+            TecFit::Kriging { range: 1000.0 },
+        ] {
+            let opts = IonexOpts::default().with_fit(fit);
+            let ionex = Rinex::ionex_from_network(&network, &nav, &dcbs, &opts).unwrap();
+            let fields = ionex.header.ionex.as_ref().unwrap();
+            // last station only has a few epochs: no arc is long enough
+            assert_eq!(fields.nb_stations, 3);
+            assert!(fields.nb_satellites > 0);
+            assert_eq!(fields.map_dimension, 2);
+            let record = ionex.record.as_ionex().unwrap();
+            assert_eq!(record.len(), 2, "one map per hour");
+            for (t, (tec, rms, height)) in record {
+                assert!(height.is_none());
+                let rms = rms.as_ref().unwrap();
+                assert_eq!(tec.len(), rms.len());
+                // only nodes covered by the network
+                assert!(!tec.is_empty());
+                for point in tec {
+                    assert!(point.latitude > 35.0 && point.latitude < 70.0);
+                    assert!(point.value.is_finite());
+                }
+                assert!(ionex.tec_at(*t, 52.0, 4.4).is_some());
+                assert!(ionex.tec_at(*t, -30.0, 150.0).is_none());
+            }
+            // production
+            let path = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/dutch.21i";
+            ionex.to_file(&path).unwrap();
+            let parsed = Rinex::from_file(&path).unwrap();
+            let _ = std::fs::remove_file(&path);
+            assert_eq!(parsed.header.ionex, ionex.header.ionex);
+            assert_eq!(parsed.record.as_ionex().unwrap().len(), record.len());
+        }
+    }
+    #[test]
+    #[cfg(feature = "flate2")]
+    fn v3_esbc00dnk_dcb_estimation() {
+        use rinex::ionex::{BiasSource, Grid, GridLinspace};
+        use std::str::FromStr;
+        let obs = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/CRNX/V3/ESBC00DNK_R_20201770000_01D_30S_MO.crx.gz"),
+        )
+        .unwrap();
+        let nav = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/NAV/V3/ESBC00DNK_R_20201770000_01D_MN.rnx.gz"),
+        )
+        .unwrap();
+        // broadcast model ionosphere, over Northern Europe
+        let grid = Grid {
+            latitude: GridLinspace::from((70.0, 35.0, -2.5)),
+            longitude: GridLinspace::from((-10.0, 30.0, 5.0)),
+            height: GridLinspace::from((350.0, 350.0, 0.0)),
+        };
+        let t0 = Epoch::from_gregorian_utc_at_midnight(2020, 6, 25);
+        let series =
+            TimeSeries::inclusive(t0, t0 + Duration::from_days(1.0), Duration::from_hours(1.0));
+        let model = nav
+            .ionex_from_broadcast(Constellation::GPS, &grid, series)
+            .unwrap();
+        let c1w = Observable::from_str("C1W").unwrap();
+        let c2w = Observable::from_str("C2W").unwrap();
+        let dcbs = obs.dcb_estimation(&nav, &model, Constellation::GPS, &c1w, &c2w);
+        let rcvr = dcbs
+            .get(&BiasSource::Station("ESBC".to_string()))
+            .expect("missing receiver bias");
+        assert!(rcvr.0.is_finite() && rcvr.1 > 0.0);
+        let sat_biases: Vec<f64> = dcbs
+            .iter()
+            .filter_map(|(src, (bias, rms))| match src {
+                BiasSource::SpaceVehicle(sv) => {
+                    assert_eq!(sv.constellation, Constellation::GPS);
+                    assert!(*rms > 0.0 && *rms < 1.0, "{} {}", sv, rms);
+                    Some(*bias)
+                },
+                _ => None,
+            })
+            .collect();
+        assert!(sat_biases.len() > 20);
+        // zero mean constraint
+        let mean = sat_biases.iter().sum::<f64>() / sat_biases.len() as f64;
+        assert!(mean.abs() < 1.0E-9);
+        for bias in sat_biases {
+            assert!(bias.abs() < 20.0, "{}", bias);
+        }
+        // unknown observable
+        let c5 = Observable::from_str("C5X").unwrap();
+        assert!(obs
+            .dcb_estimation(&nav, &model, Constellation::GPS, &c1w, &c5)
+            .is_empty());
+        // no ionosphere available at these epochs
+        let ionex = Rinex::from_file(
+            &(env!("CARGO_MANIFEST_DIR").to_owned()
+                + "/../test_resources/IONEX/V1/CKMG0090.21I.gz"),
+        )
+        .unwrap();
+        assert!(obs
+            .dcb_estimation(&nav, &ionex, Constellation::GPS, &c1w, &c2w)
+            .is_empty());
+    }
+}
@@ -82,7 +82,6 @@ mod test {
             testbench(fp.to_str().unwrap());
         }
     }
-    /*
     #[test]
     fn nav_v4() {
         let folder = env!("CARGO_MANIFEST_DIR").to_owned() + "/../test_resources/NAV/V4/";
@@ -91,5 +90,5 @@ mod test {
             let fp = fp.path();
             testbench(fp.to_str().unwrap());
         }
-    }*/
+    }
 }
@@ -3,6 +3,8 @@ use thiserror::Error;
 //use std::collections::HashMap;
 use crate::datetime::{parse_datetime, ParseDateTimeError};
 use rinex::constellation::Constellation;
+use rinex::sv::Sv;
+use std::str::FromStr;
 
 pub mod description;
 pub mod header;
@@ -189,6 +191,29 @@ impl Solution {
     pub fn duration(&self) -> chrono::Duration {
         self.end_time - self.start_time
     }
+    /// Returns true if this solution is valid at given instant
+    pub fn is_valid(&self, t: chrono::NaiveDateTime) -> bool {
+        t >= self.start_time && t < self.end_time
+    }
+    /// Returns true if this solution applies to given vehicle:
+    /// either referenced by its PRN, or by its constellation only
+    /// (receiver biases)
+    pub fn applies_to(&self, sv: Sv) -> bool {
+        if let Ok(prn) = Sv::from_str(&self.prn) {
+            prn == sv
+        } else if let Ok(constellation) = Constellation::from_1_letter_code(&self.prn) {
+            constellation == sv.constellation
+        } else {
+            false
+        }
+    }
+    /// Returns the estimate [s], for time biases only (not expressed in cycles)
+    pub fn time_bias(&self) -> Option<f64> {
+        match self.unit.as_str() {
+            "ns" => Some(self.estimate * 1.0E-9),
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -344,4 +369,31 @@ mod tests {
             assert_eq!(obs.1.is_some(), true); // all came with OBS1+OBS2
         }
     }
+    #[test]
+    fn test_code_biases() {
+        use crate::SPEED_OF_LIGHT;
+        use rinex::prelude::{Epoch, Observable};
+        let g01 = Sv::from_str("G01").unwrap();
+        let t = Epoch::from_gregorian_utc_at_midnight(2016, 11, 1);
+        let c1c = Observable::from_str("C1C").unwrap();
+        let c2w = Observable::from_str("C2W").unwrap();
+        let p1 = Observable::from_str("P1").unwrap();
+        // observable specific biases
+        let file = env!("CARGO_MANIFEST_DIR").to_owned() + "/data/BIA/V1/example-1a.bia";
+        let sinex = Sinex::from_file(&file).unwrap();
+        let bias = sinex.code_bias(t, g01, None, &c1c).unwrap();
+        assert!((bias - 10.2472E-9 * SPEED_OF_LIGHT).abs() < 1.0E-6);
+        let bias = sinex.code_bias(t, g01, None, &p1).unwrap();
+        assert!((bias - 11.6848E-9 * SPEED_OF_LIGHT).abs() < 1.0E-6);
+        // outside of validity period
+        let t2 = Epoch::from_gregorian_utc_at_midnight(2017, 1, 1);
+        assert!(sinex.code_bias(t2, g01, None, &c1c).is_none());
+        // differential biases: C1C aligned on C1W
+        let file = env!("CARGO_MANIFEST_DIR").to_owned() + "/data/BIA/V1/example-1b.bia";
+        let sinex = Sinex::from_file(&file).unwrap();
+        let bias = sinex.code_bias(t, g01, None, &c1c).unwrap();
+        assert!((bias + 1.4376E-9 * SPEED_OF_LIGHT).abs() < 1.0E-6);
+        // inter frequency biases are not applied
+        assert!(sinex.code_bias(t, g01, None, &c2w).is_none());
+    }
 }
@@ -1,4 +1,5 @@
 use rinex::constellation::Constellation;
+use rinex::prelude::{Epoch, Observable, Rinex, Sv};
 use std::collections::HashMap;
 use std::io::{prelude::*, BufReader};
 use std::str::FromStr;
@@ -17,6 +18,9 @@ use description::Description;
 use header::{is_valid_header, Header};
 use reference::Reference;
 
+/// Speed of light in vacuum [m.s⁻¹]
+const SPEED_OF_LIGHT: f64 = 299_792_458.0_f64;
+
 fn is_comment(line: &str) -> bool {
     line.starts_with("*")
 }
@@ -287,3 +291,118 @@ impl Sinex {
         })
     }
 }
+
+/*
+ * Bias SINEX observable code, from a RINEX observable:
+ * RINEX2 codes are converted to their modern equivalent
+ */
+fn bias_code(observable: &Observable) -> String {
+    let code = observable.to_string();
+    match code.as_str() {
+        "C1" => "C1C".to_string(),
+        "P1" => "C1W".to_string(),
+        "P2" => "C2W".to_string(),
+        _ => code,
+    }
+}
+
+/*
+ * Converts given Epoch to a datetime, as used in SINEX files
+ */
+fn naive_datetime(t: Epoch) -> Option<chrono::NaiveDateTime> {
+    let (y, m, d, hh, mm, ss, ns) = t.to_gregorian_utc();
+    chrono::NaiveDate::from_ymd_opt(y, m as u32, d as u32)?
+        .and_hms_nano_opt(hh as u32, mm as u32, ss as u32, ns)
+}
+
+impl Sinex {
+    /// Returns the code bias [m] of given pseudo range `observable`,
+    /// for given Sv at given Epoch, from the Bias solutions valid at that instant.
+    /// Observable specific biases (OSB) are used when available, otherwise
+    /// differential biases (DSB) between two signals of the same frequency
+    /// (for example C1W-C1C) align the second signal on the first one.
+    /// Receiver biases apply when `station` (4 letter code) is given and matches.
+    /// Returns None when no bias is known.
+    pub fn code_bias(
+        &self,
+        t: Epoch,
+        sv: Sv,
+        station: Option<&str>,
+        observable: &Observable,
+    ) -> Option<f64> {
+        let solutions = self.record.bias_solutions()?;
+        let t = naive_datetime(t)?;
+        let code = bias_code(observable);
+        let applicable: Vec<&bias::Solution> = solutions
+            .iter()
+            .filter(|sol| sol.is_valid(t) && sol.applies_to(sv))
+            .filter(|sol| match (&sol.station, station) {
+                (None, _) => true,
+                (Some(name), Some(station)) => {
+                    let name: String = name.chars().take(4).collect();
+                    let station: String = station.chars().take(4).collect();
+                    name.eq_ignore_ascii_case(&station)
+                },
+                (Some(_), None) => false,
+            })
+            .collect();
+        let mut total: Option<f64> = None;
+        // satellite bias, then receiver bias
+        for is_station in [false, true] {
+            let solutions: Vec<&bias::Solution> = applicable
+                .iter()
+                .filter(|sol| sol.station.is_some() == is_station)
+                .copied()
+                .collect();
+            let osb = solutions
+                .iter()
+                .find(|sol| sol.btype == bias::BiasType::OSB && sol.obs.0 == code)
+                .and_then(|sol| sol.time_bias());
+            let dsb = || {
+                solutions
+                    .iter()
+                    .find(|sol| {
+                        sol.btype == bias::BiasType::DSB
+                            && sol.obs.1.as_ref() == Some(&code)
+                            && sol.obs.0.get(1..2) == code.get(1..2)
+                    })
+                    .and_then(|sol| sol.time_bias())
+                    .map(|bias| -bias)
+            };
+            if let Some(bias) = osb.or_else(dsb) {
+                *total.get_or_insert(0.0) += bias * SPEED_OF_LIGHT;
+            }
+        }
+        total
+    }
+}
+
+/// Code bias compensation, from Bias SINEX solutions
+pub trait ApplyCodeBiases {
+    /// Compensates all pseudo range observations for the code biases
+    /// described in `sinex` (see [Sinex::code_bias]), including the receiver
+    /// biases of this station, and declares the compensated constellations
+    /// in the Observation header.
+    /// ```
+    /// use rinex::prelude::*;
+    /// use sinex::{ApplyCodeBiases, Sinex};
+    /// let sinex = Sinex::from_file("data/BIA/V1/example-1a.bia")
+    ///     .unwrap();
+    /// let rinex = Rinex::from_file("../test_resources/OBS/V2/delf0010.21o")
+    ///     .unwrap();
+    /// let rinex = rinex.apply_code_biases(&sinex);
+    /// // this solution does not cover the file time frame
+    /// let obs = rinex.header.obs.as_ref().unwrap();
+    /// assert!(obs.dcb_compensations.is_empty());
+    /// ```
+    fn apply_code_biases(&self, sinex: &Sinex) -> Self;
+}
+
+impl ApplyCodeBiases for Rinex {
+    fn apply_code_biases(&self, sinex: &Sinex) -> Self {
+        let station = self.header.station.clone();
+        self.observation_code_bias_compensation(|t, sv, observable| {
+            sinex.code_bias(t, sv, Some(&station), observable)
+        })
+    }
+}